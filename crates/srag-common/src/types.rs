@@ -16,13 +16,22 @@ pub enum Language {
     Cpp,
     Java,
     Ruby,
+    CSharp,
+    Php,
+    Perl,
     Shell,
+    Dockerfile,
+    Make,
+    GraphQl,
+    Protobuf,
     Markdown,
     Toml,
     Yaml,
     Json,
     Html,
     Css,
+    Vue,
+    Svelte,
     Sql,
     Env,
     Unknown,
@@ -40,13 +49,22 @@ impl Language {
             "cpp" | "cc" | "cxx" | "hpp" | "hxx" | "hh" => Language::Cpp,
             "java" => Language::Java,
             "rb" => Language::Ruby,
+            "cs" => Language::CSharp,
+            "php" | "phtml" => Language::Php,
+            "pl" | "pm" => Language::Perl,
             "sh" | "bash" | "zsh" | "fish" => Language::Shell,
+            "dockerfile" => Language::Dockerfile,
+            "mk" | "mak" => Language::Make,
+            "graphql" | "graphqls" | "gql" => Language::GraphQl,
+            "proto" => Language::Protobuf,
             "md" | "mdx" => Language::Markdown,
             "toml" => Language::Toml,
             "yml" | "yaml" => Language::Yaml,
             "json" => Language::Json,
             "html" | "htm" => Language::Html,
             "css" | "scss" | "less" => Language::Css,
+            "vue" => Language::Vue,
+            "svelte" => Language::Svelte,
             "sql" => Language::Sql,
             _ => Language::Unknown,
         }
@@ -57,9 +75,50 @@ impl Language {
         if lower == ".env" || lower.starts_with(".env.") || lower.ends_with(".env") {
             return Some(Language::Env);
         }
+        if lower == "dockerfile" || lower.starts_with("dockerfile.") || lower == "containerfile" {
+            return Some(Language::Dockerfile);
+        }
+        if lower == "makefile" || lower == "gnumakefile" {
+            return Some(Language::Make);
+        }
         None
     }
 
+    /// language of a script from its `#!` line, for files whose name says
+    /// nothing. handles `/usr/bin/env` and versioned interpreters like
+    /// `python3.11`.
+    pub fn from_shebang(first_line: &str) -> Option<Self> {
+        let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+        let mut program = words.next()?.rsplit('/').next()?;
+        if program == "env" {
+            // skip env's own flags and variable assignments
+            program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+        }
+        let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        match name {
+            "python" => Some(Language::Python),
+            "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => Some(Language::Shell),
+            "node" | "nodejs" => Some(Language::JavaScript),
+            "ruby" => Some(Language::Ruby),
+            "perl" => Some(Language::Perl),
+            "php" => Some(Language::Php),
+            _ => None,
+        }
+    }
+
+    /// parse a language name as returned by `as_str`, ignoring case.
+    /// `unknown` is rejected since it can't be chunked or configured.
+    pub fn from_name(name: &str) -> Option<Self> {
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::de::IntoDeserializer;
+
+        let lower = name.trim().to_lowercase();
+        let de: StrDeserializer<'_, Error> = lower.as_str().into_deserializer();
+        Self::deserialize(de)
+            .ok()
+            .filter(|lang| *lang != Language::Unknown)
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Language::Rust => "rust",
@@ -71,13 +130,22 @@ impl Language {
             Language::Cpp => "cpp",
             Language::Java => "java",
             Language::Ruby => "ruby",
+            Language::CSharp => "csharp",
+            Language::Php => "php",
+            Language::Perl => "perl",
             Language::Shell => "shell",
+            Language::Dockerfile => "dockerfile",
+            Language::Make => "make",
+            Language::GraphQl => "graphql",
+            Language::Protobuf => "protobuf",
             Language::Markdown => "markdown",
             Language::Toml => "toml",
             Language::Yaml => "yaml",
             Language::Json => "json",
             Language::Html => "html",
             Language::Css => "css",
+            Language::Vue => "vue",
+            Language::Svelte => "svelte",
             Language::Sql => "sql",
             Language::Env => "env",
             Language::Unknown => "unknown",
@@ -96,6 +164,10 @@ impl Language {
                 | Language::Cpp
                 | Language::Java
                 | Language::Ruby
+                | Language::CSharp
+                | Language::Php
+                | Language::Html
+                | Language::Css
         )
     }
 }
@@ -215,6 +287,8 @@ pub struct SourceReference {
     pub start_line: u32,
     pub end_line: u32,
     pub symbol: Option<String>,
+    /// empty when only references were requested
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub content: String,
 }
 
@@ -223,6 +297,9 @@ pub struct SourceReference {
 pub struct QueryResult {
     pub answer: String,
     pub sources: Vec<SourceReference>,
+    /// set when most of the sources were flagged by the injection scanner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 /// a conversation turn for chat history
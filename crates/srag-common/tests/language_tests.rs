@@ -59,6 +59,8 @@ fn test_language_from_extension_java() {
 #[test]
 fn test_language_from_extension_ruby() {
     assert_eq!(Language::from_extension("rb"), Language::Ruby);
+    assert_eq!(Language::from_extension("cs"), Language::CSharp);
+    assert_eq!(Language::from_extension("php"), Language::Php);
 }
 
 #[test]
@@ -90,6 +92,8 @@ fn test_language_from_extension_web() {
     assert_eq!(Language::from_extension("css"), Language::Css);
     assert_eq!(Language::from_extension("scss"), Language::Css);
     assert_eq!(Language::from_extension("less"), Language::Css);
+    assert_eq!(Language::from_extension("vue"), Language::Vue);
+    assert_eq!(Language::from_extension("svelte"), Language::Svelte);
 }
 
 #[test]
@@ -119,6 +123,68 @@ fn test_language_from_filename_env() {
     );
 }
 
+#[test]
+fn test_language_from_filename_dockerfile() {
+    assert_eq!(
+        Language::from_filename("Dockerfile"),
+        Some(Language::Dockerfile)
+    );
+    assert_eq!(
+        Language::from_filename("Dockerfile.prod"),
+        Some(Language::Dockerfile)
+    );
+    assert_eq!(
+        Language::from_filename("Containerfile"),
+        Some(Language::Dockerfile)
+    );
+    assert_eq!(Language::from_extension("dockerfile"), Language::Dockerfile);
+}
+
+#[test]
+fn test_language_from_filename_makefile() {
+    assert_eq!(Language::from_filename("Makefile"), Some(Language::Make));
+    assert_eq!(Language::from_filename("makefile"), Some(Language::Make));
+    assert_eq!(Language::from_filename("GNUmakefile"), Some(Language::Make));
+    assert_eq!(Language::from_extension("mk"), Language::Make);
+}
+
+#[test]
+fn test_language_from_extension_schemas() {
+    assert_eq!(Language::from_extension("graphql"), Language::GraphQl);
+    assert_eq!(Language::from_extension("gql"), Language::GraphQl);
+    assert_eq!(Language::from_extension("proto"), Language::Protobuf);
+    assert_eq!(Language::from_name("graphql"), Some(Language::GraphQl));
+}
+
+#[test]
+fn test_language_from_shebang_interpreters() {
+    let cases = [
+        ("#!/usr/bin/env python3", Language::Python),
+        ("#!/usr/bin/python3.11 -u", Language::Python),
+        ("#!/bin/bash", Language::Shell),
+        ("#!/bin/sh -e", Language::Shell),
+        ("#!/usr/bin/env zsh", Language::Shell),
+        ("#!/usr/bin/env node", Language::JavaScript),
+        (
+            "#!/usr/bin/env -S node --experimental-modules",
+            Language::JavaScript,
+        ),
+        ("#!/usr/bin/ruby", Language::Ruby),
+        ("#!/usr/bin/perl -w", Language::Perl),
+    ];
+    for (line, expected) in cases {
+        assert_eq!(Language::from_shebang(line), Some(expected), "{}", line);
+    }
+}
+
+#[test]
+fn test_language_from_shebang_rejects_other_lines() {
+    assert_eq!(Language::from_shebang("import os"), None);
+    assert_eq!(Language::from_shebang("# !/bin/bash"), None);
+    assert_eq!(Language::from_shebang("#!/usr/bin/env"), None);
+    assert_eq!(Language::from_shebang("#!/usr/bin/awk -f"), None);
+}
+
 #[test]
 fn test_language_from_filename_non_env() {
     assert_eq!(Language::from_filename("main.rs"), None);
@@ -138,13 +204,22 @@ fn test_language_as_str_round_trip() {
         Language::Cpp,
         Language::Java,
         Language::Ruby,
+        Language::CSharp,
+        Language::Php,
+        Language::Perl,
         Language::Shell,
+        Language::Dockerfile,
+        Language::Make,
+        Language::GraphQl,
+        Language::Protobuf,
         Language::Markdown,
         Language::Toml,
         Language::Yaml,
         Language::Json,
         Language::Html,
         Language::Css,
+        Language::Vue,
+        Language::Svelte,
         Language::Sql,
         Language::Env,
         Language::Unknown,
@@ -155,9 +230,24 @@ fn test_language_as_str_round_trip() {
         let json_str = serde_json::to_string(&lang).unwrap();
         let parsed: Language = serde_json::from_str(&json_str).unwrap();
         assert_eq!(parsed, lang);
+        if lang != Language::Unknown {
+            assert_eq!(Language::from_name(s), Some(lang));
+        }
     }
 }
 
+#[test]
+fn test_language_from_name() {
+    assert_eq!(Language::from_name("Rust"), Some(Language::Rust));
+    assert_eq!(
+        Language::from_name(" typescript "),
+        Some(Language::TypeScript)
+    );
+    assert_eq!(Language::from_name("unknown"), None);
+    assert_eq!(Language::from_name("cobol"), None);
+    assert_eq!(Language::from_name(""), None);
+}
+
 #[test]
 fn test_language_has_tree_sitter_support() {
     assert!(Language::Rust.has_tree_sitter_support());
@@ -169,15 +259,18 @@ fn test_language_has_tree_sitter_support() {
     assert!(Language::Cpp.has_tree_sitter_support());
     assert!(Language::Java.has_tree_sitter_support());
     assert!(Language::Ruby.has_tree_sitter_support());
+    assert!(Language::CSharp.has_tree_sitter_support());
+    assert!(Language::Php.has_tree_sitter_support());
+    assert!(Language::Html.has_tree_sitter_support());
+    assert!(Language::Css.has_tree_sitter_support());
 
     assert!(!Language::Shell.has_tree_sitter_support());
     assert!(!Language::Markdown.has_tree_sitter_support());
     assert!(!Language::Toml.has_tree_sitter_support());
     assert!(!Language::Yaml.has_tree_sitter_support());
     assert!(!Language::Json.has_tree_sitter_support());
-    assert!(!Language::Html.has_tree_sitter_support());
-    assert!(!Language::Css.has_tree_sitter_support());
     assert!(!Language::Sql.has_tree_sitter_support());
+    assert!(!Language::Vue.has_tree_sitter_support());
     assert!(!Language::Env.has_tree_sitter_support());
     assert!(!Language::Unknown.has_tree_sitter_support());
 }
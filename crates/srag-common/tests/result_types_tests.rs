@@ -159,6 +159,7 @@ fn test_query_result() {
             symbol: Some("main".into()),
             content: "fn main() {}".into(),
         }],
+        warning: None,
     };
 
     let json_str = serde_json::to_string(&result).unwrap();
@@ -173,6 +174,7 @@ fn test_query_result_empty_sources() {
     let result = QueryResult {
         answer: "No relevant sources found".into(),
         sources: vec![],
+        warning: None,
     };
 
     let json_str = serde_json::to_string(&result).unwrap();
@@ -201,6 +203,7 @@ fn test_query_result_multiple_sources() {
                 content: "code b".into(),
             },
         ],
+        warning: None,
     };
     let json_str = serde_json::to_string(&result).unwrap();
     let parsed: QueryResult = serde_json::from_str(&json_str).unwrap();
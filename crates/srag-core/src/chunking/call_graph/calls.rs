@@ -14,6 +14,14 @@ pub fn try_extract_call(node: Node, source: &str, language: Language) -> Option<
         Language::C | Language::Cpp => kind == "call_expression",
         Language::Java => kind == "method_invocation",
         Language::Ruby => matches!(kind, "call" | "method_call"),
+        Language::CSharp => kind == "invocation_expression",
+        Language::Php => matches!(
+            kind,
+            "function_call_expression"
+                | "member_call_expression"
+                | "nullsafe_member_call_expression"
+                | "scoped_call_expression"
+        ),
         _ => false,
     };
 
@@ -25,6 +33,12 @@ pub fn try_extract_call(node: Node, source: &str, language: Language) -> Option<
 }
 
 fn extract_callee_name(node: Node, source: &str, language: Language) -> Option<String> {
+    match language {
+        Language::CSharp => return csharp_callee(node.child_by_field_name("function")?, source),
+        Language::Php => return php_callee(node, source),
+        _ => {}
+    }
+
     let mut cursor = node.walk();
 
     for child in node.children(&mut cursor) {
@@ -58,15 +72,8 @@ fn extract_callee_name(node: Node, source: &str, language: Language) -> Option<S
                     return extract_method_name(child, source);
                 }
             }
-            Language::Java => {
-                if child_kind == "identifier" {
-                    return Some(source[child.start_byte()..child.end_byte()].to_string());
-                }
-            }
-            Language::Ruby => {
-                if child_kind == "identifier" {
-                    return Some(source[child.start_byte()..child.end_byte()].to_string());
-                }
+            Language::Java | Language::Ruby if child_kind == "identifier" => {
+                return Some(source[child.start_byte()..child.end_byte()].to_string());
             }
             _ => {}
         }
@@ -75,6 +82,44 @@ fn extract_callee_name(node: Node, source: &str, language: Language) -> Option<S
     None
 }
 
+/// `Foo()`, `Foo<T>()`, `obj.Foo()` and `A.B.Foo<T>()`
+fn csharp_callee(function: Node, source: &str) -> Option<String> {
+    match function.kind() {
+        "identifier" => Some(source[function.start_byte()..function.end_byte()].to_string()),
+        "generic_name" => {
+            let mut cursor = function.walk();
+            let name = function
+                .children(&mut cursor)
+                .find(|c| c.kind() == "identifier")?;
+            Some(source[name.start_byte()..name.end_byte()].to_string())
+        }
+        "member_access_expression" => csharp_callee(function.child_by_field_name("name")?, source),
+        _ => None,
+    }
+}
+
+/// `foo()`, `\App\foo()`, `$obj->foo()`, `$obj?->foo()` and `Cls::foo()`
+fn php_callee(node: Node, source: &str) -> Option<String> {
+    let name = if node.kind() == "function_call_expression" {
+        let function = node.child_by_field_name("function")?;
+        match function.kind() {
+            "name" => function,
+            "qualified_name" => {
+                let mut cursor = function.walk();
+                function
+                    .children(&mut cursor)
+                    .filter(|c| c.kind() == "name")
+                    .last()?
+            }
+            _ => return None,
+        }
+    } else {
+        node.child_by_field_name("name")
+            .filter(|n| n.kind() == "name")?
+    };
+    Some(source[name.start_byte()..name.end_byte()].to_string())
+}
+
 fn extract_method_name(node: Node, source: &str) -> Option<String> {
     let mut cursor = node.walk();
     let mut last_identifier = None;
@@ -164,4 +209,18 @@ mod tests {
         let calls = parse_and_extract_calls("func main() { helper() }", Language::Go);
         assert!(calls.contains(&"helper".to_string()));
     }
+
+    #[test]
+    fn test_csharp_calls() {
+        let code = "class A { void Run() { Helper(); repo.Load(1); Mapper.Map<User>(u); } }";
+        let calls = parse_and_extract_calls(code, Language::CSharp);
+        assert_eq!(calls, ["Helper", "Load", "Map"]);
+    }
+
+    #[test]
+    fn test_php_calls() {
+        let code = "<?php\nfunction run() { helper(); \\App\\util(); $this->load(1); $u?->save(); self::cache(); }";
+        let calls = parse_and_extract_calls(code, Language::Php);
+        assert_eq!(calls, ["helper", "util", "load", "save", "cache"]);
+    }
 }
@@ -80,11 +80,33 @@ fn is_definition_node(kind: &str, language: Language) -> (bool, &'static str) {
             "module" => (true, "module"),
             _ => (false, ""),
         },
+        Language::CSharp => match kind {
+            "method_declaration" => (true, "method"),
+            "constructor_declaration" => (true, "constructor"),
+            "class_declaration" => (true, "class"),
+            "interface_declaration" => (true, "interface"),
+            "struct_declaration" => (true, "struct"),
+            _ => (false, ""),
+        },
+        Language::Php => match kind {
+            "function_definition" => (true, "function"),
+            "method_declaration" => (true, "method"),
+            "class_declaration" => (true, "class"),
+            "interface_declaration" => (true, "interface"),
+            "trait_declaration" => (true, "trait"),
+            _ => (false, ""),
+        },
         _ => (false, ""),
     }
 }
 
 fn extract_definition_name(node: Node, source: &str, language: Language) -> Option<String> {
+    // c# return types are identifiers too, so go by the name field
+    if matches!(language, Language::CSharp | Language::Php) {
+        let name = node.child_by_field_name("name")?;
+        return Some(source[name.start_byte()..name.end_byte()].to_string());
+    }
+
     let mut cursor = node.walk();
 
     for child in node.children(&mut cursor) {
@@ -143,6 +165,17 @@ fn is_scope_node(kind: &str, language: Language) -> bool {
         }
         Language::Java => matches!(kind, "class_declaration" | "interface_declaration"),
         Language::Ruby => matches!(kind, "class" | "module"),
+        Language::CSharp => matches!(
+            kind,
+            "class_declaration"
+                | "interface_declaration"
+                | "struct_declaration"
+                | "record_declaration"
+        ),
+        Language::Php => matches!(
+            kind,
+            "class_declaration" | "interface_declaration" | "trait_declaration"
+        ),
         _ => false,
     }
 }
@@ -224,4 +257,28 @@ mod tests {
         let defs = parse_and_extract("func main() { }", Language::Go);
         assert!(defs.iter().any(|d| d.name == "main"));
     }
+
+    #[test]
+    fn test_csharp_method_scope() {
+        let code = "class UserService {\n  public User Find(int id) { return null; }\n  public UserService() { }\n}";
+        let defs = parse_and_extract(code, Language::CSharp);
+        let find = defs.iter().find(|d| d.kind == "method").unwrap();
+        assert_eq!(find.name, "Find");
+        assert_eq!(find.scope.as_deref(), Some("UserService"));
+        assert!(defs
+            .iter()
+            .any(|d| d.kind == "constructor" && d.name == "UserService"));
+    }
+
+    #[test]
+    fn test_php_method_scope() {
+        let code = "<?php\nfunction helper() {}\nclass Repo {\n  public function find($id) {}\n}\n";
+        let defs = parse_and_extract(code, Language::Php);
+        assert!(defs
+            .iter()
+            .any(|d| d.kind == "function" && d.name == "helper" && d.scope.is_none()));
+        let find = defs.iter().find(|d| d.kind == "method").unwrap();
+        assert_eq!(find.name, "find");
+        assert_eq!(find.scope.as_deref(), Some("Repo"));
+    }
 }
@@ -27,6 +27,10 @@ pub fn extract_call_graph(
     file_id: i64,
     chunk_id: i64,
 ) -> Option<CallGraphData> {
+    // markup and stylesheets have no functions to link
+    if matches!(language, Language::Html | Language::Css) {
+        return None;
+    }
     let ts_language = get_tree_sitter_language(language)?;
 
     let mut parser = Parser::new();
@@ -158,6 +162,17 @@ pub(super) fn is_scope_node(kind: &str, language: Language) -> bool {
         }
         Language::Java => matches!(kind, "class_declaration" | "interface_declaration"),
         Language::Ruby => matches!(kind, "class" | "module"),
+        Language::CSharp => matches!(
+            kind,
+            "class_declaration"
+                | "interface_declaration"
+                | "struct_declaration"
+                | "record_declaration"
+        ),
+        Language::Php => matches!(
+            kind,
+            "class_declaration" | "interface_declaration" | "trait_declaration"
+        ),
         _ => false,
     }
 }
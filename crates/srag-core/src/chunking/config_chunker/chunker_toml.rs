@@ -1,73 +1,128 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+use std::collections::HashMap;
+
 use srag_common::types::{Chunk, Language};
 
+/// split a toml file at its table headers. each `[[array.of.tables]]`
+/// entry is its own chunk, named with its index in the array, e.g. `bin[1]`.
 pub fn chunk_toml_file(text: &str, language: Language) -> Vec<Chunk> {
     let mut chunks = Vec::new();
-    let mut current_section = String::new();
+    let mut current_section: Option<String> = None;
     let mut current_lines = Vec::new();
     let mut start_line: u32 = 1;
+    let mut array_entries: HashMap<String, usize> = HashMap::new();
+    // open brackets of a multi-line array value, whose lines aren't headers
+    let mut value_depth: i32 = 0;
 
-    for (i, line) in text.lines().enumerate() {
-        let line_num = (i + 1) as u32;
-        let trimmed = line.trim();
-
-        if trimmed.starts_with('[') && trimmed.contains(']') {
-            if !current_lines.is_empty() {
-                let content = current_lines.join("\n");
-                if !content.trim().is_empty() {
-                    chunks.push(Chunk {
-                        id: None,
-                        file_id: 0,
-                        content,
-                        symbol: Some(current_section.clone()),
-                        symbol_kind: Some("section".to_string()),
-                        start_line,
-                        end_line: line_num - 1,
-                        language,
-                        suspicious: false,
-                    });
-                }
-            }
-            current_section = trimmed
-                .trim_start_matches('[')
-                .split(']')
-                .next()
-                .unwrap_or("")
-                .to_string();
-            current_lines = vec![line.to_string()];
-            start_line = line_num;
-        } else {
-            current_lines.push(line.to_string());
-        }
-    }
-
-    if !current_lines.is_empty() {
-        let content = current_lines.join("\n");
+    let mut flush = |section: Option<String>, lines: &[&str], start: u32, end: u32| {
+        let content = lines.join("\n");
         if !content.trim().is_empty() {
-            let end_line = text.lines().count() as u32;
             chunks.push(Chunk {
                 id: None,
                 file_id: 0,
                 content,
-                symbol: if current_section.is_empty() {
-                    None
-                } else {
-                    Some(current_section)
-                },
+                symbol: section,
                 symbol_kind: Some("section".to_string()),
-                start_line,
-                end_line,
+                start_line: start,
+                end_line: end,
                 language,
                 suspicious: false,
             });
         }
+    };
+
+    for (i, line) in text.lines().enumerate() {
+        let line_num = (i + 1) as u32;
+        let header = if value_depth > 0 {
+            None
+        } else {
+            parse_header(line.trim())
+        };
+
+        match header {
+            Some((path, is_array)) => {
+                flush(
+                    current_section.take(),
+                    &current_lines,
+                    start_line,
+                    line_num - 1,
+                );
+                current_section = Some(if is_array {
+                    let index = array_entries.entry(path.clone()).or_insert(0);
+                    let name = format!("{}[{}]", path, index);
+                    *index += 1;
+                    name
+                } else {
+                    path
+                });
+                current_lines = vec![line];
+                start_line = line_num;
+            }
+            None => {
+                value_depth = (value_depth + bracket_balance(line)).max(0);
+                current_lines.push(line);
+            }
+        }
     }
 
+    let end_line = text.lines().count() as u32;
+    flush(current_section, &current_lines, start_line, end_line);
+
     chunks
 }
 
+/// the table path of a `[table]` or `[[array.of.tables]]` header line, and
+/// whether it is an array entry. anything else on the line but a comment
+/// means it isn't a header.
+fn parse_header(trimmed: &str) -> Option<(String, bool)> {
+    let (rest, is_array, close) = match trimmed.strip_prefix("[[") {
+        Some(rest) => (rest, true, "]]"),
+        None => (trimmed.strip_prefix('[')?, false, "]"),
+    };
+    let end = rest.find(close)?;
+    let after = rest[end + close.len()..].trim_start();
+    if !(after.is_empty() || after.starts_with('#')) {
+        return None;
+    }
+    let path = rest[..end].trim();
+    let is_key = |c: char| c.is_alphanumeric() || "_-. \t\"'".contains(c);
+    if path.is_empty() || !path.chars().all(is_key) {
+        return None;
+    }
+    Some((path.to_string(), is_array))
+}
+
+/// opening minus closing brackets on a value line, ignoring strings and
+/// comments
+fn bracket_balance(line: &str) -> i32 {
+    let mut balance = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in line.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '#' => break,
+                '[' => balance += 1,
+                ']' => balance -= 1,
+                _ => {}
+            },
+        }
+    }
+    balance
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,6 +160,48 @@ mod tests {
         assert!(chunks[0].symbol.is_none() || chunks[0].symbol == Some("".to_string()));
     }
 
+    #[test]
+    fn test_toml_array_of_tables() {
+        let text = "[package]\nname = \"srag\"\n\n[[bin]]\nname = \"srag\"\npath = \"src/main.rs\"\n\n[[bin]]\nname = \"srag-ml\"\n\n[[tool.mypy.overrides]] # per module\nmodule = \"tests\"\n";
+        let chunks = chunk_toml_file(text, Language::Toml);
+        let symbols: Vec<_> = chunks.iter().map(|c| c.symbol.as_deref()).collect();
+        assert_eq!(
+            symbols,
+            [
+                Some("package"),
+                Some("bin[0]"),
+                Some("bin[1]"),
+                Some("tool.mypy.overrides[0]")
+            ]
+        );
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (4, 7));
+        assert!(chunks[2].content.contains("srag-ml"));
+    }
+
+    #[test]
+    fn test_toml_array_values_are_not_headers() {
+        let text = "[tool.ruff]\nselect = [\"E\", \"F\"]\nmatrix = [\n  [1, 2],\n  [3, 4]\n]\nnames = [\n  \"a]\",\n  [\"nested\"]\n]\n\n[other]\nkey = 1\n";
+        let chunks = chunk_toml_file(text, Language::Toml);
+        let symbols: Vec<_> = chunks.iter().map(|c| c.symbol.as_deref()).collect();
+        assert_eq!(symbols, [Some("tool.ruff"), Some("other")]);
+        assert!(chunks[0].content.contains("[3, 4]"));
+        assert!(chunks[0].content.contains("[\"nested\"]"));
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(parse_header("[package]"), Some(("package".into(), false)));
+        assert_eq!(parse_header("[[bin]]"), Some(("bin".into(), true)));
+        assert_eq!(
+            parse_header("[ dependencies.\"serde\" ] # pinned"),
+            Some(("dependencies.\"serde\"".into(), false))
+        );
+        assert_eq!(parse_header("[1, 2],"), None);
+        assert_eq!(parse_header("[1]"), Some(("1".into(), false)));
+        assert_eq!(parse_header("[a] = 1"), None);
+        assert_eq!(parse_header("[]"), None);
+    }
+
     #[test]
     fn test_toml_empty() {
         let chunks = chunk_toml_file("", Language::Toml);
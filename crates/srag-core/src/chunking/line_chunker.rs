@@ -4,10 +4,20 @@
 use srag_common::types::{Chunk, Language};
 use srag_common::Result;
 
-const MAX_CHUNK_LINES: usize = 60; //is this even optimal?
-const OVERLAP_LINES: usize = 5;
-
-pub fn chunk_by_lines(text: &str, language: Language) -> Result<Vec<Chunk>> {
+pub const MAX_CHUNK_LINES: usize = 60; //is this even optimal?
+pub const OVERLAP_LINES: usize = 5;
+
+/// chunks of `window` lines, each repeating the last `overlap` lines of the
+/// one before. the final window is kept even when it is shorter.
+pub fn chunk_by_line_window(
+    text: &str,
+    language: Language,
+    window: usize,
+    overlap: usize,
+) -> Result<Vec<Chunk>> {
+    // an overlap as long as the window would never move forward
+    let window = window.max(1);
+    let overlap = overlap.min(window - 1);
     let lines: Vec<&str> = text.lines().collect();
     if lines.is_empty() {
         return Ok(Vec::new());
@@ -17,7 +27,7 @@ pub fn chunk_by_lines(text: &str, language: Language) -> Result<Vec<Chunk>> {
     let mut start = 0;
 
     while start < lines.len() {
-        let end = (start + MAX_CHUNK_LINES).min(lines.len());
+        let end = (start + window).min(lines.len());
         let chunk_text = lines[start..end].join("\n");
 
         if !chunk_text.trim().is_empty() {
@@ -38,7 +48,7 @@ pub fn chunk_by_lines(text: &str, language: Language) -> Result<Vec<Chunk>> {
             break;
         }
 
-        start = end - OVERLAP_LINES;
+        start = end - overlap;
     }
 
     Ok(chunks)
@@ -48,6 +58,10 @@ pub fn chunk_by_lines(text: &str, language: Language) -> Result<Vec<Chunk>> {
 mod tests {
     use super::*;
 
+    fn chunk_by_lines(text: &str, language: Language) -> Result<Vec<Chunk>> {
+        chunk_by_line_window(text, language, MAX_CHUNK_LINES, OVERLAP_LINES)
+    }
+
     #[test]
     fn test_basic_chunking() {
         let text = "line1\nline2\nline3";
@@ -101,4 +115,36 @@ mod tests {
             assert!(chunks[1].start_line < chunks[0].end_line);
         }
     }
+
+    #[test]
+    fn test_configured_window_and_overlap() {
+        let text: String = (1..=25).map(|i| format!("line {}\n", i)).collect();
+        let chunks = chunk_by_line_window(&text, Language::Unknown, 10, 3).unwrap();
+        let spans: Vec<(u32, u32)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        // 1-10, then each window starts 3 lines before the last ended; the
+        // final partial window is kept
+        assert_eq!(spans, vec![(1, 10), (8, 17), (15, 24), (22, 25)]);
+        for chunk in &chunks {
+            assert!(chunk
+                .content
+                .starts_with(&format!("line {}\n", chunk.start_line)));
+            assert!(chunk.content.ends_with(&format!("line {}", chunk.end_line)));
+        }
+    }
+
+    #[test]
+    fn test_window_without_overlap() {
+        let text: String = (1..=6).map(|i| format!("line {}\n", i)).collect();
+        let chunks = chunk_by_line_window(&text, Language::Unknown, 2, 0).unwrap();
+        let spans: Vec<(u32, u32)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(spans, vec![(1, 2), (3, 4), (5, 6)]);
+    }
+
+    #[test]
+    fn test_overlap_not_smaller_than_window_still_advances() {
+        let text = "a\nb\nc";
+        let chunks = chunk_by_line_window(text, Language::Unknown, 2, 5).unwrap();
+        let spans: Vec<(u32, u32)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(spans, vec![(1, 2), (2, 3)]);
+    }
 }
@@ -4,13 +4,71 @@
 use srag_common::types::{Chunk, Language};
 use srag_common::Result;
 
+use crate::config::IndexingConfig;
+
 pub mod call_graph;
 mod config_chunker;
+pub mod injection_allowlist;
 pub mod injection_scanner;
 mod line_chunker;
+mod schema_chunker;
+mod script_chunker;
+mod sections;
+mod sfc_chunker;
 mod tree_sitter_chunker;
 
+pub use line_chunker::{MAX_CHUNK_LINES, OVERLAP_LINES};
+pub use tree_sitter_chunker::strip_comments;
+
+/// chunks shorter than this many characters are dropped by default
+pub const DEFAULT_MIN_CHUNK_SIZE: usize = 50;
+
+/// knobs for tree-sitter chunking
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkOptions {
+    /// pull the comments above a definition into its chunk
+    pub include_doc_comments: bool,
+    /// smallest chunk kept, in characters. named top-level definitions are
+    /// kept regardless.
+    pub min_chunk_size: usize,
+    /// lines per chunk for files split by lines, with no structure to follow
+    pub fallback_chunk_lines: usize,
+    /// lines each of those chunks repeats from the one before
+    pub fallback_overlap_lines: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            include_doc_comments: true,
+            min_chunk_size: DEFAULT_MIN_CHUNK_SIZE,
+            fallback_chunk_lines: MAX_CHUNK_LINES,
+            fallback_overlap_lines: OVERLAP_LINES,
+        }
+    }
+}
+
+impl ChunkOptions {
+    pub fn from_config(config: &IndexingConfig) -> Self {
+        Self {
+            include_doc_comments: config.include_doc_comments,
+            min_chunk_size: config.min_chunk_size,
+            fallback_chunk_lines: config.fallback_chunk_lines,
+            fallback_overlap_lines: config.fallback_overlap_lines,
+        }
+    }
+}
+
 pub fn chunk_file(content: &[u8], language: Language) -> Result<Vec<Chunk>> {
+    chunk_file_opts(content, language, ChunkOptions::default())
+}
+
+/// chunk like `chunk_file` with non-default options
+pub fn chunk_file_opts(
+    content: &[u8],
+    language: Language,
+    opts: ChunkOptions,
+) -> Result<Vec<Chunk>> {
     let text = match std::str::from_utf8(content) {
         Ok(t) => t,
         Err(_) => return Ok(Vec::new()),
@@ -29,8 +87,23 @@ pub fn chunk_file(content: &[u8], language: Language) -> Result<Vec<Chunk>> {
         _ => {}
     }
 
+    // build files, scripts, schemas and components split on their top-level
+    // blocks
+    let script_chunks = match language {
+        Language::Dockerfile => script_chunker::chunk_dockerfile(text, opts),
+        Language::Make => script_chunker::chunk_makefile(text, opts),
+        Language::Shell => script_chunker::chunk_shell_script(text, opts),
+        Language::GraphQl => schema_chunker::chunk_graphql(text, opts),
+        Language::Protobuf => schema_chunker::chunk_proto(text, opts),
+        Language::Vue | Language::Svelte => sfc_chunker::chunk_sfc(text, language, opts),
+        _ => Vec::new(),
+    };
+    if !script_chunks.is_empty() {
+        return Ok(script_chunks);
+    }
+
     if language.has_tree_sitter_support() {
-        match tree_sitter_chunker::chunk_with_tree_sitter(text, language) {
+        match tree_sitter_chunker::chunk_with_tree_sitter_opts(text, language, opts) {
             Ok(chunks) if !chunks.is_empty() => return Ok(chunks),
             Ok(_) => {}
             Err(e) => {
@@ -39,7 +112,52 @@ pub fn chunk_file(content: &[u8], language: Language) -> Result<Vec<Chunk>> {
         }
     }
 
-    line_chunker::chunk_by_lines(text, language)
+    line_chunker::chunk_by_line_window(
+        text,
+        language,
+        opts.fallback_chunk_lines,
+        opts.fallback_overlap_lines,
+    )
+}
+
+const LOCKFILE_PREVIEW_LINES: usize = 40;
+
+/// a lockfile becomes a single chunk holding its first lines so it can still
+/// be found by name without flooding results.
+pub fn chunk_lockfile(content: &[u8], language: Language) -> Result<Vec<Chunk>> {
+    let text = match std::str::from_utf8(content) {
+        Ok(t) => t,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let total_lines = text.lines().count();
+    let mut preview: String = text
+        .lines()
+        .take(LOCKFILE_PREVIEW_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if total_lines > LOCKFILE_PREVIEW_LINES {
+        preview.push_str(&format!(
+            "\n... ({} more lines)",
+            total_lines - LOCKFILE_PREVIEW_LINES
+        ));
+    }
+
+    Ok(vec![Chunk {
+        id: None,
+        file_id: 0,
+        content: preview,
+        symbol: None,
+        symbol_kind: Some("lockfile".to_string()),
+        start_line: 1,
+        end_line: total_lines.min(LOCKFILE_PREVIEW_LINES) as u32,
+        language,
+        suspicious: false,
+    }])
 }
 
 #[cfg(test)]
@@ -110,6 +228,86 @@ mod tests {
         assert!(!result.is_empty());
     }
 
+    #[test]
+    fn test_chunk_lockfile_single_chunk() {
+        let content: String = (0..500)
+            .map(|i| format!("dep-{} = \"1.0.{}\"\n", i, i))
+            .collect();
+        let result = chunk_lockfile(content.as_bytes(), Language::Toml).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].end_line, LOCKFILE_PREVIEW_LINES as u32);
+        assert_eq!(result[0].symbol_kind.as_deref(), Some("lockfile"));
+        assert!(result[0].content.ends_with("(460 more lines)"));
+    }
+
+    #[test]
+    fn test_chunk_file_routes_dockerfile() {
+        let content = b"FROM alpine AS base\nRUN apk add git\n";
+        let result = chunk_file(content, Language::Dockerfile).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].symbol.as_deref(), Some("base"));
+    }
+
+    #[test]
+    fn test_chunk_file_routes_proto() {
+        let content = b"syntax = \"proto3\";\n\nmessage Ping {\n  string id = 1;\n}\n";
+        let result = chunk_file(content, Language::Protobuf).unwrap();
+        assert!(result
+            .iter()
+            .any(|c| c.symbol.as_deref() == Some("Ping")
+                && c.symbol_kind.as_deref() == Some("message")));
+    }
+
+    #[test]
+    fn test_chunk_file_routes_vue() {
+        let content = b"<template>\n  <p>{{ msg }}</p>\n</template>\n\n<script>\nexport default {\n  methods: {\n    greet() {\n      console.log(`hello from the vue component ${this.msg}`);\n    },\n    farewell() {\n      console.log(`goodbye from the vue component ${this.msg}`);\n    },\n  },\n};\n</script>\n";
+        let result = chunk_file(content, Language::Vue).unwrap();
+        let kinds: Vec<(Option<&str>, Option<&str>)> = result
+            .iter()
+            .map(|c| (c.symbol.as_deref(), c.symbol_kind.as_deref()))
+            .collect();
+        assert!(kinds.contains(&(Some("template"), Some("template"))));
+        assert!(kinds.contains(&(Some("greet"), Some("method_definition"))));
+        assert!(kinds.contains(&(Some("farewell"), Some("method_definition"))));
+    }
+
+    #[test]
+    fn test_chunk_file_shell_without_functions_falls_back() {
+        let content = b"echo one\necho two\n";
+        let result = chunk_file(content, Language::Shell).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(result[0].symbol.is_none());
+    }
+
+    #[test]
+    fn test_chunk_file_fallback_uses_configured_window() {
+        let content: String = (1..=30).map(|i| format!("log entry {}\n", i)).collect();
+        let opts = ChunkOptions {
+            fallback_chunk_lines: 12,
+            fallback_overlap_lines: 2,
+            ..ChunkOptions::default()
+        };
+        let result = chunk_file_opts(content.as_bytes(), Language::Unknown, opts).unwrap();
+        let spans: Vec<(u32, u32)> = result.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(spans, vec![(1, 12), (11, 22), (21, 30)]);
+    }
+
+    #[test]
+    fn test_script_gaps_use_configured_window() {
+        let mut content: String = (1..=20).map(|i| format!("echo step {}\n", i)).collect();
+        content.push_str("deploy() {\n  echo deploying\n}\n");
+        let opts = ChunkOptions {
+            fallback_chunk_lines: 10,
+            fallback_overlap_lines: 0,
+            ..ChunkOptions::default()
+        };
+        let result = chunk_file_opts(content.as_bytes(), Language::Shell, opts).unwrap();
+        let spans: Vec<(u32, u32)> = result.iter().map(|c| (c.start_line, c.end_line)).collect();
+        // the top-level commands before the function are split by the
+        // configured window rather than the built-in one
+        assert_eq!(spans, vec![(1, 10), (11, 20), (21, 23)]);
+    }
+
     #[test]
     fn test_chunk_file_fallback_to_lines() {
         let content = b"just some text\nwithout structure";
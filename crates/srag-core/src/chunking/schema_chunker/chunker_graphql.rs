@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use srag_common::types::{Chunk, Language};
+
+use super::{
+    assemble, block_end, doc_start, identifier_after, strip_strings, ChunkOptions, Section,
+};
+
+/// keywords that open a top-level definition, with the kind recorded for it.
+/// kinds follow the node names of the graphql grammar.
+const KINDS: &[(&str, &str)] = &[
+    ("type", "object_type_definition"),
+    ("interface", "interface_type_definition"),
+    ("input", "input_object_type_definition"),
+    ("enum", "enum_type_definition"),
+    ("union", "union_type_definition"),
+    ("scalar", "scalar_type_definition"),
+    ("query", "operation_definition"),
+    ("mutation", "operation_definition"),
+    ("subscription", "operation_definition"),
+    ("fragment", "fragment_definition"),
+];
+
+/// split a graphql schema or document into one chunk per top-level type,
+/// operation or fragment. `extend type` blocks are chunked like the type.
+pub fn chunk_graphql(text: &str, opts: ChunkOptions) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut sections = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some((kind, name)) = parse_header(lines[i]) else {
+            i += 1;
+            continue;
+        };
+
+        let end = block_end(&lines, i, code);
+        sections.push(Section {
+            start: description_start(&lines, i),
+            end,
+            symbol: name.to_string(),
+            kind,
+        });
+        i = end;
+    }
+
+    assemble(&lines, sections, Language::GraphQl, opts)
+}
+
+fn parse_header(line: &str) -> Option<(&'static str, &str)> {
+    // only unindented lines start a definition; fields are always nested
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let trimmed = line.strip_prefix("extend ").unwrap_or(line);
+    KINDS.iter().find_map(|(keyword, kind)| {
+        let rest = trimmed.strip_prefix(keyword)?;
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        identifier_after(rest).map(|name| (*kind, name))
+    })
+}
+
+/// include `#` comments and a `"""` description directly above a definition
+fn description_start(lines: &[&str], start: usize) -> usize {
+    let i = doc_start(lines, start, |l| l.starts_with('#'));
+    let Some(prev) = i.checked_sub(1).map(|p| lines[p].trim()) else {
+        return i;
+    };
+
+    let open = if prev.starts_with('"') && prev.ends_with('"') && prev != "\"\"\"" {
+        // "short" or """short""" on one line
+        i - 1
+    } else if prev.ends_with("\"\"\"") {
+        // closing a block description, find its opening quotes
+        match (0..i - 1)
+            .rev()
+            .find(|&j| lines[j].trim_start().starts_with("\"\"\""))
+        {
+            Some(j) => j,
+            None => return i,
+        }
+    } else {
+        return i;
+    };
+
+    doc_start(lines, open, |l| l.starts_with('#'))
+}
+
+fn code(line: &str) -> String {
+    strip_strings(line, "#")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"scalar DateTime
+
+"""
+a registered user
+"""
+type User implements Node {
+  id: ID!
+  name: String
+  posts(first: Int = 10): [Post!]!
+}
+
+# anything with an id
+interface Node {
+  id: ID!
+}
+
+"a blog post"
+type Post {
+  title: String! @deprecated(reason: "use {headline}")
+  author: User
+}
+
+enum Role {
+  ADMIN
+  READER
+}
+
+extend type Query {
+  me: User
+}
+"#;
+
+    fn find<'a>(chunks: &'a [Chunk], symbol: &str) -> &'a Chunk {
+        chunks
+            .iter()
+            .find(|c| c.symbol.as_deref() == Some(symbol))
+            .unwrap_or_else(|| panic!("no chunk for {}", symbol))
+    }
+
+    #[test]
+    fn test_top_level_definitions_become_chunks() {
+        let chunks = chunk_graphql(SCHEMA, ChunkOptions::default());
+        let named: Vec<_> = chunks
+            .iter()
+            .filter_map(|c| Some((c.symbol.as_deref()?, c.symbol_kind.as_deref()?)))
+            .collect();
+        assert_eq!(
+            named,
+            vec![
+                ("DateTime", "scalar_type_definition"),
+                ("User", "object_type_definition"),
+                ("Node", "interface_type_definition"),
+                ("Post", "object_type_definition"),
+                ("Role", "enum_type_definition"),
+                ("Query", "object_type_definition"),
+            ]
+        );
+        assert!(chunks.iter().all(|c| c.language == Language::GraphQl));
+    }
+
+    #[test]
+    fn test_descriptions_attach_to_types() {
+        let chunks = chunk_graphql(SCHEMA, ChunkOptions::default());
+        let user = find(&chunks, "User");
+        assert!(user.content.starts_with("\"\"\"\na registered user"));
+        assert_eq!(user.start_line, 3);
+        assert_eq!(user.end_line, 10);
+
+        assert!(find(&chunks, "Node").content.starts_with("# anything"));
+        assert!(find(&chunks, "Post").content.starts_with("\"a blog post\""));
+    }
+
+    #[test]
+    fn test_braces_in_strings_ignored() {
+        let chunks = chunk_graphql(SCHEMA, ChunkOptions::default());
+        assert!(find(&chunks, "Post").content.contains("author: User"));
+        assert!(!find(&chunks, "Post").content.contains("ADMIN"));
+    }
+
+    #[test]
+    fn test_operations_and_fragments() {
+        let doc = "query GetUser($id: ID!) {\n  user(id: $id) { ...UserFields }\n}\n\nfragment UserFields on User {\n  name\n}\n";
+        let chunks = chunk_graphql(doc, ChunkOptions::default());
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].symbol.as_deref(), Some("GetUser"));
+        assert_eq!(
+            chunks[0].symbol_kind.as_deref(),
+            Some("operation_definition")
+        );
+        assert_eq!(chunks[1].symbol.as_deref(), Some("UserFields"));
+    }
+}
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use srag_common::types::{Chunk, Language};
+
+use super::{
+    assemble, block_end, doc_start, identifier_after, strip_strings, ChunkOptions, Section,
+};
+
+const KINDS: &[&str] = &["message", "service", "enum"];
+
+/// split a .proto file into one chunk per top-level message, service or
+/// enum. nested messages stay inside their parent. the syntax, package,
+/// import and option lines above them are line-chunked.
+pub fn chunk_proto(text: &str, opts: ChunkOptions) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut sections = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some((kind, name)) = parse_header(lines[i]) else {
+            i += 1;
+            continue;
+        };
+
+        let end = block_end(&lines, i, code);
+        sections.push(Section {
+            start: doc_start(&lines, i, |l| l.starts_with("//")),
+            end,
+            symbol: name.to_string(),
+            kind,
+        });
+        i = end;
+    }
+
+    assemble(&lines, sections, Language::Protobuf, opts)
+}
+
+fn parse_header(line: &str) -> Option<(&'static str, &str)> {
+    let trimmed = line.trim_start();
+    KINDS.iter().find_map(|kind| {
+        let rest = trimmed.strip_prefix(kind)?;
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        identifier_after(rest).map(|name| (*kind, name))
+    })
+}
+
+fn code(line: &str) -> String {
+    strip_strings(line, "//")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTO: &str = r#"syntax = "proto3";
+
+package billing.v1;
+
+import "google/protobuf/timestamp.proto";
+
+// an invoice sent to a customer
+message Invoice {
+  string id = 1;
+  repeated LineItem items = 2;
+
+  message LineItem {
+    string sku = 1;
+    int64 amount_cents = 2;
+  }
+}
+
+enum InvoiceState {
+  INVOICE_STATE_UNSPECIFIED = 0;
+  INVOICE_STATE_PAID = 1;
+}
+
+service BillingService {
+  rpc GetInvoice(GetInvoiceRequest) returns (Invoice) {
+    option (google.api.http) = { get: "/v1/invoices/{id}" };
+  }
+  rpc ListInvoices(ListInvoicesRequest) returns (stream Invoice);
+}
+"#;
+
+    fn symbols(chunks: &[Chunk]) -> Vec<(Option<&str>, Option<&str>)> {
+        chunks
+            .iter()
+            .map(|c| (c.symbol.as_deref(), c.symbol_kind.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_top_level_definitions_become_chunks() {
+        let chunks = chunk_proto(PROTO, ChunkOptions::default());
+        let named: Vec<_> = symbols(&chunks)
+            .into_iter()
+            .filter(|(s, _)| s.is_some())
+            .collect();
+        assert_eq!(
+            named,
+            vec![
+                (Some("Invoice"), Some("message")),
+                (Some("InvoiceState"), Some("enum")),
+                (Some("BillingService"), Some("service")),
+            ]
+        );
+        assert!(chunks.iter().all(|c| c.language == Language::Protobuf));
+    }
+
+    #[test]
+    fn test_nested_message_stays_in_parent() {
+        let chunks = chunk_proto(PROTO, ChunkOptions::default());
+        let invoice = chunks
+            .iter()
+            .find(|c| c.symbol.as_deref() == Some("Invoice"))
+            .unwrap();
+        assert!(invoice.content.starts_with("// an invoice"));
+        assert!(invoice.content.contains("message LineItem"));
+        assert!(invoice.content.trim_end().ends_with('}'));
+        assert_eq!(invoice.start_line, 7);
+        assert_eq!(invoice.end_line, 16);
+    }
+
+    #[test]
+    fn test_braces_in_strings_ignored() {
+        let chunks = chunk_proto(PROTO, ChunkOptions::default());
+        let service = chunks
+            .iter()
+            .find(|c| c.symbol.as_deref() == Some("BillingService"))
+            .unwrap();
+        assert!(service.content.contains("ListInvoices"));
+    }
+
+    #[test]
+    fn test_preamble_is_kept() {
+        let chunks = chunk_proto(PROTO, ChunkOptions::default());
+        assert!(chunks[0].symbol.is_none());
+        assert!(chunks[0].content.contains("package billing.v1;"));
+    }
+
+    #[test]
+    fn test_no_definitions() {
+        assert!(chunk_proto(
+            "syntax = \"proto3\";\npackage empty;\n",
+            ChunkOptions::default()
+        )
+        .is_empty());
+    }
+}
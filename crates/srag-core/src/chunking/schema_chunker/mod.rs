@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+mod chunker_graphql;
+mod chunker_proto;
+
+pub use chunker_graphql::chunk_graphql;
+pub use chunker_proto::chunk_proto;
+
+// line parsers for now. kinds use the node names of the graphql and
+// protobuf tree-sitter grammars, so switching to those keeps stored kinds
+// the same.
+use super::sections::{assemble, Section};
+use super::ChunkOptions;
+
+/// find where a brace-delimited block opened at or after `start` closes.
+/// `code` strips comments and string literals from a line. a header with
+/// no `{` before the next blank line is treated as a one-line definition.
+fn block_end(lines: &[&str], start: usize, code: fn(&str) -> String) -> usize {
+    let mut depth = 0i32;
+    let mut opened = false;
+
+    for (j, line) in lines.iter().enumerate().skip(start) {
+        if !opened && j > start && line.trim().is_empty() {
+            return start + 1;
+        }
+        for c in code(line).chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if opened && depth <= 0 {
+            return j + 1;
+        }
+    }
+
+    if opened {
+        lines.len()
+    } else {
+        start + 1
+    }
+}
+
+/// walk back from a definition over the comment lines directly above it so
+/// doc comments stay with the block they describe.
+fn doc_start(lines: &[&str], start: usize, is_doc: impl Fn(&str) -> bool) -> usize {
+    let mut i = start;
+    while i > 0 && is_doc(lines[i - 1].trim()) {
+        i -= 1;
+    }
+    i
+}
+
+/// drop double-quoted strings and everything after `comment` on a line
+fn strip_strings(line: &str, comment: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if in_string {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => in_string = false,
+                _ => escaped = false,
+            }
+            continue;
+        }
+        if line[i..].starts_with(comment) {
+            break;
+        }
+        if c == '"' {
+            in_string = true;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// the identifier following a keyword, stopping at the first delimiter
+fn identifier_after(rest: &str) -> Option<&str> {
+    let name = rest
+        .trim_start()
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()?;
+    (!name.is_empty()).then_some(name)
+}
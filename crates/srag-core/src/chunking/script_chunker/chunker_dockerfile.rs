@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use srag_common::types::{Chunk, Language};
+
+use super::{assemble, ChunkOptions, Section};
+
+/// split a dockerfile into one chunk per build stage. anything before the
+/// first FROM (global ARGs, comments) belongs to the first stage.
+pub fn chunk_dockerfile(text: &str, opts: ChunkOptions) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+
+    let stage_starts: Vec<(usize, String)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| parse_from(line).map(|name| (i, name)))
+        .collect();
+
+    let mut sections = Vec::new();
+    for (idx, (line_idx, name)) in stage_starts.iter().enumerate() {
+        let start = if idx == 0 { 0 } else { *line_idx };
+        let end = stage_starts
+            .get(idx + 1)
+            .map(|(next, _)| *next)
+            .unwrap_or(lines.len());
+        sections.push(Section {
+            start,
+            end,
+            symbol: name.clone(),
+            kind: "stage",
+        });
+    }
+
+    assemble(&lines, sections, Language::Dockerfile, opts)
+}
+
+/// `FROM image [AS name]` -> the stage name, or the image when unnamed.
+fn parse_from(line: &str) -> Option<String> {
+    let mut parts = line.split_whitespace();
+    if !parts.next()?.eq_ignore_ascii_case("FROM") {
+        return None;
+    }
+
+    let mut image = None;
+    while let Some(part) = parts.next() {
+        if part.starts_with("--") {
+            continue;
+        }
+        if part.eq_ignore_ascii_case("AS") {
+            return parts.next().map(str::to_string);
+        }
+        if image.is_none() {
+            image = Some(part.to_string());
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MULTI_STAGE: &str = "\
+ARG RUST_VERSION=1.75
+FROM rust:${RUST_VERSION} AS builder
+WORKDIR /app
+COPY . .
+RUN cargo build --release
+
+FROM --platform=linux/amd64 debian:bookworm-slim AS runtime
+COPY --from=builder /app/target/release/srag /usr/local/bin/srag
+ENTRYPOINT [\"srag\"]
+";
+
+    #[test]
+    fn test_multi_stage() {
+        let chunks = chunk_dockerfile(MULTI_STAGE, ChunkOptions::default());
+        assert_eq!(chunks.len(), 2);
+
+        assert_eq!(chunks[0].symbol.as_deref(), Some("builder"));
+        assert_eq!(chunks[0].symbol_kind.as_deref(), Some("stage"));
+        assert_eq!(chunks[0].start_line, 1);
+        assert!(chunks[0].content.contains("ARG RUST_VERSION"));
+        assert!(chunks[0].content.contains("cargo build"));
+
+        assert_eq!(chunks[1].symbol.as_deref(), Some("runtime"));
+        assert_eq!(chunks[1].start_line, 7);
+        assert!(chunks[1].content.contains("ENTRYPOINT"));
+        assert!(chunks.iter().all(|c| c.language == Language::Dockerfile));
+    }
+
+    #[test]
+    fn test_unnamed_stage_uses_image() {
+        let chunks = chunk_dockerfile(
+            "from alpine:3.19\nRUN apk add curl\n",
+            ChunkOptions::default(),
+        );
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].symbol.as_deref(), Some("alpine:3.19"));
+    }
+
+    #[test]
+    fn test_no_from_returns_empty() {
+        assert!(chunk_dockerfile("# just a comment\n", ChunkOptions::default()).is_empty());
+    }
+}
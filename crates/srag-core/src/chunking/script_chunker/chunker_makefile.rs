@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use srag_common::types::{Chunk, Language};
+
+use super::{assemble, ChunkOptions, Section};
+
+/// split a makefile into one chunk per rule. a rule runs from its target line
+/// to the next target; variables before the first rule are line-chunked.
+pub fn chunk_makefile(text: &str, opts: ChunkOptions) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+
+    let targets: Vec<(usize, String)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| parse_target(line).map(|name| (i, name)))
+        .collect();
+
+    let sections = targets
+        .iter()
+        .enumerate()
+        .map(|(idx, (start, name))| Section {
+            start: *start,
+            end: targets
+                .get(idx + 1)
+                .map(|(next, _)| *next)
+                .unwrap_or(lines.len()),
+            symbol: name.clone(),
+            kind: "target",
+        })
+        .collect();
+
+    assemble(&lines, sections, Language::Make, opts)
+}
+
+/// `name [name...]: [deps]` at column 0. skips recipes, variable assignments
+/// and special targets like `.PHONY`.
+fn parse_target(line: &str) -> Option<String> {
+    if line.starts_with(['\t', ' ', '#', '.']) {
+        return None;
+    }
+
+    let colon = line.find(':')?;
+    let rest = &line[colon + 1..];
+    if rest.starts_with('=') || rest.starts_with(":=") {
+        return None;
+    }
+
+    let name = line[..colon].trim();
+    if name.is_empty() || name.contains('=') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAKEFILE: &str = "\
+CARGO ?= cargo
+PREFIX := /usr/local
+
+.PHONY: build test install
+
+build:
+\t$(CARGO) build --release
+
+test: build
+\t$(CARGO) test --workspace
+
+install: build
+\tinstall -m 755 target/release/srag $(PREFIX)/bin/srag
+";
+
+    #[test]
+    fn test_three_targets() {
+        let chunks = chunk_makefile(MAKEFILE, ChunkOptions::default());
+        let targets: Vec<_> = chunks
+            .iter()
+            .filter(|c| c.symbol_kind.as_deref() == Some("target"))
+            .collect();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[0].symbol.as_deref(), Some("build"));
+        assert_eq!(targets[1].symbol.as_deref(), Some("test"));
+        assert_eq!(targets[2].symbol.as_deref(), Some("install"));
+
+        assert!(targets[1].content.contains("$(CARGO) test"));
+        assert!(!targets[1].content.contains("install -m"));
+        assert_eq!(targets[2].start_line, 12);
+    }
+
+    #[test]
+    fn test_variables_kept_as_preamble() {
+        let chunks = chunk_makefile(MAKEFILE, ChunkOptions::default());
+        assert!(chunks[0].symbol.is_none());
+        assert!(chunks[0].content.contains("PREFIX := /usr/local"));
+        assert!(chunks.iter().all(|c| c.language == Language::Make));
+    }
+
+    #[test]
+    fn test_assignments_are_not_targets() {
+        assert!(parse_target("CC := gcc").is_none());
+        assert!(parse_target("CC ::= gcc").is_none());
+        assert!(parse_target("\techo a:b").is_none());
+        assert!(parse_target(".PHONY: all").is_none());
+        assert_eq!(parse_target("all: build").as_deref(), Some("all"));
+    }
+}
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use srag_common::types::{Chunk, Language};
+
+use super::{assemble, ChunkOptions, Section};
+
+/// split a shell script into one chunk per function. top-level code between
+/// functions is line-chunked.
+pub fn chunk_shell_script(text: &str, opts: ChunkOptions) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut sections = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some(name) = parse_function_header(lines[i]) else {
+            i += 1;
+            continue;
+        };
+
+        let mut depth = 0i32;
+        let mut opened = false;
+        let mut end = lines.len();
+        for (j, line) in lines.iter().enumerate().skip(i) {
+            for c in strip_comment(line).chars() {
+                match c {
+                    '{' => {
+                        depth += 1;
+                        opened = true;
+                    }
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            if opened && depth <= 0 {
+                end = j + 1;
+                break;
+            }
+        }
+
+        sections.push(Section {
+            start: i,
+            end,
+            symbol: name,
+            kind: "function",
+        });
+        i = end;
+    }
+
+    assemble(&lines, sections, Language::Shell, opts)
+}
+
+/// `name() {`, `name () {` or `function name [()] {`
+fn parse_function_header(line: &str) -> Option<String> {
+    let trimmed = line.trim_start();
+
+    let name = if let Some(rest) = trimmed.strip_prefix("function ") {
+        rest.trim_start()
+            .split(|c: char| c.is_whitespace() || c == '(' || c == '{')
+            .next()?
+    } else {
+        let paren = trimmed.find("()")?;
+        let name = trimmed[..paren].trim_end();
+        if !trimmed[paren + 2..].trim_start().starts_with('{') {
+            return None;
+        }
+        name
+    };
+
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.'));
+    valid.then(|| name.to_string())
+}
+
+fn strip_comment(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') {
+        return "";
+    }
+    match line.find(" #") {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "\
+#!/usr/bin/env bash
+set -euo pipefail
+
+log() {
+    echo \"[$(date)] $*\" >&2
+}
+
+function install_deps {
+    if [ -f requirements.txt ]; then
+        pip install -r requirements.txt
+    fi
+}
+
+main () {
+    log \"starting\"
+    install_deps
+}
+
+main \"$@\"
+";
+
+    #[test]
+    fn test_functions() {
+        let chunks = chunk_shell_script(SCRIPT, ChunkOptions::default());
+        let functions: Vec<_> = chunks
+            .iter()
+            .filter(|c| c.symbol_kind.as_deref() == Some("function"))
+            .collect();
+        assert_eq!(functions.len(), 3);
+        assert_eq!(functions[0].symbol.as_deref(), Some("log"));
+        assert_eq!(functions[0].start_line, 4);
+        assert_eq!(functions[0].end_line, 6);
+        assert_eq!(functions[1].symbol.as_deref(), Some("install_deps"));
+        assert!(functions[1].content.ends_with("}"));
+        assert_eq!(functions[2].symbol.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn test_top_level_code_kept() {
+        let chunks = chunk_shell_script(SCRIPT, ChunkOptions::default());
+        assert!(chunks
+            .iter()
+            .any(|c| c.content.contains("set -euo pipefail")));
+        assert!(chunks.iter().any(|c| c.content.contains("main \"$@\"")));
+    }
+
+    #[test]
+    fn test_no_functions_returns_empty() {
+        assert!(chunk_shell_script("echo hello\nls -la\n", ChunkOptions::default()).is_empty());
+    }
+}
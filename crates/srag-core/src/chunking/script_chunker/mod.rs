@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+mod chunker_dockerfile;
+mod chunker_makefile;
+mod chunker_shell;
+
+pub use chunker_dockerfile::chunk_dockerfile;
+pub use chunker_makefile::chunk_makefile;
+pub use chunker_shell::chunk_shell_script;
+
+use super::sections::{assemble, Section};
+use super::ChunkOptions;
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use srag_common::types::{Chunk, Language};
+
+use super::{line_chunker, ChunkOptions};
+
+/// a named region of a file, as 0-indexed line range [start, end).
+pub(super) struct Section {
+    pub start: usize,
+    pub end: usize,
+    pub symbol: String,
+    pub kind: &'static str,
+}
+
+/// turn sections into chunks, line-chunking any unclaimed lines between them
+/// so nothing in the file is dropped. returns nothing when no sections were
+/// found so the caller can fall back to plain line chunking. the gaps use
+/// the configured fallback window.
+pub(super) fn assemble(
+    lines: &[&str],
+    sections: Vec<Section>,
+    language: Language,
+    opts: ChunkOptions,
+) -> Vec<Chunk> {
+    if sections.is_empty() {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    let mut cursor = 0;
+
+    for section in sections {
+        push_gap(lines, cursor, section.start, language, opts, &mut chunks);
+        let content = lines[section.start..section.end].join("\n");
+        if !content.trim().is_empty() {
+            chunks.push(Chunk {
+                id: None,
+                file_id: 0,
+                content,
+                symbol: Some(section.symbol),
+                symbol_kind: Some(section.kind.to_string()),
+                start_line: (section.start + 1) as u32,
+                end_line: section.end as u32,
+                language,
+                suspicious: false,
+            });
+        }
+        cursor = section.end;
+    }
+    push_gap(lines, cursor, lines.len(), language, opts, &mut chunks);
+
+    chunks
+}
+
+fn push_gap(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    language: Language,
+    opts: ChunkOptions,
+    out: &mut Vec<Chunk>,
+) {
+    if start >= end {
+        return;
+    }
+    let text = lines[start..end].join("\n");
+    if text.trim().is_empty() {
+        return;
+    }
+    if let Ok(gap_chunks) = line_chunker::chunk_by_line_window(
+        &text,
+        language,
+        opts.fallback_chunk_lines,
+        opts.fallback_overlap_lines,
+    ) {
+        for mut chunk in gap_chunks {
+            chunk.start_line += start as u32;
+            chunk.end_line += start as u32;
+            out.push(chunk);
+        }
+    }
+}
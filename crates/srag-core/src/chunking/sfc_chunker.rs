@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use srag_common::types::{Chunk, Language};
+
+use super::{line_chunker, tree_sitter_chunker, ChunkOptions};
+
+/// a top-level `<template>`, `<script>` or `<style>` block of a component,
+/// as byte offsets into the file
+struct Block {
+    tag: &'static str,
+    /// start of the line holding the opening tag
+    start: usize,
+    /// end of the line holding the closing tag
+    end: usize,
+    /// what sits between the tags
+    body_start: usize,
+    body_end: usize,
+    open_tag: String,
+}
+
+const BLOCK_TAGS: [&str; 3] = ["template", "script", "style"];
+
+/// split a vue or svelte single-file component into its blocks. scripts go
+/// through the js/ts grammar so their functions and methods get their own
+/// chunks; templates and styles become one chunk each. anything else at
+/// the top level (svelte markup, vue custom blocks) is kept too.
+pub fn chunk_sfc(text: &str, language: Language, opts: ChunkOptions) -> Vec<Chunk> {
+    let blocks = find_blocks(text);
+    if blocks.is_empty() {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    let mut cursor = 0;
+    for block in blocks {
+        push_gap(text, cursor, block.start, language, opts, &mut chunks);
+        match block.tag {
+            "script" => chunk_script(text, &block, language, opts, &mut chunks),
+            tag => chunks.extend(block_chunk(text, block.start, block.end, tag, language)),
+        }
+        cursor = block.end;
+    }
+    push_gap(text, cursor, text.len(), language, opts, &mut chunks);
+
+    chunks
+}
+
+fn chunk_script(
+    text: &str,
+    block: &Block,
+    language: Language,
+    opts: ChunkOptions,
+    chunks: &mut Vec<Chunk>,
+) {
+    let body = &text[block.body_start..block.body_end];
+    let script_language = if is_typescript(&block.open_tag) {
+        Language::TypeScript
+    } else {
+        Language::JavaScript
+    };
+    let line_offset = line_of(text, block.body_start) as u32;
+
+    let nested = if body.trim().is_empty() {
+        Vec::new()
+    } else {
+        tree_sitter_chunker::chunk_with_tree_sitter_opts(body, script_language, opts)
+            .unwrap_or_default()
+    };
+    if nested.is_empty() {
+        chunks.extend(block_chunk(
+            text,
+            block.start,
+            block.end,
+            "script",
+            language,
+        ));
+        return;
+    }
+    for mut chunk in nested {
+        chunk.start_line += line_offset;
+        chunk.end_line += line_offset;
+        chunk.language = language;
+        chunks.push(chunk);
+    }
+}
+
+/// `lang="ts"` or `lang="typescript"` on the opening tag
+fn is_typescript(open_tag: &str) -> bool {
+    let compact: String = open_tag
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    [
+        "lang=\"ts\"",
+        "lang='ts'",
+        "lang=\"typescript\"",
+        "lang='typescript'",
+    ]
+    .iter()
+    .any(|attr| compact.contains(attr))
+}
+
+fn block_chunk(
+    text: &str,
+    start: usize,
+    end: usize,
+    tag: &str,
+    language: Language,
+) -> Option<Chunk> {
+    if text[start..end].trim().is_empty() {
+        return None;
+    }
+    // skip the blank lines that usually separate blocks
+    let leading = text[start..end].len() - text[start..end].trim_start().len();
+    let start = start
+        + text[start..start + leading]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+    let content = text[start..end].trim_end();
+    let start_line = line_of(text, start) + 1;
+    Some(Chunk {
+        id: None,
+        file_id: 0,
+        content: content.to_string(),
+        symbol: Some(tag.to_string()),
+        symbol_kind: Some(tag.to_string()),
+        start_line: start_line as u32,
+        end_line: (start_line + content.matches('\n').count()) as u32,
+        language,
+        suspicious: false,
+    })
+}
+
+/// text outside the blocks. in svelte that is the markup, so it is kept as a
+/// template; vue only has comments and custom blocks there.
+fn push_gap(
+    text: &str,
+    start: usize,
+    end: usize,
+    language: Language,
+    opts: ChunkOptions,
+    out: &mut Vec<Chunk>,
+) {
+    if start >= end || text[start..end].trim().is_empty() {
+        return;
+    }
+    if language == Language::Svelte {
+        out.extend(block_chunk(text, start, end, "template", language));
+        return;
+    }
+    let offset = line_of(text, start) as u32;
+    if let Ok(gap_chunks) = line_chunker::chunk_by_line_window(
+        &text[start..end],
+        language,
+        opts.fallback_chunk_lines,
+        opts.fallback_overlap_lines,
+    ) {
+        for mut chunk in gap_chunks {
+            chunk.start_line += offset;
+            chunk.end_line += offset;
+            out.push(chunk);
+        }
+    }
+}
+
+/// 0-indexed line number of a byte offset
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count()
+}
+
+fn find_blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut line_start = 0;
+    while line_start < text.len() {
+        let line_end = text[line_start..]
+            .find('\n')
+            .map_or(text.len(), |i| line_start + i + 1);
+        let line = &text[line_start..line_end];
+        let indent = line.len() - line.trim_start().len();
+        let open = line_start + indent;
+
+        match BLOCK_TAGS
+            .iter()
+            .find(|tag| opens_tag(&text[open..], tag))
+            .and_then(|tag| find_block(text, line_start, open, tag))
+        {
+            Some(block) => {
+                line_start = block.end;
+                blocks.push(block);
+            }
+            None => line_start = line_end,
+        }
+    }
+    blocks
+}
+
+/// `<tag` followed by the end of the tag name
+fn opens_tag(s: &str, tag: &str) -> bool {
+    s.strip_prefix('<')
+        .and_then(|rest| rest.strip_prefix(tag))
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(|c| c == '>' || c == '/' || c.is_whitespace())
+}
+
+fn find_block(text: &str, line_start: usize, open: usize, tag: &'static str) -> Option<Block> {
+    let body_start = open + text[open..].find('>')? + 1;
+    let open_tag = text[open..body_start].to_string();
+    let close = format!("</{}", tag);
+
+    let body_end = if open_tag.ends_with("/>") {
+        body_start
+    } else if tag == "template" {
+        // vue templates nest <template> for slots and v-if groups
+        let mut depth = 1;
+        let mut pos = body_start;
+        loop {
+            let next_close = pos + text[pos..].find(&close)?;
+            let next_open = text[pos..next_close]
+                .match_indices("<template")
+                .map(|(i, _)| pos + i)
+                .find(|&i| opens_tag(&text[i..], tag));
+            match next_open {
+                Some(i) => {
+                    depth += 1;
+                    pos = i + 1;
+                }
+                None => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break next_close;
+                    }
+                    pos = next_close + close.len();
+                }
+            }
+        }
+    } else {
+        body_start + text[body_start..].find(&close)?
+    };
+
+    let end = text[body_end..]
+        .find('\n')
+        .map_or(text.len(), |i| body_end + i + 1);
+    Some(Block {
+        tag,
+        start: line_start,
+        end,
+        body_start,
+        body_end,
+        open_tag,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VUE: &str = r#"<template>
+  <div class="counter">
+    <template v-if="count > 0">
+      <span>{{ count }}</span>
+    </template>
+    <button @click="increment">add</button>
+  </div>
+</template>
+
+<script lang="ts">
+export default {
+  data() {
+    return { count: 0 };
+  },
+  methods: {
+    increment() {
+      this.count += 1;
+      this.$emit("changed", this.count);
+    },
+    reset() {
+      this.count = 0;
+      this.$emit("changed", this.count);
+    },
+  },
+};
+</script>
+
+<style scoped>
+.counter {
+  display: flex;
+}
+</style>
+"#;
+
+    fn find<'a>(chunks: &'a [Chunk], symbol: &str) -> &'a Chunk {
+        chunks
+            .iter()
+            .find(|c| c.symbol.as_deref() == Some(symbol))
+            .unwrap_or_else(|| panic!("no chunk for {}", symbol))
+    }
+
+    #[test]
+    fn test_vue_methods_and_template() {
+        let chunks = chunk_sfc(VUE, Language::Vue, ChunkOptions::default());
+
+        let increment = find(&chunks, "increment");
+        assert_eq!(increment.symbol_kind.as_deref(), Some("method_definition"));
+        assert_eq!((increment.start_line, increment.end_line), (16, 19));
+        assert_eq!(increment.language, Language::Vue);
+        let reset = find(&chunks, "reset");
+        assert_eq!((reset.start_line, reset.end_line), (20, 23));
+
+        // the nested slot template stays inside the outer one
+        let template = find(&chunks, "template");
+        assert_eq!(template.symbol_kind.as_deref(), Some("template"));
+        assert_eq!((template.start_line, template.end_line), (1, 8));
+        assert!(template.content.ends_with("</template>"));
+
+        let style = find(&chunks, "style");
+        assert_eq!((style.start_line, style.end_line), (28, 32));
+    }
+
+    #[test]
+    fn test_svelte_markup_is_a_template() {
+        let svelte = "<script>\n  let name = 'world';\n\n  function greet() {\n    alert(`hello ${name}, from a svelte component`);\n  }\n</script>\n\n<h1>Hello {name}!</h1>\n<button on:click={greet}>greet</button>\n";
+        let chunks = chunk_sfc(svelte, Language::Svelte, ChunkOptions::default());
+
+        let greet = find(&chunks, "greet");
+        assert_eq!(greet.start_line, 4);
+        let markup = find(&chunks, "template");
+        assert_eq!((markup.start_line, markup.end_line), (9, 10));
+        assert!(markup.content.contains("<h1>"));
+    }
+
+    #[test]
+    fn test_script_without_definitions_is_one_chunk() {
+        let chunks = chunk_sfc(
+            "<script setup>\nimport Child from './Child.vue'\n</script>\n",
+            Language::Vue,
+            ChunkOptions::default(),
+        );
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].symbol_kind.as_deref(), Some("script"));
+    }
+
+    #[test]
+    fn test_no_blocks_yields_nothing() {
+        assert!(chunk_sfc("just text\n", Language::Vue, ChunkOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_is_typescript() {
+        assert!(is_typescript("<script lang=\"ts\">"));
+        assert!(is_typescript("<script setup lang='TS'>"));
+        assert!(!is_typescript("<script>"));
+    }
+}
@@ -5,10 +5,19 @@ use srag_common::types::{Chunk, Language};
 use srag_common::Result;
 use tree_sitter::Parser;
 
-/// minimum chunk size in characters to filter out trivial chunks
-const MIN_CHUNK_SIZE: usize = 50;
+use super::ChunkOptions;
 
 pub fn chunk_with_tree_sitter(text: &str, language: Language) -> Result<Vec<Chunk>> {
+    chunk_with_tree_sitter_opts(text, language, ChunkOptions::default())
+}
+
+/// `include_doc_comments` extends each chunk up over the comments directly
+/// above it, so documented items are found by what their docs say
+pub fn chunk_with_tree_sitter_opts(
+    text: &str,
+    language: Language,
+    opts: ChunkOptions,
+) -> Result<Vec<Chunk>> {
     let Some(ts_language) = get_tree_sitter_language(language) else {
         return Err(srag_common::Error::Chunking(format!(
             "no tree-sitter grammar for {:?}",
@@ -29,47 +38,152 @@ pub fn chunk_with_tree_sitter(text: &str, language: Language) -> Result<Vec<Chun
     let root = tree.root_node();
     let node_kinds = extractable_kinds(language);
 
-    collect_nodes(root, text, language, &node_kinds, &mut chunks);
+    match language {
+        Language::Html => collect_html_sections(root, text, opts.min_chunk_size, &mut chunks),
+        _ => collect_nodes(root, text, language, &node_kinds, opts, true, &mut chunks),
+    }
 
     // if we didn't find any extractable nodes, fall back to root-level children
     if chunks.is_empty() {
-        collect_top_level_chunks(root, text, language, &mut chunks);
+        collect_top_level_chunks(root, text, language, opts.min_chunk_size, &mut chunks);
     }
 
     Ok(chunks)
 }
 
+/// `top_level` is true until the walk enters an extracted node. named
+/// definitions there are kept however small, so a one-line const can still
+/// be found by name; everything else has to reach `min_chunk_size`.
 fn collect_nodes(
     node: tree_sitter::Node,
     source: &str,
     language: Language,
     kinds: &[&str],
+    opts: ChunkOptions,
+    top_level: bool,
     chunks: &mut Vec<Chunk>,
 ) {
-    if kinds.contains(&node.kind()) {
-        let start = node.start_position();
-        let end = node.end_position();
-        let content = &source[node.start_byte()..node.end_byte()];
-
-        if !content.trim().is_empty() && content.len() >= MIN_CHUNK_SIZE {
-            let symbol = extract_symbol_name(node, source);
-            chunks.push(Chunk {
-                id: None,
-                file_id: 0,
-                content: content.to_string(),
-                symbol,
-                symbol_kind: Some(node.kind().to_string()),
-                start_line: (start.row + 1) as u32,
-                end_line: (end.row + 1) as u32,
-                language,
-                suspicious: false,
-            });
+    let extracted = kinds.contains(&node.kind());
+    if extracted {
+        let (symbol, kind) = match language {
+            Language::Css => (css_selector(node, source), "rule"),
+            _ => (extract_symbol_name(node, source), node.kind()),
+        };
+        let first = if opts.include_doc_comments {
+            leading_comments_start(node, source)
+        } else {
+            node
+        };
+        let min_size = if top_level && symbol.is_some() {
+            0
+        } else {
+            opts.min_chunk_size
+        };
+        chunks.extend(node_chunk(
+            first, node, source, language, symbol, kind, min_size,
+        ));
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_nodes(
+            child,
+            source,
+            language,
+            kinds,
+            opts,
+            top_level && !extracted,
+            chunks,
+        );
+    }
+}
+
+/// the first of the comment siblings directly above `node`, or `node` when
+/// there are none. rust attributes between the docs and the item are kept
+/// too. a blank line or a trailing comment on a code line ends the run.
+fn leading_comments_start<'a>(node: tree_sitter::Node<'a>, source: &str) -> tree_sitter::Node<'a> {
+    let mut first = node;
+    while let Some(prev) = first.prev_sibling() {
+        if !prev.kind().contains("comment") && prev.kind() != "attribute_item" {
+            break;
+        }
+        // line comments can end at column 0 of the next line
+        let end = prev.end_position();
+        let end_row = if end.column == 0 {
+            end.row.saturating_sub(1)
+        } else {
+            end.row
+        };
+        if end_row + 1 < first.start_position().row {
+            break;
+        }
+        let line_start = source[..prev.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+        if !source[line_start..prev.start_byte()].trim().is_empty() {
+            break;
         }
+        first = prev;
     }
+    first
+}
 
+/// `text` without its comments, going by the grammar's comment nodes. a line
+/// left holding nothing but a comment is dropped whole. text with no grammar,
+/// or that fails to parse, comes back as it is.
+pub fn strip_comments(text: &str, language: Language) -> String {
+    let Some(ts_language) = get_tree_sitter_language(language) else {
+        return text.to_string();
+    };
+    let mut parser = Parser::new();
+    if parser.set_language(&ts_language).is_err() {
+        return text.to_string();
+    }
+    let Some(tree) = parser.parse(text, None) else {
+        return text.to_string();
+    };
+
+    let mut comments = Vec::new();
+    collect_comment_ranges(tree.root_node(), &mut comments);
+    if comments.is_empty() {
+        return text.to_string();
+    }
+
+    let mut stripped = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, end) in comments {
+        // line comments can take the newline after them
+        let end = if text[start..end].ends_with('\n') {
+            end - 1
+        } else {
+            end
+        };
+        let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+        let (cut_start, cut_end) =
+            if text[line_start..start].trim().is_empty() && text[end..line_end].trim().is_empty() {
+                (line_start, (line_end + 1).min(text.len()))
+            } else {
+                // keep the code before a trailing comment, minus the gap
+                (line_start + text[line_start..start].trim_end().len(), end)
+            };
+        let cut_start = cut_start.max(copied);
+        if cut_start >= cut_end {
+            continue;
+        }
+        stripped.push_str(&text[copied..cut_start]);
+        copied = cut_end;
+    }
+    stripped.push_str(&text[copied..]);
+    stripped
+}
+
+fn collect_comment_ranges(node: tree_sitter::Node, ranges: &mut Vec<(usize, usize)>) {
+    if node.kind().contains("comment") {
+        ranges.push((node.start_byte(), node.end_byte()));
+        return;
+    }
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
-        collect_nodes(child, source, language, kinds, chunks);
+        collect_comment_ranges(child, ranges);
     }
 }
 
@@ -77,30 +191,150 @@ fn collect_top_level_chunks(
     root: tree_sitter::Node,
     source: &str,
     language: Language,
+    min_size: usize,
     chunks: &mut Vec<Chunk>,
 ) {
     let mut cursor = root.walk();
     for child in root.children(&mut cursor) {
-        let start = child.start_position();
-        let end = child.end_position();
-        let content = &source[child.start_byte()..child.end_byte()];
-
-        if !content.trim().is_empty() && content.len() >= MIN_CHUNK_SIZE {
-            chunks.push(Chunk {
-                id: None,
-                file_id: 0,
-                content: content.to_string(),
-                symbol: None,
-                symbol_kind: Some(child.kind().to_string()),
-                start_line: (start.row + 1) as u32,
-                end_line: (end.row + 1) as u32,
-                language,
-                suspicious: false,
-            });
+        chunks.extend(node_chunk(
+            child,
+            child,
+            source,
+            language,
+            None,
+            child.kind(),
+            min_size,
+        ));
+    }
+}
+
+/// a chunk from `first` through `node`, unless it's shorter than `min_size`
+/// characters
+fn node_chunk(
+    first: tree_sitter::Node,
+    node: tree_sitter::Node,
+    source: &str,
+    language: Language,
+    symbol: Option<String>,
+    kind: &str,
+    min_size: usize,
+) -> Option<Chunk> {
+    let content = &source[first.start_byte()..node.end_byte()];
+    if content.trim().is_empty() || content.len() < min_size {
+        return None;
+    }
+    Some(Chunk {
+        id: None,
+        file_id: 0,
+        content: content.to_string(),
+        symbol,
+        symbol_kind: Some(kind.to_string()),
+        start_line: (first.start_position().row + 1) as u32,
+        end_line: (node.end_position().row + 1) as u32,
+        language,
+        suspicious: false,
+    })
+}
+
+/// elements under the document wrappers. html nests deeply, so descending
+/// into every element would produce thousands of tiny chunks.
+fn collect_html_sections(
+    node: tree_sitter::Node,
+    source: &str,
+    min_size: usize,
+    chunks: &mut Vec<Chunk>,
+) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if !matches!(child.kind(), "element" | "script_element" | "style_element") {
+            continue;
+        }
+        let tag = html_tag_name(child, source);
+        if matches!(tag.as_deref(), Some("html" | "body" | "main")) {
+            collect_html_sections(child, source, min_size, chunks);
+            continue;
+        }
+        let symbol = tag.map(|tag| html_element_symbol(child, source, tag));
+        chunks.extend(node_chunk(
+            child,
+            child,
+            source,
+            Language::Html,
+            symbol,
+            "element",
+            min_size,
+        ));
+    }
+}
+
+fn html_start_tag(element: tree_sitter::Node) -> Option<tree_sitter::Node> {
+    let mut cursor = element.walk();
+    let tag = element
+        .children(&mut cursor)
+        .find(|c| c.kind() == "start_tag" || c.kind() == "self_closing_tag");
+    tag
+}
+
+fn html_tag_name(element: tree_sitter::Node, source: &str) -> Option<String> {
+    let start_tag = html_start_tag(element)?;
+    let mut cursor = start_tag.walk();
+    let name = start_tag
+        .children(&mut cursor)
+        .find(|c| c.kind() == "tag_name")
+        .map(|n| source[n.start_byte()..n.end_byte()].to_lowercase());
+    name
+}
+
+/// `tag#id`, else `tag.first-class`, else the bare tag
+fn html_element_symbol(element: tree_sitter::Node, source: &str, tag: String) -> String {
+    let Some(start_tag) = html_start_tag(element) else {
+        return tag;
+    };
+    let mut id = None;
+    let mut class = None;
+    let mut cursor = start_tag.walk();
+    for attr in start_tag.children(&mut cursor) {
+        if attr.kind() != "attribute" {
+            continue;
+        }
+        let mut attr_cursor = attr.walk();
+        let mut name = None;
+        let mut value = None;
+        for part in attr.children(&mut attr_cursor) {
+            match part.kind() {
+                "attribute_name" => name = Some(&source[part.start_byte()..part.end_byte()]),
+                "attribute_value" | "quoted_attribute_value" => {
+                    value = Some(
+                        source[part.start_byte()..part.end_byte()]
+                            .trim_matches(|c| c == '"' || c == '\''),
+                    )
+                }
+                _ => {}
+            }
         }
+        match (name.map(str::to_lowercase).as_deref(), value) {
+            (Some("id"), Some(v)) if !v.is_empty() => id = Some(v.to_string()),
+            (Some("class"), Some(v)) => class = v.split_whitespace().next().map(String::from),
+            _ => {}
+        }
+    }
+    match (id, class) {
+        (Some(id), _) => format!("{}#{}", tag, id),
+        (None, Some(class)) => format!("{}.{}", tag, class),
+        (None, None) => tag,
     }
 }
 
+/// the selector list of a css rule, whitespace collapsed
+fn css_selector(rule: tree_sitter::Node, source: &str) -> Option<String> {
+    let mut cursor = rule.walk();
+    let selectors = rule
+        .children(&mut cursor)
+        .find(|c| c.kind() == "selectors")?;
+    let text = &source[selectors.start_byte()..selectors.end_byte()];
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
 /// extract symbol name from a node, recursively searching for identifiers.
 /// this handles decorated definitions, export statements, and other nested structures.
 fn extract_symbol_name(node: tree_sitter::Node, source: &str) -> Option<String> {
@@ -117,6 +351,14 @@ fn extract_symbol_name_recursive(
         return None;
     }
 
+    // grammars that label the name field (c#, php, java) put return types
+    // before it, so prefer the field over the first identifier
+    if let Some(name) = node.child_by_field_name("name") {
+        if matches!(name.kind(), "identifier" | "name" | "type_identifier") {
+            return Some(source[name.start_byte()..name.end_byte()].to_string());
+        }
+    }
+
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
         match child.kind() {
@@ -171,6 +413,12 @@ pub fn get_tree_sitter_language(lang: Language) -> Option<tree_sitter::Language>
         Language::Cpp => Some(tree_sitter_cpp::LANGUAGE.into()),
         Language::Java => Some(tree_sitter_java::LANGUAGE.into()),
         Language::Ruby => Some(tree_sitter_ruby::LANGUAGE.into()),
+        Language::CSharp => Some(tree_sitter_c_sharp::LANGUAGE.into()),
+        // the php grammar accepts inline html around <?php blocks
+        Language::Php => Some(tree_sitter_php::LANGUAGE_PHP.into()),
+        Language::Html => Some(tree_sitter_html::LANGUAGE.into()),
+        // scss and less mostly parse as css; nesting shows up as error nodes
+        Language::Css => Some(tree_sitter_css::LANGUAGE.into()),
         _ => None,
     }
 }
@@ -222,6 +470,18 @@ fn extractable_kinds(lang: Language) -> Vec<&'static str> {
             "constructor_declaration",
         ],
         Language::Ruby => vec!["method", "class", "module", "singleton_method"],
+        Language::CSharp => vec![
+            "method_declaration",
+            "class_declaration",
+            "interface_declaration",
+            "struct_declaration",
+        ],
+        Language::Php => vec![
+            "function_definition",
+            "method_declaration",
+            "class_declaration",
+        ],
+        Language::Css => vec!["rule_set"],
         _ => vec![],
     }
 }
@@ -302,6 +562,52 @@ mod tests {
             .any(|c| c.symbol == Some("exportedFunc".to_string())));
     }
 
+    #[test]
+    fn test_rust_doc_comments_included() {
+        let code = "use std::fmt;\n\n/// parse a config line\n/// into a key and value\n#[inline]\nfn parse_line(line: &str) -> Option<(&str, &str)> {\n    line.split_once('=')\n}\n";
+        let chunks = chunk_with_tree_sitter(code, Language::Rust).unwrap();
+        let chunk = chunks
+            .iter()
+            .find(|c| c.symbol.as_deref() == Some("parse_line"))
+            .unwrap();
+        assert!(chunk.content.starts_with("/// parse a config line"));
+        assert!(chunk.content.contains("#[inline]"));
+        assert_eq!(chunk.start_line, 3);
+        assert_eq!(chunk.end_line, 8);
+
+        let opts = ChunkOptions {
+            include_doc_comments: false,
+            ..ChunkOptions::default()
+        };
+        let chunks = chunk_with_tree_sitter_opts(code, Language::Rust, opts).unwrap();
+        assert!(chunks[0].content.starts_with("fn parse_line"));
+        assert_eq!(chunks[0].start_line, 6);
+    }
+
+    #[test]
+    fn test_doc_comments_stop_at_blank_line() {
+        let code =
+            "// unrelated header\n\nfn spaced_out() {\n    let value = 123456789;\n    value\n}\n";
+        let chunks = chunk_with_tree_sitter(code, Language::Rust).unwrap();
+        assert!(chunks[0].content.starts_with("fn spaced_out"));
+        assert_eq!(chunks[0].start_line, 3);
+    }
+
+    #[test]
+    fn test_python_docstring_and_comments_included() {
+        let code = "import os\n\n# read settings from disk\ndef load(path):\n    \"\"\"load the settings file at path\"\"\"\n    return open(path).read()\n";
+        let chunks = chunk_with_tree_sitter(code, Language::Python).unwrap();
+        let chunk = chunks
+            .iter()
+            .find(|c| c.symbol.as_deref() == Some("load"))
+            .unwrap();
+        assert!(chunk.content.starts_with("# read settings from disk"));
+        assert!(chunk
+            .content
+            .contains("\"\"\"load the settings file at path\"\"\""));
+        assert_eq!(chunk.start_line, 3);
+    }
+
     #[test]
     fn test_go_function() {
         let code = "func main() {\n    fmt.Println(\"hello world\")\n    x := 42\n}";
@@ -311,18 +617,53 @@ mod tests {
 
     #[test]
     fn test_minimum_chunk_size() {
-        let code = "fn a() {}";
-        let chunks = chunk_with_tree_sitter(code, Language::Rust).unwrap();
-        // small chunks should be filtered out
+        // a nameless top-level block is too small to keep
+        let chunks = chunk_with_tree_sitter("{ 1 }", Language::Rust).unwrap();
         assert!(chunks.is_empty());
     }
 
     #[test]
-    fn test_minimum_chunk_size_threshold() {
-        let code = "fn test_function() {\n    let value = 123456789;\n}";
+    fn test_small_named_top_level_kept() {
+        let code = "const FOO: u32 = 1;\n";
+        let chunks = chunk_with_tree_sitter(code, Language::Rust).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].symbol.as_deref(), Some("FOO"));
+        assert_eq!(chunks[0].symbol_kind.as_deref(), Some("const_item"));
+
+        let code = "func Add(a, b int) int { return a + b }\n";
+        let chunks = chunk_with_tree_sitter(code, Language::Go).unwrap();
+        assert_eq!(chunks[0].symbol.as_deref(), Some("Add"));
+    }
+
+    #[test]
+    fn test_small_nested_definitions_still_filtered() {
+        let code = "impl Widget {\n    fn id(&self) -> u32 { self.id }\n    fn describe(&self) -> String {\n        format!(\"widget number {} of many\", self.id)\n    }\n}\n";
         let chunks = chunk_with_tree_sitter(code, Language::Rust).unwrap();
-        // should pass if >= 50 characters
-        assert!(chunks.is_empty() || chunks[0].content.len() >= MIN_CHUNK_SIZE);
+        let symbols: Vec<_> = chunks.iter().map(|c| c.symbol.as_deref()).collect();
+        assert!(symbols.contains(&Some("describe")));
+        assert!(!symbols.contains(&Some("id")));
+    }
+
+    #[test]
+    fn test_minimum_chunk_size_threshold() {
+        let code = "{\n    let value = 123456789;\n}";
+        let opts = ChunkOptions {
+            min_chunk_size: 20,
+            ..ChunkOptions::default()
+        };
+        assert_eq!(
+            chunk_with_tree_sitter_opts(code, Language::Rust, opts)
+                .unwrap()
+                .len(),
+            1
+        );
+        let opts = ChunkOptions {
+            min_chunk_size: code.len() + 1,
+            ..ChunkOptions::default()
+        };
+        assert!(chunk_with_tree_sitter_opts(code, Language::Rust, opts)
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
@@ -348,4 +689,170 @@ mod tests {
             assert!(chunks[0].symbol_kind.is_some());
         }
     }
+
+    #[test]
+    fn test_csharp_class_and_methods() {
+        let code = r#"namespace App.Services
+{
+    public class UserService : IUserService
+    {
+        public User FindUser(int id)
+        {
+            var user = _repo.Load(id);
+            return Mapper.Map<User>(user);
+        }
+    }
+
+    public interface IUserService
+    {
+        User FindUser(int id);
+    }
+
+    public struct Point
+    {
+        public int X;
+        public int Y;
+    }
+}
+"#;
+        let chunks = chunk_with_tree_sitter(code, Language::CSharp).unwrap();
+        let find = |kind: &str| {
+            chunks
+                .iter()
+                .find(|c| c.symbol_kind.as_deref() == Some(kind))
+                .and_then(|c| c.symbol.as_deref())
+        };
+        assert_eq!(find("class_declaration"), Some("UserService"));
+        // the return type must not be taken as the method name
+        assert_eq!(find("method_declaration"), Some("FindUser"));
+        assert_eq!(find("interface_declaration"), Some("IUserService"));
+        assert_eq!(find("struct_declaration"), Some("Point"));
+    }
+
+    #[test]
+    fn test_php_functions_and_classes() {
+        let code = r#"<?php
+
+function format_name($first, $last) {
+    return ucfirst($first) . ' ' . ucfirst($last);
+}
+
+class UserRepository
+{
+    public function findById(int $id): ?User
+    {
+        return $this->db->query('SELECT * FROM users WHERE id = ?', [$id]);
+    }
+}
+"#;
+        let chunks = chunk_with_tree_sitter(code, Language::Php).unwrap();
+        let symbols: Vec<_> = chunks
+            .iter()
+            .map(|c| (c.symbol_kind.as_deref().unwrap(), c.symbol.as_deref()))
+            .collect();
+        assert!(symbols.contains(&("function_definition", Some("format_name"))));
+        assert!(symbols.contains(&("class_declaration", Some("UserRepository"))));
+        assert!(symbols.contains(&("method_declaration", Some("findById"))));
+    }
+
+    #[test]
+    fn test_html_top_level_sections() {
+        let code = r#"<!DOCTYPE html>
+<html>
+<head><title>Demo</title></head>
+<body>
+  <section id="pricing">
+    <h2>Pricing</h2>
+    <p>Plans start at ten dollars per month, billed annually.</p>
+  </section>
+  <section class="faq wide">
+    <h2>FAQ</h2>
+    <p>Answers to the questions people ask most often about billing.</p>
+  </section>
+</body>
+</html>
+"#;
+        let chunks = chunk_with_tree_sitter(code, Language::Html).unwrap();
+        let symbols: Vec<_> = chunks.iter().map(|c| c.symbol.as_deref()).collect();
+        // nested headings and paragraphs stay inside their section
+        assert_eq!(symbols, vec![Some("section#pricing"), Some("section.faq")]);
+        assert!(chunks
+            .iter()
+            .all(|c| c.symbol_kind.as_deref() == Some("element")));
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (5, 8));
+        assert!(chunks[1].content.contains("questions people ask"));
+    }
+
+    #[test]
+    fn test_css_rules() {
+        let code = r#".button-primary {
+  background-color: #0055ff;
+  color: white;
+}
+
+nav > ul li a:hover,
+nav > ul li a:focus {
+  text-decoration: underline;
+}
+
+@media (max-width: 600px) {
+  .sidebar {
+    display: none;
+    width: 100%;
+  }
+}
+"#;
+        let chunks = chunk_with_tree_sitter(code, Language::Css).unwrap();
+        let symbols: Vec<_> = chunks.iter().map(|c| c.symbol.as_deref()).collect();
+        assert_eq!(
+            symbols,
+            vec![
+                Some(".button-primary"),
+                Some("nav > ul li a:hover, nav > ul li a:focus"),
+                Some(".sidebar"),
+            ]
+        );
+        assert!(chunks
+            .iter()
+            .all(|c| c.symbol_kind.as_deref() == Some("rule")));
+        assert!(chunks.iter().all(|c| c.language == Language::Css));
+    }
+
+    #[test]
+    fn test_strip_comments_rust() {
+        let code = "/// adds one\nfn inc(x: i32) -> i32 {\n    // the only step\n    x + 1 /* no overflow check */\n}\n";
+        assert_eq!(
+            strip_comments(code, Language::Rust),
+            "fn inc(x: i32) -> i32 {\n    x + 1\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_comments_python() {
+        let code = "# module note\ndef inc(x):\n    return x + 1  # add one\n";
+        assert_eq!(
+            strip_comments(code, Language::Python),
+            "def inc(x):\n    return x + 1\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_comments_block() {
+        let code = "/*\n * adds one\n */\nint inc(int x) {\n    return x + 1;\n}\n";
+        assert_eq!(
+            strip_comments(code, Language::C),
+            "int inc(int x) {\n    return x + 1;\n}\n"
+        );
+        let js = "function inc(x) {\n  /* add one */\n  return x + 1;\n}";
+        assert_eq!(
+            strip_comments(js, Language::JavaScript),
+            "function inc(x) {\n  return x + 1;\n}"
+        );
+    }
+
+    #[test]
+    fn test_strip_comments_without_grammar() {
+        let text = "# heading\nsome text";
+        assert_eq!(strip_comments(text, Language::Markdown), text);
+    }
 }
@@ -3,6 +3,11 @@
 
 use anyhow::Result;
 
-pub async fn run(project: Option<&str>, languages: &[String], session: Option<&str>) -> Result<()> {
-    crate::query::run_chat_repl(project, languages, session).await
+pub async fn run(
+    project: Option<&str>,
+    languages: &[String],
+    session: Option<&str>,
+    live: bool,
+) -> Result<()> {
+    crate::query::run_chat_repl(project, languages, session, live).await
 }
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::index::store::Store;
+
+use super::status_cmd::format_bytes;
+
+pub async fn run() -> Result<()> {
+    let config = Config::load()?;
+    let db_path = config.db_path();
+    if !db_path.exists() {
+        anyhow::bail!("no index found - run 'srag index <path>' first");
+    }
+
+    let store = Store::open(&db_path)?;
+    // entries from another embedding model can never be hit again, and a
+    // lowered cap (0 disables the cache) applies here too
+    let signature = crate::config::embedding_signature(&config.embedding);
+    let pruned = store.prune_embedding_cache(&signature)?
+        + store.trim_embedding_cache(config.indexing.embedding_cache_entries)?;
+    if pruned > 0 {
+        println!("dropped {} stale embedding cache entries", pruned);
+    }
+    // fold the wal back in first so the before figure isn't inflated by it
+    store.wal_checkpoint()?;
+    let before = database_size(&db_path);
+
+    store.vacuum()?;
+    let after = database_size(&db_path);
+
+    println!(
+        "compacted {}: {} -> {} ({} reclaimed)",
+        db_path.display(),
+        format_bytes(before),
+        format_bytes(after),
+        format_bytes(before.saturating_sub(after))
+    );
+    Ok(())
+}
+
+/// size of the database file plus its wal, if any
+fn database_size(db_path: &Path) -> u64 {
+    let mut wal = db_path.as_os_str().to_owned();
+    wal.push("-wal");
+    [db_path, Path::new(&wal)]
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum()
+}
@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::io::{self, Write};
 
 use crate::config::{ApiProvider, Config};
@@ -13,69 +13,99 @@ pub async fn show() -> Result<()> {
     Ok(())
 }
 
+pub async fn get(key: &str) -> Result<()> {
+    let config = Config::load()?;
+    println!("{}", get_value(&config, key)?);
+    Ok(())
+}
+
 pub async fn set(key: &str, value: &str) -> Result<()> {
     let mut config = Config::load()?;
+    apply(&mut config, key, value)?;
+    config
+        .validate()
+        .with_context(|| format!("not saving {} = {}", key, value))?;
 
+    config.save()?;
+    println!("Set {} = {}", key, value);
+    Ok(())
+}
+
+/// set one dotted key. keys with their own parsing (enums with aliases,
+/// maps keyed by extension, optional paths) are handled here; everything
+/// else goes through `set_value`.
+fn apply(config: &mut Config, key: &str, value: &str) -> Result<()> {
     match key {
-        "indexing.max_file_size_bytes" => {
-            config.indexing.max_file_size_bytes = value.parse()?;
-        }
-        "indexing.batch_size" => {
-            config.indexing.batch_size = value.parse()?;
-        }
-        "indexing.throttle_ms" => {
-            config.indexing.throttle_ms = value.parse()?;
-        }
-        "query.top_k" => {
-            config.query.top_k = value.parse()?;
-        }
-        "query.ef_search" => {
-            config.query.ef_search = value.parse()?;
-        }
-        "query.context_tokens" => {
-            config.query.context_tokens = value.parse()?;
-        }
-        "query.history_turns" => {
-            config.query.history_turns = value.parse()?;
+        k if k.starts_with("indexing.extension_language.") => {
+            eprintln!("indexing.extension_language is deprecated; setting indexing.extension_map");
+            let suffix = k.trim_start_matches("indexing.extension_language.");
+            apply(config, &format!("indexing.extension_map.{}", suffix), value)?;
         }
-        "query.temperature" => {
-            config.query.temperature = value.parse()?;
+        k if k.starts_with("indexing.extension_map.") => {
+            let suffix = k
+                .trim_start_matches("indexing.extension_map.")
+                .trim_start_matches('.')
+                .to_string();
+            if suffix.is_empty() {
+                anyhow::bail!("missing extension in key: {}", key);
+            }
+            if value.is_empty() {
+                config.indexing.extension_map.remove(&suffix);
+            } else {
+                let language = srag_common::types::Language::from_name(value)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown language: {}", value))?;
+                config
+                    .indexing
+                    .extension_map
+                    .insert(suffix, language.as_str().to_string());
+            }
         }
-        "query.max_tokens" => {
-            config.query.max_tokens = value.parse()?;
-        }
-        "query.rerank" => {
-            config.query.rerank = value.parse()?;
-        }
-        "query.broad_k" => {
-            config.query.broad_k = value.parse()?;
-        }
-        "query.hybrid_search" => {
-            config.query.hybrid_search = value.parse()?;
-        }
-        "watcher.debounce_ms" => {
-            config.watcher.debounce_ms = value.parse()?;
-        }
-        "resource.nice_level" => {
-            config.resource.nice_level = value.parse()?;
-        }
-        "resource.llm_idle_timeout_secs" => {
-            config.resource.llm_idle_timeout_secs = value.parse()?;
+        "query.on_injection" => {
+            config.query.on_injection = match value.to_lowercase().as_str() {
+                "warn" => crate::config::InjectionPolicy::Warn,
+                "refuse" => crate::config::InjectionPolicy::Refuse,
+                "ignore" => crate::config::InjectionPolicy::Ignore,
+                _ => anyhow::bail!(
+                    "Invalid injection policy: {}. Use 'warn', 'refuse' or 'ignore'",
+                    value
+                ),
+            };
         }
-        "resource.memory_budget_mb" => {
-            config.resource.memory_budget_mb = value.parse()?;
+        "query.system_prompt_file" => {
+            config.query.system_prompt_file = if value.is_empty() {
+                None
+            } else {
+                Some(value.into())
+            };
         }
-        "llm.model_filename" => {
-            config.llm.model_filename = value.to_string();
+        "resource.ipc_transport" => {
+            config.resource.ipc_transport = match value.to_lowercase().as_str() {
+                "tcp" => crate::config::IpcTransport::Tcp,
+                "unix" => crate::config::IpcTransport::Unix,
+                _ => anyhow::bail!("Invalid transport: {}. Use 'tcp' or 'unix'", value),
+            };
         }
-        "llm.model_url" => {
-            config.llm.model_url = value.to_string();
+        "embedding.provider" => {
+            config.embedding.provider = match value.to_lowercase().as_str() {
+                "local" => crate::config::EmbeddingProvider::Local,
+                "openai_compatible" | "openai" => {
+                    crate::config::EmbeddingProvider::OpenaiCompatible
+                }
+                _ => anyhow::bail!(
+                    "Invalid embedding provider: {}. Use 'local' or 'openai_compatible'",
+                    value
+                ),
+            };
         }
-        "llm.threads" => {
-            config.llm.threads = value.parse()?;
+        "embedding.base_url" => {
+            config.embedding.base_url = value.trim_end_matches('/').to_string();
         }
-        "llm.context_size" => {
-            config.llm.context_size = value.parse()?;
+        "embedding.api_key_file" => {
+            config.embedding.api_key_file = if value.is_empty() {
+                None
+            } else {
+                Some(value.into())
+            };
         }
         "api.provider" => {
             config.api.provider = match value.to_lowercase().as_str() {
@@ -88,25 +118,88 @@ pub async fn set(key: &str, value: &str) -> Result<()> {
                 ),
             };
         }
-        "api.model" => {
-            config.api.model = value.to_string();
-        }
-        "api.max_tokens" => {
-            config.api.max_tokens = value.parse()?;
-        }
-        "api.redact_secrets" => {
-            config.api.redact_secrets = value.parse()?;
-        }
-        "api.log_redactions" => {
-            config.api.log_redactions = value.parse()?;
-        }
-        _ => {
-            anyhow::bail!("Unknown config key: {}", key);
-        }
+        _ => set_value(config, key, value)?,
     }
+    Ok(())
+}
 
-    config.save()?;
-    println!("Set {} = {}", key, value);
+/// the toml tree a dotted key points into, or an error naming the key
+fn lookup<'a>(root: &'a toml::Value, key: &str) -> Result<&'a toml::Value> {
+    key.split('.')
+        .try_fold(root, |value, part| value.get(part))
+        .ok_or_else(|| anyhow::anyhow!("Unknown config key: {}", key))
+}
+
+/// a single resolved value. strings print bare, sections as toml.
+fn get_value(config: &Config, key: &str) -> Result<String> {
+    let root = toml::Value::try_from(config)?;
+    Ok(match lookup(&root, key)? {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Table(table) => toml::to_string_pretty(table)?.trim_end().to_string(),
+        other => other.to_string(),
+    })
+}
+
+/// set a key by parsing `value` as the type the field already has, then
+/// deserialising the whole config so the field's own type has the final say.
+/// lists are comma separated.
+fn set_value(config: &mut Config, key: &str, value: &str) -> Result<()> {
+    let mut root = toml::Value::try_from(&*config)?;
+    let (section, field) = match key.rsplit_once('.') {
+        Some((section, field)) => (Some(section), field),
+        None => (None, key),
+    };
+    let unknown = || anyhow::anyhow!("Unknown config key: {}", key);
+    let table = match section {
+        Some(section) => section
+            .split('.')
+            .try_fold(&mut root, |value, part| value.get_mut(part))
+            .ok_or_else(unknown)?,
+        None => &mut root,
+    }
+    .as_table_mut()
+    .ok_or_else(unknown)?;
+
+    let parsed =
+        match table.get(field) {
+            Some(toml::Value::Integer(_)) => {
+                toml::Value::Integer(value.trim().parse().map_err(|_| {
+                    anyhow::anyhow!("{} expects a whole number, got '{}'", key, value)
+                })?)
+            }
+            Some(toml::Value::Float(_)) => toml::Value::Float(
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("{} expects a number, got '{}'", key, value))?,
+            ),
+            Some(toml::Value::Boolean(_)) => {
+                toml::Value::Boolean(value.trim().parse().map_err(|_| {
+                    anyhow::anyhow!("{} expects true or false, got '{}'", key, value)
+                })?)
+            }
+            Some(toml::Value::Array(_)) => toml::Value::Array(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| toml::Value::String(item.to_string()))
+                    .collect(),
+            ),
+            Some(toml::Value::Table(_)) => {
+                anyhow::bail!("{} is a section, set one of its keys instead", key)
+            }
+            // strings, and optional fields that are currently unset
+            _ => toml::Value::String(value.to_string()),
+        };
+    table.insert(field.to_string(), parsed);
+
+    let updated: Config = root
+        .try_into()
+        .map_err(|e| anyhow::anyhow!("invalid value for {}: {}", key, e))?;
+    // unknown fields are dropped on deserialise, so check the key survived
+    lookup(&toml::Value::try_from(&updated)?, key)?;
+    *config = updated;
     Ok(())
 }
 
@@ -248,3 +341,61 @@ pub async fn edit() -> Result<()> {
     println!("Configuration saved");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_typed_values() {
+        let mut config = Config::default();
+        apply(&mut config, "query.top_k", "25").unwrap();
+        apply(&mut config, "query.temperature", "0.7").unwrap();
+        apply(&mut config, "query.rerank", "false").unwrap();
+        apply(&mut config, "llm.model_filename", "custom.gguf").unwrap();
+        apply(&mut config, "injection.allow_paths", "docs/, fixtures/**").unwrap();
+        apply(&mut config, "source_dir", "/src").unwrap();
+
+        assert_eq!(config.query.top_k, 25);
+        assert!((config.query.temperature - 0.7).abs() < 1e-6);
+        assert!(!config.query.rerank);
+        assert_eq!(config.llm.model_filename, "custom.gguf");
+        assert_eq!(config.injection.allow_paths, vec!["docs/", "fixtures/**"]);
+        assert_eq!(config.source_dir.as_deref(), Some("/src"));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_set_rejects_wrong_type() {
+        let mut config = Config::default();
+        let err = apply(&mut config, "query.top_k", "notanumber").unwrap_err();
+        assert!(err.to_string().contains("whole number"), "{}", err);
+        assert!(apply(&mut config, "query.rerank", "yes").is_err());
+        // negative values don't fit unsigned fields
+        assert!(apply(&mut config, "query.top_k", "-3").is_err());
+        assert!(apply(&mut config, "query", "1").is_err());
+        assert!(apply(&mut config, "query.not_a_key", "1").is_err());
+        assert!(apply(&mut config, "nope.top_k", "1").is_err());
+        assert_eq!(config.query.top_k, Config::default().query.top_k);
+
+        // well-typed but out of range is caught by validate
+        apply(&mut config, "query.temperature", "3.5").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_get_round_trips_nested_key() {
+        let mut config = Config::default();
+        apply(&mut config, "resource.ml_connect_backoff_ms", "750").unwrap();
+        assert_eq!(
+            get_value(&config, "resource.ml_connect_backoff_ms").unwrap(),
+            "750"
+        );
+        apply(&mut config, "api.model", "claude-test").unwrap();
+        assert_eq!(get_value(&config, "api.model").unwrap(), "claude-test");
+        assert!(get_value(&config, "watcher")
+            .unwrap()
+            .contains("debounce_ms"));
+        assert!(get_value(&config, "watcher.nope").is_err());
+    }
+}
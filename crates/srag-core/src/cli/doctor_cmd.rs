@@ -0,0 +1,334 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::index::store::Store;
+use crate::ipc::lifecycle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Status {
+    Pass,
+    /// not broken, but worth knowing about
+    Warn,
+    /// srag won't work until this is fixed
+    Fail,
+}
+
+pub(crate) struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+pub async fn run() -> Result<()> {
+    let mut checks = Vec::new();
+
+    let config = match Config::load() {
+        Ok(config) => {
+            checks.push(Check::pass(
+                "config",
+                Config::config_path().display().to_string(),
+            ));
+            config
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "config",
+                format!("{:#}", e),
+                "fix the file with 'srag config edit' or run 'srag config reset'",
+            ));
+            Config::default()
+        }
+    };
+
+    checks.push(check_dir_writable("data dir", config.data_dir(), true));
+    // lives under /tmp or XDG_RUNTIME_DIR, so it is recreated whenever needed
+    checks.push(check_dir_writable(
+        "runtime dir",
+        &Config::runtime_dir(),
+        false,
+    ));
+    checks.push(check_python(&config));
+    checks.push(check_python_package());
+    checks.push(check_model(&config));
+    checks.push(check_port_file(&Config::port_file_path()));
+    checks.push(check_database(&config.db_path()));
+
+    println!("srag doctor");
+    for check in &checks {
+        let tag = match check.status {
+            Status::Pass => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        println!("  [{:>4}] {}: {}", tag, check.name, check.detail);
+        if let Some(hint) = &check.hint {
+            println!("         -> {}", hint);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed", failed);
+    }
+    println!("all required checks passed");
+    Ok(())
+}
+
+pub(crate) fn check_dir_writable(name: &str, dir: &Path, required: bool) -> Check {
+    if !dir.is_dir() && !required {
+        return Check::warn(
+            name,
+            format!("{} does not exist", dir.display()),
+            "created automatically when the ml service starts",
+        );
+    }
+    if !dir.is_dir() {
+        return Check::fail(
+            name,
+            format!("{} does not exist", dir.display()),
+            "run 'srag setup' or 'srag index <path>' to create it",
+        );
+    }
+    match tempfile::NamedTempFile::new_in(dir) {
+        Ok(_) => Check::pass(name, dir.display().to_string()),
+        Err(e) => Check::fail(
+            name,
+            format!("{} is not writable: {}", dir.display(), e),
+            format!("check ownership and permissions of {}", dir.display()),
+        ),
+    }
+}
+
+fn check_python(config: &Config) -> Check {
+    let python = lifecycle::python_executable(config);
+    match Command::new(&python).arg("--version").output() {
+        Ok(out) if out.status.success() => {
+            // python 2 printed its version to stderr
+            let version = String::from_utf8_lossy(if out.stdout.is_empty() {
+                &out.stderr
+            } else {
+                &out.stdout
+            })
+            .trim()
+            .to_string();
+            Check::pass("python", format!("{} ({})", python.display(), version))
+        }
+        Ok(out) => Check::fail(
+            "python",
+            format!("{} exited with {}", python.display(), out.status),
+            "re-run install.sh to recreate the virtualenv",
+        ),
+        Err(e) => Check::fail(
+            "python",
+            format!("{} could not be run: {}", python.display(), e),
+            format!(
+                "install python 3 or re-run install.sh to create {}",
+                config.data_dir().join("venv").display()
+            ),
+        ),
+    }
+}
+
+fn check_python_package() -> Check {
+    match lifecycle::find_python_package() {
+        Ok(path) => Check::pass("srag_ml package", path.display().to_string()),
+        Err(e) => Check::fail(
+            "srag_ml package",
+            e.to_string(),
+            "run srag from the install directory or re-run install.sh",
+        ),
+    }
+}
+
+fn check_model(config: &Config) -> Check {
+    if config.is_external_api() {
+        return Check::pass(
+            "llm model",
+            format!("not needed, using {:?} api", config.api.provider),
+        );
+    }
+    let model = config.models_dir().join(&config.llm.model_filename);
+    if model.is_file() {
+        Check::pass("llm model", model.display().to_string())
+    } else {
+        Check::warn(
+            "llm model",
+            format!("{} not downloaded", model.display()),
+            format!(
+                "it is fetched on first chat/query, or download {} into {}",
+                config.llm.model_url,
+                config.models_dir().display()
+            ),
+        )
+    }
+}
+
+pub(crate) fn check_port_file(port_file: &Path) -> Check {
+    if !port_file.exists() {
+        return Check::pass("ml service", "not running (started on demand)");
+    }
+    match crate::ipc::client::read_service_addr(port_file) {
+        Ok(addr) if lifecycle::probe_service(&addr) => {
+            Check::pass("ml service", format!("running on {}", addr))
+        }
+        Ok(addr) => Check::warn(
+            "ml service",
+            format!("stale port file, nothing listening on {}", addr),
+            format!(
+                "harmless, it is replaced on next start; or remove {}",
+                port_file.display()
+            ),
+        ),
+        Err(e) => Check::warn(
+            "ml service",
+            e.to_string(),
+            format!("remove {}", port_file.display()),
+        ),
+    }
+}
+
+pub(crate) fn check_database(db_path: &Path) -> Check {
+    if !db_path.exists() {
+        return Check::warn(
+            "database",
+            format!("{} not created yet", db_path.display()),
+            "run 'srag index <path>' to build an index",
+        );
+    }
+    let checked = Store::open(db_path).and_then(|store| {
+        let projects = store.list_projects()?;
+        Ok((projects, store.integrity_check()?))
+    });
+    match checked {
+        Ok((projects, problems)) if problems.is_empty() => Check::pass(
+            "database",
+            format!("{} ({} projects)", db_path.display(), projects.len()),
+        ),
+        Ok((_, problems)) => Check::fail(
+            "database",
+            format!(
+                "{} failed its integrity check ({} problems, first: {})",
+                db_path.display(),
+                problems.len(),
+                problems[0]
+            ),
+            "move the file aside and re-index, or restore a backup",
+        ),
+        Err(e) => Check::fail(
+            "database",
+            format!("{} could not be opened: {}", db_path.display(), e),
+            "move the file aside and re-index, or restore a backup",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_dir_writable() {
+        let dir = tempdir().unwrap();
+        assert_eq!(
+            check_dir_writable("data dir", dir.path(), true).status,
+            Status::Pass
+        );
+
+        let missing = dir.path().join("missing");
+        let check = check_dir_writable("data dir", &missing, true);
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.hint.is_some());
+        assert_eq!(
+            check_dir_writable("runtime dir", &missing, false).status,
+            Status::Warn
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_dir_not_writable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o500)).unwrap();
+
+        // root ignores permission bits, so only assert when the probe would fail
+        let writable = tempfile::NamedTempFile::new_in(&locked).is_ok();
+        let check = check_dir_writable("data dir", &locked, true);
+        assert_eq!(check.status == Status::Pass, writable);
+
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o700)).unwrap();
+    }
+
+    #[test]
+    fn test_check_database() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("srag.db");
+        assert_eq!(check_database(&db_path).status, Status::Warn);
+
+        let store = Store::open(&db_path).unwrap();
+        store.upsert_project("a", "/tmp/a").unwrap();
+        store.upsert_project("b", "/tmp/b").unwrap();
+        drop(store);
+
+        let check = check_database(&db_path);
+        assert_eq!(check.status, Status::Pass);
+        assert!(check.detail.contains("2 projects"), "{}", check.detail);
+    }
+
+    #[test]
+    fn test_check_database_corrupt() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("srag.db");
+        std::fs::write(&db_path, b"definitely not sqlite, just some text padding").unwrap();
+        assert_eq!(check_database(&db_path).status, Status::Fail);
+    }
+
+    #[test]
+    fn test_check_port_file() {
+        let dir = tempdir().unwrap();
+        let port_file = dir.path().join("ml.port");
+        assert_eq!(check_port_file(&port_file).status, Status::Pass);
+
+        std::fs::write(&port_file, "not a port").unwrap();
+        assert_eq!(check_port_file(&port_file).status, Status::Warn);
+    }
+}
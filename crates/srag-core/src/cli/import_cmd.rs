@@ -0,0 +1,327 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use srag_common::types::Project;
+
+use crate::config::Config;
+use crate::index::hnsw::{build_hnsw_from_db, invalidate_cache};
+use crate::index::store::{ImportReport, Store};
+
+/// what to do with a project whose name is taken here by another path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Conflict {
+    /// treat them as the same project, moving the other paths under ours
+    Merge,
+    /// import under the first free `name-N`
+    Rename,
+    Skip,
+}
+
+pub async fn run(database: &Path, rename_on_conflict: bool) -> Result<()> {
+    let config = Config::load()?;
+    config.ensure_dirs()?;
+    let db_path = config.db_path();
+    if !database.exists() {
+        anyhow::bail!("{} not found", database.display());
+    }
+    if same_file(database, &db_path) {
+        anyhow::bail!("{} is this index", database.display());
+    }
+
+    let other = Store::open_read_only(database)?;
+    let store = Store::open(&db_path)?;
+    let signature = crate::config::embedding_signature(&config.embedding);
+
+    let imported = import_projects(&store, &other, &signature, |local, theirs| {
+        if rename_on_conflict {
+            Ok(Conflict::Rename)
+        } else {
+            ask_conflict(local, theirs)
+        }
+    })?;
+
+    let mut files = 0;
+    for (name, report) in &imported {
+        println!(
+            "{}: {} files, {} chunks, {} embeddings imported, {} files skipped",
+            name, report.files, report.chunks, report.embeddings, report.skipped
+        );
+        files += report.files;
+    }
+    if files > 0 {
+        // new points can't be spliced into the saved graph with their ids,
+        // so rebuild it from every embedding
+        let vector_index = build_hnsw_from_db(
+            &store,
+            crate::config::EMBEDDING_DIMENSION,
+            config.embedding.distance,
+        )?;
+        vector_index.save(&config.vectors_dir())?;
+    }
+    store.wal_checkpoint()?;
+    invalidate_cache();
+
+    println!("done: {} files imported from {}", files, database.display());
+    Ok(())
+}
+
+/// import every project of `other` embedded with `signature`, or with no
+/// recorded model. `on_conflict` decides a name clash with a different root.
+pub(crate) fn import_projects(
+    store: &Store,
+    other: &Store,
+    signature: &str,
+    mut on_conflict: impl FnMut(&Project, &Project) -> Result<Conflict>,
+) -> Result<Vec<(String, ImportReport)>> {
+    let mut imported = Vec::new();
+    for project in other.list_projects()? {
+        let project_id = project.id.unwrap_or_default();
+        if let Some(model) = other.project_embedding_model(project_id)? {
+            if model != signature {
+                eprintln!(
+                    "skipping '{}': embedded with {}, this index uses {}",
+                    project.name, model, signature
+                );
+                continue;
+            }
+        }
+
+        let local = store.list_projects()?;
+        let name = match local.iter().find(|p| p.name == project.name) {
+            Some(existing) if existing.path != project.path => {
+                match on_conflict(existing, &project)? {
+                    Conflict::Merge => project.name.clone(),
+                    Conflict::Rename => free_name(&local, &project.name),
+                    Conflict::Skip => {
+                        eprintln!("skipping '{}'", project.name);
+                        continue;
+                    }
+                }
+            }
+            _ => project.name.clone(),
+        };
+        let report = store.import_project(other, &project, &name)?;
+        imported.push((name, report));
+    }
+    Ok(imported)
+}
+
+fn ask_conflict(local: &Project, theirs: &Project) -> Result<Conflict> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(
+            "project '{}' is {} here but {} in the other index, pass --rename-on-conflict to import it under a new name",
+            local.name,
+            local.path,
+            theirs.path
+        );
+    }
+    eprint!(
+        "project '{}' is {} here but {} in the other index: [m]erge, [r]ename or [s]kip? ",
+        local.name, local.path, theirs.path
+    );
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(match input.trim().to_ascii_lowercase().as_str() {
+        "m" | "merge" => Conflict::Merge,
+        "r" | "rename" => Conflict::Rename,
+        _ => Conflict::Skip,
+    })
+}
+
+/// `name-2`, `name-3`, ... whichever is free first
+fn free_name(projects: &[Project], name: &str) -> String {
+    (2..)
+        .map(|n| format!("{}-{}", name, n))
+        .find(|candidate| !projects.iter().any(|p| &p.name == candidate))
+        .unwrap_or_default()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DistanceMetric;
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use srag_common::types::{Chunk, Language};
+
+    const DIM: usize = crate::config::EMBEDDING_DIMENSION;
+    const MODEL: &str = "test-model/384";
+
+    /// a one-chunk file with an embedding and fts row, as the indexer leaves it
+    fn add_file(store: &Store, project_id: i64, path: &str, symbol: &str, axis: usize) {
+        let file_id = seed_file(store, project_id, path, Language::Rust);
+        let chunk = Chunk {
+            symbol: Some(symbol.to_string()),
+            symbol_kind: Some("function".into()),
+            ..test_chunk(file_id, &format!("fn {}() {{}}", symbol), 1)
+        };
+        let chunk_id = seed_chunk(store, &chunk, path);
+        let mut vector = vec![0.0; DIM];
+        vector[axis] = 1.0;
+        let embedding_id = store.insert_embedding(chunk_id, &vector).unwrap();
+        store
+            .update_chunk_embedding_id(chunk_id, embedding_id)
+            .unwrap();
+    }
+
+    fn project(store: &Store, name: &str, path: &str) -> i64 {
+        let id = store.upsert_project(name, path).unwrap();
+        store.set_project_embedding_model(id, MODEL).unwrap();
+        id
+    }
+
+    fn no_conflicts(_: &Project, _: &Project) -> Result<Conflict> {
+        panic!("unexpected name conflict")
+    }
+
+    #[test]
+    fn test_import_merges_two_stores() {
+        let (store, _dir) = test_store();
+        let shared = project(&store, "shared", "/src/shared");
+        add_file(&store, shared, "/src/shared/a.rs", "alpha_here", 0);
+        let mine = project(&store, "mine", "/src/mine");
+        add_file(&store, mine, "/src/mine/m.rs", "mine_only", 1);
+
+        let (other, _other_dir) = test_store();
+        let shared_there = project(&other, "shared", "/src/shared");
+        // same file and hash as here, so it is skipped
+        add_file(&other, shared_there, "/src/shared/a.rs", "alpha_here", 0);
+        add_file(&other, shared_there, "/src/shared/b.rs", "beta_there", 2);
+        let theirs = project(&other, "theirs", "/src/theirs");
+        add_file(&other, theirs, "/src/theirs/t.rs", "theirs_only", 3);
+        let foreign = other.upsert_project("foreign", "/src/foreign").unwrap();
+        other
+            .set_project_embedding_model(foreign, "other/768")
+            .unwrap();
+        add_file(&other, foreign, "/src/foreign/f.rs", "foreign_fn", 4);
+
+        let imported = import_projects(&store, &other, MODEL, no_conflicts).unwrap();
+        assert_eq!(
+            imported,
+            vec![
+                (
+                    "shared".to_string(),
+                    ImportReport {
+                        files: 1,
+                        chunks: 1,
+                        embeddings: 1,
+                        skipped: 1,
+                    }
+                ),
+                (
+                    "theirs".to_string(),
+                    ImportReport {
+                        files: 1,
+                        chunks: 1,
+                        embeddings: 1,
+                        skipped: 0,
+                    }
+                ),
+            ]
+        );
+
+        let names: Vec<String> = store
+            .list_projects()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, vec!["mine", "shared", "theirs"]);
+        assert_eq!(store.file_count(Some(shared)).unwrap(), 2);
+        assert_eq!(store.file_count(None).unwrap(), 4);
+        assert_eq!(store.chunk_count(None).unwrap(), 4);
+        assert_eq!(store.embedding_count().unwrap(), 4);
+        assert!(store.integrity_check().unwrap().is_empty());
+
+        // imported chunks are searchable by keyword, symbol and vector
+        assert_eq!(store.search_fts("beta_there", 10).unwrap().len(), 1);
+        let theirs_here = store.get_project_id("theirs").unwrap();
+        assert_eq!(
+            store
+                .search_symbols("theirs_only", Some(theirs_here), 10)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            store
+                .project_embedding_model(theirs_here)
+                .unwrap()
+                .as_deref(),
+            Some(MODEL)
+        );
+        let index = build_hnsw_from_db(&store, DIM, DistanceMetric::Cosine).unwrap();
+        let mut query = vec![0.0; DIM];
+        query[3] = 1.0;
+        let (embedding_id, _) = index.search(&query, 1, 64).unwrap()[0];
+        let (chunk, path) = store
+            .get_chunk_by_embedding_id(embedding_id as i64)
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk.symbol.as_deref(), Some("theirs_only"));
+        assert_eq!(path, "/src/theirs/t.rs");
+
+        // importing again copies nothing
+        let again = import_projects(&store, &other, MODEL, no_conflicts).unwrap();
+        assert!(again.iter().all(|(_, report)| report.files == 0));
+        assert_eq!(store.file_count(None).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_import_name_conflicts() {
+        let (store, _dir) = test_store();
+        let here = project(&store, "app", "/home/me/app");
+        add_file(&store, here, "/home/me/app/main.rs", "main_here", 0);
+
+        let (other, _other_dir) = test_store();
+        let there = project(&other, "app", "/Users/me/app");
+        add_file(&other, there, "/Users/me/app/lib.rs", "lib_there", 1);
+
+        let skipped = import_projects(&store, &other, MODEL, |_, _| Ok(Conflict::Skip)).unwrap();
+        assert!(skipped.is_empty());
+
+        let renamed = import_projects(&store, &other, MODEL, |_, _| Ok(Conflict::Rename)).unwrap();
+        assert_eq!(renamed[0].0, "app-2");
+        let app2 = store.get_project_id("app-2").unwrap();
+        assert_eq!(
+            store.list_project_files(app2).unwrap()[0].path,
+            "/Users/me/app/lib.rs"
+        );
+
+        // merging moves the other machine's paths under the local root
+        import_projects(&store, &other, MODEL, |_, _| Ok(Conflict::Merge)).unwrap();
+        let paths: Vec<String> = store
+            .list_project_files(here)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(paths, vec!["/home/me/app/lib.rs", "/home/me/app/main.rs"]);
+    }
+
+    #[test]
+    fn test_free_name() {
+        let taken = |name: &str| Project {
+            id: None,
+            name: name.to_string(),
+            path: String::new(),
+            created_at: String::new(),
+            last_indexed_at: None,
+        };
+        let projects = vec![taken("app"), taken("app-2")];
+        assert_eq!(free_name(&projects, "app"), "app-3");
+        assert_eq!(free_name(&projects, "web"), "web-2");
+    }
+}
@@ -1,11 +1,17 @@
 // SPDX-Licence-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
-use crate::config::Config;
-use crate::index::store::Store;
+use crate::config::{Config, QueryConfig};
+use crate::index::store::{DefinitionBody, Store};
+use crate::query::retriever::ScoredChunk;
 use rmcp::ErrorData as McpError;
+use srag_common::types::Language;
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
 use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::process::Command;
 
 const PROJECT_MARKERS: &[&str] = &[
@@ -84,12 +90,23 @@ pub async fn auto_index_cwd(_config: &Config) -> Result<AutoIndexResult, McpErro
 
     let project_name = project_name.to_string();
 
+    let summary = run_index_subprocess(&cwd, &project_name).await?;
+
+    Ok(AutoIndexResult {
+        project_name,
+        summary,
+    })
+}
+
+/// runs `srag index` in a child process so its progress output never reaches
+/// the mcp stdio transport. returns the final summary line.
+pub async fn run_index_subprocess(path: &Path, project_name: &str) -> Result<String, McpError> {
     let exe = std::env::current_exe().map_err(|e| {
         McpError::internal_error(format!("Failed to get executable path: {}", e), None)
     })?;
 
     let output = Command::new(&exe)
-        .args(["index", &cwd.to_string_lossy(), "--name", &project_name])
+        .args(["index", &path.to_string_lossy(), "--name", project_name])
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -102,22 +119,22 @@ pub async fn auto_index_cwd(_config: &Config) -> Result<AutoIndexResult, McpErro
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(McpError::internal_error(
-            format!("Auto-indexing failed: {}", stderr.trim()),
+            format!("Indexing failed: {}", stderr.trim()),
             None,
         ));
     }
 
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let summary = stderr
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(summary_line(&stdout))
+}
+
+fn summary_line(stdout: &str) -> String {
+    stdout
         .lines()
-        .last()
+        .rev()
+        .find(|l| l.starts_with("done:"))
         .unwrap_or("indexing completed")
-        .to_string();
-
-    Ok(AutoIndexResult {
-        project_name,
-        summary,
-    })
+        .to_string()
 }
 
 pub async fn ensure_index_exists(config: &Config) -> Result<Option<AutoIndexResult>, McpError> {
@@ -158,32 +175,14 @@ pub fn resolve_project(store: &Store, project: Option<&str>) -> Result<(i64, Str
     }
 
     let cwd = std::env::current_dir().map_err(|e| McpError::internal_error(e.to_string(), None))?;
-    let cwd_str = cwd.to_string_lossy();
-
-    match store.find_project_by_path(&cwd_str) {
-        Ok(Some(proj)) => {
-            let id = proj
-                .id
-                .ok_or_else(|| McpError::internal_error("Project found but has no ID", None))?;
-            return Ok((id, proj.name));
-        }
-        Ok(None) => {
-            // project not found by path, fall through to check single project case
-        }
-        Err(e) => {
-            tracing::warn!("Failed to find project by path '{}': {}", cwd_str, e);
-            // fall through to check single project case
-        }
-    }
-
-    let projects = store
-        .list_projects()
+    let detected = store
+        .detect_project(&cwd.to_string_lossy())
         .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-    if projects.len() == 1 {
-        let id = projects[0]
+    if let Some(project) = detected {
+        let id = project
             .id
             .ok_or_else(|| McpError::internal_error("Project found but has no ID", None))?;
-        return Ok((id, projects[0].name.clone()));
+        return Ok((id, project.name));
     }
 
     Err(McpError::invalid_params(
@@ -192,8 +191,498 @@ pub fn resolve_project(store: &Store, project: Option<&str>) -> Result<(i64, Str
     ))
 }
 
+/// the indexed chunk `find_similar_code` starts from: `chunk_id` if given,
+/// else the first chunk named `symbol`, in `file_path` when that is set.
+/// `None` when neither is given and a snippet has to be embedded instead.
+pub fn similar_source_chunk(
+    store: &Store,
+    project_id: i64,
+    params: &super::params::FindSimilarParams,
+) -> Result<Option<i64>, McpError> {
+    if let Some(chunk_id) = params.chunk_id {
+        let found = store
+            .get_chunk_by_id(chunk_id)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let chunk_project = match found {
+            Some((chunk, _)) => store
+                .get_file_project_id(chunk.file_id)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
+            None => None,
+        };
+        // a chunk id from another project would leak its code into this one
+        return match chunk_project {
+            Some(id) if id == project_id => Ok(Some(chunk_id)),
+            _ => Err(McpError::invalid_params(
+                format!("no indexed chunk with id {} in this project", chunk_id),
+                None,
+            )),
+        };
+    }
+    let Some(symbol) = params.symbol.as_deref() else {
+        if params.file_path.is_some() {
+            return Err(McpError::invalid_params(
+                "file_path needs a symbol to pick a chunk",
+                None,
+            ));
+        }
+        return Ok(None);
+    };
+
+    let candidates = store
+        .find_symbol_chunks(project_id, symbol)
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    let chunk_id = candidates
+        .into_iter()
+        .find(|(_, path)| match params.file_path.as_deref() {
+            Some(wanted) => Path::new(path).ends_with(wanted),
+            None => true,
+        })
+        .map(|(id, _)| id);
+    match (chunk_id, params.file_path.as_deref()) {
+        (Some(id), _) => Ok(Some(id)),
+        (None, Some(file)) => Err(McpError::invalid_params(
+            format!("no indexed symbol '{}' in {}", symbol, file),
+            None,
+        )),
+        (None, None) => Err(McpError::invalid_params(
+            format!("no indexed symbol '{}'", symbol),
+            None,
+        )),
+    }
+}
+
+/// resolve vector hits to chunks in the project, leaving out the embedding
+/// the search started from
+pub fn similar_chunks(
+    store: &Store,
+    project_id: i64,
+    hits: &[(usize, f32)],
+    exclude_embedding: Option<i64>,
+) -> Result<Vec<(srag_common::types::Chunk, String)>, McpError> {
+    let hits: Vec<(usize, f32)> = hits
+        .iter()
+        .copied()
+        .filter(|&(id, _)| Some(id as i64) != exclude_embedding)
+        .collect();
+    let chunks = crate::query::retriever::resolve_results(store, &hits)
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    let project_files: std::collections::HashSet<String> = store
+        .list_project_files(project_id)
+        .map_err(|e| McpError::internal_error(format!("Failed to list files: {}", e), None))?
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+    Ok(chunks
+        .into_iter()
+        .filter(|(_, path)| project_files.contains(path))
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProjectStats {
+    pub files: u64,
+    pub chunks: u64,
+}
+
+/// per-project file/chunk counts, reused until the project's generation
+/// counter moves on.
+#[derive(Clone, Default)]
+pub struct ProjectStatsCache {
+    entries: Arc<Mutex<HashMap<i64, (i64, ProjectStats)>>>,
+}
+
+impl ProjectStatsCache {
+    pub fn get_or_load<F>(
+        &self,
+        project_id: i64,
+        generation: i64,
+        load: F,
+    ) -> Result<ProjectStats, McpError>
+    where
+        F: FnOnce() -> Result<ProjectStats, McpError>,
+    {
+        if let Some((cached_gen, stats)) = self.entries.lock().unwrap().get(&project_id) {
+            if *cached_gen == generation {
+                return Ok(*stats);
+            }
+        }
+
+        let stats = load()?;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(project_id, (generation, stats));
+        Ok(stats)
+    }
+
+    /// drop entries for projects that no longer exist.
+    pub fn retain(&self, live_ids: &[i64]) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|id, _| live_ids.contains(id));
+    }
+}
+
+/// what a search_code call asked for; `mode` folds in the filters and
+/// retrieval settings that shape the results
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct SearchKey {
+    pub project_id: i64,
+    pub query: String,
+    pub top_k: usize,
+    pub mode: String,
+}
+
+struct CachedSearch {
+    stamp: (i64, u64),
+    stored_at: Instant,
+    last_used: Instant,
+    chunks: Vec<ScoredChunk>,
+}
+
+/// recent search_code results, so an agent repeating a query skips the
+/// embed and search. an entry is stale once its ttl passes, the project's
+/// generation moves on or the vector index cache is invalidated.
+#[derive(Clone, Default)]
+pub struct SearchCache {
+    entries: Arc<Mutex<HashMap<SearchKey, CachedSearch>>>,
+}
+
+impl SearchCache {
+    /// cached results for `key`, or those of `search` when there are none
+    /// fresh enough. `stamp` is the project generation and index cache epoch.
+    /// a `max_entries` of 0 bypasses the cache.
+    pub async fn get_or_search<F, Fut>(
+        &self,
+        key: SearchKey,
+        stamp: (i64, u64),
+        ttl: Duration,
+        max_entries: usize,
+        search: F,
+    ) -> Result<Vec<ScoredChunk>, McpError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<ScoredChunk>, McpError>>,
+    {
+        if max_entries == 0 {
+            return search().await;
+        }
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&key) {
+            if entry.stamp == stamp && entry.stored_at.elapsed() < ttl {
+                entry.last_used = Instant::now();
+                return Ok(entry.chunks.clone());
+            }
+        }
+
+        let chunks = search().await?;
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.insert(
+            key,
+            CachedSearch {
+                stamp,
+                stored_at: now,
+                last_used: now,
+                chunks: chunks.clone(),
+            },
+        );
+        while entries.len() > max_entries {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+        Ok(chunks)
+    }
+}
+
+pub fn load_project_stats(store: &Store, project_id: i64) -> Result<ProjectStats, McpError> {
+    let files = store
+        .file_count(Some(project_id))
+        .map_err(|e| McpError::internal_error(format!("Failed to get file count: {}", e), None))?;
+    let chunks = store
+        .chunk_count(Some(project_id))
+        .map_err(|e| McpError::internal_error(format!("Failed to get chunk count: {}", e), None))?;
+    Ok(ProjectStats { files, chunks })
+}
+
 pub fn format_chunk(chunk: &srag_common::types::Chunk, file_path: &str) -> String {
-    let header = if let Some(ref symbol) = chunk.symbol {
+    format!("{}\n{}\n", chunk_header(chunk, file_path), chunk.content)
+}
+
+/// header line only, followed by a highlighted excerpt rather than the whole chunk.
+pub fn format_snippet(chunk: &srag_common::types::Chunk, file_path: &str, snippet: &str) -> String {
+    format!("{}\n{}\n", chunk_header(chunk, file_path), snippet)
+}
+
+/// parse a tool's language filter, rejecting names no chunk can have
+pub fn parse_languages(names: &[String]) -> Result<Vec<Language>, McpError> {
+    names
+        .iter()
+        .map(|name| {
+            Language::from_name(name).ok_or_else(|| {
+                McpError::invalid_params(format!("unknown language '{}'", name), None)
+            })
+        })
+        .collect()
+}
+
+/// a search hit with its relevance score, as `search_code` prints it
+pub fn format_scored_chunk(
+    chunk: &srag_common::types::Chunk,
+    file_path: &str,
+    score: f32,
+) -> String {
+    let header = chunk_header(chunk, file_path);
+    let header = match header.strip_suffix(") ---") {
+        Some(head) => format!("{}, score {:.2}) ---", head, score),
+        None => header,
+    };
+    format!("{}\n{}\n", header, chunk.content)
+}
+
+/// reranker logits squashed onto the same 0..=1 scale as retrieval scores
+pub fn rerank_score(logit: f32) -> f32 {
+    1.0 / (1.0 + (-logit).exp())
+}
+
+/// keep chunks in one of `languages`; an empty filter keeps everything
+pub fn filter_languages(chunks: Vec<ScoredChunk>, languages: &[Language]) -> Vec<ScoredChunk> {
+    if languages.is_empty() {
+        return chunks;
+    }
+    chunks
+        .into_iter()
+        .filter(|(chunk, _, _)| languages.contains(&chunk.language))
+        .collect()
+}
+
+/// `search_code` hits from the project's files, best first and at most
+/// `limit`. hybrid search filters languages in sql, in the full-text search
+/// and again when resolving the fused list, so the cut keeps matching chunks;
+/// plain vector hits are filtered afterwards, so the caller widens `limit`
+/// when `languages` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn search_candidates(
+    store: &Store,
+    project_id: i64,
+    query: &str,
+    vector_results: &[(usize, f32)],
+    limit: usize,
+    languages: &[Language],
+    config: &QueryConfig,
+) -> Result<Vec<ScoredChunk>, McpError> {
+    let project_files: std::collections::HashSet<String> = store
+        .list_project_files(project_id)
+        .map_err(|e| McpError::internal_error(format!("failed to list files: {}", e), None))?
+        .into_iter()
+        .map(|f| f.path)
+        .collect();
+
+    let chunks = if config.hybrid_search {
+        let fts_results = store
+            .search_fts_languages(query, project_id, languages, limit)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        crate::query::retriever::reciprocal_rank_fusion_scored(
+            vector_results,
+            &fts_results,
+            store,
+            limit,
+            crate::query::retriever::RrfWeights::from_config(config),
+            languages,
+        )
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?
+    } else {
+        let resolved = crate::query::retriever::resolve_scored(store, vector_results)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        filter_languages(resolved, languages)
+    };
+    Ok(chunks
+        .into_iter()
+        .filter(|(_, path, _)| project_files.contains(path))
+        .collect())
+}
+
+/// drop results scoring below `min_score` times the best result's score.
+/// cosine, fused rrf and reranker scores sit on different parts of 0..=1,
+/// so the threshold is taken relative to the top hit to mean the same in
+/// every search mode.
+pub fn filter_min_score(chunks: Vec<ScoredChunk>, min_score: f32) -> Vec<ScoredChunk> {
+    let best = chunks
+        .iter()
+        .map(|(_, _, score)| *score)
+        .fold(0.0f32, f32::max);
+    if best <= 0.0 {
+        return if min_score > 0.0 { Vec::new() } else { chunks };
+    }
+    chunks
+        .into_iter()
+        .filter(|(_, _, score)| *score / best >= min_score)
+        .collect()
+}
+
+/// the indexed path and `get_file` body for `file_path`, which may be
+/// relative to the project root. the file is read from disk while it
+/// exists, then from stored raw content, then rebuilt from chunks. `None`
+/// when the project has no such file.
+pub fn load_file(
+    store: &Store,
+    project_id: i64,
+    file_path: &str,
+    start_line: Option<u32>,
+    end_line: Option<u32>,
+) -> Result<Option<(String, String)>, McpError> {
+    let internal = |e: srag_common::Error| McpError::internal_error(e.to_string(), None);
+    let is_indexed = |path: &str| store.get_file_hash(project_id, path).map(|h| h.is_some());
+
+    let path = if is_indexed(file_path).map_err(internal)? {
+        file_path.to_string()
+    } else {
+        let root = store
+            .list_projects()
+            .map_err(internal)?
+            .into_iter()
+            .find(|p| p.id == Some(project_id))
+            .map(|p| p.path);
+        match root.map(|r| Path::new(&r).join(file_path).to_string_lossy().to_string()) {
+            Some(joined) if is_indexed(&joined).map_err(internal)? => joined,
+            _ => return Ok(None),
+        }
+    };
+
+    // only paths the index already knows are read, so this can't be used to
+    // pull arbitrary files off disk
+    let raw = match std::fs::read(&path) {
+        Ok(bytes) => Some(bytes),
+        Err(_) => store
+            .get_file_content(project_id, &path)
+            .map_err(internal)?,
+    };
+    let body = match (&raw, start_line.zip(end_line)) {
+        (None, None) => store
+            .reconstruct_file(project_id, &path)
+            .map_err(internal)?,
+        (None, Some(_)) => {
+            let chunks = store.get_file_chunks(project_id, &path).map_err(internal)?;
+            render_file(None, &chunks, start_line, end_line)
+        }
+        (Some(raw), _) => render_file(Some(raw), &[], start_line, end_line),
+    };
+    Ok(Some((path, body)))
+}
+
+/// the body of a `get_file` response. stored raw content is returned as-is,
+/// otherwise the file is pieced together from its chunks with markers where
+/// lines between them are missing.
+pub fn render_file(
+    raw: Option<&[u8]>,
+    chunks: &[srag_common::types::Chunk],
+    start_line: Option<u32>,
+    end_line: Option<u32>,
+) -> String {
+    let range = start_line.zip(end_line);
+    if let Some(raw) = raw {
+        let text = String::from_utf8_lossy(raw);
+        return match range {
+            Some((start, end)) => text
+                .split_inclusive('\n')
+                .skip(start.saturating_sub(1) as usize)
+                .take(end.saturating_sub(start.max(1)) as usize + 1)
+                .collect(),
+            None => text.into_owned(),
+        };
+    }
+
+    let in_range: Vec<srag_common::types::Chunk> = chunks
+        .iter()
+        .filter(|c| {
+            range.map_or(true, |(start, end)| {
+                c.end_line >= start && c.start_line <= end
+            })
+        })
+        .cloned()
+        .collect();
+    Store::reconstruct_from_chunks(&in_range, range.map_or(1, |(start, _)| start))
+}
+
+/// one call graph result line, prefixed with its distance when walking
+/// more than one level
+pub fn format_call_graph_entry(
+    entry: &srag_common::types::CallGraphEntry,
+    depth: Option<usize>,
+) -> String {
+    let scope = entry
+        .scope
+        .as_ref()
+        .map(|s| format!("{}::", s))
+        .unwrap_or_default();
+    let depth = depth.map(|d| format!("depth {}: ", d)).unwrap_or_default();
+    format!(
+        "  {}{} {}{} in {}:{}-{}\n",
+        depth,
+        entry.definition_kind,
+        scope,
+        entry.definition_name,
+        entry.file_path,
+        entry.start_line,
+        entry.end_line
+    )
+}
+
+/// one definition's signature, docstring and body, or the candidates to
+/// choose from when the name is ambiguous.
+pub fn format_definition_body(name: &str, project: &str, defs: &[DefinitionBody]) -> String {
+    let location = |def: &DefinitionBody| {
+        let e = &def.entry;
+        let scope = e
+            .scope
+            .as_ref()
+            .map(|s| format!("{}::", s))
+            .unwrap_or_default();
+        format!(
+            "{} {}{} in {}:{}-{}",
+            e.definition_kind, scope, e.definition_name, e.file_path, e.start_line, e.end_line
+        )
+    };
+
+    match defs {
+        [] => format!(
+            "no definition named '{}' found in project '{}'",
+            name, project
+        ),
+        [def] => {
+            let mut text = format!("{}\n\n", location(def));
+            if let Some(ref sig) = def.signature {
+                text.push_str(&format!("signature: {}\n", sig));
+            }
+            if let Some(ref doc) = def.docstring {
+                text.push_str(&format!("docstring:\n{}\n", doc));
+            }
+            text.push_str(&format!("body:\n{}\n", def.body));
+            text
+        }
+        _ => {
+            let mut text = format!(
+                "'{}' is ambiguous in '{}', {} definitions match. pass scope to pick one, or use get_file with a location below:\n\n",
+                name,
+                project,
+                defs.len()
+            );
+            for def in defs {
+                text.push_str(&format!("  {}\n", location(def)));
+            }
+            text
+        }
+    }
+}
+
+fn chunk_header(chunk: &srag_common::types::Chunk, file_path: &str) -> String {
+    if let Some(ref symbol) = chunk.symbol {
         format!(
             "--- {} ({}, lines {}-{}) ---",
             file_path, symbol, chunk.start_line, chunk.end_line
@@ -203,15 +692,320 @@ pub fn format_chunk(chunk: &srag_common::types::Chunk, file_path: &str) -> Strin
             "--- {} (lines {}-{}) ---",
             file_path, chunk.start_line, chunk.end_line
         )
-    };
-    format!("{}\n{}\n", header, chunk.content)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use crate::ipc::embedding::tests::CountingBackend;
+    use srag_common::types::Chunk;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_similar_by_symbol_returns_neighbours_not_itself() {
+        const DIM: usize = 4;
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/work/proj").unwrap();
+        let other = store.upsert_project("other", "/work/other").unwrap();
+        let add = |project_id: i64, path: &str, symbol: &str, vector: [f32; DIM]| {
+            let file_id = seed_file(&store, project_id, path, Language::Rust);
+            let chunk = Chunk {
+                symbol: Some(symbol.to_string()),
+                symbol_kind: Some("function".to_string()),
+                ..test_chunk(file_id, &format!("fn {}() {{}}", symbol), 1)
+            };
+            let chunk_id = seed_chunk(&store, &chunk, path);
+            let embedding_id = store.insert_embedding(chunk_id, &vector).unwrap();
+            store
+                .update_chunk_embedding_id(chunk_id, embedding_id)
+                .unwrap();
+            (chunk_id, embedding_id)
+        };
+        let (source, source_embedding) = add(
+            pid,
+            "/work/proj/src/header.rs",
+            "parse",
+            [1.0, 0.0, 0.0, 0.0],
+        );
+        let (_, footer) = add(
+            pid,
+            "/work/proj/src/footer.rs",
+            "parse_footer",
+            [0.9, 0.1, 0.0, 0.0],
+        );
+        let (_, net) = add(
+            pid,
+            "/work/proj/src/net.rs",
+            "connect",
+            [0.0, 0.0, 1.0, 0.0],
+        );
+        add(pid, "/work/proj/src/body.rs", "parse", [0.0, 1.0, 0.0, 0.0]);
+        let (foreign, foreign_embedding) = add(
+            other,
+            "/work/other/src/header.rs",
+            "parse",
+            [1.0, 0.0, 0.0, 0.0],
+        );
+
+        let params = |symbol: Option<&str>, file_path: Option<&str>| {
+            super::super::params::FindSimilarParams {
+                project: None,
+                code_snippet: None,
+                symbol: symbol.map(String::from),
+                file_path: file_path.map(String::from),
+                chunk_id: None,
+                top_k: 3,
+            }
+        };
+        assert_eq!(
+            similar_source_chunk(&store, pid, &params(Some("parse"), Some("src/header.rs")))
+                .unwrap(),
+            Some(source)
+        );
+        assert!(
+            similar_source_chunk(&store, pid, &params(Some("parse"), Some("src/none.rs"))).is_err()
+        );
+        assert!(similar_source_chunk(&store, pid, &params(None, None))
+            .unwrap()
+            .is_none());
+
+        // a chunk from another project can't be used as the source
+        let by_id = |chunk_id: i64| super::super::params::FindSimilarParams {
+            chunk_id: Some(chunk_id),
+            ..params(None, None)
+        };
+        assert_eq!(
+            similar_source_chunk(&store, pid, &by_id(source)).unwrap(),
+            Some(source)
+        );
+        assert!(similar_source_chunk(&store, pid, &by_id(foreign)).is_err());
+        assert!(store.chunk_embedding(pid, foreign, DIM).unwrap().is_none());
+
+        let (embedding_id, _) = store.chunk_embedding(pid, source, DIM).unwrap().unwrap();
+        assert_eq!(embedding_id, source_embedding);
+        // hits as the vector index would rank them; the exact search keeps
+        // the test independent of hnsw's approximate graph
+        let hits = [
+            (source_embedding as usize, 0.0),
+            (foreign_embedding as usize, 0.0),
+            (footer as usize, 0.006),
+            (net as usize, 1.0),
+        ];
+        let found = similar_chunks(&store, pid, &hits, Some(embedding_id)).unwrap();
+        let paths: Vec<&str> = found.iter().map(|(_, path)| path.as_str()).collect();
+        assert_eq!(paths, ["/work/proj/src/footer.rs", "/work/proj/src/net.rs"]);
+    }
+
+    #[test]
+    fn test_language_filter_applies_before_hybrid_cut() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/work/proj").unwrap();
+        let add = |name: &str, language: Language, content: &str| {
+            let path = format!("/work/proj/{}", name);
+            let file_id = seed_file(&store, pid, &path, language);
+            let chunk = Chunk {
+                language,
+                ..test_chunk(file_id, content, 1)
+            };
+            seed_chunk(&store, &chunk, &path);
+        };
+        // the python files outrank the rust ones on the query
+        for i in 0..6 {
+            add(
+                &format!("p{}.py", i),
+                Language::Python,
+                "def parse(): parse the config, parse again",
+            );
+        }
+        add(
+            "a.rs",
+            Language::Rust,
+            "fn parse() with other words around it",
+        );
+        add("b.rs", Language::Rust, "fn load() calls parse on the file");
+
+        let config = QueryConfig {
+            hybrid_search: true,
+            ..QueryConfig::default()
+        };
+        let all = search_candidates(&store, pid, "parse", &[], 3, &[], &config).unwrap();
+        assert!(all.iter().all(|(c, _, _)| c.language == Language::Python));
+
+        let rust =
+            search_candidates(&store, pid, "parse", &[], 3, &[Language::Rust], &config).unwrap();
+        let mut paths: Vec<&str> = rust.iter().map(|(_, p, _)| p.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/work/proj/a.rs", "/work/proj/b.rs"]);
+    }
+
+    #[test]
+    fn test_filter_languages_narrows_mixed_results() {
+        use srag_common::types::Chunk;
+        let chunk = |language: Language, path: &str| {
+            (
+                Chunk {
+                    id: None,
+                    file_id: 1,
+                    content: String::new(),
+                    symbol: None,
+                    symbol_kind: None,
+                    start_line: 1,
+                    end_line: 1,
+                    language,
+                    suspicious: false,
+                },
+                path.to_string(),
+                1.0,
+            )
+        };
+        let mixed = vec![
+            chunk(Language::Python, "a.py"),
+            chunk(Language::Rust, "b.rs"),
+            chunk(Language::TypeScript, "c.ts"),
+            chunk(Language::Rust, "d.rs"),
+        ];
+
+        let rust = parse_languages(&["Rust".to_string()]).unwrap();
+        let kept: Vec<String> = filter_languages(mixed.clone(), &rust)
+            .into_iter()
+            .map(|(_, p, _)| p)
+            .collect();
+        assert_eq!(kept, vec!["b.rs", "d.rs"]);
+
+        let several = parse_languages(&["PYTHON".to_string(), "typescript".to_string()]).unwrap();
+        assert_eq!(filter_languages(mixed.clone(), &several).len(), 2);
+        assert_eq!(filter_languages(mixed, &[]).len(), 4);
+
+        assert!(parse_languages(&["klingon".to_string()]).is_err());
+        assert!(parse_languages(&["unknown".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_min_score_and_scored_header() {
+        use srag_common::types::Chunk;
+        let hit = |symbol: Option<&str>, score: f32| {
+            let chunk = Chunk {
+                symbol: symbol.map(String::from),
+                end_line: 5,
+                ..test_chunk(1, "fn x() {}", 3)
+            };
+            (chunk, "src/x.rs".to_string(), score)
+        };
+        let hits = vec![hit(None, 0.8), hit(None, 0.4), hit(None, 0.32)];
+
+        let kept: Vec<f32> = filter_min_score(hits.clone(), 0.5)
+            .into_iter()
+            .map(|(_, _, s)| s)
+            .collect();
+        assert_eq!(kept, vec![0.8, 0.4]);
+        assert_eq!(filter_min_score(hits.clone(), 0.0).len(), 3);
+        assert_eq!(filter_min_score(hits.clone(), 1.0).len(), 1);
+
+        // fused rrf scores sit lower but the same threshold keeps the same share
+        let fused: Vec<ScoredChunk> = hits
+            .iter()
+            .cloned()
+            .map(|(c, p, s)| (c, p, s * 0.5))
+            .collect();
+        assert_eq!(filter_min_score(fused, 0.5).len(), 2);
+
+        let (chunk, path, score) = hit(Some("x"), 0.876);
+        assert_eq!(
+            format_scored_chunk(&chunk, &path, score),
+            "--- src/x.rs (x, lines 3-5, score 0.88) ---\nfn x() {}\n"
+        );
+        let (chunk, path, score) = hit(None, 0.3);
+        assert!(format_scored_chunk(&chunk, &path, score)
+            .starts_with("--- src/x.rs (lines 3-5, score 0.30) ---"));
+
+        assert!((rerank_score(0.0) - 0.5).abs() < 1e-6);
+        assert!(rerank_score(4.0) > rerank_score(1.0));
+    }
+
+    #[test]
+    fn test_load_file_reads_disk_then_falls_back_to_chunks() {
+        let (store, _db) = test_store();
+        let root = TempDir::new().unwrap();
+        let source = "use std::io;\n\nconst X: u8 = 1;\n\nfn a() {\n    1\n}\n\nfn b() {}\n";
+        let file = root.path().join("lib.rs");
+        std::fs::write(&file, source).unwrap();
+        let path = file.to_string_lossy().to_string();
+
+        let pid = store
+            .upsert_project("proj", &root.path().to_string_lossy())
+            .unwrap();
+        let file_id = seed_file(&store, pid, &path, Language::Rust);
+        // the chunker only kept the two functions
+        for (content, start) in [("fn a() {\n    1\n}", 5), ("fn b() {}", 9)] {
+            seed_chunk(&store, &test_chunk(file_id, content, start), &path);
+        }
+
+        let (resolved, whole) = load_file(&store, pid, &path, None, None).unwrap().unwrap();
+        assert_eq!(resolved, path);
+        assert_eq!(whole, source);
+
+        // relative to the project root, sliced to the true lines
+        let (_, slice) = load_file(&store, pid, "lib.rs", Some(3), Some(6))
+            .unwrap()
+            .unwrap();
+        let expected: String = source.split_inclusive('\n').skip(2).take(4).collect();
+        assert_eq!(slice, expected);
+
+        assert!(load_file(&store, pid, "missing.rs", None, None)
+            .unwrap()
+            .is_none());
+        // indexed files only, even if the path exists on disk
+        std::fs::write(root.path().join("other.rs"), "secret").unwrap();
+        assert!(load_file(&store, pid, "other.rs", None, None)
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_file(&file).unwrap();
+        let (_, rebuilt) = load_file(&store, pid, &path, None, None).unwrap().unwrap();
+        assert_eq!(
+            rebuilt,
+            "... (lines 1-4 omitted) ...\nfn a() {\n    1\n}\n... (line 8 omitted) ...\nfn b() {}\n"
+        );
+    }
+
+    #[test]
+    fn test_render_file_raw_keeps_gaps() {
+        use srag_common::types::{Chunk, Language};
+        let original = "use std::io;\n\n// between chunks\nfn a() {}\r\n\n\nfn b() {}\n";
+        // chunking only kept the two functions
+        let chunk = |content: &str, line: u32| Chunk {
+            id: None,
+            file_id: 1,
+            content: content.to_string(),
+            symbol: None,
+            symbol_kind: None,
+            start_line: line,
+            end_line: line,
+            language: Language::Rust,
+            suspicious: false,
+        };
+        let chunks = vec![chunk("fn a() {}", 4), chunk("fn b() {}", 7)];
+
+        assert_eq!(
+            render_file(None, &chunks, None, None),
+            "... (lines 1-3 omitted) ...\nfn a() {}\n... (lines 5-6 omitted) ...\nfn b() {}\n"
+        );
+        assert_eq!(
+            render_file(None, &chunks, Some(2), Some(4)),
+            "... (lines 2-3 omitted) ...\nfn a() {}\n"
+        );
+        assert_eq!(
+            render_file(Some(original.as_bytes()), &chunks, None, None).as_bytes(),
+            original.as_bytes()
+        );
+        assert_eq!(
+            render_file(Some(original.as_bytes()), &chunks, Some(3), Some(5)),
+            "// between chunks\nfn a() {}\r\n\n"
+        );
+    }
+
     #[test]
     fn test_auto_index_result_struct() {
         let result = AutoIndexResult {
@@ -222,6 +1016,204 @@ mod tests {
         assert!(result.summary.contains("indexed"));
     }
 
+    #[test]
+    fn test_project_stats_served_from_cache() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("cached", "/tmp/cached").unwrap();
+        let cache = ProjectStatsCache::default();
+        let loads = std::cell::Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            load_project_stats(&store, pid)
+        };
+
+        let generation = store.project_generation(pid).unwrap();
+        let first = cache.get_or_load(pid, generation, load).unwrap();
+        let second = cache.get_or_load(pid, generation, load).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(loads.get(), 1);
+
+        store
+            .update_project_indexed_at(pid, std::time::SystemTime::now())
+            .unwrap();
+        let generation = store.project_generation(pid).unwrap();
+        cache.get_or_load(pid, generation, load).unwrap();
+        assert_eq!(loads.get(), 2);
+    }
+
+    #[test]
+    fn test_project_stats_cache_retain() {
+        let cache = ProjectStatsCache::default();
+        let stats = ProjectStats {
+            files: 1,
+            chunks: 2,
+        };
+        cache.get_or_load(1, 0, || Ok(stats)).unwrap();
+        cache.get_or_load(2, 0, || Ok(stats)).unwrap();
+        cache.retain(&[2]);
+
+        let mut reloaded = false;
+        cache
+            .get_or_load(1, 0, || {
+                reloaded = true;
+                Ok(stats)
+            })
+            .unwrap();
+        assert!(reloaded);
+    }
+
+    fn search_key(query: &str) -> SearchKey {
+        SearchKey {
+            project_id: 1,
+            query: query.into(),
+            top_k: 5,
+            mode: String::new(),
+        }
+    }
+
+    async fn cached_search(
+        cache: &SearchCache,
+        embedder: &CountingBackend,
+        query: &str,
+        stamp: (i64, u64),
+        ttl: Duration,
+    ) -> Vec<ScoredChunk> {
+        use crate::ipc::embedding::EmbeddingBackend;
+        cache
+            .get_or_search(search_key(query), stamp, ttl, 2, || async {
+                embedder.embed(&[query.to_string()]).await.unwrap();
+                Ok(vec![(
+                    Chunk {
+                        id: Some(1),
+                        file_id: 1,
+                        content: query.into(),
+                        symbol: None,
+                        symbol_kind: None,
+                        start_line: 1,
+                        end_line: 1,
+                        language: Language::Rust,
+                        suspicious: false,
+                    },
+                    "/tmp/a.rs".into(),
+                    0.9,
+                )])
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_search_cache_skips_embedding_within_ttl() {
+        let cache = SearchCache::default();
+        let embedder = CountingBackend::new(4);
+        let ttl = Duration::from_secs(60);
+
+        let first = cached_search(&cache, &embedder, "parse config", (0, 0), ttl).await;
+        let second = cached_search(&cache, &embedder, "parse config", (0, 0), ttl).await;
+        assert_eq!(embedder.calls(), 1);
+        assert_eq!(first[0].0.content, second[0].0.content);
+
+        // a re-index or invalidated index cache makes the entry stale
+        cached_search(&cache, &embedder, "parse config", (1, 0), ttl).await;
+        cached_search(&cache, &embedder, "parse config", (1, 1), ttl).await;
+        assert_eq!(embedder.calls(), 3);
+
+        // and so does an expired ttl
+        cached_search(&cache, &embedder, "parse config", (1, 1), Duration::ZERO).await;
+        assert_eq!(embedder.calls(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_search_cache_evicts_least_recently_used() {
+        let cache = SearchCache::default();
+        let embedder = CountingBackend::new(4);
+        let ttl = Duration::from_secs(60);
+
+        cached_search(&cache, &embedder, "a", (0, 0), ttl).await;
+        cached_search(&cache, &embedder, "b", (0, 0), ttl).await;
+        cached_search(&cache, &embedder, "a", (0, 0), ttl).await;
+        // over the cap of two: "b" was used least recently
+        cached_search(&cache, &embedder, "c", (0, 0), ttl).await;
+        assert_eq!(embedder.calls(), 3);
+
+        cached_search(&cache, &embedder, "a", (0, 0), ttl).await;
+        assert_eq!(embedder.calls(), 3);
+        cached_search(&cache, &embedder, "b", (0, 0), ttl).await;
+        assert_eq!(embedder.calls(), 4);
+    }
+
+    fn definition(scope: &str) -> DefinitionBody {
+        DefinitionBody {
+            entry: srag_common::types::CallGraphEntry {
+                definition_name: "parse_line".into(),
+                definition_kind: "function".into(),
+                file_path: "/tmp/proj/src/lib.rs".into(),
+                start_line: 12,
+                end_line: 14,
+                scope: Some(scope.into()),
+            },
+            signature: Some("pub fn parse_line(&self, line: &str) -> Option<Token>".into()),
+            docstring: Some("/// parse one line".into()),
+            body: "pub fn parse_line(&self, line: &str) -> Option<Token> {\n    self.lex(line)\n}"
+                .into(),
+        }
+    }
+
+    #[test]
+    fn test_format_definition_body_single() {
+        let text = format_definition_body("parse_line", "proj", &[definition("Parser")]);
+        assert!(text.starts_with("function Parser::parse_line in /tmp/proj/src/lib.rs:12-14"));
+        assert!(text.contains("signature: pub fn parse_line(&self, line: &str) -> Option<Token>"));
+        assert!(text.contains("docstring:\n/// parse one line"));
+        assert!(text.contains("body:\npub fn parse_line"));
+        assert!(text.contains("self.lex(line)"));
+    }
+
+    #[test]
+    fn test_format_definition_body_ambiguous() {
+        let text = format_definition_body(
+            "parse_line",
+            "proj",
+            &[definition("Parser"), definition("Lexer")],
+        );
+        assert!(text.contains("'parse_line' is ambiguous in 'proj', 2 definitions match"));
+        assert!(text.contains("pass scope"));
+        assert!(text.contains("Parser::parse_line"));
+        assert!(text.contains("Lexer::parse_line"));
+        assert!(!text.contains("self.lex(line)"));
+    }
+
+    #[test]
+    fn test_format_call_graph_entry() {
+        let entry = definition("Parser").entry;
+        assert_eq!(
+            format_call_graph_entry(&entry, None),
+            "  function Parser::parse_line in /tmp/proj/src/lib.rs:12-14\n"
+        );
+        assert!(format_call_graph_entry(&entry, Some(2)).starts_with("  depth 2: function"));
+    }
+
+    #[test]
+    fn test_format_definition_body_missing() {
+        let text = format_definition_body("nope", "proj", &[]);
+        assert_eq!(text, "no definition named 'nope' found in project 'proj'");
+    }
+
+    #[test]
+    fn test_summary_line_picks_done_line() {
+        let stdout =
+            "indexing myproj\ndone: 3 files indexed, 7 chunks embedded, 1 skipped (unchanged)\n";
+        assert_eq!(
+            summary_line(stdout),
+            "done: 3 files indexed, 7 chunks embedded, 1 skipped (unchanged)"
+        );
+    }
+
+    #[test]
+    fn test_summary_line_fallback() {
+        assert_eq!(summary_line(""), "indexing completed");
+    }
+
     #[test]
     fn test_is_project_directory_with_cargo_toml() {
         let dir = TempDir::new().unwrap();
@@ -15,9 +15,22 @@ use rmcp::{
 
 use crate::config::Config;
 use crate::index::store::Store;
-use helpers::{ensure_index_exists, format_chunk, resolve_project};
+use crate::ipc::embedding::{connect_for_embedding, Embedder, EmbeddingBackend};
+use helpers::{
+    ensure_index_exists, filter_min_score, format_call_graph_entry, format_chunk,
+    format_definition_body, format_scored_chunk, format_snippet, load_file, parse_languages,
+    rerank_score, resolve_project, search_candidates, similar_chunks, similar_source_chunk,
+    ProjectStatsCache, SearchCache, SearchKey,
+};
 use params::*;
 
+/// markers around matched terms in text_search snippets
+const SNIPPET_OPEN: &str = ">>";
+const SNIPPET_CLOSE: &str = "<<";
+const MAX_IMPACT_DEPTH: usize = 10;
+/// deepest find_callers / find_callees walk
+const MAX_CALL_DEPTH: usize = 5;
+
 #[derive(Clone)]
 struct RateLimiter {
     state: Arc<Mutex<RateLimiterState>>,
@@ -63,6 +76,8 @@ impl RateLimiter {
 pub struct SragMcpServer {
     tool_router: rmcp::handler::server::tool::ToolRouter<Self>,
     rate_limiter: RateLimiter,
+    stats_cache: ProjectStatsCache,
+    search_cache: SearchCache,
 }
 
 #[tool_router]
@@ -71,6 +86,8 @@ impl SragMcpServer {
         Self {
             tool_router: Self::tool_router(),
             rate_limiter: RateLimiter::new(60, 60),
+            stats_cache: ProjectStatsCache::default(),
+            search_cache: SearchCache::default(),
         }
     }
 
@@ -84,8 +101,13 @@ impl SragMcpServer {
         Ok(())
     }
 
-    #[tool(description = "list all indexed projects with their paths")]
-    async fn list_projects(&self) -> Result<CallToolResult, McpError> {
+    #[tool(
+        description = "list indexed projects with their paths. use limit/offset to page through large installs"
+    )]
+    async fn list_projects(
+        &self,
+        rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<ListProjectsParams>,
+    ) -> Result<CallToolResult, McpError> {
         self.check_rate_limit()?;
         let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
         let db_path = config.db_path();
@@ -123,16 +145,36 @@ impl SragMcpServer {
             ));
         }
 
-        for p in &projects {
-            let files = store.file_count(p.id).map_err(|e| {
-                McpError::internal_error(format!("Failed to get file count: {}", e), None)
-            })?;
-            let chunks = store.chunk_count(p.id).map_err(|e| {
-                McpError::internal_error(format!("Failed to get chunk count: {}", e), None)
-            })?;
+        let live_ids: Vec<i64> = projects.iter().filter_map(|p| p.id).collect();
+        self.stats_cache.retain(&live_ids);
+
+        let total = projects.len();
+        let page: Vec<_> = projects
+            .iter()
+            .skip(params.offset)
+            .take(params.limit)
+            .collect();
+
+        for p in &page {
+            let Some(pid) = p.id else { continue };
+            let generation = store
+                .project_generation(pid)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            let stats = self
+                .stats_cache
+                .get_or_load(pid, generation, || helpers::load_project_stats(&store, pid))?;
             text.push_str(&format!(
                 "{}: {} ({} files, {} chunks)\n",
-                p.name, p.path, files, chunks
+                p.name, p.path, stats.files, stats.chunks
+            ));
+        }
+
+        if page.len() < total {
+            text.push_str(&format!(
+                "\nshowing {} of {} projects (offset {})\n",
+                page.len(),
+                total,
+                params.offset
             ));
         }
 
@@ -140,13 +182,20 @@ impl SragMcpServer {
     }
 
     #[tool(
-        description = "semantic search for code - finds relevant code chunks using vector similarity. use this to find implementations, patterns, or examples"
+        description = "semantic search for code - finds relevant code chunks using vector similarity. use this to find implementations, patterns, or examples. pass languages to restrict results, e.g. [\"rust\"]"
     )]
     async fn search_code(
         &self,
         rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<SearchCodeParams>,
     ) -> Result<CallToolResult, McpError> {
         self.check_rate_limit()?;
+        let languages = parse_languages(&params.languages)?;
+        if !(0.0..=1.0).contains(&params.min_score) {
+            return Err(McpError::invalid_params(
+                "min_score must be between 0.0 and 1.0",
+                None,
+            ));
+        }
         let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
         config
             .ensure_dirs()
@@ -158,96 +207,115 @@ impl SragMcpServer {
         let store =
             Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
         let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
-
-        crate::ipc::lifecycle::ensure_ml_service_running(&config)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-        let addr = crate::ipc::client::read_service_addr(&Config::port_file_path())
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-        let client = crate::ipc::client::MlClient::connect(addr)
-            .await
+        let _ = store.touch_project_queried(project_id);
+        let config = crate::query::project_config(&store, project_id, &config)
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        let query_vectors = client
-            .embed(std::slice::from_ref(&params.query))
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-        let query_vec = query_vectors
-            .into_iter()
-            .next()
-            .ok_or_else(|| McpError::internal_error("no embedding returned", None))?;
-
-        let search_k = if config.query.rerank || config.query.hybrid_search {
-            config.query.broad_k
-        } else {
-            params.top_k
+        let key = SearchKey {
+            project_id,
+            query: params.query.clone(),
+            top_k: params.top_k,
+            mode: format!(
+                "{:?}/{}/{}",
+                languages, config.query.hybrid_search, config.query.rerank
+            ),
         };
-
-        let vector_results = crate::index::hnsw::search_cached(
-            &config.vectors_dir(),
-            crate::config::EMBEDDING_DIMENSION,
-            &store,
-            &query_vec,
-            search_k,
-            config.query.ef_search,
-        )
-        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-        let project_files: std::collections::HashSet<String> = store
-            .list_project_files(project_id)
-            .map_err(|e| McpError::internal_error(format!("failed to list files: {}", e), None))?
-            .into_iter()
-            .map(|f| f.path)
-            .collect();
-
-        let context_chunks: Vec<_> = if config.query.hybrid_search {
-            let fts_results = store
-                .search_fts_project(&params.query, Some(project_id), search_k)
+        let generation = store
+            .project_generation(project_id)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let stamp = (generation, crate::index::hnsw::cache_epoch());
+        let ttl = std::time::Duration::from_secs(config.mcp.search_cache_ttl_secs);
+        let max_entries = config.mcp.search_cache_entries;
+        // owns the store: a borrowed connection can't be held across awaits
+        let search = move || async move {
+            let mut client = connect_for_embedding(&config)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            // remote embeddings only need the ml service for reranking
+            if config.query.rerank && client.is_none() {
+                crate::ipc::lifecycle::ensure_ml_service_running(&config)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                client = Some(
+                    crate::ipc::client::MlClient::connect_service(&config)
+                        .await
+                        .map_err(|e| McpError::internal_error(e.to_string(), None))?,
+                );
+            }
+            let embedder = Embedder::new(&config.embedding, client.as_ref())
                 .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-            let fused = crate::query::retriever::reciprocal_rank_fusion(
-                &vector_results,
-                &fts_results,
+            let query_vectors = embedder
+                .embed(std::slice::from_ref(&params.query))
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            let query_vec = query_vectors
+                .into_iter()
+                .next()
+                .ok_or_else(|| McpError::internal_error("no embedding returned", None))?;
+
+            // a language filter discards results, so start from a wider pool
+            let search_k =
+                if config.query.rerank || config.query.hybrid_search || !languages.is_empty() {
+                    config.query.broad_k
+                } else {
+                    params.top_k
+                };
+
+            let vector_results = crate::index::hnsw::search_cached(
+                &config.vectors_dir(),
+                crate::config::EMBEDDING_DIMENSION,
+                config.embedding.distance,
                 &store,
+                &query_vec,
                 search_k,
+                config.query.ef_search,
             )
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-            fused
-                .into_iter()
-                .filter(|(_, path)| project_files.contains(path))
-                .collect()
-        } else {
-            let all_chunks = crate::query::retriever::resolve_results(&store, &vector_results)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-            all_chunks
-                .into_iter()
-                .filter(|(_, path)| project_files.contains(path))
-                .collect()
-        };
+            let context_chunks = search_candidates(
+                &store,
+                project_id,
+                &params.query,
+                &vector_results,
+                search_k,
+                &languages,
+                &config.query,
+            )?;
 
-        let context_chunks = if config.query.rerank && context_chunks.len() > 1 {
-            let documents: Vec<String> = context_chunks
-                .iter()
-                .map(|(c, _)| c.content.clone())
-                .collect();
-            match client.rerank(&params.query, &documents, params.top_k).await {
-                Ok(ranked) => ranked
-                    .into_iter()
-                    .filter_map(|(idx, _)| context_chunks.get(idx).cloned())
-                    .collect(),
-                Err(e) => {
-                    tracing::warn!("reranking failed, falling back to original order: {}", e);
-                    context_chunks.into_iter().take(params.top_k).collect()
+            let reranker = client
+                .as_ref()
+                .filter(|_| config.query.rerank && context_chunks.len() > 1);
+            let context_chunks = if let Some(client) = reranker {
+                let documents: Vec<String> = context_chunks
+                    .iter()
+                    .map(|(c, _, _)| c.content.clone())
+                    .collect();
+                match client.rerank(&params.query, &documents, params.top_k).await {
+                    Ok(ranked) => ranked
+                        .into_iter()
+                        .filter_map(|(idx, score)| {
+                            let (chunk, path, _) = context_chunks.get(idx)?.clone();
+                            Some((chunk, path, rerank_score(score)))
+                        })
+                        .collect(),
+                    Err(e) => {
+                        tracing::warn!("reranking failed, falling back to original order: {}", e);
+                        context_chunks.into_iter().take(params.top_k).collect()
+                    }
                 }
-            }
-        } else {
-            context_chunks
-                .into_iter()
-                .take(params.top_k)
-                .collect::<Vec<_>>()
+            } else {
+                context_chunks
+                    .into_iter()
+                    .take(params.top_k)
+                    .collect::<Vec<_>>()
+            };
+            Ok(context_chunks)
         };
+        let context_chunks = self
+            .search_cache
+            .get_or_search(key, stamp, ttl, max_entries, search)
+            .await?;
+        let context_chunks = filter_min_score(context_chunks, params.min_score);
 
         let mut text = String::new();
         if let Some(result) = auto_indexed {
@@ -260,8 +328,8 @@ impl SragMcpServer {
             "search results from project '{}':\n\n",
             project_name
         ));
-        for (chunk, file_path) in &context_chunks {
-            text.push_str(&format_chunk(chunk, file_path));
+        for (chunk, file_path, score) in &context_chunks {
+            text.push_str(&format_scored_chunk(chunk, file_path, *score));
             text.push('\n');
         }
 
@@ -269,7 +337,7 @@ impl SragMcpServer {
     }
 
     #[tool(
-        description = "find code similar to a given snippet - useful for finding reusable patterns, duplicate code, or related implementations"
+        description = "find code similar to a given snippet, or to an indexed symbol or chunk without pasting it - useful for finding reusable patterns, duplicate code, or related implementations"
     )]
     async fn find_similar_code(
         &self,
@@ -286,53 +354,61 @@ impl SragMcpServer {
         let db_path = config.db_path();
         let store =
             Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
-        let (_, project_name) = resolve_project(&store, params.project.as_deref())?;
-
-        crate::ipc::lifecycle::ensure_ml_service_running(&config)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-        let addr = crate::ipc::client::read_service_addr(&Config::port_file_path())
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-        let client = crate::ipc::client::MlClient::connect(addr)
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-        let snippet_vectors = client
-            .embed(std::slice::from_ref(&params.code_snippet))
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-        let snippet_vec = snippet_vectors
-            .into_iter()
-            .next()
-            .ok_or_else(|| McpError::internal_error("no embedding returned", None))?;
+        let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
+        let _ = store.touch_project_queried(project_id);
+
+        // an indexed chunk already has its embedding; only a snippet needs the model
+        let source = similar_source_chunk(&store, project_id, &params)?;
+        let (query_vec, exclude_embedding) = match source {
+            Some(chunk_id) => {
+                let (embedding_id, vector) = store
+                    .chunk_embedding(project_id, chunk_id, crate::config::EMBEDDING_DIMENSION)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                    .ok_or_else(|| {
+                        McpError::invalid_params(
+                            format!(
+                                "chunk {} has no embedding, run 'srag reembed {}'",
+                                chunk_id, project_name
+                            ),
+                            None,
+                        )
+                    })?;
+                (vector, Some(embedding_id))
+            }
+            None => {
+                let snippet = params.code_snippet.as_ref().ok_or_else(|| {
+                    McpError::invalid_params("pass a code_snippet, a symbol or a chunk_id", None)
+                })?;
+                let client = connect_for_embedding(&config)
+                    .await
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let embedder = Embedder::new(&config.embedding, client.as_ref())
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let snippet_vectors = embedder
+                    .embed(std::slice::from_ref(snippet))
+                    .await
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                let vector = snippet_vectors
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| McpError::internal_error("no embedding returned", None))?;
+                (vector, None)
+            }
+        };
 
+        // one extra so leaving out the source still fills top_k
         let results = crate::index::hnsw::search_cached(
             &config.vectors_dir(),
             crate::config::EMBEDDING_DIMENSION,
+            config.embedding.distance,
             &store,
-            &snippet_vec,
-            params.top_k * 4,
+            &query_vec,
+            params.top_k * 4 + 1,
             config.query.ef_search,
         )
         .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        let all_chunks = crate::query::retriever::resolve_results(&store, &results)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-        let project_id = store.get_project_id(&project_name).map_err(|e| {
-            McpError::internal_error(format!("Failed to get project ID: {}", e), None)
-        })?;
-        let project_files: std::collections::HashSet<String> = store
-            .list_project_files(project_id)
-            .map_err(|e| McpError::internal_error(format!("Failed to list files: {}", e), None))?
-            .into_iter()
-            .map(|f| f.path)
-            .collect();
-
-        let context_chunks: Vec<_> = all_chunks
-            .into_iter()
-            .filter(|(_, path)| project_files.contains(path))
-            .collect();
+        let context_chunks = similar_chunks(&store, project_id, &results, exclude_embedding)?;
 
         let mut text = String::new();
         if let Some(result) = auto_indexed {
@@ -419,32 +495,24 @@ impl SragMcpServer {
             Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
         let (project_id, _) = resolve_project(&store, params.project.as_deref())?;
 
-        let chunks = store
-            .get_file_chunks(project_id, &params.file_path)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
-
-        if chunks.is_empty() {
+        let Some((path, content)) = load_file(
+            &store,
+            project_id,
+            &params.file_path,
+            params.start_line,
+            params.end_line,
+        )?
+        else {
             return Ok(CallToolResult::success(vec![Content::text(format!(
                 "file '{}' not found in project",
                 params.file_path
             ))]));
-        }
-
-        let mut content = String::new();
-        for chunk in &chunks {
-            if let (Some(start), Some(end)) = (params.start_line, params.end_line) {
-                if chunk.end_line < start || chunk.start_line > end {
-                    continue;
-                }
-            }
-            content.push_str(&chunk.content);
-            content.push('\n');
-        }
+        };
 
         let header = if let (Some(start), Some(end)) = (params.start_line, params.end_line) {
-            format!("--- {} (lines {}-{}) ---\n", params.file_path, start, end)
+            format!("--- {} (lines {}-{}) ---\n", path, start, end)
         } else {
-            format!("--- {} ---\n", params.file_path)
+            format!("--- {} ---\n", path)
         };
 
         Ok(CallToolResult::success(vec![Content::text(format!(
@@ -502,7 +570,7 @@ impl SragMcpServer {
     }
 
     #[tool(
-        description = "full-text keyword search - searches for exact terms in code. use when you know specific identifiers, strings, or keywords to find"
+        description = "full-text keyword search - searches for exact terms in code. use when you know specific identifiers, strings, or keywords to find. returns an excerpt per match with hits wrapped in >> <<; use get_file for the full code. set mode to \"phrase\" for an exact phrase or \"raw\" for fts5 operators (AND, OR, NOT, NEAR, prefix*)"
     )]
     async fn text_search(
         &self,
@@ -519,13 +587,20 @@ impl SragMcpServer {
         let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
 
         let results = store
-            .search_fts_project_paginated(
+            .search_fts_snippets(
                 &params.query,
+                params.mode.into(),
                 Some(project_id),
                 params.limit,
                 params.offset,
+                SNIPPET_OPEN,
+                SNIPPET_CLOSE,
             )
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            .map_err(|e| match e {
+                // a mistake in a raw query is the caller's to fix
+                srag_common::Error::Query(msg) => McpError::invalid_params(msg, None),
+                e => McpError::internal_error(e.to_string(), None),
+            })?;
 
         if results.is_empty() {
             return Ok(CallToolResult::success(vec![Content::text(format!(
@@ -538,10 +613,10 @@ impl SragMcpServer {
             "text search results for '{}' in '{}':\n\n",
             params.query, project_name
         );
-        for (chunk_id, _score) in &results {
+        for (chunk_id, _score, snippet) in &results {
             match store.get_chunk_by_id(*chunk_id) {
                 Ok(Some((chunk, file_path))) => {
-                    text.push_str(&format_chunk(&chunk, &file_path));
+                    text.push_str(&format_snippet(&chunk, &file_path, snippet));
                     text.push('\n');
                 }
                 Ok(None) => {
@@ -557,7 +632,45 @@ impl SragMcpServer {
     }
 
     #[tool(
-        description = "find all functions that call a specific function - useful for understanding dependencies and impact of changes"
+        description = "exact substring search, like grep - matches text the keyword index splits up, such as foo_bar, ::new or a != b. case-sensitive. returns path:line: text for each matching line. slower than text_search since it scans every chunk, and stops at 500 chunks"
+    )]
+    async fn literal_search(
+        &self,
+        rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<LiteralSearchParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_rate_limit()?;
+        let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let _ = ensure_index_exists(&config).await?;
+
+        let db_path = config.db_path();
+        let store =
+            Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
+
+        let chunks = store
+            .search_literal(&params.query, Some(project_id), params.limit)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let hits = super::search_cmd::literal_hits(&chunks, &params.query);
+        if hits.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "no matches for '{}'",
+                params.query
+            ))]));
+        }
+
+        let mut text = format!(
+            "literal matches for '{}' in '{}':\n\n",
+            params.query, project_name
+        );
+        for (path, line, content) in hits {
+            text.push_str(&format!("{}:{}: {}\n", path, line, content));
+        }
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "find all functions that call a specific function - useful for understanding dependencies and impact of changes. set depth > 1 to also follow callers of callers"
     )]
     async fn find_callers(
         &self,
@@ -573,9 +686,26 @@ impl SragMcpServer {
             Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
         let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
 
-        let callers = store
-            .find_callers(project_id, &params.function_name)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let depth = params.depth.clamp(1, MAX_CALL_DEPTH);
+        let (callers, truncated) = if depth == 1 {
+            let direct = store
+                .find_callers(project_id, &params.function_name)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            (
+                direct.into_iter().map(|e| (None, e)).collect::<Vec<_>>(),
+                false,
+            )
+        } else {
+            let walk = store
+                .find_callers_transitive(project_id, &params.function_name, depth)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            let entries = walk
+                .entries
+                .into_iter()
+                .map(|e| (Some(e.depth), e.entry))
+                .collect();
+            (entries, walk.truncated)
+        };
 
         if callers.is_empty() {
             return Ok(CallToolResult::success(vec![Content::text(format!(
@@ -588,14 +718,156 @@ impl SragMcpServer {
             "functions that call '{}' in '{}':\n\n",
             params.function_name, project_name
         );
-        for entry in &callers {
+        for (distance, entry) in &callers {
+            text.push_str(&format_call_graph_entry(entry, *distance));
+        }
+        if truncated {
+            text.push_str("\n(truncated, lower depth to narrow the result)\n");
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "find everything that would be affected by changing a function - walks callers transitively, nearest first"
+    )]
+    async fn impact(
+        &self,
+        rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<ImpactParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_rate_limit()?;
+        let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let _ = ensure_index_exists(&config).await?;
+
+        let db_path = config.db_path();
+        let store =
+            Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
+
+        let max_depth = params.max_depth.clamp(1, MAX_IMPACT_DEPTH);
+        let impact = store
+            .find_callers_transitive(project_id, &params.function_name, max_depth)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        if impact.entries.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "nothing calls '{}' in project '{}'",
+                params.function_name, project_name
+            ))]));
+        }
+
+        let mut text = format!(
+            "changing '{}' in '{}' may affect {} definitions:\n\n",
+            params.function_name,
+            project_name,
+            impact.entries.len()
+        );
+        for item in &impact.entries {
+            text.push_str(&format_call_graph_entry(&item.entry, Some(item.depth)));
+        }
+        if impact.truncated {
+            text.push_str("\n(truncated, lower max_depth to narrow the result)\n");
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "list every function, type and other definition in a project with its file and line range - a symbol outline of the project. pass kind to list one kind, e.g. \"struct\""
+    )]
+    async fn list_definitions(
+        &self,
+        rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<ListDefinitionsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_rate_limit()?;
+        let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let _ = ensure_index_exists(&config).await?;
+
+        let db_path = config.db_path();
+        let store =
+            Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
+
+        let kind = params
+            .kind
+            .as_deref()
+            .map(str::trim)
+            .filter(|k| !k.is_empty());
+        let total = store
+            .count_definitions(project_id, kind)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            as usize;
+
+        if total == 0 {
+            return Ok(CallToolResult::success(vec![Content::text(match kind {
+                Some(kind) => format!(
+                    "no '{}' definitions found in project '{}'",
+                    kind, project_name
+                ),
+                None => format!("no definitions found in project '{}'", project_name),
+            })]));
+        }
+
+        let page = store
+            .definitions_page(project_id, kind, params.offset, Some(params.limit))
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let mut text = format!("definitions in '{}':\n\n", project_name);
+        for entry in &page {
+            text.push_str(&format_call_graph_entry(entry, None));
+        }
+        if page.len() < total {
+            text.push_str(&format!(
+                "\nshowing {} of {} definitions (offset {})\n",
+                page.len(),
+                total,
+                params.offset
+            ));
+        }
+
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "list the largest functions and methods in a project by line count - useful for finding refactoring candidates"
+    )]
+    async fn largest_functions(
+        &self,
+        rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<LargestFunctionsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_rate_limit()?;
+        let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let _ = ensure_index_exists(&config).await?;
+
+        let db_path = config.db_path();
+        let store =
+            Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
+
+        let entries = store
+            .top_symbols_by_size(project_id, params.limit)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        if entries.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(format!(
+                "no functions found in project '{}'",
+                project_name
+            ))]));
+        }
+
+        let mut text = format!("largest functions in '{}':\n\n", project_name);
+        for entry in &entries {
             let scope = entry
                 .scope
                 .as_ref()
                 .map(|s| format!("{}::", s))
                 .unwrap_or_default();
             text.push_str(&format!(
-                "  {} {}{} in {}:{}-{}\n",
+                "  {} lines: {} {}{} in {}:{}-{}\n",
+                entry.end_line - entry.start_line + 1,
                 entry.definition_kind,
                 scope,
                 entry.definition_name,
@@ -609,7 +881,33 @@ impl SragMcpServer {
     }
 
     #[tool(
-        description = "find all functions called by a specific function - useful for understanding what a function depends on"
+        description = "get a function's signature, docstring and full body in one call. pass scope (e.g. the impl or class) when several definitions share the name"
+    )]
+    async fn get_definition_body(
+        &self,
+        rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<GetDefinitionBodyParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_rate_limit()?;
+        let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let _ = ensure_index_exists(&config).await?;
+
+        let db_path = config.db_path();
+        let store =
+            Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
+
+        let defs = store
+            .find_definition_bodies(project_id, &params.function_name, params.scope.as_deref())
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            format_definition_body(&params.function_name, &project_name, &defs),
+        )]))
+    }
+
+    #[tool(
+        description = "find all functions called by a specific function - useful for understanding what a function depends on. set depth > 1 to follow the calls further down"
     )]
     async fn find_callees(
         &self,
@@ -625,9 +923,26 @@ impl SragMcpServer {
             Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
         let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
 
-        let callees = store
-            .find_callees(project_id, &params.function_name)
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let depth = params.depth.clamp(1, MAX_CALL_DEPTH);
+        let (callees, truncated) = if depth == 1 {
+            let direct = store
+                .find_callees(project_id, &params.function_name)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            (
+                direct.into_iter().map(|e| (None, e)).collect::<Vec<_>>(),
+                false,
+            )
+        } else {
+            let walk = store
+                .find_callees_transitive(project_id, &params.function_name, depth)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            let entries = walk
+                .entries
+                .into_iter()
+                .map(|e| (Some(e.depth), e.entry))
+                .collect();
+            (entries, walk.truncated)
+        };
 
         if callees.is_empty() {
             return Ok(CallToolResult::success(vec![Content::text(format!(
@@ -640,25 +955,54 @@ impl SragMcpServer {
             "functions called by '{}' in '{}':\n\n",
             params.function_name, project_name
         );
-        for entry in &callees {
-            let scope = entry
-                .scope
-                .as_ref()
-                .map(|s| format!("{}::", s))
-                .unwrap_or_default();
-            text.push_str(&format!(
-                "  {} {}{} in {}:{}-{}\n",
-                entry.definition_kind,
-                scope,
-                entry.definition_name,
-                entry.file_path,
-                entry.start_line,
-                entry.end_line
-            ));
+        for (distance, entry) in &callees {
+            text.push_str(&format_call_graph_entry(entry, *distance));
+        }
+        if truncated {
+            text.push_str("\n(truncated, lower depth to narrow the result)\n");
         }
 
         Ok(CallToolResult::success(vec![Content::text(text)]))
     }
+
+    #[tool(
+        description = "incrementally re-index a project after editing files so searches see the changes. only changed files are re-embedded"
+    )]
+    async fn reindex_project(
+        &self,
+        rmcp::handler::server::wrapper::Parameters(params): rmcp::handler::server::wrapper::Parameters<ReindexProjectParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.check_rate_limit()?;
+        let config = Config::load().map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let _ = ensure_index_exists(&config).await?;
+
+        let db_path = config.db_path();
+        let store =
+            Store::open(&db_path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let (project_id, project_name) = resolve_project(&store, params.project.as_deref())?;
+
+        let project_path = store
+            .list_projects()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .into_iter()
+            .find(|p| p.id == Some(project_id))
+            .map(|p| p.path)
+            .ok_or_else(|| {
+                McpError::internal_error(format!("project '{}' has no path", project_name), None)
+            })?;
+        drop(store);
+
+        let summary =
+            helpers::run_index_subprocess(std::path::Path::new(&project_path), &project_name)
+                .await?;
+        crate::index::hnsw::invalidate_cache();
+
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "re-indexed '{}': {}",
+            project_name, summary
+        ))]))
+    }
 }
 
 #[tool_handler]
@@ -670,6 +1014,7 @@ impl ServerHandler for SragMcpServer {
                 use search_code for natural language queries, find_similar_code to discover \
                 reusable patterns, search_symbols to find definitions, get_project_patterns \
                 to understand conventions, and text_search for exact keyword matches. \
+                after editing files, call reindex_project to refresh the index. \
                 projects are auto-detected from the current directory when not specified."
                     .into(),
             ),
@@ -680,6 +1025,9 @@ impl ServerHandler for SragMcpServer {
 }
 
 pub async fn run() -> Result<()> {
+    if let Ok(config) = Config::load() {
+        crate::ipc::lifecycle::spawn_idle_shutdown(&config);
+    }
     let server = SragMcpServer::new();
     let service = server.serve(stdio()).await?;
     service.waiting().await?;
@@ -4,6 +4,14 @@
 use rmcp::schemars::{self, JsonSchema};
 use serde::Deserialize;
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListProjectsParams {
+    #[serde(default = "default_project_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct SearchCodeParams {
     #[serde(default)]
@@ -11,13 +19,37 @@ pub struct SearchCodeParams {
     pub query: String,
     #[serde(default = "default_top_k")]
     pub top_k: usize,
+    /// only return chunks in these languages, case-insensitive: rust, python,
+    /// javascript, typescript, go, c, cpp, java, ruby, csharp, php, shell,
+    /// dockerfile, make, graphql, protobuf, markdown, toml, yaml, json, html,
+    /// css, vue, svelte, sql, env
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// drop results whose relevance score, shown in each result header, is
+    /// below this fraction of the best result's score, from 0.0 to 1.0. e.g.
+    /// 0.5 keeps results at least half as relevant as the top one
+    #[serde(default)]
+    pub min_score: f32,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct FindSimilarParams {
     #[serde(default)]
     pub project: Option<String>,
-    pub code_snippet: String,
+    /// code to find similar code to. not needed with `symbol` or `chunk_id`
+    #[serde(default)]
+    pub code_snippet: Option<String>,
+    /// start from this indexed symbol instead of a snippet, using its stored
+    /// embedding. the symbol itself is left out of the results
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// narrows `symbol` to the file at this path, absolute or relative to
+    /// the project root
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// start from this indexed chunk instead of a snippet
+    #[serde(default)]
+    pub chunk_id: Option<i64>,
     #[serde(default = "default_top_k")]
     pub top_k: usize,
 }
@@ -59,6 +91,40 @@ pub struct FtsSearchParams {
     pub limit: usize,
     #[serde(default)]
     pub offset: usize,
+    /// how the query is read: "terms" (default) matches each word,
+    /// "phrase" the exact phrase, "raw" takes fts5 syntax such as
+    /// `retry AND backoff` or `NEAR(open close, 5)`
+    #[serde(default)]
+    pub mode: TextSearchMode,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LiteralSearchParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    /// text to find exactly as written, punctuation and case included
+    pub query: String,
+    #[serde(default = "default_top_k")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TextSearchMode {
+    #[default]
+    Terms,
+    Phrase,
+    Raw,
+}
+
+impl From<TextSearchMode> for crate::index::store::FtsQueryMode {
+    fn from(mode: TextSearchMode) -> Self {
+        match mode {
+            TextSearchMode::Terms => Self::Terms,
+            TextSearchMode::Phrase => Self::Phrase,
+            TextSearchMode::Raw => Self::Raw,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -66,6 +132,19 @@ pub struct FindCallersParams {
     #[serde(default)]
     pub project: Option<String>,
     pub function_name: String,
+    /// levels of callers to follow, 1 for direct callers only (max 5)
+    #[serde(default = "default_call_depth")]
+    pub depth: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetDefinitionBodyParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    pub function_name: String,
+    /// enclosing impl, class or module, to pick between same-named definitions
+    #[serde(default)]
+    pub scope: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -73,11 +152,64 @@ pub struct FindCalleesParams {
     #[serde(default)]
     pub project: Option<String>,
     pub function_name: String,
+    /// levels of callees to follow, 1 for direct callees only (max 5)
+    #[serde(default = "default_call_depth")]
+    pub depth: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImpactParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    pub function_name: String,
+    /// how many levels of callers to follow
+    #[serde(default = "default_impact_depth")]
+    pub max_depth: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LargestFunctionsParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default = "default_symbol_limit")]
+    pub limit: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListDefinitionsParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    /// only list definitions of this kind, e.g. function, method, struct,
+    /// class, trait, interface, enum or module
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default = "default_definition_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReindexProjectParams {
+    #[serde(default)]
+    pub project: Option<String>,
 }
 
 fn default_top_k() -> usize {
     10
 }
+fn default_definition_limit() -> usize {
+    100
+}
 fn default_symbol_limit() -> usize {
     20
 }
+fn default_call_depth() -> usize {
+    1
+}
+fn default_impact_depth() -> usize {
+    5
+}
+fn default_project_limit() -> usize {
+    50
+}
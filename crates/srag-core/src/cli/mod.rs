@@ -1,12 +1,22 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+mod audit_cmd;
 mod chat_cmd;
+mod compact_cmd;
 mod config_cmd;
+mod doctor_cmd;
+mod import_cmd;
 pub(crate) mod index_cmd;
 mod mcp;
+mod model_cmd;
 mod query_cmd;
+mod reembed_cmd;
 mod remove_cmd;
+mod reset_cmd;
+mod search_cmd;
+mod selftest_cmd;
+mod sessions_cmd;
 mod setup_cmd;
 mod status_cmd;
 mod sync_cmd;
@@ -14,6 +24,7 @@ mod update_cmd;
 mod watch_cmd;
 
 use clap::{Parser, Subcommand};
+use std::io::{self, Write};
 
 #[derive(Parser)]
 #[command(
@@ -30,19 +41,47 @@ enum Commands {
     /// index a code directory
     Index {
         /// path to the directory to index
-        path: String,
+        #[arg(required_unless_present_any = ["file", "stdin"])]
+        path: Option<String>,
         /// project name (defaults to directory name)
         #[arg(long)]
         name: Option<String>,
+        /// index just this file into its project, without walking the directory
+        #[arg(long, conflicts_with_all = ["path", "stdin"])]
+        file: Option<String>,
+        /// index content piped on stdin as the file given by --path
+        #[arg(long, requires = "virtual_path", conflicts_with = "path")]
+        stdin: bool,
+        /// path the piped content is indexed under, relative to the project root
+        #[arg(
+            long = "path",
+            id = "virtual_path",
+            value_name = "PATH",
+            requires = "stdin"
+        )]
+        virtual_path: Option<String>,
+        /// project for --file or --stdin (defaults to the indexed project containing the file)
+        #[arg(long, conflicts_with = "name")]
+        project: Option<String>,
         /// force full re-index, ignoring cache
         #[arg(long)]
         force: bool,
-        /// dry run: show what would be indexed without indexing
+        /// dry run: show what would be indexed, with each file's language and chunk estimate, without indexing
         #[arg(long)]
         dry_run: bool,
         /// index all files: include hidden files, .env, configs, and ignore .gitignore
         #[arg(long)]
         all: bool,
+        /// finish with a breakdown of skipped files by reason
+        #[arg(long)]
+        print_skipped_reasons: bool,
+        /// also index the diff hunks of the last N commits (0 = off)
+        #[arg(long, default_value_t = 0)]
+        commit_limit: usize,
+        /// finish with the time spent discovering, chunking, embedding,
+        /// writing and saving the vector index
+        #[arg(long, conflicts_with_all = ["file", "stdin", "dry_run"])]
+        profile: bool,
     },
     /// start file watcher daemon for auto-reindexing
     Watch {
@@ -64,18 +103,58 @@ enum Commands {
         /// resume a previous session
         #[arg(long)]
         session: Option<String>,
+        /// reindex files as they change so answers see your latest edits
+        #[arg(long)]
+        live: bool,
+    },
+    /// list, inspect or delete saved chat sessions
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
     },
     /// non-interactive query against indexed code
     Query {
-        /// project to query
+        /// project to query (defaults to the one containing the current
+        /// directory, or the only one indexed)
         #[arg(short, long)]
-        project: String,
+        project: Option<String>,
         /// the question to ask
         #[arg(short, long)]
         query: String,
-        /// output as JSON
-        #[arg(long)]
+        /// how to print the answer and sources
+        #[arg(long, value_enum, default_value_t = query_cmd::OutputFormat::Text)]
+        format: query_cmd::OutputFormat,
+        /// output as JSON, same as --format json
+        #[arg(long, conflicts_with = "format")]
         json: bool,
+        /// include source content (full) or only locations (refs)
+        #[arg(long, value_enum, default_value_t = query_cmd::SourcesMode::Full)]
+        sources_mode: query_cmd::SourcesMode,
+        /// only retrieve the ranked sources, without asking the llm for an answer
+        #[arg(long)]
+        sources_only: bool,
+        /// trim each source to this many lines around its symbol
+        #[arg(long, value_name = "N")]
+        snippet_lines: Option<usize>,
+        /// finish with the time spent embedding the question, searching,
+        /// reranking and generating, on stderr
+        #[arg(long)]
+        profile: bool,
+    },
+    /// find code by keyword, or by exact substring with --mode literal
+    Search {
+        /// the words or text to find
+        text: String,
+        /// only search this project (defaults to all)
+        #[arg(short, long)]
+        project: Option<String>,
+        /// keyword uses the full-text index; literal matches the text exactly,
+        /// `foo_bar` and `::new` included, but scans every chunk
+        #[arg(long, value_enum, default_value_t = search_cmd::SearchMode::Keyword)]
+        mode: search_cmd::SearchMode,
+        /// most matches to show; literal search stops at 500 regardless
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
     },
     /// interactive setup wizard: scan and index projects
     Setup {
@@ -95,17 +174,43 @@ enum Commands {
         action: ConfigAction,
     },
     /// re-index all registered projects (incremental, skips unchanged files)
-    Sync,
+    Sync {
+        /// only look at files modified after this: a duration like `2h` or
+        /// `3d`, or a date/time. defaults to each project's last index time
+        #[arg(long)]
+        since: Option<String>,
+    },
     /// start MCP server (stdio transport) for agent integration
     Mcp,
-    /// remove a project from the index
+    /// remove a project, one of its files, or everything from the index
     Remove {
         /// project name to remove
-        project: String,
+        #[arg(required_unless_present = "all")]
+        project: Option<String>,
+        /// remove only this file from the project (relative to its root)
+        #[arg(long)]
+        file: Option<String>,
+        /// remove every project and the vector index
+        #[arg(long, conflicts_with_all = ["project", "file"])]
+        all: bool,
         /// skip confirmation prompt
         #[arg(long, short = 'y')]
         force: bool,
     },
+    /// regenerate embeddings for a project after the embedding model changed
+    Reembed {
+        /// project name to re-embed
+        project: String,
+    },
+    /// copy the projects of another srag database into this one
+    Import {
+        /// the other metadata.db, e.g. copied from another machine
+        database: std::path::PathBuf,
+        /// import a project whose name is taken here by another path under a
+        /// new name instead of asking
+        #[arg(long)]
+        rename_on_conflict: bool,
+    },
     /// update srag to the latest version from GitHub
     Update {
         /// force update even if already up to date
@@ -116,13 +221,42 @@ enum Commands {
     CheckUpdate,
     /// print shell hook for automatic update checking
     ShellHook,
+    /// index a small synthetic project end-to-end to verify the install
+    Selftest,
+    /// check the install for common problems and suggest fixes
+    Doctor,
+    /// reclaim space in the index database and optimise the full-text index
+    Compact,
+    /// check, download or verify the local llm model without starting the
+    /// ml service
+    Model {
+        #[command(subcommand)]
+        action: ModelAction,
+    },
+    /// delete the index database and vectors, keeping config and models
+    ResetIndex {
+        /// skip confirmation prompt
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// list chunks flagged as possible prompt injection
+    Audit {
+        /// only audit this project
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// output format
+        #[arg(long, value_enum, default_value_t = audit_cmd::AuditFormat::Text)]
+        format: audit_cmd::AuditFormat,
+    },
 }
 
 #[derive(Subcommand)]
 enum ConfigAction {
     /// show current configuration
     Show,
-    /// set a configuration value
+    /// print one value by its dotted key, e.g. query.top_k
+    Get { key: String },
+    /// set a configuration value by its dotted key; lists are comma separated
     Set { key: String, value: String },
     /// reset configuration to defaults
     Reset,
@@ -137,46 +271,181 @@ enum ConfigAction {
     ApiCheck,
 }
 
+#[derive(Subcommand)]
+enum SessionsAction {
+    /// list saved sessions, most recent first
+    List,
+    /// print the transcript of a session
+    Show {
+        /// session id or a unique prefix of it
+        id: String,
+    },
+    /// delete a session and its turns
+    Delete {
+        /// session id or a unique prefix of it
+        id: String,
+        /// skip confirmation prompt
+        #[arg(long, short = 'y')]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ModelAction {
+    /// show where the model is, its size and whether it has been verified
+    Status,
+    /// download the model from llm.model_url, checking llm.model_sha256
+    Download,
+    /// hash the model and compare it with llm.model_sha256
+    Verify,
+}
+
 impl Cli {
     pub async fn run(self) -> anyhow::Result<()> {
         match self.command {
             Commands::Index {
                 path,
                 name,
+                file,
+                stdin,
+                virtual_path,
+                project,
                 force,
                 dry_run,
                 all,
-            } => index_cmd::run_opts(&path, name.as_deref(), force, dry_run, all).await,
+                print_skipped_reasons,
+                commit_limit,
+                profile,
+            } => {
+                if let Some(file) = file {
+                    return index_cmd::run_file(&file, project.as_deref()).await;
+                }
+                if stdin {
+                    let virtual_path = virtual_path.unwrap_or_default();
+                    return index_cmd::run_stdin(&virtual_path, project.as_deref()).await;
+                }
+                let path = path.unwrap_or_else(|| ".".to_string());
+                let name = name.or(project);
+                index_cmd::run_opts(
+                    &path,
+                    name.as_deref(),
+                    force,
+                    dry_run,
+                    all,
+                    print_skipped_reasons,
+                    None,
+                    profile,
+                )
+                .await?;
+                if commit_limit > 0 && !dry_run {
+                    let commits =
+                        index_cmd::index_commit_history(&path, name.as_deref(), commit_limit)
+                            .await?;
+                    println!("{} commits indexed", commits);
+                }
+                Ok(())
+            }
             Commands::Watch { foreground, stop } => watch_cmd::run(foreground, stop).await,
             Commands::Chat {
                 project,
                 language,
                 session,
-            } => chat_cmd::run(project.as_deref(), &language, session.as_deref()).await,
+                live,
+            } => chat_cmd::run(project.as_deref(), &language, session.as_deref(), live).await,
+            Commands::Sessions { action } => match action {
+                SessionsAction::List => sessions_cmd::list().await,
+                SessionsAction::Show { id } => sessions_cmd::show(&id).await,
+                SessionsAction::Delete { id, force } => sessions_cmd::delete(&id, force).await,
+            },
             Commands::Query {
                 project,
                 query,
+                format,
                 json,
-            } => query_cmd::run(&project, &query, json).await,
+                sources_mode,
+                sources_only,
+                snippet_lines,
+                profile,
+            } => {
+                let format = if json {
+                    query_cmd::OutputFormat::Json
+                } else {
+                    format
+                };
+                query_cmd::run(
+                    project.as_deref(),
+                    &query,
+                    format,
+                    sources_mode,
+                    sources_only,
+                    snippet_lines,
+                    profile,
+                )
+                .await
+            }
             Commands::Setup { all } => setup_cmd::run(all).await,
             Commands::Status { detailed } => status_cmd::run(detailed).await,
             Commands::Config { action } => match action {
                 ConfigAction::Show => config_cmd::show().await,
+                ConfigAction::Get { key } => config_cmd::get(&key).await,
                 ConfigAction::Set { key, value } => config_cmd::set(&key, &value).await,
                 ConfigAction::Reset => config_cmd::reset().await,
                 ConfigAction::Edit => config_cmd::edit().await,
                 ConfigAction::ApiKey { key } => config_cmd::set_api_key(key.as_deref()).await,
                 ConfigAction::ApiCheck => config_cmd::check_api_safety().await,
             },
-            Commands::Sync => sync_cmd::run().await,
+            Commands::Sync { since } => sync_cmd::run(since.as_deref()).await,
             Commands::Mcp => mcp::run().await,
-            Commands::Remove { project, force } => remove_cmd::run(&project, force).await,
+            Commands::Remove {
+                project,
+                file,
+                all,
+                force,
+            } => match (project, file) {
+                _ if all => remove_cmd::run_all(force).await,
+                (Some(project), Some(file)) => remove_cmd::run_file(&project, &file).await,
+                (Some(project), None) => remove_cmd::run(&project, force).await,
+                (None, _) => anyhow::bail!("a project name or --all is required"),
+            },
+            Commands::Reembed { project } => reembed_cmd::run(&project).await,
+            Commands::Import {
+                database,
+                rename_on_conflict,
+            } => import_cmd::run(&database, rename_on_conflict).await,
             Commands::Update { force } => update_cmd::run(force).await,
             Commands::CheckUpdate => update_cmd::check().await,
             Commands::ShellHook => {
                 update_cmd::print_shell_hook();
                 Ok(())
             }
+            Commands::Selftest => selftest_cmd::run().await,
+            Commands::Doctor => doctor_cmd::run().await,
+            Commands::Compact => compact_cmd::run().await,
+            Commands::Model { action } => match action {
+                ModelAction::Status => model_cmd::status().await,
+                ModelAction::Download => model_cmd::download().await,
+                ModelAction::Verify => model_cmd::verify().await,
+            },
+            Commands::Search {
+                text,
+                project,
+                mode,
+                limit,
+            } => search_cmd::run(&text, project.as_deref(), mode, limit).await,
+            Commands::ResetIndex { yes } => reset_cmd::run(yes).await,
+            Commands::Audit { project, format } => audit_cmd::run(project.as_deref(), format).await,
         }
     }
 }
+
+/// ask on stderr before a destructive command goes ahead; anything but
+/// "y" declines
+fn confirm() -> anyhow::Result<bool> {
+    eprintln!();
+    eprint!("are you sure? [y/N] ");
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
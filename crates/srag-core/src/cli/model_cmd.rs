@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::status_cmd::format_bytes;
+use crate::config::Config;
+use crate::ipc::lifecycle;
+
+fn model_path(config: &Config) -> PathBuf {
+    config.models_dir().join(&config.llm.model_filename)
+}
+
+/// where the local model lives, whether it is there and whether it has been
+/// checked, without hashing it or starting the ml service
+pub async fn status() -> Result<()> {
+    let config = Config::load()?;
+    let path = model_path(&config);
+
+    println!("model:   {}", path.display());
+    println!("source:  {}", config.llm.model_url);
+    match std::fs::metadata(&path) {
+        Ok(meta) => println!("size:    {}", format_bytes(meta.len())),
+        Err(_) => println!("status:  not downloaded, run 'srag model download'"),
+    }
+    match &config.llm.model_sha256 {
+        Some(sha) if path.is_file() => {
+            let state = if lifecycle::model_verified(&path, sha) {
+                "verified"
+            } else {
+                "not verified yet, run 'srag model verify'"
+            };
+            println!("sha256:  {} ({})", sha, state);
+        }
+        Some(sha) => println!("sha256:  {}", sha),
+        None => println!("sha256:  not pinned, set llm.model_sha256 to check the model"),
+    }
+    if config.is_external_api() {
+        println!(
+            "note:    chat uses the {:?} api, so the local model is not needed",
+            config.api.provider
+        );
+    }
+    Ok(())
+}
+
+/// fetch the model from `llm.model_url` with progress, checking it against
+/// `llm.model_sha256` before it replaces anything
+pub async fn download() -> Result<()> {
+    let config = Config::load()?;
+    config.ensure_dirs()?;
+    let path = model_path(&config);
+    let expected = config.llm.model_sha256.as_deref();
+
+    if path.is_file() {
+        if let Some(sha) = expected {
+            lifecycle::verify_model_file(&path, sha)?;
+        }
+        println!("{} is already downloaded", path.display());
+        return Ok(());
+    }
+
+    eprintln!("downloading {}", config.llm.model_url);
+    let actual = fetch_model(&config.llm.model_url, &path, expected).await?;
+    if let Some(sha) = expected {
+        lifecycle::check_model_hash(&path, sha, &actual)?;
+    }
+    println!("saved {}", path.display());
+    println!("sha256: {}", actual);
+    Ok(())
+}
+
+/// hash the model and compare it with `llm.model_sha256`
+pub async fn verify() -> Result<()> {
+    let config = Config::load()?;
+    let path = model_path(&config);
+    if !path.is_file() {
+        anyhow::bail!(
+            "{} is not downloaded, run 'srag model download'",
+            path.display()
+        );
+    }
+
+    let actual = lifecycle::sha256_file(&path)?;
+    println!("sha256: {}", actual);
+    match &config.llm.model_sha256 {
+        Some(sha) => {
+            lifecycle::check_model_hash(&path, sha, &actual)?;
+            println!("matches llm.model_sha256");
+        }
+        None => {
+            println!("llm.model_sha256 is not set; pin this hash there to check it on every start")
+        }
+    }
+    Ok(())
+}
+
+/// stream `url` into a `.download` file next to `dest`, then move it into
+/// place. returns the sha256 of what arrived; with `expected` set a
+/// mismatch is deleted instead of kept.
+async fn fetch_model(url: &str, dest: &Path, expected: Option<&str>) -> Result<String> {
+    let tmp = dest.with_extension("download");
+    let mut response = reqwest::get(url).await?.error_for_status()?;
+    let total = response.content_length();
+
+    let result: Result<String> = async {
+        let mut file = std::fs::File::create(&tmp)?;
+        let mut hasher = Sha256::new();
+        let mut done = 0u64;
+        let mut shown = None;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+            hasher.update(&chunk);
+            done += chunk.len() as u64;
+            // redraw once per MiB rather than per chunk
+            let mib = done / (1024 * 1024);
+            if shown != Some(mib) {
+                shown = Some(mib);
+                eprint!("\r{}", progress_line(done, total));
+                std::io::stderr().flush()?;
+            }
+        }
+        eprintln!();
+        file.sync_all()?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+    .await;
+
+    let actual = match result {
+        Ok(actual) => actual,
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e);
+        }
+    };
+    if let Some(sha) = expected {
+        if !actual.eq_ignore_ascii_case(sha) {
+            let _ = std::fs::remove_file(&tmp);
+            anyhow::bail!(
+                "downloaded model does not match llm.model_sha256: expected {}, got {}",
+                sha.to_lowercase(),
+                actual
+            );
+        }
+    }
+    std::fs::rename(&tmp, dest)?;
+    Ok(actual)
+}
+
+fn progress_line(done: u64, total: Option<u64>) -> String {
+    const BAR_WIDTH: u64 = 30;
+    let mb = |bytes: u64| bytes / (1024 * 1024);
+    match total {
+        Some(total) if total > 0 => {
+            let pct = (done * 100 / total).min(100);
+            let filled = (BAR_WIDTH * pct / 100) as usize;
+            format!(
+                "  [{}{}] {}/{} MB ({}%)",
+                "=".repeat(filled),
+                "-".repeat(BAR_WIDTH as usize - filled),
+                mb(done),
+                mb(total),
+                pct
+            )
+        }
+        _ => format!("  {} MB", mb(done)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_line() {
+        let mib = 1024 * 1024;
+        assert_eq!(
+            progress_line(50 * mib, Some(200 * mib)),
+            format!("  [{}{}] 50/200 MB (25%)", "=".repeat(7), "-".repeat(23))
+        );
+        assert_eq!(progress_line(3 * mib, None), "  3 MB");
+    }
+}
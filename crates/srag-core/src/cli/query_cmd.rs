@@ -2,31 +2,554 @@
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
 use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use srag_common::types::{Project, QueryResult, SourceReference};
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
 
 use crate::config::Config;
+use crate::discovery;
+use crate::index::store::Store;
+use crate::profile::Profiler;
 
-pub async fn run(project: &str, query: &str, json_output: bool) -> Result<()> {
+/// files and directories looked at when checking a project for changes
+const STALE_CHECK_MAX_ENTRIES: usize = 20_000;
+
+/// how much of each source to include in the result
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SourcesMode {
+    /// include the chunk content with each source
+    #[default]
+    Full,
+    /// only file path, line range and symbol
+    Refs,
+}
+
+/// how the answer and its sources are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// the answer, then a plain list of sources
+    #[default]
+    Text,
+    /// the result as json
+    Json,
+    /// the answer, then a sources section with fenced code, for pasting into
+    /// docs or pull request comments
+    Markdown,
+}
+
+/// `--sources-only` json: the ranked sources without an answer
+#[derive(Serialize)]
+struct SourcesOnly<'a> {
+    sources: &'a [SourceReference],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<&'a str>,
+}
+
+pub async fn run(
+    project: Option<&str>,
+    query: &str,
+    format: OutputFormat,
+    sources_mode: SourcesMode,
+    sources_only: bool,
+    snippet_lines: Option<usize>,
+    profile: bool,
+) -> Result<()> {
+    let mut profiler = Profiler::new();
     let config = Config::load()?;
     config.ensure_dirs()?;
 
-    let result = crate::query::query_once(project, query, &config).await?;
+    if !config.db_path().exists() {
+        anyhow::bail!("no index found. run 'srag index <path>' first.");
+    }
+    let store = Store::open(&config.db_path())?;
+    let cwd = std::env::current_dir()?;
+    let project = resolve_project(&store, project, &cwd.to_string_lossy())?;
+    let synced = sync_if_stale(&store, &project, &config, |p| async move {
+        eprintln!("'{}' changed since it was last indexed, syncing...", p.name);
+        let summary =
+            super::index_cmd::index_directory(&p.path, Some(&p.name), false, false, false, None)
+                .await?;
+        if summary.interrupted {
+            anyhow::bail!("sync interrupted: partial index saved, resume with 'srag sync'");
+        }
+        Ok(())
+    })
+    .await?;
+    if synced {
+        eprintln!("sync complete");
+    }
+    drop(store);
 
-    if json_output {
-        println!("{}", serde_json::to_string_pretty(&result)?);
-    } else {
-        println!("{}", result.answer);
-
-        if !result.sources.is_empty() {
-            println!("\nSources:");
-            let mut seen = std::collections::HashSet::new();
-            for src in &result.sources {
-                let label = format!("  {}:{}-{}", src.file_path, src.start_line, src.end_line);
-                if seen.insert(label.clone()) {
-                    println!("{}", label);
-                }
-            }
+    let mut result =
+        crate::query::query_once_opts(&project, query, &config, !sources_only, &mut profiler)
+            .await?;
+    if let Some(lines) = snippet_lines {
+        for src in &mut result.sources {
+            truncate_to_snippet(src, lines);
         }
     }
+    apply_sources_mode(&mut result, sources_mode);
+
+    let rendered = match format {
+        OutputFormat::Text => render_text(&result, sources_only),
+        OutputFormat::Json => render_json(&result, sources_only)?,
+        OutputFormat::Markdown => render_markdown(&result, sources_only),
+    };
+    if !rendered.is_empty() {
+        println!("{}", rendered);
+    }
+    // the answer carries the warning otherwise, and json has its own field
+    if sources_only && format != OutputFormat::Json {
+        if let Some(warning) = &result.warning {
+            eprintln!("{}", warning);
+        }
+    }
+    if profile {
+        eprint!("{}", profiler.report());
+    }
 
     Ok(())
 }
+
+/// the named project, else the one containing `cwd`, else the only one
+/// indexed
+fn resolve_project(store: &Store, project: Option<&str>, cwd: &str) -> Result<String> {
+    if let Some(name) = project {
+        store
+            .get_project_id(name)
+            .map_err(|_| anyhow::anyhow!("project '{}' not found", name))?;
+        return Ok(name.to_string());
+    }
+    match store.detect_project(cwd)? {
+        Some(project) => Ok(project.name),
+        None => anyhow::bail!(
+            "could not determine the project - pass --project or run from inside an indexed project"
+        ),
+    }
+}
+
+/// if anything under the project root changed since it was last indexed,
+/// run `sync` when auto_sync_if_stale is on, otherwise warn. returns whether
+/// a sync ran. unknown projects are left for the query to report.
+pub(crate) async fn sync_if_stale<F, Fut>(
+    store: &Store,
+    project: &str,
+    config: &Config,
+    sync: F,
+) -> Result<bool>
+where
+    F: FnOnce(Project) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let Some(project) = store
+        .list_projects()?
+        .into_iter()
+        .find(|p| p.name == project)
+    else {
+        return Ok(false);
+    };
+    let Some(project_id) = project.id else {
+        return Ok(false);
+    };
+    let Some(indexed_at) = store.project_last_indexed_unix(project_id)? else {
+        return Ok(false);
+    };
+
+    let since = UNIX_EPOCH + Duration::from_secs(indexed_at.max(0) as u64);
+    let changed = discovery::find_modified_since(
+        Path::new(&project.path),
+        config,
+        since,
+        STALE_CHECK_MAX_ENTRIES,
+    )?;
+    let Some(changed) = changed else {
+        return Ok(false);
+    };
+
+    if !config.query.auto_sync_if_stale {
+        eprintln!(
+            "warning: {} changed after '{}' was last indexed, results may be stale. \
+             run 'srag sync' or set query.auto_sync_if_stale = true",
+            changed.display(),
+            project.name
+        );
+        return Ok(false);
+    }
+
+    sync(project).await?;
+    Ok(true)
+}
+
+/// `file:start-end` for a source
+fn source_location(src: &SourceReference) -> String {
+    format!("{}:{}-{}", src.file_path, src.start_line, src.end_line)
+}
+
+/// sources in order, skipping repeats of a location already listed
+fn unique_sources(sources: &[SourceReference]) -> Vec<&SourceReference> {
+    let mut seen = std::collections::HashSet::new();
+    sources
+        .iter()
+        .filter(|src| seen.insert(source_location(src)))
+        .collect()
+}
+
+fn render_text(result: &QueryResult, sources_only: bool) -> String {
+    let mut out = String::new();
+    if !sources_only {
+        out.push_str(&result.answer);
+    }
+    if !result.sources.is_empty() {
+        if !sources_only {
+            out.push_str("\n\n");
+        }
+        out.push_str("Sources:");
+        for src in unique_sources(&result.sources) {
+            out.push_str(&format!("\n  {}", source_location(src)));
+        }
+    }
+    out
+}
+
+fn render_markdown(result: &QueryResult, sources_only: bool) -> String {
+    let mut sections = Vec::new();
+    if !sources_only {
+        sections.push(result.answer.trim_end().to_string());
+    }
+    if !result.sources.is_empty() {
+        let mut section = String::from("## Sources\n");
+        for src in unique_sources(&result.sources) {
+            section.push_str(&format!("\n- `{}`", source_location(src)));
+            if let Some(symbol) = &src.symbol {
+                section.push_str(&format!(" - `{}`", symbol));
+            }
+            section.push('\n');
+            if !src.content.is_empty() {
+                section.push_str(&fenced_block(&src.content, &src.file_path));
+            }
+        }
+        sections.push(section.trim_end().to_string());
+    }
+    sections.join("\n\n")
+}
+
+/// `content` as a fenced code block indented under a list item, tagged with
+/// the file's extension. the fence outgrows any backtick run in the content.
+fn fenced_block(content: &str, file_path: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let lang = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    let mut block = format!("\n  {}{}\n", fence, lang);
+    for line in content.lines() {
+        if line.is_empty() {
+            block.push('\n');
+        } else {
+            block.push_str(&format!("  {}\n", line));
+        }
+    }
+    block.push_str(&format!("  {}\n", fence));
+    block
+}
+
+fn render_json(result: &QueryResult, sources_only: bool) -> Result<String> {
+    Ok(if sources_only {
+        serde_json::to_string_pretty(&SourcesOnly {
+            sources: &result.sources,
+            warning: result.warning.as_deref(),
+        })?
+    } else {
+        serde_json::to_string_pretty(result)?
+    })
+}
+
+/// cut a source down to `lines` lines around its symbol's first mention,
+/// or its first `lines` lines when there's no symbol to find. the line
+/// range is narrowed to match.
+fn truncate_to_snippet(src: &mut SourceReference, lines: usize) {
+    let all: Vec<&str> = src.content.lines().collect();
+    if lines == 0 || all.len() <= lines {
+        return;
+    }
+    let anchor = src
+        .symbol
+        .as_deref()
+        .and_then(|symbol| all.iter().position(|line| line.contains(symbol)))
+        .unwrap_or(0);
+    let start = anchor.saturating_sub(lines / 2).min(all.len() - lines);
+    src.content = all[start..start + lines].join("\n");
+    src.start_line += start as u32;
+    src.end_line = src.start_line + lines as u32 - 1;
+}
+
+fn apply_sources_mode(result: &mut QueryResult, mode: SourcesMode) {
+    if mode == SourcesMode::Refs {
+        for src in &mut result.sources {
+            src.content.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::store::tests::test_store;
+    use srag_common::types::SourceReference;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_resolve_project_explicit_cwd_and_single() {
+        let (store, _dir) = test_store();
+        store.upsert_project("api", "/work/api").unwrap();
+
+        // a lone project is picked from anywhere
+        assert_eq!(resolve_project(&store, None, "/elsewhere").unwrap(), "api");
+
+        store.upsert_project("web", "/work/web").unwrap();
+        assert_eq!(
+            resolve_project(&store, Some("api"), "/work/web").unwrap(),
+            "api"
+        );
+        assert_eq!(
+            resolve_project(&store, None, "/work/web/src/components").unwrap(),
+            "web"
+        );
+        assert!(resolve_project(&store, Some("missing"), "/work/web").is_err());
+    }
+
+    #[test]
+    fn test_resolve_project_ambiguous() {
+        let (store, _dir) = test_store();
+        store.upsert_project("api", "/work/api").unwrap();
+        store.upsert_project("web", "/work/web").unwrap();
+
+        let err = resolve_project(&store, None, "/elsewhere")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("--project"), "{}", err);
+    }
+
+    /// a project indexed "now" whose only file was last modified an hour ago
+    fn indexed_project() -> (Store, tempfile::TempDir, tempfile::TempDir) {
+        let (store, db_dir) = test_store();
+        let project_dir = tempfile::tempdir().unwrap();
+        let file = project_dir.path().join("main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        std::fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(an_hour_ago)
+            .unwrap();
+        let root = std::fs::File::open(project_dir.path()).unwrap();
+        let _ = root.set_modified(an_hour_ago);
+
+        let id = store
+            .upsert_project("proj", &project_dir.path().to_string_lossy())
+            .unwrap();
+        store
+            .update_project_indexed_at(id, SystemTime::now())
+            .unwrap();
+        (store, db_dir, project_dir)
+    }
+
+    fn touch(path: &Path) {
+        let later = SystemTime::now() + Duration::from_secs(120);
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+    }
+
+    async fn run_check(store: &Store, config: &Config, calls: &AtomicUsize) -> bool {
+        sync_if_stale(store, "proj", config, |_| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_touched_file_triggers_sync_when_enabled() {
+        let (store, _db, project_dir) = indexed_project();
+        let mut config = Config::default();
+        config.query.auto_sync_if_stale = true;
+        let calls = AtomicUsize::new(0);
+
+        assert!(!run_check(&store, &config, &calls).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        touch(&project_dir.path().join("main.rs"));
+        assert!(run_check(&store, &config, &calls).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_project_only_warns_when_disabled() {
+        let (store, _db, project_dir) = indexed_project();
+        touch(&project_dir.path().join("main.rs"));
+        let config = Config::default();
+        let calls = AtomicUsize::new(0);
+
+        assert!(!run_check(&store, &config, &calls).await);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    fn sample_result() -> QueryResult {
+        QueryResult {
+            answer: "it hashes passwords".to_string(),
+            sources: vec![SourceReference {
+                file_path: "src/auth.rs".to_string(),
+                start_line: 1,
+                end_line: 7,
+                symbol: Some("hash_password".to_string()),
+                content: "fn hash_password() {}".to_string(),
+            }],
+            warning: None,
+        }
+    }
+
+    #[test]
+    fn test_snippet_lines_centre_on_symbol() {
+        let mut content: Vec<String> = (1..=20).map(|i| format!("line {}", i)).collect();
+        content[11] = "fn hash_password() {".to_string();
+        let mut src = SourceReference {
+            file_path: "src/auth.rs".to_string(),
+            start_line: 101,
+            end_line: 120,
+            symbol: Some("hash_password".to_string()),
+            content: content.join("\n"),
+        };
+        truncate_to_snippet(&mut src, 4);
+        assert_eq!(
+            src.content,
+            "line 10\nline 11\nfn hash_password() {\nline 13"
+        );
+        assert_eq!((src.start_line, src.end_line), (110, 113));
+
+        // no symbol: the head of the chunk
+        let mut src = SourceReference {
+            symbol: None,
+            content: content.join("\n"),
+            start_line: 1,
+            end_line: 20,
+            ..sample_result().sources[0].clone()
+        };
+        truncate_to_snippet(&mut src, 3);
+        assert_eq!(src.content, "line 1\nline 2\nline 3");
+        assert_eq!((src.start_line, src.end_line), (1, 3));
+
+        // near the end the window is pulled back inside the chunk
+        let mut src = SourceReference {
+            symbol: Some("line 20".to_string()),
+            content: content.join("\n"),
+            start_line: 1,
+            end_line: 20,
+            ..sample_result().sources[0].clone()
+        };
+        truncate_to_snippet(&mut src, 5);
+        assert_eq!((src.start_line, src.end_line), (16, 20));
+
+        // shorter than the window: untouched
+        let mut src = sample_result().sources[0].clone();
+        truncate_to_snippet(&mut src, 5);
+        assert_eq!(src.content, "fn hash_password() {}");
+        assert_eq!((src.start_line, src.end_line), (1, 7));
+    }
+
+    #[test]
+    fn test_text_format_lists_each_location_once() {
+        let mut result = sample_result();
+        result.sources.push(result.sources[0].clone());
+        assert_eq!(
+            render_text(&result, false),
+            "it hashes passwords\n\nSources:\n  src/auth.rs:1-7"
+        );
+        assert_eq!(render_text(&result, true), "Sources:\n  src/auth.rs:1-7");
+    }
+
+    #[test]
+    fn test_json_format_round_trips() {
+        let json = render_json(&sample_result(), false).unwrap();
+        let parsed: QueryResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.answer, "it hashes passwords");
+        assert_eq!(parsed.sources.len(), 1);
+        assert_eq!(parsed.sources[0].symbol.as_deref(), Some("hash_password"));
+        assert_eq!(parsed.sources[0].content, "fn hash_password() {}");
+    }
+
+    #[test]
+    fn test_markdown_format_fences_sources() {
+        let rendered = render_markdown(&sample_result(), false);
+        assert_eq!(
+            rendered,
+            "it hashes passwords\n\n## Sources\n\n\
+             - `src/auth.rs:1-7` - `hash_password`\n\n  ```rs\n  fn hash_password() {}\n  ```"
+        );
+
+        // refs only: no code, and no answer when only sources were asked for
+        let mut result = sample_result();
+        apply_sources_mode(&mut result, SourcesMode::Refs);
+        assert_eq!(
+            render_markdown(&result, true),
+            "## Sources\n\n- `src/auth.rs:1-7` - `hash_password`"
+        );
+
+        // a fence longer than any backticks inside the content
+        result.sources[0].content = "let s = \"```\";".to_string();
+        assert!(render_markdown(&result, true).contains("\n  ````rs\n"));
+    }
+
+    #[test]
+    fn test_sources_only_json_has_no_answer() {
+        let mut result = sample_result();
+        result.answer.clear();
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(&result, true).unwrap()).unwrap();
+        assert!(json.get("answer").is_none());
+        assert!(json.get("warning").is_none());
+        assert_eq!(json["sources"][0]["file_path"], "src/auth.rs");
+        assert_eq!(json["sources"][0]["content"], "fn hash_password() {}");
+
+        // flagged sources keep their warning without an answer to carry it
+        result.warning = Some("untrusted".to_string());
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(&result, true).unwrap()).unwrap();
+        assert_eq!(json["warning"], "untrusted");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(&sample_result(), false).unwrap()).unwrap();
+        assert_eq!(json["answer"], "it hashes passwords");
+    }
+
+    #[test]
+    fn test_sources_mode_full_includes_content() {
+        let mut result = sample_result();
+        apply_sources_mode(&mut result, SourcesMode::Full);
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["sources"][0]["content"], "fn hash_password() {}");
+    }
+
+    #[test]
+    fn test_sources_mode_refs_omits_content() {
+        let mut result = sample_result();
+        apply_sources_mode(&mut result, SourcesMode::Refs);
+        let json = serde_json::to_value(&result).unwrap();
+        let source = &json["sources"][0];
+        assert!(source.get("content").is_none());
+        assert_eq!(source["file_path"], "src/auth.rs");
+        assert_eq!(source["start_line"], 1);
+        assert_eq!(source["end_line"], 7);
+        assert_eq!(source["symbol"], "hash_password");
+    }
+}
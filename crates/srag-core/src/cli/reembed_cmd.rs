@@ -0,0 +1,302 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use anyhow::Result;
+use std::future::Future;
+
+use crate::config::Config;
+use crate::index::hnsw::{build_hnsw_from_db, invalidate_cache};
+use crate::index::store::Store;
+use crate::ipc::embedding::{connect_for_embedding, Embedder, EmbeddingBackend, ML_EMBED_LIMIT};
+
+use super::index_cmd::enrich_chunk_text;
+
+pub async fn run(project: &str) -> Result<()> {
+    let config = Config::load()?;
+    let db_path = config.db_path();
+    if !db_path.exists() {
+        anyhow::bail!("no index found - run 'srag index <path>' first");
+    }
+
+    let store = Store::open(&db_path)?;
+    let project_id = store
+        .get_project_id(project)
+        .map_err(|_| anyhow::anyhow!("project '{}' not found", project))?;
+
+    let ml_client = connect_for_embedding(&config).await?;
+    let embedder = Embedder::new(&config.embedding, ml_client.as_ref())?;
+
+    let signature = crate::config::embedding_signature(&config.embedding);
+    let strip_comments = config.indexing.strip_comments_for_embedding;
+    let count = reembed(&store, project_id, &signature, strip_comments, |texts| {
+        let embedder = &embedder;
+        async move { Ok(embedder.embed(&texts).await?) }
+    })
+    .await?;
+
+    // existing hnsw points can't be updated in place, so rebuild from scratch
+    let vector_index = build_hnsw_from_db(
+        &store,
+        crate::config::EMBEDDING_DIMENSION,
+        config.embedding.distance,
+    )?;
+    vector_index.save(&config.vectors_dir())?;
+    store.wal_checkpoint()?;
+    invalidate_cache();
+
+    println!(
+        "done: {} chunks re-embedded in '{}' with {}",
+        count, project, signature
+    );
+    Ok(())
+}
+
+/// re-embed every chunk in a project from its stored content and path.
+/// chunks are read, embedded and written one window at a time inside a
+/// single transaction, so memory stays bounded and a failed batch leaves the
+/// old vectors in place.
+pub(crate) async fn reembed<F, Fut>(
+    store: &Store,
+    project_id: i64,
+    signature: &str,
+    strip_comments: bool,
+    embed: F,
+) -> Result<u64>
+where
+    F: Fn(Vec<String>) -> Fut,
+    Fut: Future<Output = Result<Vec<Vec<f32>>>>,
+{
+    store.begin_transaction()?;
+    let result = async {
+        let mut count = 0u64;
+        let mut after_id = 0;
+        loop {
+            let page = store.chunks_after(Some(project_id), after_id, ML_EMBED_LIMIT)?;
+            let Some((last, _)) = page.last() else {
+                break;
+            };
+            after_id = last.id.unwrap_or(after_id);
+
+            let (ids, texts): (Vec<i64>, Vec<String>) = page
+                .iter()
+                .filter_map(|(chunk, path)| {
+                    let id = chunk.id?;
+                    Some((id, enrich_chunk_text(path, chunk, strip_comments)))
+                })
+                .unzip();
+            let embedded = embed(texts).await?;
+            if embedded.len() != ids.len() {
+                anyhow::bail!(
+                    "embedder returned {} vectors for {} chunks",
+                    embedded.len(),
+                    ids.len()
+                );
+            }
+            let vectors: Vec<(i64, Vec<f32>)> = ids.into_iter().zip(embedded).collect();
+            store.replace_chunk_vectors(&vectors)?;
+            count += vectors.len() as u64;
+        }
+        store.finish_reembed(project_id, signature)?;
+        Ok(count)
+    }
+    .await;
+
+    match result {
+        Ok(count) => {
+            store.commit()?;
+            Ok(count)
+        }
+        Err(e) => {
+            let _ = store.rollback();
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use srag_common::types::Language;
+
+    const DIM: usize = 4;
+
+    fn seed(store: &Store) -> i64 {
+        let project_id = store.upsert_project("proj", "/tmp/proj").unwrap();
+        let path = "/tmp/proj/src/lib.rs";
+        let file_id = seed_file(store, project_id, path, Language::Rust);
+        for (i, content) in ["fn one() {}", "fn two() {}"].iter().enumerate() {
+            seed_chunk(store, &test_chunk(file_id, content, i as u32 + 1), path);
+        }
+        project_id
+    }
+
+    fn stored_vectors(store: &Store) -> Vec<(i64, Vec<f32>)> {
+        let mut out = Vec::new();
+        store
+            .for_each_embedding(DIM, |id, v| {
+                out.push((id, v));
+                Ok(())
+            })
+            .unwrap();
+        out
+    }
+
+    fn chunk_ids(store: &Store, project_id: i64) -> Vec<i64> {
+        store
+            .list_project_chunks(project_id)
+            .unwrap()
+            .into_iter()
+            .filter_map(|(c, _)| c.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reembed_keeps_chunks_and_replaces_vectors() {
+        let (store, _dir) = test_store();
+        let project_id = seed(&store);
+
+        let first = reembed(
+            &store,
+            project_id,
+            "old-model/4",
+            false,
+            |texts| async move { Ok(texts.iter().map(|_| vec![1.0; DIM]).collect()) },
+        )
+        .await
+        .unwrap();
+        assert_eq!(first, 2);
+        let ids_before = chunk_ids(&store, project_id);
+        let vectors_before = stored_vectors(&store);
+
+        let second = reembed(
+            &store,
+            project_id,
+            "new-model/4",
+            false,
+            |texts| async move {
+                Ok(texts
+                    .iter()
+                    .map(|t| vec![t.len() as f32, 0.5, 0.25, 0.0])
+                    .collect())
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(second, 2);
+
+        assert_eq!(chunk_ids(&store, project_id), ids_before);
+        let vectors_after = stored_vectors(&store);
+        assert_eq!(vectors_after.len(), vectors_before.len());
+        for ((id_before, v_before), (id_after, v_after)) in
+            vectors_before.iter().zip(&vectors_after)
+        {
+            assert_eq!(id_before, id_after);
+            assert_ne!(v_before, v_after);
+        }
+
+        assert_eq!(
+            store
+                .project_embedding_model(project_id)
+                .unwrap()
+                .as_deref(),
+            Some("new-model/4")
+        );
+        assert_eq!(store.search_fts("two", 10).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reembed_then_reindex_original_with_duplicate() {
+        let (store, _dir) = test_store();
+        let project_id = seed(&store);
+        let original_file = store.list_project_files(project_id).unwrap()[0].id.unwrap();
+        let copy_file = seed_file(&store, project_id, "/tmp/proj/src/copy.rs", Language::Rust);
+        for (chunk, _) in store.list_project_chunks(project_id).unwrap() {
+            store
+                .link_duplicate_chunk(project_id, chunk.id.unwrap(), &chunk.content)
+                .unwrap();
+        }
+        let copy_id = store
+            .insert_chunk(&test_chunk(copy_file, "fn one() {}", 1), None)
+            .unwrap();
+        assert!(store
+            .link_duplicate_chunk(project_id, copy_id, "fn one() {}")
+            .unwrap());
+
+        let count = reembed(&store, project_id, "m/4", false, |texts| async move {
+            Ok(texts.iter().map(|_| vec![1.0; DIM]).collect())
+        })
+        .await
+        .unwrap();
+        // the copy shares its original's vector instead of getting its own
+        assert_eq!(count, 2);
+        assert_eq!(store.embedding_count().unwrap(), 2);
+        assert!(store
+            .chunk_embedding(project_id, copy_id, DIM)
+            .unwrap()
+            .is_some());
+
+        // reindexing the original's file hands its embedding to the copy
+        store.delete_file_embeddings(original_file).unwrap();
+        store.delete_file_chunks(original_file).unwrap();
+        assert_eq!(store.embedding_count().unwrap(), 1);
+        assert!(store
+            .chunk_embedding(project_id, copy_id, DIM)
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_reembed_rejects_short_batch() {
+        let (store, _dir) = test_store();
+        let project_id = seed(&store);
+
+        let result = reembed(&store, project_id, "m/4", false, |_| async move {
+            Ok(vec![vec![1.0; DIM]])
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(store.embedding_count().unwrap(), 0);
+        assert!(store.project_embedding_model(project_id).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reembed_failure_in_a_later_window_keeps_old_vectors() {
+        let (store, _dir) = test_store();
+        let project_id = seed(&store);
+        let file_id = store.list_project_files(project_id).unwrap()[0].id.unwrap();
+        for i in 0..ML_EMBED_LIMIT {
+            let chunk = test_chunk(file_id, &format!("fn extra_{}() {{}}", i), i as u32 + 3);
+            store.insert_chunk(&chunk, None).unwrap();
+        }
+        reembed(&store, project_id, "old/4", false, |texts| async move {
+            Ok(texts.iter().map(|_| vec![1.0; DIM]).collect())
+        })
+        .await
+        .unwrap();
+        let before = stored_vectors(&store);
+
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let result = reembed(&store, project_id, "new/4", false, |texts| {
+            let call = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                assert!(texts.len() <= ML_EMBED_LIMIT);
+                if call > 0 {
+                    anyhow::bail!("embedder went away");
+                }
+                Ok(texts.iter().map(|_| vec![2.0; DIM]).collect())
+            }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.into_inner(), 2);
+        assert_eq!(stored_vectors(&store), before);
+        assert_eq!(
+            store
+                .project_embedding_model(project_id)
+                .unwrap()
+                .as_deref(),
+            Some("old/4")
+        );
+    }
+}
@@ -2,8 +2,9 @@
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
 use anyhow::Result;
-use std::io::{self, Write};
+use std::path::Path;
 
+use super::confirm;
 use crate::config::Config;
 use crate::index::store::Store;
 
@@ -16,21 +17,7 @@ pub async fn run(project: &str, force: bool) -> Result<()> {
     }
 
     let store = Store::open(&db_path)?;
-
-    let project_id = match store.get_project_id(project) {
-        Ok(id) => id,
-        Err(_) => {
-            let projects = store.list_projects()?;
-            if projects.is_empty() {
-                anyhow::bail!("no projects indexed");
-            }
-            eprintln!("project '{}' not found. available projects:", project);
-            for p in &projects {
-                eprintln!("  - {}", p.name);
-            }
-            anyhow::bail!("project not found");
-        }
-    };
+    let project_id = find_project(&store, project)?;
 
     let file_count = store.file_count(Some(project_id))?;
     let chunk_count = store.chunk_count(Some(project_id))?;
@@ -38,14 +25,7 @@ pub async fn run(project: &str, force: bool) -> Result<()> {
     if !force {
         eprintln!("this will remove project '{}' from the index:", project);
         eprintln!("  {} files, {} chunks", file_count, chunk_count);
-        eprintln!();
-        eprint!("are you sure? [y/N] ");
-        io::stderr().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        if !input.trim().eq_ignore_ascii_case("y") {
+        if !confirm()? {
             eprintln!("cancelled");
             return Ok(());
         }
@@ -53,6 +33,7 @@ pub async fn run(project: &str, force: bool) -> Result<()> {
 
     store.delete_project(project_id)?;
     store.wal_checkpoint()?;
+    crate::index::hnsw::invalidate_cache();
 
     println!(
         "removed project '{}' ({} files, {} chunks)",
@@ -60,3 +41,199 @@ pub async fn run(project: &str, force: bool) -> Result<()> {
     );
     Ok(())
 }
+
+/// remove every project and the vector index
+pub async fn run_all(force: bool) -> Result<()> {
+    let config = Config::load()?;
+    let db_path = config.db_path();
+
+    if !db_path.exists() {
+        anyhow::bail!("no index found - nothing to remove");
+    }
+
+    let store = Store::open(&db_path)?;
+    let projects = store.list_projects()?;
+    if projects.is_empty() {
+        anyhow::bail!("no projects indexed");
+    }
+
+    if !force {
+        eprintln!(
+            "this will remove all {} projects from the index:",
+            projects.len()
+        );
+        for p in &projects {
+            eprintln!("  - {}", p.name);
+        }
+        eprintln!(
+            "  {} files, {} chunks",
+            store.file_count(None)?,
+            store.chunk_count(None)?
+        );
+        if !confirm()? {
+            eprintln!("cancelled");
+            return Ok(());
+        }
+    }
+
+    let removed = remove_all_projects(&store)?;
+    store.wal_checkpoint()?;
+    clear_vectors_dir(&config.vectors_dir())?;
+    crate::index::hnsw::invalidate_cache();
+
+    println!("removed {} projects", removed);
+    Ok(())
+}
+
+/// remove one file from a project. relative paths are taken from the
+/// project root.
+pub async fn run_file(project: &str, file: &str) -> Result<()> {
+    let config = Config::load()?;
+    let db_path = config.db_path();
+
+    if !db_path.exists() {
+        anyhow::bail!("no index found - nothing to remove");
+    }
+
+    let store = Store::open(&db_path)?;
+    let project_id = find_project(&store, project)?;
+    let root = store
+        .list_projects()?
+        .into_iter()
+        .find(|p| p.id == Some(project_id))
+        .map(|p| p.path)
+        .unwrap_or_default();
+    let path = resolve_file_path(Path::new(&root), file);
+
+    if !remove_file(&store, project_id, &path)? {
+        anyhow::bail!("'{}' is not indexed in project '{}'", path, project);
+    }
+    store.wal_checkpoint()?;
+    crate::index::hnsw::invalidate_cache();
+
+    println!("removed {} from '{}'", path, project);
+    Ok(())
+}
+
+fn find_project(store: &Store, project: &str) -> Result<i64> {
+    match store.get_project_id(project) {
+        Ok(id) => Ok(id),
+        Err(_) => {
+            let projects = store.list_projects()?;
+            if projects.is_empty() {
+                anyhow::bail!("no projects indexed");
+            }
+            eprintln!("project '{}' not found. available projects:", project);
+            for p in &projects {
+                eprintln!("  - {}", p.name);
+            }
+            anyhow::bail!("project not found");
+        }
+    }
+}
+
+fn remove_all_projects(store: &Store) -> Result<usize> {
+    let projects = store.list_projects()?;
+    for project_id in projects.iter().filter_map(|p| p.id) {
+        store.delete_project(project_id)?;
+    }
+    Ok(projects.len())
+}
+
+/// drop a file's chunks, fts rows, embeddings and call graph. false when
+/// the file isn't indexed.
+fn remove_file(store: &Store, project_id: i64, path: &str) -> Result<bool> {
+    if store.get_file_hash(project_id, path)?.is_none() {
+        return Ok(false);
+    }
+    store.delete_file(project_id, path)?;
+    store.bump_project_generation(project_id)?;
+    Ok(true)
+}
+
+/// indexed paths are absolute; canonicalise when the file still exists so
+/// symlinked roots match what was indexed
+fn resolve_file_path(root: &Path, file: &str) -> String {
+    let path = root.join(file);
+    std::fs::canonicalize(&path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// the hnsw files hold vectors for every project; with none left they are
+/// rebuilt empty on the next index
+fn clear_vectors_dir(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use srag_common::types::{Chunk, Language};
+
+    fn index_file(store: &Store, project_id: i64, path: &str, symbol: &str) {
+        let file_id = seed_file(store, project_id, path, Language::Rust);
+        let chunk = Chunk {
+            symbol: Some(symbol.to_string()),
+            symbol_kind: Some("function".to_string()),
+            ..test_chunk(file_id, &format!("fn {}() {{}}", symbol), 1)
+        };
+        let chunk_id = seed_chunk(store, &chunk, path);
+        let embedding_id = store.insert_embedding(chunk_id, &[0.5; 4]).unwrap();
+        store
+            .update_chunk_embedding_id(chunk_id, embedding_id)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_remove_all_projects_empties_store() {
+        let (store, _dir) = test_store();
+        let a = store.upsert_project("a", "/work/a").unwrap();
+        let b = store.upsert_project("b", "/work/b").unwrap();
+        index_file(&store, a, "/work/a/lib.rs", "alpha_fn");
+        index_file(&store, b, "/work/b/lib.rs", "beta_fn");
+
+        assert_eq!(remove_all_projects(&store).unwrap(), 2);
+        assert!(store.list_projects().unwrap().is_empty());
+        assert_eq!(store.file_count(None).unwrap(), 0);
+        assert_eq!(store.chunk_count(None).unwrap(), 0);
+        assert_eq!(store.embedding_count().unwrap(), 0);
+        assert!(store.search_fts("alpha_fn", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remove_file_leaves_rest_of_project() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/work/proj").unwrap();
+        index_file(&store, pid, "/work/proj/gone.rs", "gone_fn");
+        index_file(&store, pid, "/work/proj/kept.rs", "kept_fn");
+
+        let path = resolve_file_path(Path::new("/work/proj"), "gone.rs");
+        assert_eq!(path, "/work/proj/gone.rs");
+        assert!(remove_file(&store, pid, &path).unwrap());
+        assert!(!remove_file(&store, pid, &path).unwrap());
+
+        assert!(store.search_fts("gone_fn", 10).unwrap().is_empty());
+        assert_eq!(store.search_fts("kept_fn", 10).unwrap().len(), 1);
+        assert_eq!(store.file_count(Some(pid)).unwrap(), 1);
+        assert_eq!(store.embedding_count().unwrap(), 1);
+        assert_eq!(store.project_generation(pid).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_clear_vectors_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let vectors = dir.path().join("vectors");
+        std::fs::create_dir_all(&vectors).unwrap();
+        std::fs::write(vectors.join("srag.hnsw.graph"), b"x").unwrap();
+        clear_vectors_dir(&vectors).unwrap();
+        assert!(vectors.is_dir());
+        assert_eq!(std::fs::read_dir(&vectors).unwrap().count(), 0);
+    }
+}
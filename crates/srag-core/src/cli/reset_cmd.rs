@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+use super::confirm;
+use crate::config::Config;
+
+/// delete the index database and vectors, keeping config and models
+pub async fn run(yes: bool) -> Result<()> {
+    let config = Config::load()?;
+    let targets = index_paths(&config);
+    let runtime: Vec<PathBuf> = [
+        Config::port_file_path(),
+        Config::token_file_path(),
+        Config::socket_path(),
+    ]
+    .into_iter()
+    .filter(|p| p.exists())
+    .collect();
+
+    if targets.is_empty() && runtime.is_empty() {
+        println!("nothing to reset in {}", config.data_dir().display());
+        return Ok(());
+    }
+
+    if !yes {
+        eprintln!("this will delete:");
+        for path in targets.iter().chain(&runtime) {
+            eprintln!("  {}", path.display());
+        }
+        eprintln!("config.toml and downloaded models are kept");
+        if !confirm()? {
+            eprintln!("cancelled");
+            return Ok(());
+        }
+    }
+
+    // nothing may hold the database open while it goes
+    if Config::watcher_pid_path().exists() {
+        crate::watcher::stop_watcher()?;
+    }
+    crate::ipc::lifecycle::stop_ml_service()?;
+    for path in &runtime {
+        let _ = std::fs::remove_file(path);
+    }
+
+    let removed = reset_index(&config)?;
+    crate::index::hnsw::invalidate_cache();
+    println!(
+        "removed {} index files from {}",
+        removed,
+        config.data_dir().display()
+    );
+    Ok(())
+}
+
+/// the database, its wal and shm, and everything in the vectors dir, as
+/// far as they exist
+fn index_paths(config: &Config) -> Vec<PathBuf> {
+    let db_path = config.db_path();
+    let mut paths: Vec<PathBuf> = ["", "-wal", "-shm"]
+        .iter()
+        .map(|suffix| {
+            let mut path = db_path.as_os_str().to_owned();
+            path.push(suffix);
+            PathBuf::from(path)
+        })
+        .filter(|p| p.exists())
+        .collect();
+    if let Ok(entries) = std::fs::read_dir(config.vectors_dir()) {
+        let mut vectors: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        vectors.sort();
+        paths.extend(vectors);
+    }
+    paths
+}
+
+/// delete what `index_paths` lists, leaving an empty vectors dir. returns
+/// how many entries were removed.
+fn reset_index(config: &Config) -> Result<usize> {
+    let paths = index_paths(config);
+    for path in &paths {
+        if path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+    }
+    std::fs::create_dir_all(config.vectors_dir())?;
+    Ok(paths.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::store::Store;
+
+    #[test]
+    fn test_reset_index_clears_store_and_vectors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            data_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        config.ensure_dirs().unwrap();
+        std::fs::write(config.models_dir().join("model.gguf"), b"weights").unwrap();
+
+        let store = Store::open(&config.db_path()).unwrap();
+        store.upsert_project("proj", "/work/proj").unwrap();
+        drop(store);
+        std::fs::write(config.vectors_dir().join("srag.hnsw.graph"), b"x").unwrap();
+        std::fs::write(config.vectors_dir().join("srag.hnsw.data"), b"x").unwrap();
+
+        assert!(reset_index(&config).unwrap() >= 3);
+
+        assert!(config.vectors_dir().is_dir());
+        assert_eq!(std::fs::read_dir(config.vectors_dir()).unwrap().count(), 0);
+        let store = Store::open(&config.db_path()).unwrap();
+        assert!(store.list_projects().unwrap().is_empty());
+        // models are left alone
+        assert!(config.models_dir().join("model.gguf").exists());
+    }
+}
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use srag_common::types::Chunk;
+
+use crate::config::Config;
+use crate::index::store::{FtsQueryMode, Store};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SearchMode {
+    /// full-text search over words, through the fts index
+    #[default]
+    Keyword,
+    /// exact substring, punctuation and case included; scans every chunk
+    Literal,
+}
+
+pub async fn run(text: &str, project: Option<&str>, mode: SearchMode, limit: usize) -> Result<()> {
+    let config = Config::load()?;
+    let db_path = config.db_path();
+    if !db_path.exists() {
+        anyhow::bail!("no index found - run 'srag index <path>' first");
+    }
+    let store = Store::open(&db_path)?;
+    let project_id = match project {
+        Some(name) => Some(
+            store
+                .get_project_id(name)
+                .map_err(|_| anyhow::anyhow!("project '{}' not found", name))?,
+        ),
+        None => None,
+    };
+
+    match mode {
+        SearchMode::Keyword => {
+            let results = store.search_fts_snippets(
+                text,
+                FtsQueryMode::Terms,
+                project_id,
+                limit,
+                0,
+                ">>",
+                "<<",
+            )?;
+            for (chunk_id, _, snippet) in results {
+                if let Some((chunk, path)) = store.get_chunk_by_id(chunk_id)? {
+                    println!("{}:{}-{}", path, chunk.start_line, chunk.end_line);
+                    for line in snippet.lines() {
+                        println!("    {}", line);
+                    }
+                }
+            }
+        }
+        SearchMode::Literal => {
+            let chunks = store.search_literal(text, project_id, limit)?;
+            for (path, line, content) in literal_hits(&chunks, text) {
+                println!("{}:{}: {}", path, line, content);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// the lines of `chunks` containing `needle` as (path, line number, line),
+/// grep style. a line shared by overlapping chunks is listed once.
+pub(crate) fn literal_hits(chunks: &[(Chunk, String)], needle: &str) -> Vec<(String, u32, String)> {
+    let mut seen = HashSet::new();
+    let mut hits = Vec::new();
+    for (chunk, path) in chunks {
+        for (i, line) in chunk.content.lines().enumerate() {
+            let line_no = chunk.start_line + i as u32;
+            if line.contains(needle) && seen.insert((path.as_str(), line_no)) {
+                hits.push((path.clone(), line_no, line.to_string()));
+            }
+        }
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use srag_common::types::Language;
+
+    fn chunk(content: &str, start_line: u32) -> Chunk {
+        Chunk {
+            id: None,
+            file_id: 1,
+            content: content.to_string(),
+            symbol: None,
+            symbol_kind: None,
+            start_line,
+            end_line: start_line + content.lines().count() as u32 - 1,
+            language: Language::Rust,
+            suspicious: false,
+        }
+    }
+
+    #[test]
+    fn test_literal_hits_lists_each_line_once() {
+        let outer = chunk(
+            "impl Foo {\n    fn new() -> Self {\n        Foo::new_inner()\n    }\n}",
+            10,
+        );
+        let inner = chunk(
+            "    fn new() -> Self {\n        Foo::new_inner()\n    }",
+            11,
+        );
+        let chunks = vec![
+            (outer, "/p/foo.rs".to_string()),
+            (inner, "/p/foo.rs".to_string()),
+        ];
+
+        assert_eq!(
+            literal_hits(&chunks, "::new"),
+            vec![(
+                "/p/foo.rs".to_string(),
+                12,
+                "        Foo::new_inner()".to_string()
+            )]
+        );
+    }
+}
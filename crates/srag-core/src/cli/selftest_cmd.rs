@@ -0,0 +1,312 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::discovery;
+use crate::index::hnsw::VectorIndex;
+use crate::index::store::Store;
+use crate::ipc::client::MlClient;
+use crate::ipc::lifecycle;
+use crate::query::{context, prompt, retriever};
+use srag_common::types::FileRecord;
+
+const PROJECT_NAME: &str = "srag-selftest";
+const QUERY: &str = "how are passwords hashed with a salt";
+const EXPECTED_FILE: &str = "auth.rs";
+const SEARCH_K: usize = 3;
+
+const FIXTURES: &[(&str, &str)] = &[
+    (
+        "src/auth.rs",
+        r#"/// hash a user password with a random salt before storing it.
+pub fn hash_password(password: &str, salt: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(salt);
+    hasher.update(password.as_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+/// check a login attempt against the stored password hash and salt.
+pub fn verify_password(password: &str, salt: &[u8], expected: &str) -> bool {
+    hash_password(password, salt) == expected
+}
+"#,
+    ),
+    (
+        "src/network.rs",
+        r#"use std::net::TcpStream;
+
+/// open a tcp connection to the remote server with a timeout.
+pub fn connect_to_server(addr: &str) -> std::io::Result<TcpStream> {
+    let stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+"#,
+    ),
+    (
+        "scripts/report.py",
+        r#"def render_report(rows):
+    """format table rows into a plain text report for the terminal."""
+    lines = []
+    for row in rows:
+        lines.append(" | ".join(str(cell) for cell in row))
+    return "\n".join(lines)
+"#,
+    ),
+];
+
+/// the ml operations the self-test exercises. implemented by the real client
+/// and by a mock in tests.
+pub(crate) trait SelftestBackend {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+    async fn generate(&self, prompt: &str, max_tokens: u32, temperature: f32) -> Result<String>;
+}
+
+impl SelftestBackend for MlClient {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(MlClient::embed(self, texts).await?)
+    }
+
+    async fn generate(&self, prompt: &str, max_tokens: u32, temperature: f32) -> Result<String> {
+        Ok(MlClient::generate(self, prompt, max_tokens, temperature).await?)
+    }
+}
+
+pub(crate) struct SelftestReport {
+    pub files: usize,
+    pub chunks: usize,
+    pub top_file: String,
+    pub answer: String,
+}
+
+pub async fn run() -> Result<()> {
+    let config = Config::load()?;
+    config.ensure_dirs()?;
+
+    println!("starting ml service...");
+    lifecycle::ensure_ml_service_running(&config)?;
+    let client = MlClient::connect_service(&config).await?;
+
+    let report = run_selftest(&client, &config).await?;
+
+    println!(
+        "indexed {} files ({} chunks) into a temporary index",
+        report.files, report.chunks
+    );
+    println!("query '{}' retrieved {}", QUERY, report.top_file);
+    println!("generated a {} character answer", report.answer.len());
+    println!("selftest passed");
+    Ok(())
+}
+
+/// index a synthetic project in a throwaway directory, query it and check
+/// the expected file comes back. never touches the user's index.
+pub(crate) async fn run_selftest<B: SelftestBackend>(
+    backend: &B,
+    config: &Config,
+) -> Result<SelftestReport> {
+    let tmp = tempfile::Builder::new().prefix("srag-selftest").tempdir()?;
+    let project_dir = tmp.path().join("project");
+    write_fixtures(&project_dir)?;
+
+    let store = Store::open(&tmp.path().join("selftest.db"))?;
+    let project_id = store.upsert_project(PROJECT_NAME, &project_dir.to_string_lossy())?;
+
+    let files = discovery::walk_directory(&project_dir, config)?;
+    if files.len() != FIXTURES.len() {
+        anyhow::bail!(
+            "discovery found {} files, expected {}",
+            files.len(),
+            FIXTURES.len()
+        );
+    }
+
+    let mut pending: Vec<(i64, String)> = Vec::new();
+    for file_path in &files {
+        let content = std::fs::read(file_path)?;
+        let path_str = file_path.to_string_lossy().to_string();
+        let language = discovery::detect_language(file_path, &content, config);
+        let chunks = crate::chunking::chunk_file_opts(
+            &content,
+            language,
+            crate::chunking::ChunkOptions::from_config(&config.indexing),
+        )?;
+
+        let file_id = store.upsert_file(&FileRecord {
+            id: None,
+            project_id,
+            path: path_str.clone(),
+            blake3_hash: blake3::hash(&content).to_hex().to_string(),
+            language,
+            size_bytes: content.len() as u64,
+            chunk_count: chunks.len() as u32,
+            indexed_at: String::new(),
+        })?;
+
+        for chunk in &chunks {
+            let mut c = chunk.clone();
+            c.file_id = file_id;
+            let chunk_id = store.insert_chunk(&c, None)?;
+            pending.push((
+                chunk_id,
+                crate::cli::index_cmd::enrich_chunk_text(
+                    &path_str,
+                    &c,
+                    config.indexing.strip_comments_for_embedding,
+                ),
+            ));
+        }
+    }
+
+    if pending.is_empty() {
+        anyhow::bail!("chunking produced no chunks");
+    }
+
+    let texts: Vec<String> = pending.iter().map(|(_, text)| text.clone()).collect();
+    let vectors = backend.embed(&texts).await?;
+    if vectors.len() != pending.len() {
+        anyhow::bail!(
+            "embedder returned {} vectors for {} chunks",
+            vectors.len(),
+            pending.len()
+        );
+    }
+
+    let mut vector_index = VectorIndex::new(crate::config::EMBEDDING_DIMENSION, 1_000)?;
+    for ((chunk_id, _), vector) in pending.iter().zip(&vectors) {
+        let embedding_id = store.insert_embedding(*chunk_id, vector)?;
+        store.update_chunk_embedding_id(*chunk_id, embedding_id)?;
+        vector_index.insert(embedding_id as usize, vector)?;
+    }
+
+    let query_vec = backend
+        .embed(&[QUERY.to_string()])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("no embedding returned for query"))?;
+
+    let results = vector_index.search(&query_vec, SEARCH_K, config.query.ef_search)?;
+    let retrieved = retriever::resolve_results(&store, &results)?;
+    let top_file = retrieved
+        .first()
+        .map(|(_, path)| path.clone())
+        .ok_or_else(|| anyhow::anyhow!("query returned no results"))?;
+    if !top_file.ends_with(EXPECTED_FILE) {
+        anyhow::bail!(
+            "expected {} as the top result for '{}', got {}",
+            EXPECTED_FILE,
+            QUERY,
+            top_file
+        );
+    }
+
+    let context_text = context::assemble_context(
+        &retrieved,
+        config.query.context_tokens,
+        crate::query::tokens::counter_for(config).as_ref(),
+    );
+    let built = prompt::build_prompt(
+        QUERY,
+        &context_text,
+        &[],
+        config.query.grounding_instructions,
+        None,
+    );
+    let answer = backend
+        .generate(
+            &built.text,
+            config.query.max_tokens,
+            config.query.temperature,
+        )
+        .await?;
+    if answer.trim().is_empty() {
+        anyhow::bail!("generator returned an empty answer");
+    }
+
+    Ok(SelftestReport {
+        files: files.len(),
+        chunks: pending.len(),
+        top_file,
+        answer,
+    })
+}
+
+fn write_fixtures(root: &Path) -> Result<()> {
+    for (rel, content) in FIXTURES {
+        let path = root.join(rel);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// bag-of-words embedder: each lowercase word is hashed into a bucket so
+    /// texts sharing vocabulary land close together.
+    struct MockBackend;
+
+    impl SelftestBackend for MockBackend {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts.iter().map(|t| bag_of_words(t)).collect())
+        }
+
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _max_tokens: u32,
+            _temperature: f32,
+        ) -> Result<String> {
+            Ok("passwords are hashed with blake3 and a salt in hash_password".to_string())
+        }
+    }
+
+    fn bag_of_words(text: &str) -> Vec<f32> {
+        let mut v = vec![0.0f32; crate::config::EMBEDDING_DIMENSION];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.len() > 2)
+        {
+            let bucket = blake3::hash(word.to_lowercase().as_bytes()).as_bytes()[0] as usize;
+            v[bucket] += 1.0;
+        }
+        // keep the vector non-zero for cosine distance
+        v[crate::config::EMBEDDING_DIMENSION - 1] += 0.01;
+        v
+    }
+
+    #[tokio::test]
+    async fn test_selftest_passes_with_mock_backend() {
+        let config = Config::default();
+        let report = run_selftest(&MockBackend, &config).await.unwrap();
+        assert_eq!(report.files, FIXTURES.len());
+        assert!(report.chunks >= FIXTURES.len());
+        assert!(report.top_file.ends_with(EXPECTED_FILE));
+        assert!(!report.answer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_selftest_fails_on_empty_answer() {
+        struct SilentBackend;
+        impl SelftestBackend for SilentBackend {
+            async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+                MockBackend.embed(texts).await
+            }
+            async fn generate(&self, _: &str, _: u32, _: f32) -> Result<String> {
+                Ok(String::new())
+            }
+        }
+
+        let config = Config::default();
+        assert!(run_selftest(&SilentBackend, &config).await.is_err());
+    }
+}
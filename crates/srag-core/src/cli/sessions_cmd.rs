@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use anyhow::Result;
+use std::io::{self, Write};
+
+use crate::config::Config;
+use crate::index::store::{SessionSummary, Store};
+use srag_common::types::ConversationTurn;
+
+/// length of the id prefix shown in listings, matching the chat banner
+const SHORT_ID_LEN: usize = 8;
+
+fn open_store() -> Result<Store> {
+    let config = Config::load()?;
+    let db_path = config.db_path();
+    if !db_path.exists() {
+        anyhow::bail!("no index found - no chat sessions yet");
+    }
+    Ok(Store::open(&db_path)?)
+}
+
+pub async fn list() -> Result<()> {
+    let store = open_store()?;
+    let sessions = store.list_sessions()?;
+    if sessions.is_empty() {
+        println!("no chat sessions");
+        return Ok(());
+    }
+
+    println!("{:<10} {:<20} {:<20} turns", "id", "project", "created");
+    for s in &sessions {
+        println!(
+            "{:<10} {:<20} {:<20} {}",
+            short_id(&s.id),
+            s.project_name.as_deref().unwrap_or("-"),
+            s.created_at,
+            s.turn_count
+        );
+    }
+    Ok(())
+}
+
+pub async fn show(id: &str) -> Result<()> {
+    let store = open_store()?;
+    let session_id = resolve_session(&store, id)?;
+    let summary = store
+        .get_session(&session_id)?
+        .ok_or_else(|| anyhow::anyhow!("session '{}' not found", id))?;
+    let turns = store.get_all_turns(&session_id)?;
+    print!("{}", format_transcript(&summary, &turns));
+    Ok(())
+}
+
+pub async fn delete(id: &str, force: bool) -> Result<()> {
+    let store = open_store()?;
+    let session_id = resolve_session(&store, id)?;
+    let turns = store.count_turns(&session_id)?;
+
+    if !force {
+        eprint!("delete session {} ({} turns)? [y/N] ", session_id, turns);
+        io::stderr().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        if !input.trim().eq_ignore_ascii_case("y") {
+            eprintln!("cancelled");
+            return Ok(());
+        }
+    }
+
+    store.delete_session(&session_id)?;
+    println!("deleted session {} ({} turns)", session_id, turns);
+    Ok(())
+}
+
+/// accept a full session id or any unambiguous prefix of one
+pub(crate) fn resolve_session(store: &Store, id: &str) -> Result<String> {
+    if store.get_session(id)?.is_some() {
+        return Ok(id.to_string());
+    }
+    let mut matches = store.find_sessions_by_prefix(id)?;
+    match matches.len() {
+        0 => anyhow::bail!("session '{}' not found - see 'srag sessions list'", id),
+        1 => Ok(matches.remove(0)),
+        n => anyhow::bail!(
+            "'{}' matches {} sessions, use more of the id: {}",
+            id,
+            n,
+            matches.join(", ")
+        ),
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+pub(crate) fn format_transcript(summary: &SessionSummary, turns: &[ConversationTurn]) -> String {
+    let mut out = format!(
+        "session {} (project: {}, created {}, {} turns)\n",
+        summary.id,
+        summary.project_name.as_deref().unwrap_or("-"),
+        summary.created_at,
+        summary.turn_count
+    );
+    for turn in turns {
+        out.push_str(&format!(
+            "\n[{}] {}\n{}\n",
+            turn.role,
+            turn.created_at,
+            turn.content.trim_end()
+        ));
+        let sources: Vec<String> = turn
+            .sources
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default();
+        if !sources.is_empty() {
+            out.push_str("sources:\n");
+            for source in sources {
+                out.push_str(&format!("  {}\n", source));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::store::tests::test_store;
+
+    #[test]
+    fn test_resolve_session_by_prefix() {
+        let (store, _dir) = test_store();
+        store.create_session("3f2a9c10-aaaa", None).unwrap();
+        store.create_session("3f2b0000-bbbb", None).unwrap();
+
+        assert_eq!(
+            resolve_session(&store, "3f2a9c10-aaaa").unwrap(),
+            "3f2a9c10-aaaa"
+        );
+        assert_eq!(resolve_session(&store, "3f2a").unwrap(), "3f2a9c10-aaaa");
+        let err = resolve_session(&store, "3f2").unwrap_err().to_string();
+        assert!(err.contains("matches 2 sessions"), "{}", err);
+        assert!(resolve_session(&store, "ffff").is_err());
+    }
+
+    #[test]
+    fn test_format_transcript() {
+        let summary = SessionSummary {
+            id: "abc".into(),
+            project_name: Some("proj".into()),
+            created_at: "2026-01-01 10:00:00".into(),
+            turn_count: 2,
+        };
+        let turn = |role: &str, content: &str, sources: Option<&str>| ConversationTurn {
+            id: None,
+            session_id: "abc".into(),
+            role: role.into(),
+            content: content.into(),
+            sources: sources.map(String::from),
+            created_at: "2026-01-01 10:00:01".into(),
+        };
+        let text = format_transcript(
+            &summary,
+            &[
+                turn("user", "where is auth?", None),
+                turn("assistant", "in auth.rs", Some(r#"["src/auth.rs:1-20"]"#)),
+            ],
+        );
+        assert!(text.starts_with("session abc (project: proj"));
+        assert!(text.contains("[user] 2026-01-01 10:00:01\nwhere is auth?"));
+        assert!(text.contains("sources:\n  src/auth.rs:1-20\n"));
+    }
+}
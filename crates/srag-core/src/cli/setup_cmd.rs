@@ -82,6 +82,9 @@ pub async fn run(all: bool) -> Result<()> {
             false,
             false,
             all,
+            false,
+            None,
+            false,
         )
         .await?;
     }
@@ -6,6 +6,9 @@ use anyhow::Result;
 use crate::config::Config;
 use crate::index::store::Store;
 
+/// files listed under a project's flagged chunk count with --detailed
+const TOP_SUSPICIOUS_FILES: usize = 5;
+
 pub async fn run(detailed: bool) -> Result<()> {
     let config = Config::load()?;
     let db_path = config.db_path();
@@ -21,11 +24,16 @@ pub async fn run(detailed: bool) -> Result<()> {
     let total_chunks = store.chunk_count(None)?;
     let total_embedded = store.embedded_chunk_count(None)?;
     let total_bytes = store.total_size_bytes(None)?;
+    let total_flagged = store.suspicious_chunk_count(None)?;
 
     println!("srag status");
     println!("  projects: {}", projects.len());
     println!("  files:    {}", total_files);
     println!("  chunks:   {} ({} embedded)", total_chunks, total_embedded);
+    println!(
+        "  flagged:  {} chunks (possible prompt injection)",
+        total_flagged
+    );
     println!("  size:     {}", format_bytes(total_bytes));
     println!("  db:       {}", db_path.display());
 
@@ -37,22 +45,30 @@ pub async fn run(detailed: bool) -> Result<()> {
             let chunks = store.chunk_count(Some(pid))?;
             let embedded = store.embedded_chunk_count(Some(pid))?;
             let size = store.total_size_bytes(Some(pid))?;
+            let flagged = store.suspicious_chunk_count(Some(pid))?;
             println!("  [{}]", p.name);
             println!("    path:         {}", p.path);
             println!("    files:        {}", files);
             println!("    chunks:       {} ({} embedded)", chunks, embedded);
+            println!("    flagged:      {} chunks", flagged);
+            for (path, count) in store.top_suspicious_files(pid, TOP_SUSPICIOUS_FILES)? {
+                println!("      {:>4}  {}", count, path);
+            }
             println!("    size:         {}", format_bytes(size));
             println!(
                 "    last indexed: {}",
                 p.last_indexed_at.as_deref().unwrap_or("never")
             );
+            if store.project_needs_reembed(pid)? {
+                println!("    embeddings:   evicted, run 'srag reembed {}'", p.name);
+            }
         }
     }
 
     Ok(())
 }
 
-fn format_bytes(bytes: u64) -> String {
+pub(crate) fn format_bytes(bytes: u64) -> String {
     if bytes < 1024 {
         format!("{} B", bytes)
     } else if bytes < 1024 * 1024 {
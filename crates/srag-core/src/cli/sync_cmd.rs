@@ -2,11 +2,18 @@
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
 use anyhow::Result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::config::Config;
 use crate::index::store::Store;
 
-pub async fn run() -> Result<()> {
+/// `since` overrides the mtime cutoff; by default each project only looks at
+/// files modified after it was last indexed
+pub async fn run(since: Option<&str>) -> Result<()> {
+    let since = since
+        .map(|s| parse_since(s, SystemTime::now()))
+        .transpose()?;
+
     let config = Config::load()?;
     let db_path = config.db_path();
 
@@ -17,6 +24,16 @@ pub async fn run() -> Result<()> {
 
     let store = Store::open(&db_path)?;
     let projects = store.list_projects()?;
+    let mut cutoffs = Vec::with_capacity(projects.len());
+    for project in &projects {
+        cutoffs.push(match (since, project.id) {
+            (Some(cutoff), _) => Some(cutoff),
+            (None, Some(id)) => store
+                .project_last_indexed_unix(id)?
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)),
+            (None, None) => None,
+        });
+    }
     drop(store);
 
     if projects.is_empty() {
@@ -28,7 +45,7 @@ pub async fn run() -> Result<()> {
 
     let mut errors = Vec::new();
 
-    for project in &projects {
+    for (project, cutoff) in projects.iter().zip(cutoffs) {
         let path = std::path::Path::new(&project.path);
         if !path.is_dir() {
             println!(
@@ -40,9 +57,17 @@ pub async fn run() -> Result<()> {
         }
 
         println!("--- {} ---", project.name);
-        if let Err(e) =
-            super::index_cmd::run_opts(&project.path, Some(&project.name), false, false, false)
-                .await
+        if let Err(e) = super::index_cmd::run_opts(
+            &project.path,
+            Some(&project.name),
+            false,
+            false,
+            false,
+            false,
+            cutoff,
+            false,
+        )
+        .await
         {
             println!("error syncing {}: {}\n", project.name, e);
             errors.push(project.name.clone());
@@ -59,3 +84,82 @@ pub async fn run() -> Result<()> {
 
     Ok(())
 }
+
+/// a relative duration (`90s`, `30m`, `2h`, `3d`, `1w`) counted back from
+/// `now`, or an absolute utc time (`2026-01-31`, `2026-01-31 14:00:00`,
+/// rfc 3339).
+fn parse_since(value: &str, now: SystemTime) -> Result<SystemTime> {
+    let value = value.trim();
+    if let Some(unit) = value.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(amount) = value[..value.len() - 1].parse::<u64>() {
+            let secs = match unit {
+                's' => 1,
+                'm' => 60,
+                'h' => 60 * 60,
+                'd' => 24 * 60 * 60,
+                'w' => 7 * 24 * 60 * 60,
+                _ => anyhow::bail!("unknown duration unit '{}' in --since {}", unit, value),
+            };
+            return amount
+                .checked_mul(secs)
+                .and_then(|total| now.checked_sub(Duration::from_secs(total)))
+                .ok_or_else(|| anyhow::anyhow!("--since {} reaches too far back", value));
+        }
+    }
+
+    let parsed = chrono::DateTime::parse_from_rfc3339(value)
+        .map(|t| t.timestamp())
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .map(|t| t.and_utc().timestamp())
+        })
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp())
+        })
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "invalid --since '{}': expected a duration like 2h or 3d, or a date like 2026-01-31",
+                value
+            )
+        })?;
+    Ok(UNIX_EPOCH + Duration::from_secs(parsed.max(0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since_durations() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert_eq!(
+            parse_since("90s", now).unwrap(),
+            now - Duration::from_secs(90)
+        );
+        assert_eq!(
+            parse_since("2h", now).unwrap(),
+            now - Duration::from_secs(7200)
+        );
+        assert_eq!(
+            parse_since("3d", now).unwrap(),
+            now - Duration::from_secs(3 * 86400)
+        );
+        assert!(parse_since("3y", now).is_err());
+        assert!(parse_since(&format!("{}w", u64::MAX), now).is_err());
+        assert!(parse_since(&format!("{}d", u64::MAX / 86400), now).is_err());
+    }
+
+    #[test]
+    fn test_parse_since_timestamps() {
+        let now = SystemTime::now();
+        let day = UNIX_EPOCH + Duration::from_secs(1_769_817_600);
+        assert_eq!(parse_since("2026-01-31", now).unwrap(), day);
+        assert_eq!(
+            parse_since("2026-01-31 01:00:00", now).unwrap(),
+            day + Duration::from_secs(3600)
+        );
+        assert_eq!(parse_since("2026-01-31T01:00:00+01:00", now).unwrap(), day);
+        assert!(parse_since("yesterday", now).is_err());
+    }
+}
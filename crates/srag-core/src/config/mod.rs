@@ -3,18 +3,73 @@
 
 mod sections;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 pub use sections::{
-    ApiConfig, ApiProvider, IndexingConfig, LlmConfig, McpConfig, QueryConfig, ResourceConfig,
-    WatcherConfig,
+    ApiConfig, ApiProvider, ContextMode, DistanceMetric, EmbeddingConfig, EmbeddingProvider,
+    IndexingConfig, InjectionConfig, InjectionPolicy, IpcTransport, LlmConfig, McpConfig,
+    QueryConfig, ResourceConfig, StorageConfig, WatcherConfig,
 };
 
 pub const EMBEDDING_DIMENSION: usize = 384;
 
+/// per-project overrides, read from the project root
+pub const PROJECT_CONFIG_FILE: &str = ".srag.toml";
+
+/// top-level keys a project file may override. everything else (model
+/// downloads, api provider, ml service, storage) stays global-only so a
+/// cloned repository can't redirect where code or credentials go.
+const PROJECT_KEYS: &[&str] = &["indexing", "query", "injection", "ignore_patterns"];
+
+/// fields inside `PROJECT_KEYS` sections that are still global-only
+const GLOBAL_ONLY_FIELDS: &[&str] = &[
+    "query.system_prompt_file",
+    "query.on_injection",
+    "query.grounding_instructions",
+];
+
+/// the model the ml service embeds with. must match python/srag_ml/embedder.py.
+pub const EMBEDDING_MODEL: &str = "BAAI/bge-small-en-v1.5";
+
+/// recorded per project so vectors from a different model can be detected.
+pub fn embedding_signature(embedding: &EmbeddingConfig) -> String {
+    let model = match embedding.provider {
+        EmbeddingProvider::Local => EMBEDDING_MODEL,
+        EmbeddingProvider::OpenaiCompatible => embedding.model.as_str(),
+    };
+    format!("{}/{}", model, EMBEDDING_DIMENSION)
+}
+
+/// dependency lockfiles. generated, huge and rarely useful to search, so they
+/// are skipped by discovery and reduced to one chunk when indexed with --all.
+pub const LOCKFILE_NAMES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "deno.lock",
+    "Cargo.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "pdm.lock",
+    "uv.lock",
+    "conda-lock.yml",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "mix.lock",
+    "pubspec.lock",
+    "Podfile.lock",
+    "Package.resolved",
+    "packages.lock.json",
+    "gradle.lockfile",
+    "flake.lock",
+];
+
 pub const DEPENDENCY_DIRS: &[&str] = &[
     "node_modules",
     "bower_components",
@@ -71,6 +126,12 @@ pub struct Config {
     #[serde(default)]
     pub mcp: McpConfig,
     #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub embedding: EmbeddingConfig,
+    #[serde(default)]
+    pub injection: InjectionConfig,
+    #[serde(default)]
     pub ignore_patterns: Vec<String>,
 }
 
@@ -86,6 +147,9 @@ impl Default for Config {
             llm: LlmConfig::default(),
             api: ApiConfig::default(),
             mcp: McpConfig::default(),
+            storage: StorageConfig::default(),
+            embedding: EmbeddingConfig::default(),
+            injection: InjectionConfig::default(),
             ignore_patterns: vec![
                 "*.lock".into(),
                 "*.min.js".into(),
@@ -132,7 +196,7 @@ impl Config {
 
     pub fn load() -> Result<Self> {
         let path = Self::config_path();
-        let config = if path.exists() {
+        let mut config = if path.exists() {
             let content = std::fs::read_to_string(&path)
                 .with_context(|| format!("failed to read config from {}", path.display()))?;
             let config: Config =
@@ -141,17 +205,92 @@ impl Config {
         } else {
             Config::default()
         };
+        config.indexing.fold_extension_language();
         config.validate()?;
         Ok(config)
     }
 
-    fn validate(&self) -> Result<()> {
+    /// the global config with the project's `.srag.toml` layered over it
+    pub fn load_with_project_overrides(root: &Path) -> Result<Self> {
+        Self::load()?.with_project_overrides(root)
+    }
+
+    /// layer `root/.srag.toml` over this config. fields the project file
+    /// leaves out keep their global values; global-only fields it sets are
+    /// ignored with a warning.
+    pub fn with_project_overrides(&self, root: &Path) -> Result<Self> {
+        let path = root.join(PROJECT_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(self.clone());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let overrides: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        let config = self
+            .merge_project_table(overrides)
+            .with_context(|| format!("invalid {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("invalid {}", path.display()))?;
+        Ok(config)
+    }
+
+    fn merge_project_table(&self, overrides: toml::Table) -> Result<Self> {
+        let mut merged = toml::Table::try_from(self)?;
+        for (key, value) in overrides {
+            if !PROJECT_KEYS.contains(&key.as_str()) {
+                tracing::warn!("ignoring global-only '{}' in {}", key, PROJECT_CONFIG_FILE);
+                continue;
+            }
+            match (merged.get_mut(&key), value) {
+                (Some(toml::Value::Table(section)), toml::Value::Table(fields)) => {
+                    for (field, value) in fields {
+                        let dotted = format!("{}.{}", key, field);
+                        if GLOBAL_ONLY_FIELDS.contains(&dotted.as_str()) {
+                            tracing::warn!(
+                                "ignoring global-only '{}' in {}",
+                                dotted,
+                                PROJECT_CONFIG_FILE
+                            );
+                            continue;
+                        }
+                        section.insert(field, value);
+                    }
+                }
+                (_, value) => {
+                    merged.insert(key, value);
+                }
+            }
+        }
+        let mut config: Config = toml::Value::Table(merged).try_into()?;
+        config.indexing.fold_extension_language();
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
         if self.indexing.batch_size == 0 {
             anyhow::bail!("indexing.batch_size must be > 0");
         }
+        if self.indexing.embed_concurrency == 0 {
+            anyhow::bail!("indexing.embed_concurrency must be > 0");
+        }
         if self.indexing.max_file_size_bytes == 0 {
             anyhow::bail!("indexing.max_file_size_bytes must be > 0");
         }
+        if self.indexing.fallback_chunk_lines == 0 {
+            anyhow::bail!("indexing.fallback_chunk_lines must be > 0");
+        }
+        if self.indexing.fallback_overlap_lines >= self.indexing.fallback_chunk_lines {
+            anyhow::bail!("indexing.fallback_overlap_lines must be less than fallback_chunk_lines");
+        }
+        if self.indexing.hnsw_capacity == 0 {
+            anyhow::bail!("indexing.hnsw_capacity must be > 0");
+        }
+        if !self.indexing.hnsw_growth_factor.is_finite() || self.indexing.hnsw_growth_factor <= 1.0
+        {
+            anyhow::bail!("indexing.hnsw_growth_factor must be > 1");
+        }
         if self.query.top_k == 0 {
             anyhow::bail!("query.top_k must be > 0");
         }
@@ -161,6 +300,9 @@ impl Config {
         if self.query.context_tokens == 0 {
             anyhow::bail!("query.context_tokens must be > 0");
         }
+        if self.query.max_context_chunks == 0 {
+            anyhow::bail!("query.max_context_chunks must be > 0");
+        }
         if self.query.max_tokens == 0 {
             anyhow::bail!("query.max_tokens must be > 0");
         }
@@ -170,9 +312,75 @@ impl Config {
         if self.query.broad_k == 0 {
             anyhow::bail!("query.broad_k must be > 0");
         }
+        if !self.query.chars_per_token.is_finite() || self.query.chars_per_token < 0.0 {
+            anyhow::bail!("query.chars_per_token must be >= 0");
+        }
+        for (suffix, name) in &self.indexing.extension_map {
+            if suffix.trim_start_matches('.').is_empty() {
+                anyhow::bail!("indexing.extension_map has an empty extension");
+            }
+            if srag_common::types::Language::from_name(name).is_none() {
+                anyhow::bail!(
+                    "indexing.extension_map.{}: unknown language '{}'",
+                    suffix,
+                    name
+                );
+            }
+        }
+        for (name, weight) in [
+            ("query.rrf_vector_weight", self.query.rrf_vector_weight),
+            ("query.rrf_text_weight", self.query.rrf_text_weight),
+        ] {
+            if !weight.is_finite() || weight < 0.0 {
+                anyhow::bail!("{} must be a non-negative number", name);
+            }
+        }
+        if self.watcher.batch_files == 0 {
+            anyhow::bail!("watcher.batch_files must be > 0");
+        }
+        if self.resource.ml_startup_timeout_secs == 0 {
+            anyhow::bail!("resource.ml_startup_timeout_secs must be > 0");
+        }
+        if self.resource.ml_connect_attempts == 0 {
+            anyhow::bail!("resource.ml_connect_attempts must be > 0");
+        }
+        if !self.query.min_similarity.is_finite() || self.query.min_similarity > 1.0 {
+            anyhow::bail!("query.min_similarity must be at most 1.0");
+        }
+        if !(0.0..=1.0).contains(&self.query.mmr_lambda) {
+            anyhow::bail!("query.mmr_lambda must be between 0.0 and 1.0");
+        }
+        if let Some(path) = &self.query.system_prompt_file {
+            crate::query::prompt::PromptTemplate::load(path)
+                .context("query.system_prompt_file is not a usable template")?;
+        }
+        // compiled the way discovery does, so a bad glob fails here rather
+        // than partway into indexing
+        let mut ignore_patterns = ignore::overrides::OverrideBuilder::new("");
+        for pattern in &self.ignore_patterns {
+            if let Err(e) = ignore_patterns.add(&format!("!{}", pattern)) {
+                anyhow::bail!("ignore_patterns: invalid pattern '{}': {}", pattern, e);
+            }
+        }
+        let mut allow_paths = ignore::gitignore::GitignoreBuilder::new("");
+        for pattern in &self.injection.allow_paths {
+            if let Err(e) = allow_paths.add_line(None, pattern) {
+                anyhow::bail!("injection.allow_paths: {}", e);
+            }
+        }
         if let Err(e) = self.llm.validate() {
             anyhow::bail!(e);
         }
+        if self.embedding.provider == EmbeddingProvider::OpenaiCompatible {
+            if !self.embedding.base_url.starts_with("http://")
+                && !self.embedding.base_url.starts_with("https://")
+            {
+                anyhow::bail!("embedding.base_url must be an http(s) url");
+            }
+            if self.embedding.model.is_empty() {
+                anyhow::bail!("embedding.model must be set for openai_compatible");
+            }
+        }
         Ok(())
     }
 
@@ -237,10 +445,19 @@ impl Config {
         Self::runtime_dir().join("ml.port")
     }
 
+    pub fn socket_path() -> PathBuf {
+        Self::runtime_dir().join("ml.sock")
+    }
+
     pub fn token_file_path() -> PathBuf {
         Self::runtime_dir().join("ml.token")
     }
 
+    /// when any srag process last used the ml service, as unix seconds
+    pub fn activity_file_path() -> PathBuf {
+        Self::runtime_dir().join("ml.activity")
+    }
+
     pub fn watcher_pid_path() -> PathBuf {
         Self::runtime_dir().join("watcher.pid")
     }
@@ -347,6 +564,164 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_config_validation_negative_rrf_weight() {
+        let mut config = Config::default();
+        config.query.rrf_text_weight = -1.0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_injection_allow_paths() {
+        let mut config = Config::default();
+        config.injection.allow_paths = vec!["docs/**".into(), "*.prompt".into()];
+        assert!(config.validate().is_ok());
+        config.injection.allow_paths.push("fixtures/{a,b".into());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_hnsw_growth() {
+        let mut config = Config::default();
+        config.indexing.hnsw_growth_factor = 1.0;
+        assert!(config.validate().is_err());
+        config.indexing.hnsw_growth_factor = 1.5;
+        assert!(config.validate().is_ok());
+        config.indexing.hnsw_capacity = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_fallback_window() {
+        let mut config = Config::default();
+        config.indexing.fallback_chunk_lines = 20;
+        config.indexing.fallback_overlap_lines = 20;
+        assert!(config.validate().is_err());
+        config.indexing.fallback_overlap_lines = 0;
+        assert!(config.validate().is_ok());
+        config.indexing.fallback_chunk_lines = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_embed_concurrency() {
+        let mut config = Config::default();
+        config.indexing.embed_concurrency = 0;
+        assert!(config.validate().is_err());
+        config.indexing.embed_concurrency = 4;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_validation_chars_per_token() {
+        let mut config = Config::default();
+        config.query.chars_per_token = -1.0;
+        assert!(config.validate().is_err());
+        config.query.chars_per_token = f32::NAN;
+        assert!(config.validate().is_err());
+        config.query.chars_per_token = 3.5;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_validation_watcher_batch_files() {
+        let mut config = Config::default();
+        config.watcher.batch_files = 0;
+        assert!(config.validate().is_err());
+        config.watcher.batch_files = 1;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_validation_openai_compatible_embedding() {
+        let mut config = Config::default();
+        config.embedding.provider = EmbeddingProvider::OpenaiCompatible;
+        assert!(config.validate().is_err());
+        config.embedding.base_url = "http://localhost:8080/v1".into();
+        assert!(config.validate().is_err());
+        config.embedding.model = "nomic-embed-text".into();
+        assert!(config.validate().is_ok());
+        config.embedding.base_url = "localhost:8080".into();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_embedding_signature_follows_provider() {
+        let mut embedding = EmbeddingConfig::default();
+        assert_eq!(
+            embedding_signature(&embedding),
+            format!("{}/{}", EMBEDDING_MODEL, EMBEDDING_DIMENSION)
+        );
+        embedding.provider = EmbeddingProvider::OpenaiCompatible;
+        embedding.model = "nomic-embed-text".into();
+        assert_eq!(
+            embedding_signature(&embedding),
+            format!("nomic-embed-text/{}", EMBEDDING_DIMENSION)
+        );
+    }
+
+    #[test]
+    fn test_config_validation_ml_connect_attempts() {
+        let mut config = Config::default();
+        config.resource.ml_connect_attempts = 0;
+        assert!(config.validate().is_err());
+        config.resource.ml_connect_attempts = 1;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_validation_mmr_lambda() {
+        let mut config = Config::default();
+        config.query.mmr_lambda = 0.0;
+        assert!(config.validate().is_ok());
+        config.query.mmr_lambda = 1.5;
+        assert!(config.validate().is_err());
+        config.query.mmr_lambda = f32::NAN;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_validation_system_prompt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prompt.txt");
+        let mut config = Config::default();
+        config.query.system_prompt_file = Some(path.clone());
+        assert!(config.validate().is_err());
+
+        std::fs::write(&path, "house style\n{question}").unwrap();
+        assert!(config.validate().is_err());
+        std::fs::write(&path, "house style\n{context}\n{question}").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_validation_extension_map() {
+        let mut config = Config::default();
+        config
+            .indexing
+            .extension_map
+            .insert("rs.in".into(), "rust".into());
+        assert!(config.validate().is_ok());
+
+        config
+            .indexing
+            .extension_map
+            .insert("foo".into(), "cobol".into());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("cobol"), "{}", err);
+    }
+
+    #[test]
+    fn test_config_validation_ignore_patterns() {
+        let mut config = Config::default();
+        config.ignore_patterns.push("generated/**/*.rs".into());
+        assert!(config.validate().is_ok());
+
+        config.ignore_patterns.push("src/[ab".into());
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("'src/[ab'"), "{}", err);
+    }
+
     #[test]
     fn test_config_validation_invalid_max_file_size() {
         let mut config = Config::default();
@@ -410,4 +785,82 @@ batch_size = 64
         assert_eq!(config.indexing.batch_size, 64);
         assert_eq!(config.indexing.max_file_size_bytes, 1_048_576);
     }
+
+    #[test]
+    fn test_project_overrides_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "ignore_patterns = [\"fixtures\"]\n[indexing]\nmax_file_size_bytes = 4096\n[query]\ntop_k = 3\n",
+        )
+        .unwrap();
+        let mut global = Config::default();
+        global.query.broad_k = 77;
+        global.indexing.batch_size = 8;
+
+        let config = global.with_project_overrides(dir.path()).unwrap();
+        assert_eq!(config.indexing.max_file_size_bytes, 4096);
+        assert_eq!(config.query.top_k, 3);
+        assert_eq!(config.ignore_patterns, vec!["fixtures".to_string()]);
+        // fields the project leaves out keep their global values
+        assert_eq!(config.query.broad_k, 77);
+        assert_eq!(config.indexing.batch_size, 8);
+
+        // no project file means the global config unchanged
+        let empty = tempfile::tempdir().unwrap();
+        let config = global.with_project_overrides(empty.path()).unwrap();
+        assert_eq!(config.query.top_k, global.query.top_k);
+    }
+
+    #[test]
+    fn test_project_overrides_ignore_global_only_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            r#"
+data_dir = "/tmp/elsewhere"
+[llm]
+model_url = "https://evil.example/model.gguf"
+[api]
+provider = "openai"
+[query]
+on_injection = "ignore"
+top_k = 4
+"#,
+        )
+        .unwrap();
+        let global = Config::default();
+
+        let config = global.with_project_overrides(dir.path()).unwrap();
+        assert_eq!(config.query.top_k, 4);
+        assert_eq!(config.data_dir, global.data_dir);
+        assert_eq!(config.llm.model_url, global.llm.model_url);
+        assert_eq!(config.api.provider, global.api.provider);
+        assert_eq!(config.query.on_injection, global.query.on_injection);
+    }
+
+    #[test]
+    fn test_project_overrides_are_validated() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), "[query]\ntop_k = 0\n").unwrap();
+        assert!(Config::default()
+            .with_project_overrides(dir.path())
+            .is_err());
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[query]\ntop_k = \"x\"\n",
+        )
+        .unwrap();
+        assert!(Config::default()
+            .with_project_overrides(dir.path())
+            .is_err());
+    }
+
+    #[test]
+    fn test_ipc_transport_parsing() {
+        let config: Config = toml::from_str("[resource]\nipc_transport = \"unix\"\n").unwrap();
+        assert_eq!(config.resource.ipc_transport, IpcTransport::Unix);
+        assert_eq!(Config::default().resource.ipc_transport, IpcTransport::Tcp);
+        assert!(toml::from_str::<Config>("[resource]\nipc_transport = \"pipe\"\n").is_err());
+    }
 }
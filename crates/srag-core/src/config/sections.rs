@@ -1,12 +1,104 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpConfig {
     #[serde(default = "default_true")]
     pub auto_index_cwd: bool,
+    /// search_code results kept for repeated queries. 0 disables the cache.
+    #[serde(default = "default_search_cache_entries")]
+    pub search_cache_entries: usize,
+    /// seconds a cached search_code result is served for
+    #[serde(default = "default_search_cache_ttl_secs")]
+    pub search_cache_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StorageConfig {
+    /// live index database size to stay under after each index run, by
+    /// evicting embeddings of the least recently queried projects. 0 = no limit.
+    #[serde(default)]
+    pub max_index_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct InjectionConfig {
+    /// gitignore-style globs, relative to the project root, whose chunks are
+    /// never flagged as prompt injection. a project's `.sragallow` adds more.
+    #[serde(default)]
+    pub allow_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EmbeddingConfig {
+    #[serde(default)]
+    pub provider: EmbeddingProvider,
+    /// root of an openai-compatible api, e.g. `http://localhost:8080/v1`
+    #[serde(default)]
+    pub base_url: String,
+    #[serde(default)]
+    pub model: String,
+    /// file holding a bearer token for the endpoint, if it needs one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<PathBuf>,
+    /// how the vector index compares embeddings
+    #[serde(default)]
+    pub distance: DistanceMetric,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingProvider {
+    /// the bundled python ml service
+    #[default]
+    Local,
+    /// any server implementing `POST /embeddings` from the openai api
+    OpenaiCompatible,
+}
+
+/// distance used by the hnsw graph. the index on disk remembers which one
+/// it was built with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    /// 1 - cosine similarity
+    #[default]
+    Cosine,
+    /// inner product, for models trained on dot-product similarity
+    Dot,
+    /// euclidean distance
+    L2,
+}
+
+/// what a query does when most of its context was flagged by the injection
+/// scanner
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectionPolicy {
+    /// answer, with a warning in front
+    #[default]
+    Warn,
+    /// don't send the context to the model at all
+    Refuse,
+    Ignore,
+}
+
+/// how much of each retrieved chunk's file reaches the model
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextMode {
+    /// only the retrieved chunks
+    #[default]
+    Chunk,
+    /// each chunk plus the chunks just before and after it in its file
+    Expand,
+    /// the whole file of the top chunks, where it fits the budget
+    File,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +111,68 @@ pub struct IndexingConfig {
     pub throttle_ms: u64,
     #[serde(default)]
     pub include_dependencies: bool,
+    /// deprecated alias of `extension_map`, folded into it on load
+    #[serde(default, skip_serializing)]
+    pub extension_language: BTreeMap<String, String>,
+    /// threads used to chunk files while indexing. 0 uses every core.
+    #[serde(default)]
+    pub threads: usize,
+    /// file suffixes mapped to language names, e.g. `h = "cpp"` or
+    /// `"rs.in" = "rust"`. checked before every built-in mapping; the longest
+    /// matching suffix wins.
+    #[serde(default)]
+    pub extension_map: BTreeMap<String, String>,
+    /// pull the doc comments above a definition into its chunk
+    #[serde(default = "default_true")]
+    pub include_doc_comments: bool,
+    /// chunks shorter than this many characters are dropped. named top-level
+    /// definitions are always kept.
+    #[serde(default = "default_min_chunk_size")]
+    pub min_chunk_size: usize,
+    /// lines per chunk for files with no structure to split on, such as
+    /// prose or logs
+    #[serde(default = "default_fallback_chunk_lines")]
+    pub fallback_chunk_lines: usize,
+    /// lines each of those chunks repeats from the end of the one before
+    #[serde(default = "default_fallback_overlap_lines")]
+    pub fallback_overlap_lines: usize,
+    /// embed chunks with their comments taken out, so matches follow what the
+    /// code does rather than what it says. stored chunks keep their comments.
+    #[serde(default)]
+    pub strip_comments_for_embedding: bool,
+    /// embedding requests kept in flight while earlier batches are written
+    #[serde(default = "default_embed_concurrency")]
+    pub embed_concurrency: usize,
+    /// while indexing, flush pending embeddings once the oldest has waited
+    /// this long, even below batch_size. 0 only flushes full batches. the
+    /// watcher ignores it: it embeds each debounced batch of changes at once.
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    /// keep each file's original bytes in the database so `get_file` can
+    /// return it exactly once it is gone from disk. roughly adds the size of
+    /// the indexed sources.
+    #[serde(default)]
+    pub store_raw_content: bool,
+    /// embed byte-identical chunks once per project. later copies share the
+    /// first one's embedding and are left out of similarity search.
+    #[serde(default)]
+    pub dedupe_chunks: bool,
+    /// points the vector index is allocated for when it is first built
+    #[serde(default = "default_hnsw_capacity")]
+    pub hnsw_capacity: usize,
+    /// the vector index is rebuilt this many times larger once it fills up
+    #[serde(default = "default_hnsw_growth_factor")]
+    pub hnsw_growth_factor: f32,
+    /// vectors kept for reuse when identical text is embedded again, 0 to
+    /// turn the cache off. about 1.5 KiB each.
+    #[serde(default = "default_embedding_cache_entries")]
+    pub embedding_cache_entries: usize,
+    /// stop discovering files after this many, 0 for no limit
+    #[serde(default)]
+    pub max_files: usize,
+    /// stop discovering files once their sizes add up to this, 0 for no limit
+    #[serde(default)]
+    pub max_total_bytes: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,22 +195,94 @@ pub struct QueryConfig {
     pub broad_k: usize,
     #[serde(default = "default_hybrid_search")]
     pub hybrid_search: bool,
+    #[serde(default = "default_rrf_k")]
+    pub rrf_k: usize,
+    #[serde(default = "default_rrf_weight")]
+    pub rrf_vector_weight: f32,
+    #[serde(default = "default_rrf_weight")]
+    pub rrf_text_weight: f32,
+    /// maximal marginal relevance trade-off applied after reranking.
+    /// 0 favours diverse chunks, 1 (the default) keeps pure relevance order.
+    #[serde(default = "default_mmr_lambda")]
+    pub mmr_lambda: f32,
+    /// incrementally sync a project before querying it if files changed since
+    /// it was last indexed. when off, a warning is printed instead.
+    #[serde(default)]
+    pub auto_sync_if_stale: bool,
+    /// ask the model to cite the file:line labels of the context and to say
+    /// when the answer is not in the provided code.
+    #[serde(default)]
+    pub grounding_instructions: bool,
+    /// replaces the built-in instructions with a template using `{context}`,
+    /// `{question}` and `{history}` placeholders.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_file: Option<PathBuf>,
+    /// applied when most of the retrieved chunks are injection-flagged
+    #[serde(default)]
+    pub on_injection: InjectionPolicy,
+    /// drop retrieved chunks whose lines another retrieved chunk already covers
+    #[serde(default = "default_true")]
+    pub dedupe_sources: bool,
+    /// at most this many chunks reach the context, after rerank and dedupe
+    #[serde(default = "default_max_context_chunks")]
+    pub max_context_chunks: usize,
+    /// size context by this many characters per token instead of the
+    /// model's tokenizer. 0 uses the tokenizer.
+    #[serde(default)]
+    pub chars_per_token: f32,
+    /// widen the retrieved chunks with their surroundings before they are
+    /// packed into `context_tokens`
+    #[serde(default)]
+    pub context_mode: ContextMode,
+    /// answer without context when even the closest vector match is less
+    /// similar than this (1 - distance), instead of from unrelated code.
+    /// 0 turns the cutoff off.
+    #[serde(default)]
+    pub min_similarity: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WatcherConfig {
     #[serde(default = "default_debounce_ms")]
     pub debounce_ms: u64,
+    /// queued files reindexed per transaction and round of embedding calls
+    #[serde(default = "default_watcher_batch_files")]
+    pub batch_files: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceConfig {
     #[serde(default = "default_nice_level")]
     pub nice_level: i32,
+    /// long-running processes stop the ml service after this many seconds
+    /// without a request. 0 keeps it running.
     #[serde(default = "default_llm_idle_timeout_secs")]
     pub llm_idle_timeout_secs: u64,
+    /// resident memory indexing tries to stay under. 0 disables the check.
     #[serde(default = "default_memory_budget_mb")]
     pub memory_budget_mb: u64,
+    /// how long to wait for the ml service to come up. the wait is extended
+    /// while it reports model download progress.
+    #[serde(default = "default_ml_startup_timeout_secs")]
+    pub ml_startup_timeout_secs: u64,
+    /// how srag talks to the ml service. unix sockets are only reachable by
+    /// the owning user; windows always uses tcp.
+    #[serde(default)]
+    pub ipc_transport: IpcTransport,
+    /// attempts made when connecting (or reconnecting) to the ml service
+    #[serde(default = "default_ml_connect_attempts")]
+    pub ml_connect_attempts: u32,
+    /// delay before the first retry; doubles on each further attempt
+    #[serde(default = "default_ml_connect_backoff_ms")]
+    pub ml_connect_backoff_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IpcTransport {
+    #[default]
+    Tcp,
+    Unix,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +291,10 @@ pub struct LlmConfig {
     pub model_filename: String,
     #[serde(default = "default_model_url")]
     pub model_url: String,
+    /// expected sha256 of the model file. when set, a model that doesn't
+    /// match is never loaded, whichever mirror it came from.
+    #[serde(default)]
+    pub model_sha256: Option<String>,
     #[serde(default = "default_llm_threads")]
     pub threads: usize,
     #[serde(default = "default_llm_context_size")]
@@ -94,12 +324,49 @@ pub struct ApiConfig {
     pub log_redactions: bool,
 }
 
+fn default_search_cache_entries() -> usize {
+    64
+}
+
+fn default_search_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_max_context_chunks() -> usize {
+    64
+}
+
 fn default_max_file_size() -> u64 {
     1_048_576
 }
 fn default_batch_size() -> usize {
     32
 }
+fn default_embed_concurrency() -> usize {
+    2
+}
+fn default_min_chunk_size() -> usize {
+    crate::chunking::DEFAULT_MIN_CHUNK_SIZE
+}
+fn default_fallback_chunk_lines() -> usize {
+    crate::chunking::MAX_CHUNK_LINES
+}
+fn default_fallback_overlap_lines() -> usize {
+    crate::chunking::OVERLAP_LINES
+}
+fn default_flush_interval_ms() -> u64 {
+    2000
+}
+fn default_hnsw_capacity() -> usize {
+    100_000
+}
+fn default_embedding_cache_entries() -> usize {
+    50_000
+}
+
+fn default_hnsw_growth_factor() -> f32 {
+    2.0
+}
 fn default_throttle_ms() -> u64 {
     10
 }
@@ -130,15 +397,36 @@ fn default_broad_k() -> usize {
 fn default_hybrid_search() -> bool {
     true
 }
+fn default_rrf_k() -> usize {
+    60
+}
+fn default_rrf_weight() -> f32 {
+    1.0
+}
+fn default_mmr_lambda() -> f32 {
+    1.0
+}
 fn default_debounce_ms() -> u64 {
     500
 }
+fn default_watcher_batch_files() -> usize {
+    32
+}
 fn default_nice_level() -> i32 {
     10
 }
 fn default_llm_idle_timeout_secs() -> u64 {
     300
 }
+fn default_ml_startup_timeout_secs() -> u64 {
+    30
+}
+fn default_ml_connect_attempts() -> u32 {
+    5
+}
+fn default_ml_connect_backoff_ms() -> u64 {
+    100
+}
 fn default_memory_budget_mb() -> u64 {
     2048
 }
@@ -171,6 +459,37 @@ impl Default for IndexingConfig {
             batch_size: default_batch_size(),
             throttle_ms: default_throttle_ms(),
             include_dependencies: false,
+            extension_language: BTreeMap::new(),
+            threads: 0,
+            extension_map: BTreeMap::new(),
+            include_doc_comments: true,
+            min_chunk_size: default_min_chunk_size(),
+            fallback_chunk_lines: default_fallback_chunk_lines(),
+            fallback_overlap_lines: default_fallback_overlap_lines(),
+            strip_comments_for_embedding: false,
+            embed_concurrency: default_embed_concurrency(),
+            flush_interval_ms: default_flush_interval_ms(),
+            store_raw_content: false,
+            dedupe_chunks: false,
+            hnsw_capacity: default_hnsw_capacity(),
+            hnsw_growth_factor: default_hnsw_growth_factor(),
+            embedding_cache_entries: default_embedding_cache_entries(),
+            max_files: 0,
+            max_total_bytes: 0,
+        }
+    }
+}
+
+impl IndexingConfig {
+    /// move entries from the deprecated `extension_language` key into
+    /// `extension_map`. an extension set in both keeps its `extension_map` value.
+    pub(crate) fn fold_extension_language(&mut self) {
+        if self.extension_language.is_empty() {
+            return;
+        }
+        tracing::warn!("indexing.extension_language is deprecated; use indexing.extension_map");
+        for (ext, language) in std::mem::take(&mut self.extension_language) {
+            self.extension_map.entry(ext).or_insert(language);
         }
     }
 }
@@ -187,6 +506,19 @@ impl Default for QueryConfig {
             rerank: default_rerank(),
             broad_k: default_broad_k(),
             hybrid_search: default_hybrid_search(),
+            rrf_k: default_rrf_k(),
+            rrf_vector_weight: default_rrf_weight(),
+            rrf_text_weight: default_rrf_weight(),
+            mmr_lambda: default_mmr_lambda(),
+            auto_sync_if_stale: false,
+            grounding_instructions: false,
+            system_prompt_file: None,
+            on_injection: InjectionPolicy::Warn,
+            dedupe_sources: true,
+            max_context_chunks: default_max_context_chunks(),
+            chars_per_token: 0.0,
+            context_mode: ContextMode::Chunk,
+            min_similarity: 0.0,
         }
     }
 }
@@ -195,6 +527,7 @@ impl Default for WatcherConfig {
     fn default() -> Self {
         Self {
             debounce_ms: default_debounce_ms(),
+            batch_files: default_watcher_batch_files(),
         }
     }
 }
@@ -205,6 +538,10 @@ impl Default for ResourceConfig {
             nice_level: default_nice_level(),
             llm_idle_timeout_secs: default_llm_idle_timeout_secs(),
             memory_budget_mb: default_memory_budget_mb(),
+            ml_startup_timeout_secs: default_ml_startup_timeout_secs(),
+            ipc_transport: IpcTransport::default(),
+            ml_connect_attempts: default_ml_connect_attempts(),
+            ml_connect_backoff_ms: default_ml_connect_backoff_ms(),
         }
     }
 }
@@ -214,6 +551,7 @@ impl Default for LlmConfig {
         Self {
             model_filename: default_model_filename(),
             model_url: default_model_url(),
+            model_sha256: None,
             threads: default_llm_threads(),
             context_size: default_llm_context_size(),
         }
@@ -225,6 +563,15 @@ const ALLOWED_MODEL_DOMAINS: &[&str] = &["huggingface.co", "github.com"];
 impl LlmConfig {
     /// validate the model_url to ensure it uses https and points to allowed domains.
     pub fn validate(&self) -> Result<(), String> {
+        if let Some(sha) = &self.model_sha256 {
+            if sha.len() != 64 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "llm.model_sha256 must be 64 hex characters, got: {}",
+                    sha
+                ));
+            }
+        }
+
         if self.model_url.is_empty() {
             return Ok(());
         }
@@ -270,6 +617,8 @@ impl Default for McpConfig {
     fn default() -> Self {
         Self {
             auto_index_cwd: true,
+            search_cache_entries: default_search_cache_entries(),
+            search_cache_ttl_secs: default_search_cache_ttl_secs(),
         }
     }
 }
@@ -278,6 +627,49 @@ impl Default for McpConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extension_language_folds_into_extension_map() {
+        let mut config: IndexingConfig = toml::from_str(
+            r#"
+            [extension_language]
+            h = "cpp"
+            m = "c"
+
+            [extension_map]
+            m = "objc"
+            "#,
+        )
+        .unwrap();
+        config.fold_extension_language();
+        assert!(config.extension_language.is_empty());
+        assert_eq!(
+            config.extension_map.get("h").map(String::as_str),
+            Some("cpp")
+        );
+        // the new key wins over the deprecated one
+        assert_eq!(
+            config.extension_map.get("m").map(String::as_str),
+            Some("objc")
+        );
+    }
+
+    #[test]
+    fn test_extension_map_deserialize() {
+        let config: IndexingConfig = toml::from_str(
+            r#"
+            [extension_map]
+            "rs.in" = "rust"
+            tpl = "html"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.extension_map.get("rs.in").map(String::as_str),
+            Some("rust")
+        );
+        assert_eq!(config.extension_map.len(), 2);
+    }
+
     #[test]
     fn test_api_provider_serde() {
         assert_eq!(
@@ -318,33 +710,52 @@ mod tests {
 
     #[test]
     fn test_llm_config_validate_empty_url() {
-        let mut config = LlmConfig::default();
-        config.model_url = String::new();
+        let config = LlmConfig {
+            model_url: String::new(),
+            ..Default::default()
+        };
         assert!(config.validate().is_ok());
     }
 
     #[test]
     fn test_llm_config_validate_http_rejected() {
-        let mut config = LlmConfig::default();
-        config.model_url = "http://huggingface.co/model.gguf".into();
+        let config = LlmConfig {
+            model_url: "http://huggingface.co/model.gguf".into(),
+            ..Default::default()
+        };
         assert!(config.validate().is_err());
     }
 
     #[test]
     fn test_llm_config_validate_invalid_domain() {
-        let mut config = LlmConfig::default();
-        config.model_url = "https://evil.com/malware.gguf".into();
+        let config = LlmConfig {
+            model_url: "https://evil.com/malware.gguf".into(),
+            ..Default::default()
+        };
         assert!(config.validate().is_err());
     }
 
     #[test]
     fn test_llm_config_validate_allowed_domains() {
-        let mut config = LlmConfig::default();
-
-        config.model_url = "https://huggingface.co/model.gguf".into();
+        let mut config = LlmConfig {
+            model_url: "https://huggingface.co/model.gguf".into(),
+            ..Default::default()
+        };
         assert!(config.validate().is_ok());
 
         config.model_url = "https://github.com/repo/model.gguf".into();
         assert!(config.validate().is_ok());
     }
+
+    #[test]
+    fn test_llm_config_validate_model_sha256() {
+        let mut config = LlmConfig {
+            model_sha256: Some("ab".repeat(32)),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.model_sha256 = Some("not-a-hash".into());
+        assert!(config.validate().is_err());
+    }
 }
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::path::Path;
+use std::process::Command;
+
+use srag_common::{Error, Result};
+
+use crate::index::store::CommitRecord;
+
+/// commits whose diff is larger than this are recorded without hunks;
+/// they're usually vendored code, generated files or mass reformatting
+pub const MAX_COMMIT_DIFF_BYTES: usize = 512 * 1024;
+
+/// one `@@` hunk of a commit's diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    /// path of the changed file, relative to the repository root
+    pub file: String,
+    /// line range in the new version of the file (1-indexed)
+    pub start_line: u32,
+    pub end_line: u32,
+    /// the hunk header and its lines
+    pub text: String,
+}
+
+/// path of the synthetic file row holding a commit's hunks
+pub fn commit_path(hash: &str) -> String {
+    format!("git:{}", hash)
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| Error::Discovery(format!("failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(Error::Discovery(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// the newest `limit` non-merge commits reachable from HEAD
+pub fn recent_commits(repo: &Path, limit: usize) -> Result<Vec<CommitRecord>> {
+    let limit = format!("-n{}", limit);
+    let log = git(
+        repo,
+        &[
+            "log",
+            &limit,
+            "--no-merges",
+            "--format=%H%x1f%an%x1f%ct%x1f%s",
+        ],
+    )?;
+    Ok(log
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\x1f');
+            Some(CommitRecord {
+                hash: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                committed_at: fields.next()?.parse().ok()?,
+                summary: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// the unified diff a commit introduced, against its first parent
+pub fn commit_diff(repo: &Path, hash: &str) -> Result<String> {
+    git(
+        repo,
+        &[
+            "show",
+            "--format=",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-renames",
+            "--unified=3",
+            hash,
+        ],
+    )
+}
+
+/// split a unified diff into hunks. binary files have no hunks, so they
+/// drop out here.
+pub fn parse_hunks(diff: &str) -> Vec<DiffHunk> {
+    let mut hunks = Vec::new();
+    let mut file: Option<String> = None;
+    let mut current: Option<DiffHunk> = None;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            hunks.extend(current.take());
+            file = None;
+            continue;
+        }
+        if current.is_none() || file.is_none() {
+            // deleted files only name their old path
+            if let Some(path) = line.strip_prefix("+++ b/") {
+                file = Some(path.to_string());
+                continue;
+            }
+            if let Some(path) = line.strip_prefix("--- a/") {
+                file.get_or_insert_with(|| path.to_string());
+                continue;
+            }
+        }
+        if line.starts_with("@@") {
+            hunks.extend(current.take());
+            let Some(path) = file.clone() else {
+                continue;
+            };
+            let (start_line, end_line) = hunk_range(line);
+            current = Some(DiffHunk {
+                file: path,
+                start_line,
+                end_line,
+                text: line.to_string(),
+            });
+            continue;
+        }
+        if let Some(hunk) = current.as_mut() {
+            hunk.text.push('\n');
+            hunk.text.push_str(line);
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// new-file line range from a `@@ -a,b +c,d @@` header
+fn hunk_range(header: &str) -> (u32, u32) {
+    let new_range = header
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))
+        .unwrap_or("1");
+    let mut parts = new_range.splitn(2, ',');
+    let start: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+    let count: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+    let start = start.max(1);
+    (start, start + count.saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ fn a() {}
++fn b() {}
+ fn c() {}
+ fn d() {}
+@@ -10,2 +11,2 @@ fn e() {
+-    old();
++    new();
+diff --git a/logo.png b/logo.png
+index 3333333..4444444 100644
+Binary files a/logo.png and b/logo.png differ
+diff --git a/gone.rs b/gone.rs
+deleted file mode 100644
+index 5555555..0000000
+--- a/gone.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn gone() {}
+-fn also_gone() {}
+";
+
+    #[test]
+    fn test_parse_hunks() {
+        let hunks = parse_hunks(DIFF);
+        assert_eq!(hunks.len(), 3);
+
+        assert_eq!(hunks[0].file, "src/lib.rs");
+        assert_eq!((hunks[0].start_line, hunks[0].end_line), (1, 4));
+        assert!(hunks[0].text.starts_with("@@ -1,3 +1,4 @@"));
+        assert!(hunks[0].text.contains("+fn b() {}"));
+        assert!(!hunks[0].text.contains("@@ -10"));
+
+        assert_eq!((hunks[1].start_line, hunks[1].end_line), (11, 12));
+        assert!(hunks[1].text.ends_with("+    new();"));
+
+        // the binary file contributes nothing; the deletion keeps its old path
+        assert_eq!(hunks[2].file, "gone.rs");
+        assert_eq!((hunks[2].start_line, hunks[2].end_line), (1, 1));
+    }
+
+    #[test]
+    fn test_hunk_range() {
+        assert_eq!(hunk_range("@@ -1 +1 @@"), (1, 1));
+        assert_eq!(hunk_range("@@ -5,0 +6,3 @@ fn x()"), (6, 8));
+    }
+}
@@ -1,18 +1,295 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+pub mod git_history;
+
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
 
-use ignore::WalkBuilder;
+use ignore::{WalkBuilder, WalkState};
+use srag_common::types::Language;
 use srag_common::Result;
 
-use crate::config::{Config, DEPENDENCY_DIRS};
+use crate::config::{Config, DEPENDENCY_DIRS, LOCKFILE_NAMES};
 
 pub fn walk_directory(root: &Path, config: &Config) -> Result<Vec<PathBuf>> {
     walk_directory_opts(root, config, false)
 }
 
 pub fn walk_directory_opts(root: &Path, config: &Config, all: bool) -> Result<Vec<PathBuf>> {
+    walk_directory_report(root, config, all, false).map(|(files, _)| files)
+}
+
+/// files left out of a walk, by reason
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WalkSkips {
+    pub too_large: u64,
+    pub binary: u64,
+    /// lockfiles, plus ignore-rule matches when they were counted
+    pub ignored: u64,
+    /// the cap that cut the walk short, if any
+    pub capped: Option<WalkCap>,
+}
+
+/// an `indexing.max_*` limit reached during discovery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkCap {
+    Files(usize),
+    TotalBytes(u64),
+}
+
+impl WalkCap {
+    /// warning for the user, saying how to get past the cap
+    pub fn warning(&self, root: &Path) -> String {
+        let (what, key, value) = match self {
+            WalkCap::Files(n) => (format!("{} files", n), "max_files", n.to_string()),
+            WalkCap::TotalBytes(n) => (format!("{} bytes", n), "max_total_bytes", n.to_string()),
+        };
+        format!(
+            "warning: stopped discovering files in {} after {} (indexing.{} = {}). \
+             raise it with 'srag config set indexing.{} <n>', set it to 0 for no limit, \
+             or index a narrower path",
+            root.display(),
+            what,
+            key,
+            value,
+            key
+        )
+    }
+}
+
+/// walk like `walk_directory_opts`, also counting what was left out. files
+/// matched by ignore rules never reach the walker, so `count_ignored` makes a
+/// second unfiltered pass (skipping .git) to find them.
+///
+/// files come in path order either way, so when `indexing.max_files` or
+/// `indexing.max_total_bytes` cut the result short the same files are kept
+/// each time. with a cap the tree is walked in order and the walk stops at
+/// the cap; without one it is walked in parallel and sorted afterwards.
+pub fn walk_directory_report(
+    root: &Path,
+    config: &Config,
+    all: bool,
+    count_ignored: bool,
+) -> Result<(Vec<PathBuf>, WalkSkips)> {
+    let mut builder = walk_builder(root, config, all)?;
+    let mut kept = Kept::new(config, count_ignored);
+
+    if config.indexing.max_files > 0 || config.indexing.max_total_bytes > 0 {
+        builder.sort_by_file_name(|a, b| a.cmp(b));
+        let mut discovered = 0usize;
+        for entry in builder.build() {
+            let entry = entry.map_err(|e| srag_common::Error::Discovery(e.to_string()))?;
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let found = classify(path, config, all);
+            if !kept.add(path.to_path_buf(), found) {
+                break;
+            }
+            discovered += 1;
+            if discovered % DISCOVERY_PROGRESS_EVERY == 0 {
+                eprint!("\rdiscovered {} files", discovered);
+            }
+        }
+        if discovered >= DISCOVERY_PROGRESS_EVERY {
+            eprintln!();
+        }
+    } else {
+        let mut found = discover_files(&builder, config, all)?;
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        for (path, found) in found {
+            kept.add(path, found);
+        }
+    }
+
+    let Kept {
+        files,
+        mut skips,
+        seen,
+        ..
+    } = kept;
+    // everything past the cap went unwalked, not ignored
+    if count_ignored && skips.capped.is_none() {
+        skips.ignored += count_unwalked_files(root, &seen);
+    }
+
+    Ok((files, skips))
+}
+
+/// files a walk keeps, in the order they're offered, up to the caps
+struct Kept {
+    files: Vec<PathBuf>,
+    skips: WalkSkips,
+    /// every path offered, when ignored files are being counted
+    seen: HashSet<PathBuf>,
+    count_seen: bool,
+    total_bytes: u64,
+    max_files: usize,
+    max_total_bytes: u64,
+}
+
+impl Kept {
+    fn new(config: &Config, count_seen: bool) -> Self {
+        Self {
+            files: Vec::new(),
+            skips: WalkSkips::default(),
+            seen: HashSet::new(),
+            count_seen,
+            total_bytes: 0,
+            max_files: config.indexing.max_files,
+            max_total_bytes: config.indexing.max_total_bytes,
+        }
+    }
+
+    /// keep or count `path`. false once a cap is reached, so the walk can stop.
+    fn add(&mut self, path: PathBuf, found: Found) -> bool {
+        if self.skips.capped.is_some() {
+            return false;
+        }
+        if self.count_seen {
+            self.seen.insert(path.clone());
+        }
+        let size = match found {
+            Found::TooLarge => {
+                self.skips.too_large += 1;
+                return true;
+            }
+            Found::Lockfile => {
+                self.skips.ignored += 1;
+                return true;
+            }
+            Found::Binary => {
+                self.skips.binary += 1;
+                return true;
+            }
+            Found::File(size) => size,
+        };
+
+        if self.max_files > 0 && self.files.len() >= self.max_files {
+            self.skips.capped = Some(WalkCap::Files(self.max_files));
+            return false;
+        }
+        if self.max_total_bytes > 0 && self.total_bytes + size > self.max_total_bytes {
+            self.skips.capped = Some(WalkCap::TotalBytes(self.max_total_bytes));
+            return false;
+        }
+        self.total_bytes += size;
+        self.files.push(path);
+        true
+    }
+}
+
+/// what the walk made of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Found {
+    File(u64),
+    TooLarge,
+    Lockfile,
+    Binary,
+}
+
+/// files passed between progress lines while discovering
+const DISCOVERY_PROGRESS_EVERY: usize = 5_000;
+
+/// every file the walker yields with what it is, in no particular order.
+/// huge trees get a "discovered N files" line on stderr as they go.
+fn discover_files(
+    builder: &WalkBuilder,
+    config: &Config,
+    all: bool,
+) -> Result<Vec<(PathBuf, Found)>> {
+    let found = Mutex::new(Vec::new());
+    let error = Mutex::new(None);
+    let discovered = AtomicUsize::new(0);
+
+    builder.build_parallel().run(|| {
+        Box::new(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    error.lock().unwrap().get_or_insert(e.to_string());
+                    return WalkState::Quit;
+                }
+            };
+            let path = entry.path();
+            if !path.is_file() {
+                return WalkState::Continue;
+            }
+
+            let kind = classify(path, config, all);
+            found.lock().unwrap().push((path.to_path_buf(), kind));
+
+            let n = discovered.fetch_add(1, Ordering::Relaxed) + 1;
+            if n % DISCOVERY_PROGRESS_EVERY == 0 {
+                eprint!("\rdiscovered {} files", n);
+            }
+            WalkState::Continue
+        })
+    });
+
+    if discovered.load(Ordering::Relaxed) >= DISCOVERY_PROGRESS_EVERY {
+        eprintln!();
+    }
+    if let Some(e) = error.into_inner().unwrap() {
+        return Err(srag_common::Error::Discovery(e));
+    }
+    Ok(found.into_inner().unwrap())
+}
+
+/// stat and sniff one walked file
+fn classify(path: &Path, config: &Config, all: bool) -> Found {
+    let size = path.metadata().map(|m| m.len()).unwrap_or(0);
+    if size > config.indexing.max_file_size_bytes {
+        Found::TooLarge
+    } else if !all && is_lockfile(path) {
+        Found::Lockfile
+    } else if is_likely_binary(path) {
+        Found::Binary
+    } else {
+        Found::File(size)
+    }
+}
+
+fn count_unwalked_files(root: &Path, seen: &HashSet<PathBuf>) -> u64 {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .follow_links(false)
+        .standard_filters(false)
+        .filter_entry(|e| e.file_name() != ".git");
+    builder
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()) && !seen.contains(e.path()))
+        .count() as u64
+}
+
+/// first path under `root` (file or directory, so deletions count) modified
+/// after `since`. gives up after `max_entries` so huge trees stay cheap.
+pub fn find_modified_since(
+    root: &Path,
+    config: &Config,
+    since: SystemTime,
+    max_entries: usize,
+) -> Result<Option<PathBuf>> {
+    let builder = walk_builder(root, config, false)?;
+    for entry in builder.build().take(max_entries) {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+        if modified.is_some_and(|m| m > since) {
+            return Ok(Some(entry.into_path()));
+        }
+    }
+    Ok(None)
+}
+
+fn walk_builder(root: &Path, config: &Config, all: bool) -> Result<WalkBuilder> {
     let mut builder = WalkBuilder::new(root);
     builder.follow_links(false);
 
@@ -60,30 +337,76 @@ pub fn walk_directory_opts(root: &Path, config: &Config, all: bool) -> Result<Ve
         builder.add_ignore(&sragignore);
     }
 
-    let mut files = Vec::new();
+    Ok(builder)
+}
 
-    for entry in builder.build() {
-        let entry = entry.map_err(|e| srag_common::Error::Discovery(e.to_string()))?;
-        let path = entry.path();
+pub fn is_lockfile(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|name| LOCKFILE_NAMES.contains(&name))
+}
 
-        if !path.is_file() {
-            continue;
-        }
+/// pick a language for a file: configured suffix overrides first, then the built-in extension and filename mappings, then content
+/// heuristics for ambiguous extensions.
+pub fn detect_language(path: &Path, content: &[u8], config: &Config) -> Language {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if let Some(lang) = custom_extension(file_name, &config.indexing.extension_map) {
+        return lang;
+    }
 
-        if let Ok(metadata) = path.metadata() {
-            if metadata.len() > config.indexing.max_file_size_bytes {
-                continue;
-            }
-        }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let language = Language::from_extension(ext);
+    if language == Language::Unknown {
+        return Language::from_filename(file_name)
+            .or_else(|| shebang_language(content))
+            .unwrap_or(language);
+    }
 
-        if is_likely_binary(path) {
-            continue;
-        }
+    if ext == "h" && looks_like_cpp(content) {
+        return Language::Cpp;
+    }
 
-        files.push(path.to_path_buf());
+    language
+}
+
+/// language named by a leading `#!` line, if any
+fn shebang_language(content: &[u8]) -> Option<Language> {
+    if !content.starts_with(b"#!") {
+        return None;
     }
+    let end = content
+        .iter()
+        .position(|&b| b == b'\n')
+        .unwrap_or(content.len());
+    let first_line = std::str::from_utf8(&content[..end]).ok()?;
+    Language::from_shebang(first_line.trim_end())
+}
+
+/// longest configured suffix the file name ends with. suffixes may span
+/// several dots (`rs.in`), so this matches on the whole name, not extension().
+fn custom_extension(file_name: &str, map: &BTreeMap<String, String>) -> Option<Language> {
+    let lower = file_name.to_lowercase();
+    map.iter()
+        .filter(|(suffix, _)| {
+            let suffix = suffix.trim_start_matches('.').to_lowercase();
+            lower
+                .strip_suffix(&suffix)
+                .is_some_and(|stem| stem.ends_with('.'))
+        })
+        .max_by_key(|(suffix, _)| suffix.len())
+        .and_then(|(_, name)| Language::from_name(name))
+}
 
-    Ok(files)
+/// `.h` is shared by c and c++; treat headers declaring classes, namespaces or
+/// templates as c++.
+fn looks_like_cpp(content: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(content);
+    text.lines().map(str::trim_start).any(|line| {
+        line.starts_with("class ")
+            || line.starts_with("namespace ")
+            || line.starts_with("template<")
+            || line.starts_with("template <")
+    })
 }
 
 fn is_likely_binary(path: &Path) -> bool {
@@ -111,6 +434,266 @@ mod tests {
         Config::default()
     }
 
+    #[test]
+    fn test_parallel_walk_matches_sequential() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        for i in 0..40 {
+            let sub = root.join(format!("pkg{}/src/nested{}", i % 7, i % 3));
+            std::fs::create_dir_all(&sub).unwrap();
+            std::fs::write(sub.join(format!("mod{}.rs", i)), "fn x() {}\n").unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "pkg3/\n").unwrap();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::write(root.join("snapshot"), [0u8; 64]).unwrap();
+        std::fs::write(root.join("package-lock.json"), "{}\n").unwrap();
+        let config = test_config();
+
+        let (files, skips) = walk_directory_report(root, &config, false, false).unwrap();
+
+        let mut sequential: Vec<PathBuf> = walk_builder(root, &config, false)
+            .unwrap()
+            .build()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| p.is_file() && !is_lockfile(p) && !is_likely_binary(p))
+            .collect();
+        sequential.sort();
+        assert_eq!(files, sequential);
+        assert!(files.iter().all(|p| !p.starts_with(root.join("pkg3"))));
+        assert_eq!(skips.binary, 1);
+        assert_eq!(skips.ignored, 1);
+
+        // same answer, same order, every time
+        let (again, _) = walk_directory_report(root, &config, false, false).unwrap();
+        assert_eq!(files, again);
+    }
+
+    #[test]
+    fn test_detect_language_h_defaults_to_c() {
+        let content = b"#include <stdio.h>\nint add(int a, int b);\n";
+        let lang = detect_language(Path::new("src/math.h"), content, &test_config());
+        assert_eq!(lang, Language::C);
+    }
+
+    #[test]
+    fn test_detect_language_h_with_cpp_constructs() {
+        let content = b"#pragma once\nnamespace geo {\nclass Point {\npublic:\n  int x;\n};\n}\n";
+        let lang = detect_language(Path::new("src/point.h"), content, &test_config());
+        assert_eq!(lang, Language::Cpp);
+
+        let content = b"template <typename T>\nT max(T a, T b);\n";
+        let lang = detect_language(Path::new("src/max.h"), content, &test_config());
+        assert_eq!(lang, Language::Cpp);
+    }
+
+    #[test]
+    fn test_detect_language_config_override() {
+        let mut config = test_config();
+        let map = &mut config.indexing.extension_map;
+        map.insert("h".to_string(), "c".to_string());
+        map.insert("pl".to_string(), "shell".to_string());
+
+        let content = b"class Point {};\n";
+        assert_eq!(
+            detect_language(Path::new("point.h"), content, &config),
+            Language::C
+        );
+        assert_eq!(
+            detect_language(Path::new("build.pl"), b"", &config),
+            Language::Shell
+        );
+    }
+
+    #[test]
+    fn test_detect_language_extension_map() {
+        let mut config = test_config();
+        let map = &mut config.indexing.extension_map;
+        map.insert("rs.in".to_string(), "rust".to_string());
+        map.insert("in".to_string(), "make".to_string());
+        map.insert(".tsx.snap".to_string(), "TypeScript".to_string());
+        map.insert("s.in".to_string(), "shell".to_string());
+
+        let content =
+            b"pub fn generated() -> u32 {\n    let answer = 40 + 2;\n    println!(\"{}\", answer);\n    answer\n}\n";
+        let lang = detect_language(Path::new("src/build.rs.in"), content, &config);
+        assert_eq!(lang, Language::Rust);
+        // "s.in" must not match "rules.in": suffixes start at a dot
+        assert_eq!(
+            detect_language(Path::new("rules.in"), b"", &config),
+            Language::Make
+        );
+        assert_eq!(
+            detect_language(Path::new("Button.TSX.SNAP"), b"", &config),
+            Language::TypeScript
+        );
+
+        let chunks = crate::chunking::chunk_file(content, lang).unwrap();
+        assert!(chunks
+            .iter()
+            .any(|c| c.symbol.as_deref() == Some("generated")));
+    }
+
+    #[test]
+    fn test_detect_language_from_shebang() {
+        let config = test_config();
+        let script = b"#!/usr/bin/env python3\nprint('hi')\n";
+        assert_eq!(
+            detect_language(Path::new("bin/deploy"), script, &config),
+            Language::Python
+        );
+        // the extension still wins when there is one
+        assert_eq!(
+            detect_language(Path::new("tool.rb"), script, &config),
+            Language::Ruby
+        );
+        assert_eq!(
+            detect_language(Path::new("bin/notes"), b"just text\n", &config),
+            Language::Unknown
+        );
+    }
+
+    #[test]
+    fn test_detect_language_env_filename() {
+        let lang = detect_language(Path::new(".env.local"), b"KEY=1", &test_config());
+        assert_eq!(lang, Language::Env);
+    }
+
+    #[test]
+    fn test_lockfiles_skipped_by_default() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package-lock.json"),
+            "{\"lockfileVersion\": 3}",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("Cargo.lock"), "version = 3").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+
+        let mut config = test_config();
+        config.ignore_patterns.clear();
+        let files = walk_directory(dir.path(), &config).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with("main.rs"));
+    }
+
+    #[test]
+    fn test_walk_directory_report_counts_skips() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("big.rs"), "x".repeat(4096)).unwrap();
+        std::fs::write(dir.path().join("blob.dat"), [0u8; 64]).unwrap();
+        std::fs::write(dir.path().join("generated.rs"), "fn g() {}").unwrap();
+        std::fs::write(dir.path().join("Cargo.lock"), "version = 3").unwrap();
+
+        let mut config = test_config();
+        config.indexing.max_file_size_bytes = 1024;
+        config.ignore_patterns = vec!["generated.rs".into()];
+
+        let (files, skips) = walk_directory_report(dir.path(), &config, false, true).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with("main.rs"));
+        assert_eq!(
+            skips,
+            WalkSkips {
+                too_large: 1,
+                binary: 1,
+                ignored: 2,
+                capped: None,
+            }
+        );
+
+        // without the extra pass only lockfiles are known to be ignored
+        let (_, skips) = walk_directory_report(dir.path(), &config, false, false).unwrap();
+        assert_eq!(skips.ignored, 1);
+    }
+
+    #[test]
+    fn test_walk_caps_keep_sorted_prefix() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("b")).unwrap();
+        for name in ["d.rs", "a.rs", "b/c.rs", "b/a.rs", "c.rs"] {
+            std::fs::write(dir.path().join(name), "fn x() {}\n").unwrap();
+        }
+        let relative = |files: &[PathBuf]| -> Vec<String> {
+            files
+                .iter()
+                .map(|f| {
+                    f.strip_prefix(dir.path())
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect()
+        };
+
+        // zero means no limit
+        let mut config = test_config();
+        let (files, skips) = walk_directory_report(dir.path(), &config, false, true).unwrap();
+        assert_eq!(
+            relative(&files),
+            ["a.rs", "b/a.rs", "b/c.rs", "c.rs", "d.rs"]
+        );
+        assert_eq!(skips.capped, None);
+
+        config.indexing.max_files = 3;
+        for _ in 0..3 {
+            let (files, skips) = walk_directory_report(dir.path(), &config, false, true).unwrap();
+            assert_eq!(relative(&files), ["a.rs", "b/a.rs", "b/c.rs"]);
+            assert_eq!(skips.capped, Some(WalkCap::Files(3)));
+            // files past the cap aren't counted as ignored
+            assert_eq!(skips.ignored, 0);
+        }
+
+        // each file is 10 bytes, so 25 fits two
+        config.indexing.max_files = 0;
+        config.indexing.max_total_bytes = 25;
+        let (files, skips) = walk_directory_report(dir.path(), &config, false, false).unwrap();
+        assert_eq!(relative(&files), ["a.rs", "b/a.rs"]);
+        assert_eq!(skips.capped, Some(WalkCap::TotalBytes(25)));
+
+        // a cap the tree fits under isn't reported
+        config.indexing.max_total_bytes = 50;
+        let (files, skips) = walk_directory_report(dir.path(), &config, false, false).unwrap();
+        assert_eq!(files.len(), 5);
+        assert_eq!(skips.capped, None);
+    }
+
+    #[test]
+    fn test_walk_stops_at_cap() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(dir.path().join("c_snapshot"), [0u8; 64]).unwrap();
+
+        let mut config = test_config();
+        let (_, skips) = walk_directory_report(dir.path(), &config, false, false).unwrap();
+        assert_eq!(skips.binary, 1);
+
+        // the walk ends at b.rs, so c_snapshot is never opened
+        config.indexing.max_files = 1;
+        let (files, skips) = walk_directory_report(dir.path(), &config, false, false).unwrap();
+        assert_eq!(files, vec![dir.path().join("a.rs")]);
+        assert_eq!(skips.capped, Some(WalkCap::Files(1)));
+        assert_eq!(skips.binary, 0);
+    }
+
+    #[test]
+    fn test_lockfiles_included_with_all() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("package-lock.json"), "{}").unwrap();
+        let files = walk_directory_opts(dir.path(), &test_config(), true).unwrap();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_is_lockfile() {
+        assert!(is_lockfile(Path::new("/repo/yarn.lock")));
+        assert!(is_lockfile(Path::new("go.sum")));
+        assert!(!is_lockfile(Path::new("/repo/package.json")));
+        assert!(!is_lockfile(Path::new("/repo/src/lock.rs")));
+    }
+
     #[test]
     fn test_walk_empty_directory() {
         let dir = tempdir().unwrap();
@@ -2,28 +2,91 @@
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
 
-use hnsw_rs::anndists::dist::distances::DistCosine;
+use hnsw_rs::anndists::dist::distances::{DistCosine, DistL2, Distance};
 use hnsw_rs::api::AnnT;
-use hnsw_rs::hnsw::Hnsw;
+use hnsw_rs::hnsw::{Hnsw, Neighbour};
 use hnsw_rs::hnswio::HnswIo;
+use serde::{Deserialize, Serialize};
 use srag_common::{Error, Result};
 
 use super::store::Store;
+use crate::config::{Config, DistanceMetric};
 
 const BASENAME: &str = "srag_vectors";
 const MAX_NB_CONNECTION: usize = 16;
 const MAX_LAYER: usize = 16;
 const EF_CONSTRUCTION: usize = 200;
 const DEFAULT_MAX_ELEMENTS: usize = 100_000;
+const DEFAULT_GROWTH_FACTOR: f32 = 2.0;
 
 // global cached vector index for mcp queries
 static CACHED_INDEX: OnceLock<Mutex<Option<CachedVectorIndex>>> = OnceLock::new();
+/// bumped by every invalidate_cache so other caches can tell they are stale
+static CACHE_EPOCH: AtomicU64 = AtomicU64::new(0);
 
 struct CachedVectorIndex {
     index: VectorIndex,
     vectors_dir: PathBuf,
+    /// `Store::vectors_generation` when built, so writes by another process
+    /// (an index run, the watcher) are picked up too
+    generation: (i64, i64),
+}
+
+/// a positive distance that falls as `a.b` rises: `2 - a.b` up to a dot
+/// product of 1, `1 / a.b` past it. hnsw_rs rejects negative distances, and
+/// its own DistDot asserts on vectors that aren't unit length, which is
+/// exactly what dot product models produce.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DistInnerProduct;
+
+impl DistInnerProduct {
+    /// the dot product a distance from `eval` came from
+    fn dot(distance: f32) -> f32 {
+        if distance >= 1.0 {
+            2.0 - distance
+        } else {
+            1.0 / distance
+        }
+    }
+}
+
+impl Distance<f32> for DistInnerProduct {
+    fn eval(&self, va: &[f32], vb: &[f32]) -> f32 {
+        let dot: f32 = va.iter().zip(vb).map(|(a, b)| a * b).sum();
+        if dot <= 1.0 {
+            2.0 - dot
+        } else {
+            1.0 / dot
+        }
+    }
+}
+
+/// hnsw_rs fixes the distance as a type parameter, so the configured metric
+/// picks one of these at runtime
+enum Graph {
+    Cosine(Hnsw<'static, f32, DistCosine>),
+    Dot(Hnsw<'static, f32, DistInnerProduct>),
+    L2(Hnsw<'static, f32, DistL2>),
+}
+
+/// run the same code against whichever graph is in use
+macro_rules! with_graph {
+    ($graph:expr, $hnsw:ident => $body:expr) => {
+        match $graph {
+            Graph::Cosine($hnsw) => $body,
+            Graph::Dot($hnsw) => $body,
+            Graph::L2($hnsw) => $body,
+        }
+    };
+}
+
+/// written next to the graph so a reload uses the metric it was built with
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexMeta {
+    distance: DistanceMetric,
 }
 
 /// wraps hnsw_rs for vector similarity search.
@@ -34,11 +97,12 @@ pub struct VectorIndex {
     // IMPORTANT: field order matters for drop safety.
     // Rust drops fields in declaration order, so `hnsw` is dropped before `_loader`.
     // This is required because `hnsw` holds references into `_loader`'s memory-mapped data.
-    hnsw: Hnsw<'static, f32, DistCosine>,
+    hnsw: Graph,
+    metric: DistanceMetric,
     dimension: usize,
     next_id: usize,
     max_elements: usize,
-    capacity_warned: bool,
+    growth_factor: f32,
     loaded_from_disk: bool,
     // _loader MUST be the last field so it is dropped last, after hnsw releases its references.
     _loader: Option<Box<HnswIo>>,
@@ -46,33 +110,86 @@ pub struct VectorIndex {
 
 impl VectorIndex {
     pub fn new(dimension: usize, max_elements: usize) -> Result<Self> {
-        let hnsw = Hnsw::new(
-            MAX_NB_CONNECTION,
-            max_elements,
-            MAX_LAYER,
-            EF_CONSTRUCTION,
-            DistCosine,
-        );
+        Self::with_metric(dimension, max_elements, DistanceMetric::default())
+    }
+
+    pub fn with_metric(
+        dimension: usize,
+        max_elements: usize,
+        metric: DistanceMetric,
+    ) -> Result<Self> {
         Ok(Self {
-            hnsw,
+            hnsw: empty_graph(metric, max_elements),
+            metric,
             dimension,
             next_id: 0,
             max_elements,
-            capacity_warned: false,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
             loaded_from_disk: false,
             _loader: None,
         })
     }
 
-    pub fn open(path: &Path, dimension: usize) -> Result<Self> {
+    /// how much the capacity is multiplied by when the index outgrows it
+    pub fn with_growth_factor(mut self, growth_factor: f32) -> Self {
+        self.growth_factor = growth_factor;
+        self
+    }
+
+    /// open the index in the configured vectors dir, sized and grown as
+    /// `indexing.hnsw_*` says
+    pub fn open_configured(config: &Config) -> Result<Self> {
+        Self::open_sized(
+            &config.vectors_dir(),
+            crate::config::EMBEDDING_DIMENSION,
+            config.embedding.distance,
+            config.indexing.hnsw_capacity,
+            config.indexing.hnsw_growth_factor,
+        )
+    }
+
+    /// load the index saved in `path`, or start an empty one. an index built
+    /// with a different metric is an error: its neighbours would be wrong.
+    pub fn open(path: &Path, dimension: usize, metric: DistanceMetric) -> Result<Self> {
+        Self::open_sized(
+            path,
+            dimension,
+            metric,
+            DEFAULT_MAX_ELEMENTS,
+            DEFAULT_GROWTH_FACTOR,
+        )
+    }
+
+    /// `open` with an explicit capacity and growth factor. a loaded index
+    /// gets at least `capacity`, and one growth step past its current points
+    /// so the first insert doesn't rebuild it.
+    fn open_sized(
+        path: &Path,
+        dimension: usize,
+        metric: DistanceMetric,
+        capacity: usize,
+        growth_factor: f32,
+    ) -> Result<Self> {
         let graph_file = path.join(format!("{}.hnsw.graph", BASENAME));
         let data_file = path.join(format!("{}.hnsw.data", BASENAME));
 
         if graph_file.exists() && data_file.exists() {
-            match Self::load_from_disk(path, dimension) {
-                Ok(index) => {
+            let stored = stored_metric(path)?;
+            if stored != metric {
+                return Err(Error::Index(format!(
+                    "the vector index in {} was built with {:?} distance but embedding.distance is {:?}; \
+                     run 'srag reembed' to rebuild it or set embedding.distance back",
+                    path.display(),
+                    stored,
+                    metric
+                )));
+            }
+            match Self::load_from_disk(path, dimension, metric) {
+                Ok(mut index) => {
                     tracing::info!("loaded hnsw index from disk ({} points)", index.len());
-                    return Ok(index);
+                    let headroom = (index.len() as f64 * growth_factor as f64).ceil() as usize;
+                    index.max_elements = capacity.max(headroom);
+                    return Ok(index.with_growth_factor(growth_factor));
                 }
                 Err(e) => {
                     tracing::warn!("failed to load hnsw index: {}, will rebuild from db", e);
@@ -80,10 +197,10 @@ impl VectorIndex {
             }
         }
 
-        Self::new(dimension, DEFAULT_MAX_ELEMENTS)
+        Ok(Self::with_metric(dimension, capacity, metric)?.with_growth_factor(growth_factor))
     }
 
-    fn load_from_disk(path: &Path, dimension: usize) -> Result<Self> {
+    fn load_from_disk(path: &Path, dimension: usize, metric: DistanceMetric) -> Result<Self> {
         let loader = Box::new(HnswIo::new(path, BASENAME));
 
         let loader_ptr = Box::into_raw(loader);
@@ -95,18 +212,29 @@ impl VectorIndex {
         // 2. Reconstructing the Box below so it is properly freed when the struct drops.
         let loader_ref: &'static mut HnswIo = unsafe { &mut *loader_ptr };
 
-        let hnsw: Hnsw<'static, f32, DistCosine> = loader_ref
-            .load_hnsw()
-            .map_err(|e| Error::Index(format!("Failed to load HNSW: {}", e)))?;
+        let loaded = match metric {
+            DistanceMetric::Cosine => loader_ref.load_hnsw().map(Graph::Cosine),
+            DistanceMetric::Dot => loader_ref.load_hnsw().map(Graph::Dot),
+            DistanceMetric::L2 => loader_ref.load_hnsw().map(Graph::L2),
+        };
+        let hnsw = match loaded {
+            Ok(hnsw) => hnsw,
+            Err(e) => {
+                // SAFETY: nothing borrows from the loader when loading failed
+                drop(unsafe { Box::from_raw(loader_ptr) });
+                return Err(Error::Index(format!("Failed to load HNSW: {}", e)));
+            }
+        };
 
-        let nb_point = hnsw.get_nb_point();
+        let nb_point = with_graph!(&hnsw, h => h.get_nb_point());
 
         Ok(Self {
             hnsw,
+            metric,
             dimension,
             next_id: nb_point,
-            max_elements: DEFAULT_MAX_ELEMENTS.max(nb_point),
-            capacity_warned: false,
+            max_elements: nb_point,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
             loaded_from_disk: true,
             // SAFETY: loader_ptr was created from Box::into_raw above; reconstruct to free on drop.
             _loader: Some(unsafe { Box::from_raw(loader_ptr) }),
@@ -121,20 +249,49 @@ impl VectorIndex {
                 vector.len()
             )));
         }
-        if !self.capacity_warned && self.len() >= self.max_elements {
-            tracing::warn!(
-                "hnsw index exceeded pre-allocated capacity of {} elements, performance may degrade",
-                self.max_elements
-            );
-            self.capacity_warned = true;
+        if self.len() >= self.max_elements {
+            let target = (self.next_id.max(self.len()) as f64 * self.growth_factor as f64).ceil();
+            self.reserve(target as usize)?;
         }
-        self.hnsw.insert_slice((vector, id));
+        with_graph!(&self.hnsw, h => h.insert_slice((vector, id)));
         if id >= self.next_id {
             self.next_id = id + 1;
         }
         Ok(())
     }
 
+    /// make room for at least `capacity` points. hnsw_rs can't resize a
+    /// graph and degrades past its preallocated size, so this rebuilds it
+    /// into a larger one from the points already inserted.
+    pub fn reserve(&mut self, capacity: usize) -> Result<()> {
+        if capacity <= self.max_elements {
+            return Ok(());
+        }
+        let points: Vec<(Vec<f32>, usize)> = with_graph!(&self.hnsw, h => h
+            .get_point_indexation()
+            .into_iter()
+            .map(|point| (point.get_v().to_vec(), point.get_origin_id()))
+            .collect());
+        tracing::info!(
+            "growing hnsw index from {} to {} elements ({} points)",
+            self.max_elements,
+            capacity,
+            points.len()
+        );
+        let hnsw = empty_graph(self.metric, capacity);
+        let data: Vec<(&Vec<f32>, usize)> = points.iter().map(|(v, id)| (v, *id)).collect();
+        with_graph!(&hnsw, h => h.parallel_insert(&data));
+        // the old graph may borrow from the loader, so it goes first
+        self.hnsw = hnsw;
+        self._loader = None;
+        self.max_elements = capacity;
+        Ok(())
+    }
+
+    /// nearest neighbours of `query`, as `1 - similarity`. similarity is the
+    /// cosine for cosine, the dot product itself for dot (over 1 for long
+    /// vectors), and `1 - d^2/2` for l2, which is the cosine only when the
+    /// vectors are unit length.
     pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Result<Vec<(usize, f32)>> {
         if query.len() != self.dimension {
             return Err(Error::Index(format!(
@@ -143,23 +300,35 @@ impl VectorIndex {
                 query.len()
             )));
         }
-        let results = self.hnsw.search(query, k, ef);
+        let results: Vec<Neighbour> = with_graph!(&self.hnsw, h => h.search(query, k, ef));
         Ok(results
             .into_iter()
-            .map(|n| (n.get_origin_id(), n.get_distance()))
+            .map(|n| (n.get_origin_id(), self.rescale(n.get_distance())))
             .collect())
     }
 
+    fn rescale(&self, distance: f32) -> f32 {
+        match self.metric {
+            // for unit vectors |a-b|^2 = 2 - 2cos
+            DistanceMetric::L2 => distance * distance / 2.0,
+            DistanceMetric::Dot => 1.0 - DistInnerProduct::dot(distance),
+            DistanceMetric::Cosine => distance,
+        }
+    }
+
     pub fn save(&self, path: &Path) -> Result<()> {
         std::fs::create_dir_all(path)?;
-        self.hnsw
-            .file_dump(path, BASENAME)
+        with_graph!(&self.hnsw, h => h.file_dump(path, BASENAME))
             .map_err(|e| Error::Index(format!("Failed to save HNSW index: {}", e)))?;
+        let meta = IndexMeta {
+            distance: self.metric,
+        };
+        std::fs::write(meta_path(path), serde_json::to_vec(&meta)?)?;
         Ok(())
     }
 
     pub fn len(&self) -> usize {
-        self.hnsw.get_nb_point()
+        with_graph!(&self.hnsw, h => h.get_nb_point())
     }
 
     pub fn is_empty(&self) -> bool {
@@ -170,6 +339,10 @@ impl VectorIndex {
         self.dimension
     }
 
+    pub fn metric(&self) -> DistanceMetric {
+        self.metric
+    }
+
     pub fn next_id(&self) -> usize {
         self.next_id
     }
@@ -177,6 +350,45 @@ impl VectorIndex {
     pub fn loaded_from_disk(&self) -> bool {
         self.loaded_from_disk
     }
+
+    /// points the graph was allocated for
+    pub fn capacity(&self) -> usize {
+        self.max_elements
+    }
+}
+
+fn empty_graph(metric: DistanceMetric, max_elements: usize) -> Graph {
+    fn empty<D: Distance<f32> + Send + Sync>(
+        max_elements: usize,
+        dist: D,
+    ) -> Hnsw<'static, f32, D> {
+        Hnsw::new(
+            MAX_NB_CONNECTION,
+            max_elements,
+            MAX_LAYER,
+            EF_CONSTRUCTION,
+            dist,
+        )
+    }
+    match metric {
+        DistanceMetric::Cosine => Graph::Cosine(empty(max_elements, DistCosine)),
+        DistanceMetric::Dot => Graph::Dot(empty(max_elements, DistInnerProduct)),
+        DistanceMetric::L2 => Graph::L2(empty(max_elements, DistL2)),
+    }
+}
+
+fn meta_path(path: &Path) -> PathBuf {
+    path.join(format!("{}.meta", BASENAME))
+}
+
+/// the metric an index on disk was saved with. indexes from before the
+/// metric was configurable have no meta file and are cosine.
+fn stored_metric(path: &Path) -> Result<DistanceMetric> {
+    match std::fs::read(meta_path(path)) {
+        Ok(bytes) => Ok(serde_json::from_slice::<IndexMeta>(&bytes)?.distance),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(DistanceMetric::Cosine),
+        Err(e) => Err(e.into()),
+    }
 }
 
 pub fn rebuild_hnsw_from_db(store: &Store, index: &mut VectorIndex) -> Result<()> {
@@ -188,16 +400,36 @@ pub fn rebuild_hnsw_from_db(store: &Store, index: &mut VectorIndex) -> Result<()
         return Ok(());
     }
     tracing::info!("rebuilding hnsw index from {} embeddings", total);
+    // size it up front rather than growing part way through
+    if total as usize > index.capacity() {
+        let target = (total as f64 * index.growth_factor as f64).ceil();
+        index.reserve(target as usize)?;
+    }
     let dim = index.dimension();
     store.for_each_embedding(dim, |id, vector| index.insert(id as usize, &vector))?;
     tracing::info!("hnsw rebuild complete ({} points)", index.len());
     Ok(())
 }
 
-/// search using the cached index, avoiding rebuilds on each mcp request
+/// build a fresh index from every stored embedding, ignoring whatever is on
+/// disk. used when existing vectors were replaced rather than appended.
+pub fn build_hnsw_from_db(
+    store: &Store,
+    dimension: usize,
+    metric: DistanceMetric,
+) -> Result<VectorIndex> {
+    let capacity = DEFAULT_MAX_ELEMENTS.max(store.embedding_count()? as usize);
+    let mut index = VectorIndex::with_metric(dimension, capacity, metric)?;
+    rebuild_hnsw_from_db(store, &mut index)?;
+    Ok(index)
+}
+
+/// search using the cached index, avoiding rebuilds on each mcp request.
+/// the index is rebuilt once the store's vectors generation moves on.
 pub fn search_cached(
     vectors_dir: &Path,
     dimension: usize,
+    metric: DistanceMetric,
     store: &Store,
     query: &[f32],
     k: usize,
@@ -208,18 +440,24 @@ pub fn search_cached(
         .lock()
         .map_err(|e| Error::Index(format!("Failed to acquire index lock: {}", e)))?;
 
+    let generation = store.vectors_generation()?;
     let needs_init = match &*guard {
         None => true,
-        Some(cached) => cached.vectors_dir != vectors_dir,
+        Some(cached) => {
+            cached.vectors_dir != vectors_dir
+                || cached.index.metric() != metric
+                || cached.generation != generation
+        }
     };
 
     if needs_init {
         tracing::debug!("initialising cached vector index");
-        let mut index = VectorIndex::open(vectors_dir, dimension)?;
+        let mut index = VectorIndex::open(vectors_dir, dimension, metric)?;
         rebuild_hnsw_from_db(store, &mut index)?;
         *guard = Some(CachedVectorIndex {
             index,
             vectors_dir: vectors_dir.to_path_buf(),
+            generation,
         });
     }
 
@@ -229,9 +467,10 @@ pub fn search_cached(
     cached.index.search(query, k, ef)
 }
 
-/// invalidate the cached index after modifying content.
-/// NOTE: callers must call invalidate_cache() after indexing to refresh search results
+/// invalidate the cached index after modifying content so the next
+/// search_cached call rebuilds it.
 pub fn invalidate_cache() {
+    CACHE_EPOCH.fetch_add(1, Ordering::SeqCst);
     if let Some(mutex) = CACHED_INDEX.get() {
         if let Ok(mut guard) = mutex.lock() {
             *guard = None;
@@ -240,6 +479,11 @@ pub fn invalidate_cache() {
     }
 }
 
+/// how many times the cached index has been invalidated
+pub fn cache_epoch() -> u64 {
+    CACHE_EPOCH.load(Ordering::SeqCst)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,7 +555,7 @@ mod tests {
             index.save(dir.path()).unwrap();
         }
 
-        let loaded = VectorIndex::open(dir.path(), dim).unwrap();
+        let loaded = VectorIndex::open(dir.path(), dim, DistanceMetric::Cosine).unwrap();
         assert_eq!(loaded.len(), 1);
         assert!(loaded.loaded_from_disk());
     }
@@ -319,7 +563,7 @@ mod tests {
     #[test]
     fn test_open_nonexistent() {
         let dir = tempdir().unwrap();
-        let index = VectorIndex::open(dir.path(), TEST_DIM).unwrap();
+        let index = VectorIndex::open(dir.path(), TEST_DIM, DistanceMetric::Cosine).unwrap();
         assert!(index.is_empty());
         assert!(!index.loaded_from_disk());
     }
@@ -339,8 +583,282 @@ mod tests {
         assert_eq!(index.next_id(), 11);
     }
 
+    #[test]
+    fn test_insert_past_capacity_grows() {
+        let mut index = VectorIndex::new(TEST_DIM, 4)
+            .unwrap()
+            .with_growth_factor(2.0);
+        let vectors: Vec<Vec<f32>> = (0..20)
+            .map(|i| {
+                let mut v = vec![0.01; TEST_DIM];
+                v[i % TEST_DIM] = 1.0 + (i / TEST_DIM) as f32;
+                v[(i + 1) % TEST_DIM] = i as f32 * 0.1;
+                v
+            })
+            .collect();
+        for (id, v) in vectors.iter().enumerate() {
+            index.insert(id, v).unwrap();
+        }
+
+        assert_eq!(index.len(), 20);
+        assert!(index.capacity() >= 20);
+        assert_eq!(index.next_id(), 20);
+        for (id, v) in vectors.iter().enumerate() {
+            let results = index.search(v, 1, 100).unwrap();
+            assert_eq!(results[0].0, id, "vector {}", id);
+        }
+    }
+
+    #[test]
+    fn test_grow_after_load_from_disk() {
+        let dir = tempdir().unwrap();
+        {
+            let mut index = VectorIndex::new(TEST_DIM, 1000).unwrap();
+            index.insert(0, &random_vector(TEST_DIM)).unwrap();
+            index.save(dir.path()).unwrap();
+        }
+
+        let mut loaded = VectorIndex::open(dir.path(), TEST_DIM, DistanceMetric::Cosine).unwrap();
+        let capacity = loaded.capacity();
+        loaded.reserve(capacity * 2).unwrap();
+        assert_eq!(loaded.capacity(), capacity * 2);
+        assert_eq!(loaded.len(), 1);
+        let results = loaded.search(&random_vector(TEST_DIM), 1, 50).unwrap();
+        assert_eq!(results[0].0, 0);
+    }
+
+    #[test]
+    fn test_loaded_capacity_follows_config() {
+        let dir = tempdir().unwrap();
+        {
+            let mut index = VectorIndex::new(TEST_DIM, 1000).unwrap();
+            for id in 0..10 {
+                index.insert(id, &random_vector(TEST_DIM)).unwrap();
+            }
+            index.save(dir.path()).unwrap();
+        }
+
+        let open = |capacity, growth| {
+            VectorIndex::open_sized(
+                dir.path(),
+                TEST_DIM,
+                DistanceMetric::Cosine,
+                capacity,
+                growth,
+            )
+            .unwrap()
+        };
+        assert_eq!(open(500, 2.0).capacity(), 500);
+        // a capacity below the points on disk still leaves room to insert
+        let mut small = open(4, 1.5);
+        assert_eq!(small.capacity(), 15);
+        small.insert(10, &random_vector(TEST_DIM)).unwrap();
+        assert_eq!(small.capacity(), 15);
+        assert_eq!(small.len(), 11);
+    }
+
+    /// ids of a, b and c nearest first, as the index measures them. against
+    /// q = x-axis, a points the same way but is short, b is close and long,
+    /// c is off at 45 degrees. ranked exhaustively: hnsw_rs can leave a point
+    /// of a three point graph unreachable, which is not what's under test.
+    fn rank_fixture(metric: DistanceMetric) -> Vec<usize> {
+        let index = VectorIndex::with_metric(4, 100, metric).unwrap();
+        let query = [1.0, 0.0, 0.0, 0.0];
+        let fixture = [
+            [0.5, 0.0, 0.0, 0.0],
+            [0.9, 0.3, 0.0, 0.0],
+            [0.7, 0.7, 0.0, 0.0],
+        ];
+        let mut scored: Vec<(usize, f32)> = fixture
+            .iter()
+            .enumerate()
+            .map(|(id, v)| {
+                let distance = with_graph!(&index.hnsw, h => h.get_distance().eval(&query, v));
+                (id, index.rescale(distance))
+            })
+            .collect();
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+        scored.into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn test_each_metric_ranks_fixture() {
+        // cosine only sees direction: 0, 0.05, 0.29
+        assert_eq!(rank_fixture(DistanceMetric::Cosine), vec![0, 1, 2]);
+        // dot rewards length too: 1 - 0.5, 1 - 0.9, 1 - 0.7
+        assert_eq!(rank_fixture(DistanceMetric::Dot), vec![1, 2, 0]);
+        // l2 is plain distance: 0.5, 0.32, 0.76
+        assert_eq!(rank_fixture(DistanceMetric::L2), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_dot_distance_keeps_order_past_one() {
+        let query = [1.0, 0.0, 0.0, 0.0];
+        let distances: Vec<f32> = [-2.0, 0.0, 0.9, 1.0, 2.0, 3.0, 40.0]
+            .iter()
+            .map(|x| DistInnerProduct.eval(&query, &[*x, 0.0, 0.0, 0.0]))
+            .collect();
+        assert!(distances.iter().all(|d| *d > 0.0), "{:?}", distances);
+        assert!(distances.windows(2).all(|w| w[0] > w[1]), "{:?}", distances);
+
+        // search reports 1 - dot, so similarity is the dot product
+        let index = VectorIndex::with_metric(4, 100, DistanceMetric::Dot).unwrap();
+        assert!((index.rescale(distances[5]) - (1.0 - 3.0)).abs() < 1e-5);
+        assert!((index.rescale(distances[0]) - (1.0 + 2.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_l2_distance_is_on_cosine_scale() {
+        let mut index = VectorIndex::with_metric(4, 100, DistanceMetric::L2).unwrap();
+        index.insert(0, &[0.0, 1.0, 0.0, 0.0]).unwrap();
+        let results = index.search(&[1.0, 0.0, 0.0, 0.0], 1, 10).unwrap();
+        assert!((results[0].1 - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_reopen_keeps_metric() {
+        let dir = tempdir().unwrap();
+        {
+            let mut index = VectorIndex::with_metric(4, 100, DistanceMetric::Dot).unwrap();
+            index.insert(0, &[0.9, 0.3, 0.0, 0.0]).unwrap();
+            index.save(dir.path()).unwrap();
+        }
+
+        let loaded = VectorIndex::open(dir.path(), 4, DistanceMetric::Dot).unwrap();
+        assert!(loaded.loaded_from_disk());
+        assert_eq!(loaded.metric(), DistanceMetric::Dot);
+        let results = loaded.search(&[1.0, 0.0, 0.0, 0.0], 1, 50).unwrap();
+        assert_eq!(results[0].0, 0);
+        assert!((results[0].1 - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_reopen_with_other_metric_errors() {
+        let dir = tempdir().unwrap();
+        {
+            let mut index = VectorIndex::with_metric(4, 100, DistanceMetric::L2).unwrap();
+            index.insert(0, &[0.5, 0.0, 0.0, 0.0]).unwrap();
+            index.save(dir.path()).unwrap();
+        }
+
+        let err = VectorIndex::open(dir.path(), 4, DistanceMetric::Cosine)
+            .err()
+            .expect("metric mismatch should fail");
+        assert!(err.to_string().contains("srag reembed"), "{}", err);
+    }
+
+    #[test]
+    fn test_index_without_meta_is_cosine() {
+        let dir = tempdir().unwrap();
+        {
+            let mut index = VectorIndex::new(TEST_DIM, 100).unwrap();
+            index.insert(0, &random_vector(TEST_DIM)).unwrap();
+            index.save(dir.path()).unwrap();
+        }
+        std::fs::remove_file(meta_path(dir.path())).unwrap();
+
+        assert!(VectorIndex::open(dir.path(), TEST_DIM, DistanceMetric::Cosine).is_ok());
+        assert!(VectorIndex::open(dir.path(), TEST_DIM, DistanceMetric::Dot).is_err());
+    }
+
     #[test]
     fn test_invalidate_cache() {
         invalidate_cache();
     }
+
+    fn insert_embedded_chunk(store: &Store, file_id: i64, vector: &[f32]) -> i64 {
+        let chunk = srag_common::types::Chunk {
+            id: None,
+            file_id,
+            content: "fn cached() {}".to_string(),
+            symbol: None,
+            symbol_kind: None,
+            start_line: 1,
+            end_line: 1,
+            language: srag_common::types::Language::Rust,
+            suspicious: false,
+        };
+        let chunk_id = store.insert_chunk(&chunk, None).unwrap();
+        let embedding_id = store.insert_embedding(chunk_id, vector).unwrap();
+        store
+            .update_chunk_embedding_id(chunk_id, embedding_id)
+            .unwrap();
+        embedding_id
+    }
+
+    #[test]
+    fn test_search_cached_reinitialises_after_invalidate() {
+        let (store, dir) = crate::index::store::tests::test_store();
+        let vectors_dir = dir.path().join("vectors");
+        std::fs::create_dir_all(&vectors_dir).unwrap();
+
+        let project_id = store.upsert_project("cache", "/tmp/cache").unwrap();
+        let file_id = store
+            .upsert_file(&srag_common::types::FileRecord {
+                id: None,
+                project_id,
+                path: "/tmp/cache/lib.rs".to_string(),
+                blake3_hash: "abc".to_string(),
+                language: srag_common::types::Language::Rust,
+                size_bytes: 14,
+                chunk_count: 2,
+                indexed_at: String::new(),
+            })
+            .unwrap();
+
+        let mut first = vec![0.0; TEST_DIM];
+        first[0] = 1.0;
+        let first_id = insert_embedded_chunk(&store, file_id, &first);
+
+        invalidate_cache();
+        let results = search_cached(
+            &vectors_dir,
+            TEST_DIM,
+            DistanceMetric::Cosine,
+            &store,
+            &first,
+            5,
+            50,
+        )
+        .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, first_id as usize);
+
+        let mut second = vec![0.0; TEST_DIM];
+        second[TEST_DIM - 1] = 1.0;
+        let second_id = insert_embedded_chunk(&store, file_id, &second);
+
+        invalidate_cache();
+        let results = search_cached(
+            &vectors_dir,
+            TEST_DIM,
+            DistanceMetric::Cosine,
+            &store,
+            &second,
+            5,
+            50,
+        )
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, second_id as usize);
+
+        // another process indexing can't call invalidate_cache here; its
+        // generation bump is enough
+        let mut third = vec![0.0; TEST_DIM];
+        third[1] = 1.0;
+        let third_id = insert_embedded_chunk(&store, file_id, &third);
+        store.bump_project_generation(project_id).unwrap();
+        let results = search_cached(
+            &vectors_dir,
+            TEST_DIM,
+            DistanceMetric::Cosine,
+            &store,
+            &third,
+            5,
+            50,
+        )
+        .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0, third_id as usize);
+    }
 }
@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use rusqlite::Connection;
+use srag_common::{Error, Result};
+
+type Migration = fn(&Connection) -> rusqlite::Result<()>;
+
+/// ordered schema migrations. entry n brings the database to version n + 1.
+/// append new entries, never edit or reorder released ones.
+const MIGRATIONS: &[Migration] = &[
+    |conn| conn.execute_batch(BASE_SCHEMA),
+    |conn| add_column(conn, "chunks", "suspicious", "INTEGER NOT NULL DEFAULT 0"),
+    |conn| conn.execute_batch(CALL_GRAPH_SCHEMA),
+    |conn| add_column(conn, "projects", "generation", "INTEGER NOT NULL DEFAULT 0"),
+    |conn| add_column(conn, "projects", "embedding_model", "TEXT"),
+    |conn| add_column(conn, "projects", "last_queried_at", "TEXT"),
+    |conn| {
+        add_column(
+            conn,
+            "projects",
+            "needs_reembed",
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+    },
+    |conn| conn.execute_batch(COMMITS_SCHEMA),
+    |conn| conn.execute_batch(FILE_CONTENTS_SCHEMA),
+    |conn| conn.execute_batch(EMBEDDING_CACHE_SCHEMA),
+    |conn| {
+        add_column(conn, "chunks", "content_hash", "TEXT")?;
+        add_column(conn, "chunks", "dup_of", "INTEGER")?;
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_chunks_content_hash ON chunks(content_hash);
+             CREATE INDEX IF NOT EXISTS idx_chunks_dup_of ON chunks(dup_of);",
+        )
+    },
+];
+
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+// tables use IF NOT EXISTS so databases created before versioning (user_version 0)
+// migrate cleanly.
+const BASE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS projects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    path TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    last_indexed_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS files (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    blake3_hash TEXT NOT NULL,
+    language TEXT NOT NULL DEFAULT 'unknown',
+    size_bytes INTEGER NOT NULL DEFAULT 0,
+    chunk_count INTEGER NOT NULL DEFAULT 0,
+    indexed_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE(project_id, path)
+);
+
+CREATE TABLE IF NOT EXISTS chunks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    symbol TEXT,
+    symbol_kind TEXT,
+    start_line INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    language TEXT NOT NULL DEFAULT 'unknown',
+    embedding_id INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    project_name TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS turns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    sources TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE IF NOT EXISTS reindex_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    file_path TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    queued_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE(project_id, file_path)
+);
+
+CREATE TABLE IF NOT EXISTS embeddings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chunk_id INTEGER NOT NULL UNIQUE REFERENCES chunks(id) ON DELETE CASCADE,
+    vector BLOB NOT NULL
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
+    chunk_id UNINDEXED,
+    content,
+    file_path,
+    symbol,
+    tokenize='porter unicode61'
+);
+
+CREATE INDEX IF NOT EXISTS idx_files_project ON files(project_id);
+CREATE INDEX IF NOT EXISTS idx_files_hash ON files(blake3_hash);
+CREATE INDEX IF NOT EXISTS idx_chunks_file ON chunks(file_id);
+CREATE INDEX IF NOT EXISTS idx_chunks_embedding ON chunks(embedding_id);
+CREATE INDEX IF NOT EXISTS idx_embeddings_chunk ON embeddings(chunk_id);
+CREATE INDEX IF NOT EXISTS idx_turns_session ON turns(session_id);
+CREATE INDEX IF NOT EXISTS idx_reindex_queue_project ON reindex_queue(project_id);
+";
+
+const CALL_GRAPH_SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS definitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chunk_id INTEGER NOT NULL REFERENCES chunks(id) ON DELETE CASCADE,
+    file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    scope TEXT,
+    language TEXT NOT NULL,
+    start_line INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    signature TEXT,
+    UNIQUE(file_id, name, scope, start_line)
+);
+
+CREATE TABLE IF NOT EXISTS function_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chunk_id INTEGER NOT NULL REFERENCES chunks(id) ON DELETE CASCADE,
+    file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    caller_name TEXT,
+    caller_scope TEXT,
+    callee_name TEXT NOT NULL,
+    line_number INTEGER NOT NULL,
+    language TEXT NOT NULL,
+    callee_definition_id INTEGER REFERENCES definitions(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_definitions_file ON definitions(file_id);
+CREATE INDEX IF NOT EXISTS idx_definitions_name ON definitions(name);
+CREATE INDEX IF NOT EXISTS idx_definitions_chunk ON definitions(chunk_id);
+CREATE INDEX IF NOT EXISTS idx_calls_file ON function_calls(file_id);
+CREATE INDEX IF NOT EXISTS idx_calls_callee ON function_calls(callee_name);
+CREATE INDEX IF NOT EXISTS idx_calls_caller ON function_calls(caller_name);
+CREATE INDEX IF NOT EXISTS idx_calls_definition ON function_calls(callee_definition_id);
+";
+
+// each commit owns a synthetic files row holding its hunks as chunks, so
+// removing the project or force re-indexing drops them with everything else
+const COMMITS_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS commits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
+    hash TEXT NOT NULL,
+    author TEXT NOT NULL,
+    committed_at INTEGER NOT NULL,
+    summary TEXT NOT NULL,
+    UNIQUE(project_id, hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_commits_file ON commits(file_id);
+";
+
+// original bytes of indexed files, kept only with indexing.store_raw_content
+const FILE_CONTENTS_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS file_contents (
+    file_id INTEGER PRIMARY KEY REFERENCES files(id) ON DELETE CASCADE,
+    content BLOB NOT NULL
+);
+";
+
+// vectors by a hash of the embedding model and the text embedded, so
+// identical chunks aren't embedded twice. not tied to chunks: it outlives
+// --force and removed projects, and is trimmed to indexing.embedding_cache_entries
+const EMBEDDING_CACHE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS embedding_cache (
+    key TEXT PRIMARY KEY,
+    signature TEXT NOT NULL,
+    vector BLOB NOT NULL,
+    last_used INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_embedding_cache_used ON embedding_cache(last_used);
+";
+
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| Error::Sqlite(e.to_string()))
+}
+
+/// apply every migration newer than the database's user_version, each in its
+/// own transaction together with the version bump.
+pub fn run_migrations(conn: &Connection) -> Result<()> {
+    let current = schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        return Err(Error::Database(format!(
+            "database schema version {} is newer than supported version {}",
+            current, SCHEMA_VERSION
+        )));
+    }
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = idx as i64 + 1;
+        conn.execute_batch("BEGIN IMMEDIATE")
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        let result =
+            migration(conn).and_then(|_| conn.pragma_update(None, "user_version", version));
+        match result {
+            Ok(()) => conn
+                .execute_batch("COMMIT")
+                .map_err(|e| Error::Sqlite(e.to_string()))?,
+            Err(e) => {
+                let _ = conn.execute_batch("ROLLBACK");
+                return Err(Error::Database(format!(
+                    "schema migration {} failed: {}",
+                    version, e
+                )));
+            }
+        }
+        tracing::debug!("applied schema migration {}", version);
+    }
+
+    Ok(())
+}
+
+/// databases from before versioning may already have columns added by the old
+/// best-effort ALTER, so check first.
+fn add_column(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    if has_column(conn, table, column)? {
+        return Ok(());
+    }
+    conn.execute_batch(&format!(
+        "ALTER TABLE {} ADD COLUMN {} {};",
+        table, column, decl
+    ))
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::store::Store;
+    use tempfile::tempdir;
+
+    /// schema as shipped before versioning: base tables and call graph, no
+    /// suspicious column, user_version 0.
+    fn legacy_db(path: &std::path::Path, with_suspicious: bool) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(BASE_SCHEMA).unwrap();
+        conn.execute_batch(CALL_GRAPH_SCHEMA).unwrap();
+        if with_suspicious {
+            conn.execute_batch(
+                "ALTER TABLE chunks ADD COLUMN suspicious INTEGER NOT NULL DEFAULT 0;",
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO projects (name, path) VALUES ('legacy', '/tmp/legacy')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_fresh_database_is_current() {
+        let dir = tempdir().unwrap();
+        let store = Store::open(&dir.path().join("fresh.db")).unwrap();
+        assert_eq!(schema_version(&store.conn).unwrap(), SCHEMA_VERSION);
+        assert!(has_column(&store.conn, "chunks", "suspicious").unwrap());
+        assert!(has_column(&store.conn, "projects", "generation").unwrap());
+        assert!(has_column(&store.conn, "projects", "embedding_model").unwrap());
+        assert!(has_column(&store.conn, "projects", "last_queried_at").unwrap());
+        assert!(has_column(&store.conn, "projects", "needs_reembed").unwrap());
+        assert!(has_column(&store.conn, "chunks", "dup_of").unwrap());
+    }
+
+    #[test]
+    fn test_legacy_database_migrates() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("legacy.db");
+        legacy_db(&path, false);
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(schema_version(&store.conn).unwrap(), SCHEMA_VERSION);
+        assert!(has_column(&store.conn, "chunks", "suspicious").unwrap());
+        assert!(has_column(&store.conn, "projects", "generation").unwrap());
+        assert_eq!(store.get_project_id("legacy").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_legacy_database_with_suspicious_column_migrates() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("legacy.db");
+        legacy_db(&path, true);
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(schema_version(&store.conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrations_idempotent() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("legacy.db");
+        legacy_db(&path, false);
+
+        drop(Store::open(&path).unwrap());
+        let store = Store::open(&path).unwrap();
+        run_migrations(&store.conn).unwrap();
+        assert_eq!(schema_version(&store.conn).unwrap(), SCHEMA_VERSION);
+        assert_eq!(store.list_projects().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_newer_schema_rejected() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("future.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        drop(conn);
+
+        assert!(Store::open(&path).is_err());
+    }
+}
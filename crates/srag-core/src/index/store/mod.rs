@@ -1,10 +1,15 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+mod migrations;
 mod store_callgraph;
 mod store_chunks;
+mod store_commits;
+mod store_embedding_cache;
 mod store_embeddings;
+mod store_eviction;
 mod store_file;
+mod store_import;
 mod store_project;
 mod store_query;
 mod store_session;
@@ -12,9 +17,13 @@ mod store_stats;
 
 use std::path::Path;
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
+use srag_common::types::CallGraphEntry;
 use srag_common::{Error, Result};
 
+/// most rows `search_literal` returns, whatever limit is asked for
+pub const MAX_LITERAL_RESULTS: usize = 500;
+
 pub(crate) fn escape_like_pattern(pattern: &str) -> String {
     pattern
         .replace('\\', "\\\\")
@@ -34,6 +43,26 @@ impl Store {
         Ok(store)
     }
 
+    /// open another srag database to copy from. nothing is migrated or
+    /// written, so it has to be on the schema this build uses.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let version = migrations::schema_version(&conn)?;
+        if version != migrations::SCHEMA_VERSION {
+            return Err(Error::Database(format!(
+                "{} has schema version {}, this build of srag uses {}",
+                path.display(),
+                version,
+                migrations::SCHEMA_VERSION
+            )));
+        }
+        Ok(Self { conn })
+    }
+
     fn init_schema(&self) -> Result<()> {
         self.conn
             .execute_batch(
@@ -42,128 +71,11 @@ impl Store {
             PRAGMA synchronous = NORMAL;
             PRAGMA foreign_keys = ON;
             PRAGMA busy_timeout = 5000;
-
-            CREATE TABLE IF NOT EXISTS projects (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                path TEXT NOT NULL,
-                created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                last_indexed_at TEXT
-            );
-
-            CREATE TABLE IF NOT EXISTS files (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
-                path TEXT NOT NULL,
-                blake3_hash TEXT NOT NULL,
-                language TEXT NOT NULL DEFAULT 'unknown',
-                size_bytes INTEGER NOT NULL DEFAULT 0,
-                chunk_count INTEGER NOT NULL DEFAULT 0,
-                indexed_at TEXT NOT NULL DEFAULT (datetime('now')),
-                UNIQUE(project_id, path)
-            );
-
-            CREATE TABLE IF NOT EXISTS chunks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
-                content TEXT NOT NULL,
-                symbol TEXT,
-                symbol_kind TEXT,
-                start_line INTEGER NOT NULL,
-                end_line INTEGER NOT NULL,
-                language TEXT NOT NULL DEFAULT 'unknown',
-                embedding_id INTEGER
-            );
-
-            CREATE TABLE IF NOT EXISTS sessions (
-                id TEXT PRIMARY KEY,
-                project_name TEXT,
-                created_at TEXT NOT NULL DEFAULT (datetime('now'))
-            );
-
-            CREATE TABLE IF NOT EXISTS turns (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
-                role TEXT NOT NULL,
-                content TEXT NOT NULL,
-                sources TEXT,
-                created_at TEXT NOT NULL DEFAULT (datetime('now'))
-            );
-
-            CREATE TABLE IF NOT EXISTS reindex_queue (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
-                file_path TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                queued_at TEXT NOT NULL DEFAULT (datetime('now')),
-                UNIQUE(project_id, file_path)
-            );
-
-            CREATE TABLE IF NOT EXISTS embeddings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                chunk_id INTEGER NOT NULL UNIQUE REFERENCES chunks(id) ON DELETE CASCADE,
-                vector BLOB NOT NULL
-            );
-
-            CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
-                chunk_id UNINDEXED,
-                content,
-                file_path,
-                symbol,
-                tokenize='porter unicode61'
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_files_project ON files(project_id);
-            CREATE INDEX IF NOT EXISTS idx_files_hash ON files(blake3_hash);
-            CREATE INDEX IF NOT EXISTS idx_chunks_file ON chunks(file_id);
-            CREATE INDEX IF NOT EXISTS idx_chunks_embedding ON chunks(embedding_id);
-            CREATE INDEX IF NOT EXISTS idx_embeddings_chunk ON embeddings(chunk_id);
-            CREATE INDEX IF NOT EXISTS idx_turns_session ON turns(session_id);
-            CREATE INDEX IF NOT EXISTS idx_reindex_queue_project ON reindex_queue(project_id);
-
-            CREATE TABLE IF NOT EXISTS definitions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                chunk_id INTEGER NOT NULL REFERENCES chunks(id) ON DELETE CASCADE,
-                file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
-                name TEXT NOT NULL,
-                kind TEXT NOT NULL,
-                scope TEXT,
-                language TEXT NOT NULL,
-                start_line INTEGER NOT NULL,
-                end_line INTEGER NOT NULL,
-                signature TEXT,
-                UNIQUE(file_id, name, scope, start_line)
-            );
-
-            CREATE TABLE IF NOT EXISTS function_calls (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                chunk_id INTEGER NOT NULL REFERENCES chunks(id) ON DELETE CASCADE,
-                file_id INTEGER NOT NULL REFERENCES files(id) ON DELETE CASCADE,
-                caller_name TEXT,
-                caller_scope TEXT,
-                callee_name TEXT NOT NULL,
-                line_number INTEGER NOT NULL,
-                language TEXT NOT NULL,
-                callee_definition_id INTEGER REFERENCES definitions(id) ON DELETE SET NULL
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_definitions_file ON definitions(file_id);
-            CREATE INDEX IF NOT EXISTS idx_definitions_name ON definitions(name);
-            CREATE INDEX IF NOT EXISTS idx_definitions_chunk ON definitions(chunk_id);
-            CREATE INDEX IF NOT EXISTS idx_calls_file ON function_calls(file_id);
-            CREATE INDEX IF NOT EXISTS idx_calls_callee ON function_calls(callee_name);
-            CREATE INDEX IF NOT EXISTS idx_calls_caller ON function_calls(caller_name);
-            CREATE INDEX IF NOT EXISTS idx_calls_definition ON function_calls(callee_definition_id);
             ",
             )
             .map_err(|e| Error::Sqlite(e.to_string()))?;
 
-        // migration: add suspicious column to chunks (ignore error if already exists)
-        let _ = self
-            .conn
-            .execute_batch("ALTER TABLE chunks ADD COLUMN suspicious INTEGER NOT NULL DEFAULT 0;");
-
-        Ok(())
+        migrations::run_migrations(&self.conn)
     }
 
     pub fn begin_transaction(&self) -> Result<()> {
@@ -193,6 +105,80 @@ impl Store {
             .map_err(|e| Error::Sqlite(e.to_string()))?;
         Ok(())
     }
+
+    /// rebuild the database file to reclaim space left by deleted rows and
+    /// merge the fts index segments. VACUUM can't run inside a transaction.
+    pub fn vacuum(&self) -> Result<()> {
+        if !self.conn.is_autocommit() {
+            return Err(Error::Sqlite(
+                "cannot vacuum while a transaction is open".into(),
+            ));
+        }
+        self.conn
+            .execute_batch(
+                "INSERT INTO chunks_fts(chunks_fts) VALUES('optimize');
+                 VACUUM;",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        self.wal_checkpoint()
+    }
+
+    /// run sqlite's integrity and foreign key checks. returns one line per
+    /// problem found; empty means the database is healthy.
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+
+        let mut stmt = self
+            .conn
+            .prepare("PRAGMA integrity_check")
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        for row in rows {
+            let line = row.map_err(|e| Error::Sqlite(e.to_string()))?;
+            if line != "ok" {
+                problems.push(line);
+            }
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("PRAGMA foreign_key_check")
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        for row in rows {
+            let (table, rowid, parent) = row.map_err(|e| Error::Sqlite(e.to_string()))?;
+            problems.push(match rowid {
+                Some(rowid) => format!(
+                    "{} row {} references a missing {} row",
+                    table, rowid, parent
+                ),
+                None => format!("{} row references a missing {} row", table, parent),
+            });
+        }
+        Ok(problems)
+    }
+}
+
+/// how a full-text query is turned into an fts5 MATCH expression
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FtsQueryMode {
+    /// every word quoted on its own, so operators are plain text
+    #[default]
+    Terms,
+    /// the whole query as one exact phrase
+    Phrase,
+    /// fts5 syntax as given: AND/OR/NOT, "phrases", NEAR(...), prefix*
+    Raw,
 }
 
 #[derive(Debug, Clone)]
@@ -203,6 +189,60 @@ pub struct ProjectPatterns {
     pub directories: Vec<(String, u64)>,
 }
 
+/// definitions that transitively call a function, nearest callers first
+#[derive(Debug, Clone, Default)]
+pub struct ImpactSet {
+    pub entries: Vec<ImpactEntry>,
+    /// stopped early at the size cap
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImpactEntry {
+    /// 1 for direct callers, 2 for their callers, and so on
+    pub depth: usize,
+    pub entry: CallGraphEntry,
+}
+
+/// a definition cut out of its chunk, with file-relative lines
+#[derive(Debug, Clone)]
+pub struct DefinitionBody {
+    pub entry: CallGraphEntry,
+    pub signature: Option<String>,
+    /// doc comment directly above the definition, or a python docstring
+    pub docstring: Option<String>,
+    pub body: String,
+}
+
+/// a commit whose diff hunks are indexed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitRecord {
+    pub hash: String,
+    pub author: String,
+    /// unix seconds
+    pub committed_at: i64,
+    /// first line of the message
+    pub summary: String,
+}
+
+/// what `Store::import_project` copied
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub files: u64,
+    pub chunks: u64,
+    pub embeddings: u64,
+    /// files already here with the same hash, or indexed here more recently
+    pub skipped: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub project_name: Option<String>,
+    pub created_at: String,
+    pub turn_count: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct LanguageStats {
     pub language: String,
@@ -222,11 +262,77 @@ pub(crate) mod tests {
         (store, dir)
     }
 
+    /// add a file row at `path`, hashed by its path, and return its id
+    pub fn seed_file(
+        store: &Store,
+        project_id: i64,
+        path: &str,
+        language: srag_common::types::Language,
+    ) -> i64 {
+        store
+            .upsert_file(&srag_common::types::FileRecord {
+                id: None,
+                project_id,
+                path: path.to_string(),
+                blake3_hash: path.to_string(),
+                language,
+                size_bytes: 0,
+                chunk_count: 0,
+                indexed_at: String::new(),
+            })
+            .unwrap()
+    }
+
+    /// an unnamed rust chunk of `content` starting at `start_line`, ready to
+    /// adjust with struct update syntax before `seed_chunk`
+    pub fn test_chunk(file_id: i64, content: &str, start_line: u32) -> srag_common::types::Chunk {
+        let lines = content.lines().count().max(1) as u32;
+        srag_common::types::Chunk {
+            id: None,
+            file_id,
+            content: content.to_string(),
+            symbol: None,
+            symbol_kind: None,
+            start_line,
+            end_line: start_line + lines - 1,
+            language: srag_common::types::Language::Rust,
+            suspicious: false,
+        }
+    }
+
+    /// insert `chunk` and its fts row under `path`, returning the chunk id
+    pub fn seed_chunk(store: &Store, chunk: &srag_common::types::Chunk, path: &str) -> i64 {
+        let chunk_id = store.insert_chunk(chunk, None).unwrap();
+        store
+            .insert_chunk_fts(chunk_id, &chunk.content, path, chunk.symbol.as_deref())
+            .unwrap();
+        chunk_id
+    }
+
     #[test]
     fn test_store_open_and_init() {
         let (_store, _dir) = test_store();
     }
 
+    #[test]
+    fn test_open_read_only() {
+        let (store, dir) = test_store();
+        store.upsert_project("proj", "/tmp/proj").unwrap();
+        drop(store);
+
+        let path = dir.path().join("test.db");
+        let other = Store::open_read_only(&path).unwrap();
+        assert_eq!(other.list_projects().unwrap().len(), 1);
+        assert!(other.upsert_project("more", "/tmp/more").is_err());
+        drop(other);
+
+        Connection::open(&path)
+            .unwrap()
+            .pragma_update(None, "user_version", migrations::SCHEMA_VERSION - 1)
+            .unwrap();
+        assert!(Store::open_read_only(&path).is_err());
+    }
+
     #[test]
     fn test_transaction_commit() {
         let (store, _dir) = test_store();
@@ -259,4 +365,84 @@ pub(crate) mod tests {
         assert_eq!(escape_like_pattern("test\\pattern"), "test\\\\pattern");
         assert_eq!(escape_like_pattern("normal"), "normal");
     }
+
+    #[test]
+    fn test_integrity_check_healthy() {
+        let (store, _dir) = test_store();
+        store.upsert_project("proj", "/tmp/proj").unwrap();
+        assert!(store.integrity_check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_integrity_check_reports_orphans() {
+        let (store, _dir) = test_store();
+        store
+            .conn
+            .execute_batch(
+                "PRAGMA foreign_keys = OFF;
+                 INSERT INTO chunks (file_id, content, start_line, end_line)
+                 VALUES (999, 'orphan', 1, 1);
+                 PRAGMA foreign_keys = ON;",
+            )
+            .unwrap();
+        let problems = store.integrity_check().unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("chunks row"), "{}", problems[0]);
+        assert!(problems[0].contains("missing files row"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn test_vacuum_rejects_open_transaction() {
+        let (store, _dir) = test_store();
+        store.begin_transaction().unwrap();
+        assert!(store.vacuum().is_err());
+        store.rollback().unwrap();
+        assert!(store.vacuum().is_ok());
+    }
+
+    #[test]
+    fn test_vacuum_after_churn_keeps_index_queryable() {
+        use srag_common::types::{Chunk, Language};
+
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        for i in 0..200 {
+            let path = format!("/tmp/proj/f{}.rs", i);
+            let content = format!("fn churn_{}() {{ {} }}", i, "x".repeat(512));
+            let file_id = seed_file(&store, pid, &path, Language::Rust);
+            let chunk = Chunk {
+                symbol: Some(format!("churn_{}", i)),
+                symbol_kind: Some("function".into()),
+                ..test_chunk(file_id, &content, 1)
+            };
+            seed_chunk(&store, &chunk, &path);
+        }
+        // drop all but the last ten files
+        for i in 0..190 {
+            let path = format!("/tmp/proj/f{}.rs", i);
+            let file_id: i64 = store
+                .conn
+                .query_row(
+                    "SELECT id FROM files WHERE project_id = ?1 AND path = ?2",
+                    rusqlite::params![pid, path],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            store.delete_file_chunks_fts(file_id).unwrap();
+            store.delete_file(pid, &path).unwrap();
+        }
+
+        store.vacuum().unwrap();
+
+        assert_eq!(store.chunk_count(Some(pid)).unwrap(), 10);
+        assert_eq!(store.search_fts("churn_195", 10).unwrap().len(), 1);
+        assert!(store.search_fts("churn_5", 10).unwrap().is_empty());
+        assert_eq!(
+            store
+                .search_symbols("churn_19", Some(pid), 50)
+                .unwrap()
+                .len(),
+            10
+        );
+    }
 }
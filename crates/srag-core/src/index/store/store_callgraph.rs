@@ -3,8 +3,35 @@
 use rusqlite::params;
 use srag_common::types::{CallGraphEntry, Definition, FunctionCall};
 use srag_common::{Error, Result};
+use std::collections::HashSet;
 
-use super::Store;
+use super::{DefinitionBody, ImpactEntry, ImpactSet, Store};
+
+/// upper bound on definitions returned by a transitive call graph walk
+const IMPACT_MAX_ENTRIES: usize = 500;
+
+/// definitions calling the function named ?2. definition lines are relative
+/// to their chunk, so they're offset to file lines here.
+const CALLERS_SQL: &str = "SELECT DISTINCT d.name, d.kind, f.path,
+        c.start_line + d.start_line - 1, c.start_line + d.end_line - 1, d.scope
+    FROM function_calls fc
+    JOIN definitions d ON fc.caller_name = d.name AND fc.file_id = d.file_id
+    AND (fc.caller_scope IS NULL OR d.scope IS NULL OR fc.caller_scope = d.scope)
+    JOIN chunks c ON d.chunk_id = c.id
+    JOIN files f ON d.file_id = f.id
+    WHERE f.project_id = ?1 AND fc.callee_name = ?2
+    ORDER BY f.path, 4";
+
+/// definitions the function named ?2 calls, lines offset like `CALLERS_SQL`
+const CALLEES_SQL: &str = "SELECT DISTINCT d.name, d.kind, f.path,
+        c.start_line + d.start_line - 1, c.start_line + d.end_line - 1, d.scope
+    FROM function_calls fc
+    JOIN definitions d ON fc.callee_name = d.name
+    JOIN chunks c ON d.chunk_id = c.id
+    JOIN files f ON d.file_id = f.id
+    WHERE f.project_id = ?1 AND fc.caller_name = ?2
+    AND fc.file_id IN (SELECT id FROM files WHERE project_id = ?1)
+    ORDER BY f.path, 4";
 
 impl Store {
     pub fn insert_definition(&self, def: &Definition) -> Result<i64> {
@@ -55,6 +82,34 @@ impl Store {
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// store the definitions and calls extracted from one chunk, pointing
+    /// them at the stored file and chunk. rows that fail to insert are
+    /// skipped so one bad extraction doesn't fail the whole file.
+    pub fn insert_chunk_call_graph(
+        &self,
+        file_id: i64,
+        chunk_id: i64,
+        definitions: &[Definition],
+        calls: &[FunctionCall],
+    ) {
+        for def in definitions {
+            let def = Definition {
+                file_id,
+                chunk_id,
+                ..def.clone()
+            };
+            let _ = self.insert_definition(&def);
+        }
+        for call in calls {
+            let call = FunctionCall {
+                file_id,
+                chunk_id,
+                ..call.clone()
+            };
+            let _ = self.insert_function_call(&call);
+        }
+    }
+
     pub fn delete_file_call_graph(&self, file_id: i64) -> Result<()> {
         self.conn
             .execute(
@@ -73,18 +128,21 @@ impl Store {
         Ok(())
     }
 
+    /// link unresolved calls to the definition they name, preferring one in
+    /// the calling file over the first elsewhere in the project
     pub fn resolve_calls_for_project(&self, project_id: i64) -> Result<u64> {
         let updated = self
             .conn
             .execute(
                 "UPDATE function_calls
-                 SET callee_definition_id = (
-                     SELECT d.id FROM definitions d
-                     JOIN files f ON d.file_id = f.id
-                     WHERE f.project_id = ?1
-                     AND d.name = function_calls.callee_name
-                     ORDER BY (d.file_id = function_calls.file_id) DESC, d.id ASC
-                     LIMIT 1
+                 SET callee_definition_id = COALESCE(
+                     (SELECT MIN(d.id) FROM definitions d
+                      WHERE d.file_id = function_calls.file_id
+                      AND d.name = function_calls.callee_name),
+                     (SELECT MIN(d.id) FROM definitions d
+                      JOIN files f ON d.file_id = f.id
+                      WHERE f.project_id = ?1
+                      AND d.name = function_calls.callee_name)
                  )
                  WHERE file_id IN (SELECT id FROM files WHERE project_id = ?1)
                  AND callee_definition_id IS NULL",
@@ -95,70 +153,40 @@ impl Store {
         Ok(updated as u64)
     }
 
+    /// definitions that call `function_name`, with file lines
     pub fn find_callers(
         &self,
         project_id: i64,
         function_name: &str,
     ) -> Result<Vec<CallGraphEntry>> {
-        let mut stmt = self
-            .conn
-            .prepare(
-                "SELECT DISTINCT d.name, d.kind, f.path, d.start_line, d.end_line, d.scope
-                 FROM function_calls fc
-                 JOIN definitions d ON fc.caller_name = d.name AND fc.file_id = d.file_id
-                 AND (fc.caller_scope IS NULL OR d.scope IS NULL OR fc.caller_scope = d.scope)
-                 JOIN files f ON d.file_id = f.id
-                 WHERE f.project_id = ?1 AND fc.callee_name = ?2
-                 ORDER BY f.path, d.start_line",
-            )
-            .map_err(|e| Error::Sqlite(e.to_string()))?;
-
-        let rows = stmt
-            .query_map(params![project_id, function_name], |row| {
-                Ok(CallGraphEntry {
-                    definition_name: row.get(0)?,
-                    definition_kind: row.get(1)?,
-                    file_path: row.get(2)?,
-                    start_line: row.get(3)?,
-                    end_line: row.get(4)?,
-                    scope: row.get(5)?,
-                })
-            })
-            .map_err(|e| Error::Sqlite(e.to_string()))?;
-
-        rows.collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| Error::Sqlite(e.to_string()))
+        self.call_graph_neighbours(CALLERS_SQL, project_id, function_name)
     }
 
+    /// definitions `function_name` calls, with file lines
     pub fn find_callees(
         &self,
         project_id: i64,
         function_name: &str,
+    ) -> Result<Vec<CallGraphEntry>> {
+        self.call_graph_neighbours(CALLEES_SQL, project_id, function_name)
+    }
+
+    fn call_graph_neighbours(
+        &self,
+        sql: &str,
+        project_id: i64,
+        function_name: &str,
     ) -> Result<Vec<CallGraphEntry>> {
         let mut stmt = self
             .conn
-            .prepare(
-                "SELECT DISTINCT d.name, d.kind, f.path, d.start_line, d.end_line, d.scope
-                 FROM function_calls fc
-                 JOIN definitions d ON fc.callee_name = d.name
-                 JOIN files f ON d.file_id = f.id
-                 WHERE f.project_id = ?1 AND fc.caller_name = ?2
-                 AND fc.file_id IN (SELECT id FROM files WHERE project_id = ?1)
-                 ORDER BY f.path, d.start_line",
-            )
+            .prepare(sql)
             .map_err(|e| Error::Sqlite(e.to_string()))?;
 
         let rows = stmt
-            .query_map(params![project_id, function_name], |row| {
-                Ok(CallGraphEntry {
-                    definition_name: row.get(0)?,
-                    definition_kind: row.get(1)?,
-                    file_path: row.get(2)?,
-                    start_line: row.get(3)?,
-                    end_line: row.get(4)?,
-                    scope: row.get(5)?,
-                })
-            })
+            .query_map(
+                params![project_id, function_name],
+                Self::map_call_graph_entry,
+            )
             .map_err(|e| Error::Sqlite(e.to_string()))?;
 
         rows.collect::<std::result::Result<Vec<_>, _>>()
@@ -189,42 +217,219 @@ impl Store {
         Ok((def_count as u64, call_count as u64))
     }
 
+    /// every definition in a project, optionally of one kind, in file order.
+    /// lines are offset from their chunk to file lines.
     pub fn list_definitions(
         &self,
         project_id: i64,
         kind_filter: Option<&str>,
     ) -> Result<Vec<CallGraphEntry>> {
-        let query = match kind_filter {
-            Some(_) => {
-                "SELECT d.name, d.kind, f.path, d.start_line, d.end_line, d.scope
-                 FROM definitions d JOIN files f ON d.file_id = f.id
-                 WHERE f.project_id = ?1 AND d.kind = ?2
-                 ORDER BY f.path, d.start_line"
-            }
-            None => {
-                "SELECT d.name, d.kind, f.path, d.start_line, d.end_line, d.scope
-                 FROM definitions d JOIN files f ON d.file_id = f.id
-                 WHERE f.project_id = ?1
-                 ORDER BY f.path, d.start_line"
-            }
-        };
+        self.definitions_page(project_id, kind_filter, 0, None)
+    }
 
+    /// one page of `list_definitions`: skip `offset` rows, then return at
+    /// most `limit` (all remaining when none)
+    pub fn definitions_page(
+        &self,
+        project_id: i64,
+        kind_filter: Option<&str>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<CallGraphEntry>> {
+        // sqlite treats a negative limit as no limit
+        let limit = limit.map_or(-1, |l| i64::try_from(l).unwrap_or(i64::MAX));
+        let offset = i64::try_from(offset).unwrap_or(i64::MAX);
         let mut stmt = self
             .conn
-            .prepare(query)
+            .prepare(
+                "SELECT d.name, d.kind, f.path,
+                        c.start_line + d.start_line - 1, c.start_line + d.end_line - 1, d.scope
+                 FROM definitions d
+                 JOIN chunks c ON d.chunk_id = c.id
+                 JOIN files f ON d.file_id = f.id
+                 WHERE f.project_id = ?1 AND (?2 IS NULL OR d.kind = ?2)
+                 ORDER BY f.path, c.start_line + d.start_line, d.id
+                 LIMIT ?3 OFFSET ?4",
+            )
             .map_err(|e| Error::Sqlite(e.to_string()))?;
 
-        let rows = if let Some(kind) = kind_filter {
-            stmt.query_map(params![project_id, kind], Self::map_call_graph_entry)
-        } else {
-            stmt.query_map(params![project_id], Self::map_call_graph_entry)
-        }
-        .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(
+                params![project_id, kind_filter, limit, offset],
+                Self::map_call_graph_entry,
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
 
         rows.collect::<std::result::Result<Vec<_>, _>>()
             .map_err(|e| Error::Sqlite(e.to_string()))
     }
 
+    /// how many definitions `list_definitions` would return
+    pub fn count_definitions(&self, project_id: i64, kind_filter: Option<&str>) -> Result<u64> {
+        let count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM definitions d
+                 JOIN chunks c ON d.chunk_id = c.id
+                 JOIN files f ON d.file_id = f.id
+                 WHERE f.project_id = ?1 AND (?2 IS NULL OR d.kind = ?2)",
+                params![project_id, kind_filter],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(count as u64)
+    }
+
+    /// functions and methods ordered by line span, largest first. definition
+    /// lines are relative to their chunk, so they're offset to file lines here.
+    pub fn top_symbols_by_size(
+        &self,
+        project_id: i64,
+        limit: usize,
+    ) -> Result<Vec<CallGraphEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT d.name, d.kind, f.path,
+                        c.start_line + d.start_line - 1, c.start_line + d.end_line - 1, d.scope
+                 FROM definitions d
+                 JOIN chunks c ON d.chunk_id = c.id
+                 JOIN files f ON d.file_id = f.id
+                 WHERE f.project_id = ?1 AND d.kind IN ('function', 'method')
+                 ORDER BY d.end_line - d.start_line DESC, f.path, d.start_line
+                 LIMIT ?2",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(
+                params![project_id, limit as i64],
+                Self::map_call_graph_entry,
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// definitions named `name`, optionally within `scope`, with their source
+    /// taken from the chunk they were extracted from.
+    pub fn find_definition_bodies(
+        &self,
+        project_id: i64,
+        name: &str,
+        scope: Option<&str>,
+    ) -> Result<Vec<DefinitionBody>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT d.name, d.kind, f.path,
+                        c.start_line + d.start_line - 1, c.start_line + d.end_line - 1, d.scope,
+                        d.signature, c.content, d.start_line, d.end_line
+                 FROM definitions d
+                 JOIN chunks c ON d.chunk_id = c.id
+                 JOIN files f ON d.file_id = f.id
+                 WHERE f.project_id = ?1 AND d.name = ?2 AND (?3 IS NULL OR d.scope = ?3)
+                 ORDER BY f.path, d.start_line",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(params![project_id, name, scope], |row| {
+                let content: String = row.get(7)?;
+                let (docstring, body) = split_definition(&content, row.get(8)?, row.get(9)?);
+                Ok(DefinitionBody {
+                    entry: Self::map_call_graph_entry(row)?,
+                    signature: row.get(6)?,
+                    docstring,
+                    body,
+                })
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// everything that transitively calls `function_name`, walking callers
+    /// breadth-first up to `max_depth` levels. each name is expanded once so
+    /// recursive and mutually recursive functions terminate.
+    pub fn find_callers_transitive(
+        &self,
+        project_id: i64,
+        function_name: &str,
+        max_depth: usize,
+    ) -> Result<ImpactSet> {
+        self.walk_call_graph(CALLERS_SQL, project_id, function_name, max_depth)
+    }
+
+    /// everything `function_name` transitively calls, the mirror of
+    /// `find_callers_transitive`
+    pub fn find_callees_transitive(
+        &self,
+        project_id: i64,
+        function_name: &str,
+        max_depth: usize,
+    ) -> Result<ImpactSet> {
+        self.walk_call_graph(CALLEES_SQL, project_id, function_name, max_depth)
+    }
+
+    /// breadth-first walk of call edges; `sql` maps a name (?2) to its
+    /// neighbours in one direction
+    fn walk_call_graph(
+        &self,
+        sql: &str,
+        project_id: i64,
+        function_name: &str,
+        max_depth: usize,
+    ) -> Result<ImpactSet> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        let mut result = ImpactSet::default();
+        let mut expanded: HashSet<String> = HashSet::from([function_name.to_string()]);
+        let mut seen: HashSet<(String, String, u32)> = HashSet::new();
+        let mut frontier = vec![function_name.to_string()];
+
+        for depth in 1..=max_depth {
+            let mut next = Vec::new();
+            for name in &frontier {
+                let neighbours = stmt
+                    .query_map(params![project_id, name], Self::map_call_graph_entry)
+                    .map_err(|e| Error::Sqlite(e.to_string()))?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+                for entry in neighbours {
+                    let key = (
+                        entry.definition_name.clone(),
+                        entry.file_path.clone(),
+                        entry.start_line,
+                    );
+                    if !seen.insert(key) {
+                        continue;
+                    }
+                    if result.entries.len() >= IMPACT_MAX_ENTRIES {
+                        result.truncated = true;
+                        return Ok(result);
+                    }
+                    if expanded.insert(entry.definition_name.clone()) {
+                        next.push(entry.definition_name.clone());
+                    }
+                    result.entries.push(ImpactEntry { depth, entry });
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        Ok(result)
+    }
+
     fn map_call_graph_entry(row: &rusqlite::Row) -> rusqlite::Result<CallGraphEntry> {
         Ok(CallGraphEntry {
             definition_name: row.get(0)?,
@@ -236,3 +441,465 @@ impl Store {
         })
     }
 }
+
+/// cut lines `start..=end` (1-based, chunk-relative) out of a chunk, along
+/// with the comment block directly above them or a python docstring.
+fn split_definition(content: &str, start_line: u32, end_line: u32) -> (Option<String>, String) {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = (start_line.max(1) as usize - 1).min(lines.len());
+    let end = (end_line as usize).clamp(start, lines.len());
+    let body = &lines[start..end];
+
+    let mut doc_start = start;
+    while doc_start > 0 && is_comment_line(lines[doc_start - 1]) {
+        doc_start -= 1;
+    }
+    let docstring = if doc_start < start {
+        Some(lines[doc_start..start].join("\n"))
+    } else {
+        python_docstring(body)
+    };
+    (docstring, body.join("\n"))
+}
+
+fn is_comment_line(line: &str) -> bool {
+    let t = line.trim_start();
+    t.starts_with("//")
+        || t.starts_with("/*")
+        || t.starts_with('*')
+        || t.starts_with("# ")
+        || t == "#"
+}
+
+/// a triple-quoted string opening the body, right after the `def` line
+fn python_docstring(body: &[&str]) -> Option<String> {
+    let first = body.get(1)?.trim();
+    let quote = ["\"\"\"", "'''"]
+        .into_iter()
+        .find(|q| first.starts_with(q))?;
+    for (i, line) in body[1..].iter().enumerate() {
+        let rest = if i == 0 { &first[3..] } else { line.trim() };
+        if rest.contains(quote) {
+            let doc: Vec<&str> = body[1..=i + 1].iter().map(|l| l.trim()).collect();
+            return Some(doc.join("\n"));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::store::tests::test_store;
+    use crate::index::store::{ImpactSet, Store};
+    use srag_common::types::{
+        CallGraphEntry, Chunk, Definition, FileRecord, FunctionCall, Language,
+    };
+
+    fn test_file(store: &Store) -> (i64, i64) {
+        let project_id = store.upsert_project("proj", "/tmp/proj").unwrap();
+        let file_id = store
+            .upsert_file(&FileRecord {
+                id: None,
+                project_id,
+                path: "/tmp/proj/src/lib.rs".to_string(),
+                blake3_hash: "h".to_string(),
+                language: Language::Rust,
+                size_bytes: 0,
+                chunk_count: 0,
+                indexed_at: String::new(),
+            })
+            .unwrap();
+        (project_id, file_id)
+    }
+
+    fn insert_call(store: &Store, chunk_id: i64, file_id: i64, caller: &str, callee: &str) {
+        store
+            .insert_function_call(&FunctionCall {
+                id: None,
+                chunk_id,
+                file_id,
+                caller_name: Some(caller.to_string()),
+                caller_scope: None,
+                callee_name: callee.to_string(),
+                line_number: 1,
+                language: Language::Rust,
+                callee_definition_id: None,
+            })
+            .unwrap();
+    }
+
+    fn impact_names(
+        store: &Store,
+        project_id: i64,
+        name: &str,
+        depth: usize,
+    ) -> Vec<(usize, String)> {
+        store
+            .find_callers_transitive(project_id, name, depth)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| (e.depth, e.entry.definition_name))
+            .collect()
+    }
+
+    fn insert_function(store: &Store, file_id: i64, name: &str, start: u32, lines: u32) -> i64 {
+        let chunk = Chunk {
+            id: None,
+            file_id,
+            content: format!("fn {}() {{}}", name),
+            symbol: Some(name.to_string()),
+            symbol_kind: Some("function_item".to_string()),
+            start_line: start,
+            end_line: start + lines - 1,
+            language: Language::Rust,
+            suspicious: false,
+        };
+        let chunk_id = store.insert_chunk(&chunk, None).unwrap();
+        store
+            .insert_definition(&Definition {
+                id: None,
+                chunk_id,
+                file_id,
+                name: name.to_string(),
+                kind: "function".to_string(),
+                scope: None,
+                language: Language::Rust,
+                start_line: 1,
+                end_line: lines,
+                signature: None,
+            })
+            .unwrap();
+        chunk_id
+    }
+
+    #[test]
+    fn test_direct_and_transitive_lines_agree() {
+        let (store, _dir) = test_store();
+        let (project_id, file_id) = test_file(&store);
+        insert_function(&store, file_id, "leaf", 40, 5);
+        let middle = insert_function(&store, file_id, "middle", 20, 5);
+        insert_call(&store, middle, file_id, "middle", "leaf");
+        let top = insert_function(&store, file_id, "top", 60, 5);
+        insert_call(&store, top, file_id, "top", "middle");
+
+        let lines = |entries: Vec<CallGraphEntry>| -> Vec<(String, u32, u32)> {
+            entries
+                .into_iter()
+                .map(|e| (e.definition_name, e.start_line, e.end_line))
+                .collect()
+        };
+        let transitive = |set: ImpactSet, depth: usize| {
+            lines(
+                set.entries
+                    .into_iter()
+                    .filter(|e| e.depth == depth)
+                    .map(|e| e.entry)
+                    .collect(),
+            )
+        };
+
+        let callers = lines(store.find_callers(project_id, "leaf").unwrap());
+        assert_eq!(callers, vec![("middle".to_string(), 20, 24)]);
+        let walked = store
+            .find_callers_transitive(project_id, "leaf", 2)
+            .unwrap();
+        assert_eq!(transitive(walked, 1), callers);
+
+        let callees = lines(store.find_callees(project_id, "top").unwrap());
+        assert_eq!(callees, vec![("middle".to_string(), 20, 24)]);
+        let walked = store.find_callees_transitive(project_id, "top", 2).unwrap();
+        assert_eq!(transitive(walked, 2), vec![("leaf".to_string(), 40, 44)]);
+        let walked = store.find_callees_transitive(project_id, "top", 2).unwrap();
+        assert_eq!(transitive(walked, 1), callees);
+    }
+
+    #[test]
+    fn test_top_symbols_by_size() {
+        let (store, _dir) = test_store();
+        let project_id = store.upsert_project("proj", "/tmp/proj").unwrap();
+        let file_id = store
+            .upsert_file(&FileRecord {
+                id: None,
+                project_id,
+                path: "/tmp/proj/src/lib.rs".to_string(),
+                blake3_hash: "h".to_string(),
+                language: Language::Rust,
+                size_bytes: 0,
+                chunk_count: 2,
+                indexed_at: String::new(),
+            })
+            .unwrap();
+        insert_function(&store, file_id, "small", 1, 10);
+        insert_function(&store, file_id, "huge", 20, 100);
+
+        let top = store.top_symbols_by_size(project_id, 10).unwrap();
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].definition_name, "huge");
+        assert_eq!(top[0].start_line, 20);
+        assert_eq!(top[0].end_line, 119);
+        assert_eq!(top[1].definition_name, "small");
+
+        let limited = store.top_symbols_by_size(project_id, 1).unwrap();
+        assert_eq!(limited.len(), 1);
+        assert_eq!(limited[0].definition_name, "huge");
+    }
+
+    #[test]
+    fn test_resolve_calls_prefers_same_file() {
+        let (store, _dir) = test_store();
+        let (project_id, first) = test_file(&store);
+        let second = store
+            .upsert_file(&FileRecord {
+                id: None,
+                project_id,
+                path: "/tmp/proj/src/other.rs".to_string(),
+                blake3_hash: "h2".to_string(),
+                language: Language::Rust,
+                size_bytes: 0,
+                chunk_count: 0,
+                indexed_at: String::new(),
+            })
+            .unwrap();
+        insert_function(&store, first, "helper", 1, 3);
+        insert_function(&store, second, "helper", 1, 3);
+        let caller = insert_function(&store, second, "main", 10, 3);
+        insert_call(&store, caller, second, "main", "helper");
+
+        store.resolve_calls_for_project(project_id).unwrap();
+        let file: i64 = store
+            .conn
+            .query_row(
+                "SELECT d.file_id FROM function_calls fc
+                 JOIN definitions d ON fc.callee_definition_id = d.id",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(file, second);
+    }
+
+    #[test]
+    fn test_list_definitions_by_kind() {
+        let (store, _dir) = test_store();
+        let (project_id, file_id) = test_file(&store);
+        insert_function(&store, file_id, "helper", 30, 3);
+        let chunk_id = insert_function(&store, file_id, "run", 10, 5);
+        store
+            .insert_definition(&Definition {
+                id: None,
+                chunk_id,
+                file_id,
+                name: "Runner".to_string(),
+                kind: "struct".to_string(),
+                scope: None,
+                language: Language::Rust,
+                start_line: 2,
+                end_line: 4,
+                signature: None,
+            })
+            .unwrap();
+
+        let all = store.list_definitions(project_id, None).unwrap();
+        let names: Vec<&str> = all.iter().map(|e| e.definition_name.as_str()).collect();
+        assert_eq!(names, ["run", "Runner", "helper"]);
+        // lines are file lines, not relative to the chunk
+        assert_eq!((all[1].start_line, all[1].end_line), (11, 13));
+
+        let structs = store.list_definitions(project_id, Some("struct")).unwrap();
+        assert_eq!(structs.len(), 1);
+        assert_eq!(structs[0].definition_name, "Runner");
+        assert_eq!(structs[0].definition_kind, "struct");
+        assert_eq!(
+            store
+                .list_definitions(project_id, Some("function"))
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(store
+            .list_definitions(project_id, Some("trait"))
+            .unwrap()
+            .is_empty());
+
+        // paging happens in sql and walks the same order
+        assert_eq!(store.count_definitions(project_id, None).unwrap(), 3);
+        assert_eq!(
+            store.count_definitions(project_id, Some("struct")).unwrap(),
+            1
+        );
+        let page = store
+            .definitions_page(project_id, None, 1, Some(1))
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].definition_name, "Runner");
+        let rest = store.definitions_page(project_id, None, 1, None).unwrap();
+        let names: Vec<&str> = rest.iter().map(|e| e.definition_name.as_str()).collect();
+        assert_eq!(names, ["Runner", "helper"]);
+    }
+
+    #[test]
+    fn test_impact_set_walks_callers_transitively() {
+        let (store, _dir) = test_store();
+        let (project_id, file_id) = test_file(&store);
+        insert_function(&store, file_id, "a", 1, 3);
+        let b = insert_function(&store, file_id, "b", 10, 3);
+        let c = insert_function(&store, file_id, "c", 20, 3);
+        insert_call(&store, b, file_id, "b", "a");
+        insert_call(&store, c, file_id, "c", "b");
+
+        assert_eq!(
+            impact_names(&store, project_id, "a", 5),
+            vec![(1, "b".to_string()), (2, "c".to_string())]
+        );
+        assert_eq!(
+            impact_names(&store, project_id, "a", 1),
+            vec![(1, "b".to_string())]
+        );
+        assert!(impact_names(&store, project_id, "c", 5).is_empty());
+
+        let set = store.find_callers_transitive(project_id, "a", 5).unwrap();
+        assert!(!set.truncated);
+        assert_eq!(set.entries[1].entry.start_line, 20);
+    }
+
+    #[test]
+    fn test_transitive_callers_and_callees() {
+        let (store, _dir) = test_store();
+        let (project_id, file_id) = test_file(&store);
+        let a = insert_function(&store, file_id, "a", 1, 3);
+        let b = insert_function(&store, file_id, "b", 10, 3);
+        insert_function(&store, file_id, "c", 20, 3);
+        insert_call(&store, a, file_id, "a", "b");
+        insert_call(&store, b, file_id, "b", "c");
+
+        let names = |set: ImpactSet| {
+            set.entries
+                .into_iter()
+                .map(|e| (e.depth, e.entry.definition_name))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(store.find_callers_transitive(project_id, "c", 2).unwrap()),
+            vec![(1, "b".to_string()), (2, "a".to_string())]
+        );
+        assert_eq!(
+            names(store.find_callers_transitive(project_id, "c", 1).unwrap()),
+            vec![(1, "b".to_string())]
+        );
+        assert_eq!(
+            names(store.find_callees_transitive(project_id, "a", 2).unwrap()),
+            vec![(1, "b".to_string()), (2, "c".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_impact_set_stops_on_cycles() {
+        let (store, _dir) = test_store();
+        let (project_id, file_id) = test_file(&store);
+        let ping = insert_function(&store, file_id, "ping", 1, 3);
+        let pong = insert_function(&store, file_id, "pong", 10, 3);
+        insert_call(&store, ping, file_id, "ping", "pong");
+        insert_call(&store, pong, file_id, "pong", "ping");
+        insert_call(&store, ping, file_id, "ping", "ping");
+
+        let names = impact_names(&store, project_id, "ping", 50);
+        assert_eq!(
+            names,
+            vec![(1, "ping".to_string()), (1, "pong".to_string())]
+        );
+    }
+
+    fn insert_method(store: &Store, file_id: i64, scope: &str, content: &str, start: u32) {
+        let chunk_id = store
+            .insert_chunk(
+                &Chunk {
+                    id: None,
+                    file_id,
+                    content: content.to_string(),
+                    symbol: Some(scope.to_string()),
+                    symbol_kind: Some("impl_item".to_string()),
+                    start_line: start,
+                    end_line: start + content.lines().count() as u32 - 1,
+                    language: Language::Rust,
+                    suspicious: false,
+                },
+                None,
+            )
+            .unwrap();
+        store
+            .insert_definition(&Definition {
+                id: None,
+                chunk_id,
+                file_id,
+                name: "parse_line".to_string(),
+                kind: "function".to_string(),
+                scope: Some(scope.to_string()),
+                language: Language::Rust,
+                start_line: 3,
+                end_line: 5,
+                signature: Some("pub fn parse_line(&self, line: &str) -> Option<Token>".into()),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_find_definition_bodies() {
+        let (store, _dir) = test_store();
+        let (project_id, file_id) = test_file(&store);
+        insert_method(
+            &store,
+            file_id,
+            "Parser",
+            "impl Parser {\n    /// parse one line\n    pub fn parse_line(&self, line: &str) -> Option<Token> {\n        self.lex(line)\n    }\n}",
+            10,
+        );
+        insert_method(
+            &store,
+            file_id,
+            "Lexer",
+            "impl Lexer {\n\n    pub fn parse_line(&self, line: &str) -> Option<Token> {\n        None\n    }\n}",
+            40,
+        );
+
+        let all = store
+            .find_definition_bodies(project_id, "parse_line", None)
+            .unwrap();
+        assert_eq!(all.len(), 2);
+
+        let found = store
+            .find_definition_bodies(project_id, "parse_line", Some("Parser"))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        let def = &found[0];
+        assert_eq!((def.entry.start_line, def.entry.end_line), (12, 14));
+        assert_eq!(
+            def.signature.as_deref(),
+            Some("pub fn parse_line(&self, line: &str) -> Option<Token>")
+        );
+        assert_eq!(def.docstring.as_deref(), Some("    /// parse one line"));
+        assert!(def.body.starts_with("    pub fn parse_line"));
+        assert!(def.body.contains("self.lex(line)"));
+        assert!(!def.body.contains("impl Parser"));
+
+        let lexer = store
+            .find_definition_bodies(project_id, "parse_line", Some("Lexer"))
+            .unwrap();
+        assert_eq!(lexer[0].docstring, None);
+    }
+
+    #[test]
+    fn test_split_definition_python_docstring() {
+        let content = "def load(path):\n    \"\"\"read a config file.\n\n    returns a dict.\n    \"\"\"\n    return {}\n";
+        let (doc, body) = super::split_definition(content, 1, 6);
+        assert_eq!(
+            doc.as_deref(),
+            Some("\"\"\"read a config file.\n\nreturns a dict.\n\"\"\"")
+        );
+        assert!(body.ends_with("return {}"));
+
+        let (doc, _) =
+            super::split_definition("def f():\n    \"\"\"one line.\"\"\"\n    pass", 1, 3);
+        assert_eq!(doc.as_deref(), Some("\"\"\"one line.\"\"\""));
+    }
+}
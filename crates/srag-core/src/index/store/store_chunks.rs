@@ -9,10 +9,12 @@ use super::Store;
 
 impl Store {
     pub fn delete_file_chunks(&self, file_id: i64) -> Result<Vec<i64>> {
+        // duplicates borrow their original's embedding rather than owning one
         let mut stmt = self
             .conn
             .prepare(
-                "SELECT embedding_id FROM chunks WHERE file_id = ?1 AND embedding_id IS NOT NULL",
+                "SELECT embedding_id FROM chunks
+                 WHERE file_id = ?1 AND embedding_id IS NOT NULL AND dup_of IS NULL",
             )
             .map_err(|e| Error::Sqlite(e.to_string()))?;
         let ids: Vec<i64> = stmt
@@ -53,13 +55,215 @@ impl Store {
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// record a chunk's content hash and, if a byte-identical chunk already
+    /// exists in the project, mark this one as its duplicate. a duplicate
+    /// shares the original's embedding, so true means there is nothing to
+    /// embed.
+    pub fn link_duplicate_chunk(
+        &self,
+        project_id: i64,
+        chunk_id: i64,
+        content: &str,
+    ) -> Result<bool> {
+        let hash = blake3::hash(content.as_bytes()).to_hex().to_string();
+        self.conn
+            .execute(
+                "UPDATE chunks SET content_hash = ?1 WHERE id = ?2",
+                params![hash, chunk_id],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        let original: Option<(i64, Option<i64>)> = self
+            .conn
+            .query_row(
+                "SELECT c.id, c.embedding_id FROM chunks c JOIN files f ON c.file_id = f.id
+                 WHERE f.project_id = ?1 AND c.content_hash = ?2 AND c.dup_of IS NULL
+                   AND c.id != ?3
+                 ORDER BY c.id LIMIT 1",
+                params![project_id, hash, chunk_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let Some((original_id, embedding_id)) = original else {
+            return Ok(false);
+        };
+
+        // an original still waiting to be embedded passes its id on later,
+        // in update_chunk_embedding_id
+        self.conn
+            .execute(
+                "UPDATE chunks SET dup_of = ?1, embedding_id = ?2 WHERE id = ?3",
+                params![original_id, embedding_id, chunk_id],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(true)
+    }
+
+    /// before a file's chunks go, hand each of its originals that has
+    /// duplicates in other files over to the first of them, embedding
+    /// included, so the copies stay searchable.
+    pub(super) fn promote_file_duplicates(&self, file_id: i64) -> Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT c.id, MIN(d.id) FROM chunks c JOIN chunks d ON d.dup_of = c.id
+                 WHERE c.file_id = ?1 AND c.dup_of IS NULL AND d.file_id != ?1
+                 GROUP BY c.id",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let handovers: Vec<(i64, i64)> = stmt
+            .query_map(params![file_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| Error::Sqlite(e.to_string()))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        for (original, heir) in handovers {
+            // an heir can't hold a row of its own (chunk_id is unique), but
+            // one left by an older reembed would otherwise block the move
+            let steps: [(&str, Vec<i64>); 5] = [
+                ("UPDATE chunks SET dup_of = NULL WHERE id = ?1", vec![heir]),
+                ("DELETE FROM embeddings WHERE chunk_id = ?1", vec![heir]),
+                (
+                    "UPDATE chunks SET dup_of = ?1 WHERE dup_of = ?2 AND file_id != ?3",
+                    vec![heir, original, file_id],
+                ),
+                (
+                    "UPDATE embeddings SET chunk_id = ?1 WHERE chunk_id = ?2",
+                    vec![heir, original],
+                ),
+                (
+                    "UPDATE chunks SET embedding_id = NULL WHERE id = ?1",
+                    vec![original],
+                ),
+            ];
+            for (sql, values) in steps {
+                self.conn
+                    .execute(sql, rusqlite::params_from_iter(values))
+                    .map_err(|e| Error::Sqlite(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// every chunk in a project that owns its embedding, with its file path,
+    /// ordered by chunk id. duplicates are left out.
+    pub fn list_project_chunks(&self, project_id: i64) -> Result<Vec<(Chunk, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT c.id, c.file_id, c.content, c.symbol, c.symbol_kind,
+                        c.start_line, c.end_line, c.language, f.path, c.suspicious
+                 FROM chunks c JOIN files f ON c.file_id = f.id
+                 WHERE f.project_id = ?1 AND c.dup_of IS NULL
+                 ORDER BY c.id",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![project_id], |row| {
+                let lang_str: String = row.get(7)?;
+                let language: Language =
+                    serde_json::from_value(serde_json::Value::String(lang_str))
+                        .unwrap_or(Language::Unknown);
+                let suspicious: i32 = row.get::<_, Option<i32>>(9)?.unwrap_or(0);
+                Ok((
+                    Chunk {
+                        id: Some(row.get(0)?),
+                        file_id: row.get(1)?,
+                        content: row.get(2)?,
+                        symbol: row.get(3)?,
+                        symbol_kind: row.get(4)?,
+                        start_line: row.get(5)?,
+                        end_line: row.get(6)?,
+                        language,
+                        suspicious: suspicious != 0,
+                    },
+                    row.get::<_, String>(8)?,
+                ))
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let mut chunks = Vec::new();
+        for row in rows {
+            chunks.push(row.map_err(|e| Error::Sqlite(e.to_string()))?);
+        }
+        Ok(chunks)
+    }
+
+    /// ids and file paths of a project's chunks named `symbol`, in path and
+    /// line order
+    pub fn find_symbol_chunks(&self, project_id: i64, symbol: &str) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT c.id, f.path FROM chunks c JOIN files f ON c.file_id = f.id
+                 WHERE f.project_id = ?1 AND c.symbol = ?2
+                 ORDER BY f.path, c.start_line",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![project_id, symbol], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// up to `limit` chunks that own their embedding, with their file paths,
+    /// in chunk id order starting after `after_id`, optionally limited to one
+    /// project. duplicates are left out. pass the last id seen to walk a
+    /// project a page at a time without holding a statement open.
+    pub fn chunks_after(
+        &self,
+        project_id: Option<i64>,
+        after_id: i64,
+        limit: usize,
+    ) -> Result<Vec<(Chunk, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "{} WHERE (?1 IS NULL OR f.project_id = ?1) AND c.dup_of IS NULL AND c.id > ?2
+                 ORDER BY c.id LIMIT ?3",
+                super::store_embeddings::CHUNK_WITH_PATH_SELECT
+            ))
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(
+                params![project_id, after_id, limit as i64],
+                super::store_embeddings::map_chunk_with_path,
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// chunks the injection scanner flagged, with their file paths, in file
+    /// and line order
+    pub fn list_suspicious_chunks(&self, project_id: i64) -> Result<Vec<(Chunk, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "{} WHERE f.project_id = ?1 AND c.suspicious = 1 ORDER BY f.path, c.start_line",
+                super::store_embeddings::CHUNK_WITH_PATH_SELECT
+            ))
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(
+                params![project_id],
+                super::store_embeddings::map_chunk_with_path,
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
     pub fn get_chunk_by_embedding_id(&self, embedding_id: i64) -> Result<Option<(Chunk, String)>> {
         self.conn
             .query_row(
                 "SELECT c.id, c.file_id, c.content, c.symbol, c.symbol_kind,
                         c.start_line, c.end_line, c.language, f.path, c.suspicious
                  FROM chunks c JOIN files f ON c.file_id = f.id
-                 WHERE c.embedding_id = ?1",
+                 WHERE c.embedding_id = ?1 AND c.dup_of IS NULL",
                 params![embedding_id],
                 |row| {
                     let lang_str: String = row.get(7)?;
@@ -87,3 +291,49 @@ impl Store {
             .map_err(|e| Error::Sqlite(e.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use crate::index::store::Store;
+    use srag_common::types::Language;
+
+    fn add_file(store: &Store, project_id: i64, path: &str, chunks: &[&str]) {
+        let file_id = seed_file(store, project_id, path, Language::Rust);
+        for (i, content) in chunks.iter().enumerate() {
+            seed_chunk(store, &test_chunk(file_id, content, i as u32 + 1), path);
+        }
+    }
+
+    #[test]
+    fn test_chunks_after_pages_through_a_project() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        let other = store.upsert_project("other", "/tmp/other").unwrap();
+        add_file(&store, pid, "/tmp/proj/a.rs", &["fn a1() {}", "fn a2() {}"]);
+        add_file(&store, other, "/tmp/other/x.rs", &["fn x() {}"]);
+        add_file(&store, pid, "/tmp/proj/b.rs", &["fn b() {}"]);
+
+        let mut seen = Vec::new();
+        let mut after = 0;
+        loop {
+            let page = store.chunks_after(Some(pid), after, 2).unwrap();
+            if page.is_empty() {
+                break;
+            }
+            assert!(page.len() <= 2);
+            after = page.last().unwrap().0.id.unwrap();
+            seen.extend(page.into_iter().map(|(chunk, path)| (path, chunk.content)));
+        }
+        assert_eq!(
+            seen,
+            vec![
+                ("/tmp/proj/a.rs".to_string(), "fn a1() {}".to_string()),
+                ("/tmp/proj/a.rs".to_string(), "fn a2() {}".to_string()),
+                ("/tmp/proj/b.rs".to_string(), "fn b() {}".to_string()),
+            ]
+        );
+
+        assert_eq!(store.chunks_after(None, 0, 10).unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::collections::HashSet;
+
+use rusqlite::params;
+use srag_common::{Error, Result};
+
+use super::{CommitRecord, Store};
+
+impl Store {
+    /// hashes of the commits already indexed for a project
+    pub fn commit_hashes(&self, project_id: i64) -> Result<HashSet<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT hash FROM commits WHERE project_id = ?1")
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![project_id], |row| row.get(0))
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<HashSet<String>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// record a commit whose hunks live in the chunks of `file_id`
+    pub fn insert_commit(
+        &self,
+        project_id: i64,
+        file_id: i64,
+        commit: &CommitRecord,
+    ) -> Result<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO commits (project_id, file_id, hash, author, committed_at, summary)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(project_id, hash) DO UPDATE SET file_id = ?2",
+                params![
+                    project_id,
+                    file_id,
+                    commit.hash,
+                    commit.author,
+                    commit.committed_at,
+                    commit.summary
+                ],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        self.conn
+            .query_row(
+                "SELECT id FROM commits WHERE project_id = ?1 AND hash = ?2",
+                params![project_id, commit.hash],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::store::tests::{seed_file, test_store};
+    use crate::index::store::CommitRecord;
+    use srag_common::types::Language;
+
+    #[test]
+    fn test_commits_follow_their_file() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        let file_id = seed_file(&store, pid, "git:abc123", Language::Unknown);
+        let commit = CommitRecord {
+            hash: "abc123".into(),
+            author: "dev".into(),
+            committed_at: 1_700_000_000,
+            summary: "add parser".into(),
+        };
+        let id = store.insert_commit(pid, file_id, &commit).unwrap();
+        assert_eq!(store.insert_commit(pid, file_id, &commit).unwrap(), id);
+        assert!(store.commit_hashes(pid).unwrap().contains("abc123"));
+
+        store.delete_file(pid, "git:abc123").unwrap();
+        assert!(store.commit_hashes(pid).unwrap().is_empty());
+    }
+}
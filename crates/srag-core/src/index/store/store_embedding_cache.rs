@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::collections::HashMap;
+
+use rusqlite::params;
+use srag_common::{Error, Result};
+
+use super::store_embeddings::{decode_vector, encode_vector};
+use super::Store;
+
+impl Store {
+    /// cached vectors for whichever of `keys` are present, marking them used
+    pub fn cached_embeddings(
+        &self,
+        keys: &[String],
+        dim: usize,
+    ) -> Result<HashMap<String, Vec<f32>>> {
+        let mut found = HashMap::new();
+        let mut select = self
+            .conn
+            .prepare_cached("SELECT vector FROM embedding_cache WHERE key = ?1")
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        for key in keys {
+            if found.contains_key(key) {
+                continue;
+            }
+            let blob: Option<Vec<u8>> = match select.query_row(params![key], |row| row.get(0)) {
+                Ok(blob) => Some(blob),
+                Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                Err(e) => return Err(Error::Sqlite(e.to_string())),
+            };
+            if let Some(blob) = blob {
+                found.insert(key.clone(), decode_vector(&blob, dim)?);
+            }
+        }
+        if !found.is_empty() {
+            let mut touch = self
+                .conn
+                .prepare_cached("UPDATE embedding_cache SET last_used = unixepoch() WHERE key = ?1")
+                .map_err(|e| Error::Sqlite(e.to_string()))?;
+            for key in found.keys() {
+                touch
+                    .execute(params![key])
+                    .map_err(|e| Error::Sqlite(e.to_string()))?;
+            }
+        }
+        Ok(found)
+    }
+
+    /// remember freshly embedded vectors under their keys
+    pub fn cache_embeddings(&self, signature: &str, entries: &[(&str, &[f32])]) -> Result<()> {
+        let mut insert = self
+            .conn
+            .prepare_cached(
+                "INSERT INTO embedding_cache (key, signature, vector, last_used)
+                 VALUES (?1, ?2, ?3, unixepoch())
+                 ON CONFLICT(key) DO UPDATE SET vector = ?3, last_used = unixepoch()",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        for (key, vector) in entries {
+            insert
+                .execute(params![key, signature, encode_vector(vector)])
+                .map_err(|e| Error::Sqlite(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// drop the least recently used entries beyond `max_entries`. returns
+    /// how many were removed.
+    pub fn trim_embedding_cache(&self, max_entries: usize) -> Result<u64> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM embedding_cache WHERE key NOT IN (
+                     SELECT key FROM embedding_cache
+                     ORDER BY last_used DESC, rowid DESC
+                     LIMIT ?1
+                 )",
+                params![max_entries as i64],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(removed as u64)
+    }
+
+    /// drop entries embedded by a model other than `signature`, which can
+    /// never be hit again
+    pub fn prune_embedding_cache(&self, signature: &str) -> Result<u64> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM embedding_cache WHERE signature != ?1",
+                params![signature],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(removed as u64)
+    }
+
+    pub fn embedding_cache_len(&self) -> Result<u64> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM embedding_cache", [], |row| row.get(0))
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::store::tests::test_store;
+
+    #[test]
+    fn test_cache_round_trip_and_trim() {
+        let (store, _dir) = test_store();
+        store
+            .cache_embeddings(
+                "model/3",
+                &[("a", &[1.0, 2.0, 3.0][..]), ("b", &[4.0, 5.0, 6.0][..])],
+            )
+            .unwrap();
+        store
+            .cache_embeddings("old/3", &[("c", &[0.0, 0.0, 1.0][..])])
+            .unwrap();
+
+        let keys = vec!["a".to_string(), "missing".to_string()];
+        let found = store.cached_embeddings(&keys, 3).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found["a"], vec![1.0, 2.0, 3.0]);
+
+        assert_eq!(store.prune_embedding_cache("model/3").unwrap(), 1);
+        assert_eq!(store.embedding_cache_len().unwrap(), 2);
+
+        // a cap of one keeps a single entry
+        assert_eq!(store.trim_embedding_cache(1).unwrap(), 1);
+        assert_eq!(store.embedding_cache_len().unwrap(), 1);
+        assert_eq!(store.trim_embedding_cache(10).unwrap(), 0);
+    }
+}
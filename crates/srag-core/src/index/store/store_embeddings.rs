@@ -5,9 +5,12 @@ use rusqlite::{params, OptionalExtension};
 use srag_common::types::{Chunk, Language};
 use srag_common::{Error, Result};
 
-use super::Store;
+use super::{FtsQueryMode, Store};
 
-fn encode_vector(vector: &[f32]) -> Vec<u8> {
+/// tokens of context fts5 keeps around a match in a snippet (fts5 caps this at 64)
+const SNIPPET_TOKENS: i64 = 24;
+
+pub(super) fn encode_vector(vector: &[f32]) -> Vec<u8> {
     let mut buf = Vec::with_capacity(vector.len() * 4);
     for &v in vector {
         buf.extend_from_slice(&v.to_le_bytes());
@@ -15,7 +18,7 @@ fn encode_vector(vector: &[f32]) -> Vec<u8> {
     buf
 }
 
-fn decode_vector(bytes: &[u8], dim: usize) -> Result<Vec<f32>> {
+pub(super) fn decode_vector(bytes: &[u8], dim: usize) -> Result<Vec<f32>> {
     let expected = dim * 4;
     if bytes.len() < expected {
         return Err(Error::Sqlite(format!(
@@ -38,6 +41,32 @@ fn decode_vector(bytes: &[u8], dim: usize) -> Result<Vec<f32>> {
     Ok(vec)
 }
 
+pub(super) const CHUNK_WITH_PATH_SELECT: &str =
+    "SELECT c.id, c.file_id, c.content, c.symbol, c.symbol_kind,
+            c.start_line, c.end_line, c.language, f.path, c.suspicious
+     FROM chunks c JOIN files f ON c.file_id = f.id";
+
+pub(super) fn map_chunk_with_path(row: &rusqlite::Row) -> rusqlite::Result<(Chunk, String)> {
+    let lang_str: String = row.get(7)?;
+    let language: Language =
+        serde_json::from_value(serde_json::Value::String(lang_str)).unwrap_or(Language::Unknown);
+    let suspicious: i32 = row.get::<_, Option<i32>>(9)?.unwrap_or(0);
+    Ok((
+        Chunk {
+            id: Some(row.get(0)?),
+            file_id: row.get(1)?,
+            content: row.get(2)?,
+            symbol: row.get(3)?,
+            symbol_kind: row.get(4)?,
+            start_line: row.get(5)?,
+            end_line: row.get(6)?,
+            language,
+            suspicious: suspicious != 0,
+        },
+        row.get::<_, String>(8)?,
+    ))
+}
+
 /// escape a user query for safe use in fts5 MATCH.
 /// wraps each whitespace-delimited term in double quotes to disable
 /// fts5 operators (AND, OR, NOT, NEAR, column filters).
@@ -53,6 +82,33 @@ fn escape_fts5_query(query: &str) -> String {
         .join(" ")
 }
 
+/// the MATCH expression for `query` in `mode`. empty when there is nothing
+/// to search for.
+fn fts5_match_expr(query: &str, mode: FtsQueryMode) -> String {
+    match mode {
+        FtsQueryMode::Terms => escape_fts5_query(query),
+        FtsQueryMode::Phrase => {
+            let words: Vec<&str> = query.split_whitespace().collect();
+            if words.is_empty() {
+                String::new()
+            } else {
+                format!("\"{}\"", words.join(" ").replace('"', "\"\""))
+            }
+        }
+        FtsQueryMode::Raw => query.trim().to_string(),
+    }
+}
+
+/// raw queries are user-written fts5 syntax, so a failure is most likely a
+/// typo in it rather than a broken database
+fn fts_error(mode: FtsQueryMode, e: rusqlite::Error) -> Error {
+    if mode == FtsQueryMode::Raw {
+        Error::Query(format!("invalid full-text query: {}", e))
+    } else {
+        Error::Sqlite(e.to_string())
+    }
+}
+
 impl Store {
     pub fn insert_embedding(&self, chunk_id: i64, vector: &[f32]) -> Result<i64> {
         let blob = encode_vector(vector);
@@ -75,10 +131,11 @@ impl Store {
         Ok(id)
     }
 
+    /// also points the chunk's duplicates at the embedding
     pub fn update_chunk_embedding_id(&self, chunk_id: i64, embedding_id: i64) -> Result<()> {
         self.conn
             .execute(
-                "UPDATE chunks SET embedding_id = ?1 WHERE id = ?2",
+                "UPDATE chunks SET embedding_id = ?1 WHERE id = ?2 OR dup_of = ?2",
                 params![embedding_id, chunk_id],
             )
             .map_err(|e| Error::Sqlite(e.to_string()))?;
@@ -114,6 +171,64 @@ impl Store {
         Ok(count)
     }
 
+    /// replace the vectors of existing chunks after an embedding model change.
+    /// embedding ids are kept, and chunks, fts and the call graph are not
+    /// touched. the caller owns the transaction so a project can be written a
+    /// batch at a time and still land all at once.
+    pub fn replace_chunk_vectors(&self, vectors: &[(i64, Vec<f32>)]) -> Result<()> {
+        for (chunk_id, vector) in vectors {
+            let embedding_id = self.insert_embedding(*chunk_id, vector)?;
+            self.update_chunk_embedding_id(*chunk_id, embedding_id)?;
+        }
+        Ok(())
+    }
+
+    /// record the new model signature on a project once all of its vectors
+    /// have been replaced
+    pub fn finish_reembed(&self, project_id: i64, signature: &str) -> Result<()> {
+        self.set_project_embedding_model(project_id, signature)?;
+        self.clear_project_needs_reembed(project_id)?;
+        self.bump_project_generation(project_id)?;
+        Ok(())
+    }
+
+    pub fn get_chunk_vector(&self, chunk_id: i64, dim: usize) -> Result<Option<Vec<f32>>> {
+        let blob: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                "SELECT vector FROM embeddings WHERE chunk_id = ?1",
+                params![chunk_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        blob.map(|b| decode_vector(&b, dim)).transpose()
+    }
+
+    /// a chunk's embedding id and vector, none when the chunk isn't in the
+    /// project. duplicates share their original's.
+    pub fn chunk_embedding(
+        &self,
+        project_id: i64,
+        chunk_id: i64,
+        dim: usize,
+    ) -> Result<Option<(i64, Vec<f32>)>> {
+        let row: Option<(i64, Vec<u8>)> = self
+            .conn
+            .query_row(
+                "SELECT e.id, e.vector FROM chunks c
+                 JOIN files f ON f.id = c.file_id
+                 JOIN embeddings e ON e.id = c.embedding_id
+                 WHERE c.id = ?1 AND f.project_id = ?2",
+                params![chunk_id, project_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        row.map(|(id, blob)| Ok((id, decode_vector(&blob, dim)?)))
+            .transpose()
+    }
+
     pub fn embedding_count(&self) -> Result<u64> {
         let count: i64 = self
             .conn
@@ -125,37 +240,56 @@ impl Store {
     pub fn get_chunk_by_id(&self, chunk_id: i64) -> Result<Option<(Chunk, String)>> {
         self.conn
             .query_row(
-                "SELECT c.id, c.file_id, c.content, c.symbol, c.symbol_kind,
-                        c.start_line, c.end_line, c.language, f.path, c.suspicious
-                 FROM chunks c JOIN files f ON c.file_id = f.id
-                 WHERE c.id = ?1",
+                &format!("{} WHERE c.id = ?1", CHUNK_WITH_PATH_SELECT),
                 params![chunk_id],
-                |row| {
-                    let lang_str: String = row.get(7)?;
-                    let language: Language =
-                        serde_json::from_value(serde_json::Value::String(lang_str))
-                            .unwrap_or(Language::Unknown);
-                    let suspicious: i32 = row.get::<_, Option<i32>>(9)?.unwrap_or(0);
-                    Ok((
-                        Chunk {
-                            id: Some(row.get(0)?),
-                            file_id: row.get(1)?,
-                            content: row.get(2)?,
-                            symbol: row.get(3)?,
-                            symbol_kind: row.get(4)?,
-                            start_line: row.get(5)?,
-                            end_line: row.get(6)?,
-                            language,
-                            suspicious: suspicious != 0,
-                        },
-                        row.get::<_, String>(8)?,
-                    ))
-                },
+                map_chunk_with_path,
             )
             .optional()
             .map_err(|e| Error::Sqlite(e.to_string()))
     }
 
+    /// resolve many chunk ids in one query, keeping the order of `chunk_ids`.
+    /// the language filter runs in sql so off-language chunks are never read;
+    /// empty `languages` keeps every chunk.
+    pub fn get_chunks_by_ids(
+        &self,
+        chunk_ids: &[i64],
+        languages: &[Language],
+    ) -> Result<Vec<(Chunk, String)>> {
+        if chunk_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; chunk_ids.len()].join(",");
+        let mut sql = format!(
+            "{} WHERE c.id IN ({})",
+            CHUNK_WITH_PATH_SELECT, placeholders
+        );
+        let mut values: Vec<rusqlite::types::Value> =
+            chunk_ids.iter().map(|&id| id.into()).collect();
+        if !languages.is_empty() {
+            sql.push_str(&format!(
+                " AND c.language IN ({})",
+                vec!["?"; languages.len()].join(",")
+            ));
+            values.extend(languages.iter().map(|l| l.as_str().to_string().into()));
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let mut by_id: std::collections::HashMap<i64, (Chunk, String)> = stmt
+            .query_map(rusqlite::params_from_iter(values), map_chunk_with_path)
+            .map_err(|e| Error::Sqlite(e.to_string()))?
+            .map(|r| {
+                r.map(|pair| (pair.0.id.unwrap_or_default(), pair))
+                    .map_err(|e| Error::Sqlite(e.to_string()))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(chunk_ids.iter().filter_map(|id| by_id.remove(id)).collect())
+    }
+
     pub fn get_chunk_id_by_embedding_id(&self, embedding_id: i64) -> Result<Option<i64>> {
         self.conn
             .query_row(
@@ -187,7 +321,10 @@ impl Store {
         Ok(count as u64)
     }
 
+    /// embeddings that duplicates in other files still share move to one of
+    /// those instead of being deleted
     pub fn delete_file_embeddings(&self, file_id: i64) -> Result<()> {
+        self.promote_file_duplicates(file_id)?;
         self.conn
             .execute(
                 "DELETE FROM embeddings WHERE chunk_id IN (SELECT id FROM chunks WHERE file_id = ?1)",
@@ -254,6 +391,48 @@ impl Store {
         self.search_fts_project_paginated(query, project_id, limit, 0)
     }
 
+    /// like `search_fts_project`, keeping only chunks in one of `languages`
+    /// before the limit is applied. empty `languages` keeps every chunk.
+    pub fn search_fts_languages(
+        &self,
+        query: &str,
+        project_id: i64,
+        languages: &[Language],
+        limit: usize,
+    ) -> Result<Vec<(i64, f64)>> {
+        if languages.is_empty() {
+            return self.search_fts_project(query, Some(project_id), limit);
+        }
+        let escaped = escape_fts5_query(query);
+        if escaped.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let sql = format!(
+            "SELECT fts.chunk_id, fts.rank FROM chunks_fts fts
+             JOIN chunks c ON fts.chunk_id = c.id
+             JOIN files f ON c.file_id = f.id
+             WHERE fts.content MATCH ? AND f.project_id = ? AND c.language IN ({})
+             ORDER BY fts.rank LIMIT ?",
+            vec!["?"; languages.len()].join(",")
+        );
+        let mut values: Vec<rusqlite::types::Value> = vec![escaped.into(), project_id.into()];
+        values.extend(languages.iter().map(|l| l.as_str().to_string().into()));
+        values.push((limit as i64).into());
+
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?))
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
     pub fn search_fts_project_paginated(
         &self,
         query: &str,
@@ -310,4 +489,239 @@ impl Store {
         }
         Ok(results)
     }
+
+    /// total number of matches search_fts_project_paginated pages through,
+    /// so callers can show "page 1 of N".
+    pub fn count_fts_matches(&self, query: &str, project_id: Option<i64>) -> Result<u64> {
+        let escaped = escape_fts5_query(query);
+        if escaped.is_empty() {
+            return Ok(0);
+        }
+
+        let count: i64 = match project_id {
+            Some(project_id) => self.conn.query_row(
+                "SELECT COUNT(*) FROM chunks_fts fts
+                 JOIN chunks c ON fts.chunk_id = c.id
+                 JOIN files f ON c.file_id = f.id
+                 WHERE fts.content MATCH ?1 AND f.project_id = ?2",
+                params![escaped, project_id],
+                |row| row.get(0),
+            ),
+            None => self.conn.query_row(
+                "SELECT COUNT(*) FROM chunks_fts WHERE chunks_fts MATCH ?1",
+                params![escaped],
+                |row| row.get(0),
+            ),
+        }
+        .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(count as u64)
+    }
+
+    /// like search_fts_project_paginated, but also returns an excerpt of the
+    /// matching content with each hit wrapped in `open`/`close`. `mode` says
+    /// how `query` is read.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search_fts_snippets(
+        &self,
+        query: &str,
+        mode: FtsQueryMode,
+        project_id: Option<i64>,
+        limit: usize,
+        offset: usize,
+        open: &str,
+        close: &str,
+    ) -> Result<Vec<(i64, f64, String)>> {
+        let expr = fts5_match_expr(query, mode);
+        if expr.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // column 1 is content; chunk_id is column 0
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT fts.chunk_id, fts.rank,
+                        snippet(chunks_fts, 1, ?2, ?3, '...', ?4)
+                 FROM chunks_fts fts
+                 JOIN chunks c ON fts.chunk_id = c.id
+                 JOIN files f ON c.file_id = f.id
+                 WHERE chunks_fts MATCH ?1 AND (?5 IS NULL OR f.project_id = ?5)
+                 ORDER BY fts.rank LIMIT ?6 OFFSET ?7",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    expr,
+                    open,
+                    close,
+                    SNIPPET_TOKENS,
+                    project_id,
+                    limit as i64,
+                    offset as i64
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| fts_error(mode, e))?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row.map_err(|e| fts_error(mode, e))?);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use crate::index::store::FtsQueryMode;
+    use srag_common::types::Language;
+
+    fn add_chunk(store: &crate::index::store::Store, project: &str, content: &str) -> i64 {
+        add_file(store, project, "lib.rs", content)
+    }
+
+    /// a one-chunk file in `project`, creating the project if needed
+    fn add_file(
+        store: &crate::index::store::Store,
+        project: &str,
+        file: &str,
+        content: &str,
+    ) -> i64 {
+        let project_id = store
+            .get_project_id(project)
+            .or_else(|_| store.upsert_project(project, "/tmp"))
+            .unwrap();
+        let path = format!("/tmp/{}/{}", project, file);
+        let file_id = seed_file(store, project_id, &path, Language::Rust);
+        seed_chunk(store, &test_chunk(file_id, content, 1), "lib.rs");
+        project_id
+    }
+
+    #[test]
+    fn test_search_fts_snippets_highlights_term() {
+        let (store, _dir) = test_store();
+        let filler = "let unrelated = 1;\n".repeat(40);
+        let content = format!("{}fn rotate_credentials() {{}}\n{}", filler, filler);
+        let project_id = add_chunk(&store, "proj", &content);
+
+        let results = store
+            .search_fts_snippets(
+                "rotate_credentials",
+                FtsQueryMode::Terms,
+                Some(project_id),
+                10,
+                0,
+                "<<",
+                ">>",
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let snippet = &results[0].2;
+        assert!(snippet.contains("<<rotate_credentials>>"), "{}", snippet);
+        assert!(snippet.len() < content.len());
+    }
+
+    #[test]
+    fn test_fts_phrase_mode_matches_exact_phrase() {
+        let (store, _dir) = test_store();
+        let pid = add_file(&store, "proj", "a.rs", "// retry the request with backoff");
+        add_file(
+            &store,
+            "proj",
+            "b.rs",
+            "// backoff before the next request retry",
+        );
+
+        let search = |query: &str, mode: FtsQueryMode| {
+            store
+                .search_fts_snippets(query, mode, Some(pid), 10, 0, "[", "]")
+                .unwrap()
+                .len()
+        };
+        assert_eq!(search("request retry", FtsQueryMode::Terms), 2);
+        assert_eq!(search("request retry", FtsQueryMode::Phrase), 1);
+        assert_eq!(search("retry the request", FtsQueryMode::Phrase), 1);
+        assert_eq!(search("   ", FtsQueryMode::Phrase), 0);
+        // operators only mean something in raw mode
+        assert_eq!(search("NEAR(retry backoff, 3)", FtsQueryMode::Raw), 1);
+        assert_eq!(search("retry NOT backoff", FtsQueryMode::Raw), 0);
+        assert_eq!(search("retry OR missing", FtsQueryMode::Raw), 2);
+    }
+
+    #[test]
+    fn test_fts_raw_mode_reports_bad_syntax() {
+        let (store, _dir) = test_store();
+        let pid = add_chunk(&store, "proj", "fn retry() {}");
+
+        for query in ["retry AND", "\"unterminated", "NEAR(retry"] {
+            let err = store
+                .search_fts_snippets(query, FtsQueryMode::Raw, Some(pid), 10, 0, "[", "]")
+                .unwrap_err();
+            assert!(
+                matches!(err, srag_common::Error::Query(ref msg) if msg.contains("invalid full-text query")),
+                "{}: {:?}",
+                query,
+                err
+            );
+        }
+        // the same text is harmless as plain terms
+        assert!(store
+            .search_fts_snippets("retry AND", FtsQueryMode::Terms, Some(pid), 10, 0, "[", "]")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_search_fts_snippets_scoped_to_project() {
+        let (store, _dir) = test_store();
+        let a = add_chunk(&store, "a", "fn shared_name() {}");
+        add_chunk(&store, "b", "fn shared_name() {}");
+
+        let scoped = store
+            .search_fts_snippets("shared_name", FtsQueryMode::Terms, Some(a), 10, 0, "[", "]")
+            .unwrap();
+        assert_eq!(scoped.len(), 1);
+        let all = store
+            .search_fts_snippets("shared_name", FtsQueryMode::Terms, None, 10, 0, "[", "]")
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all[0].2.contains("[shared_name]"));
+    }
+
+    #[test]
+    fn test_count_fts_matches_covers_all_pages() {
+        let (store, _dir) = test_store();
+        let mut project_id = 0;
+        for i in 0..7 {
+            project_id = add_file(
+                &store,
+                "a",
+                &format!("m{}.rs", i),
+                &format!("fn needle_{}() {{ needle }}", i),
+            );
+        }
+        add_chunk(&store, "b", "fn other() { needle }");
+        add_file(&store, "a", "other.rs", "fn unrelated() {}");
+
+        let total = store.count_fts_matches("needle", Some(project_id)).unwrap();
+        assert_eq!(total, 7);
+
+        let mut paged = 0;
+        let mut offset = 0;
+        loop {
+            let page = store
+                .search_fts_project_paginated("needle", Some(project_id), 3, offset)
+                .unwrap();
+            if page.is_empty() {
+                break;
+            }
+            paged += page.len();
+            offset += 3;
+        }
+        assert_eq!(paged as u64, total);
+
+        assert_eq!(store.count_fts_matches("needle", None).unwrap(), 8);
+        assert_eq!(store.count_fts_matches("   ", None).unwrap(), 0);
+    }
 }
@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use rusqlite::params;
+use srag_common::{Error, Result};
+
+use super::Store;
+
+impl Store {
+    /// bytes held by in-use pages. pages freed by deletes are excluded, so
+    /// this drops as soon as rows are removed, before any vacuum.
+    pub fn live_database_bytes(&self) -> Result<u64> {
+        let pragma = |name: &str| -> Result<i64> {
+            self.conn
+                .pragma_query_value(None, name, |row| row.get(0))
+                .map_err(|e| Error::Sqlite(e.to_string()))
+        };
+        let pages = pragma("page_count")? - pragma("freelist_count")?;
+        Ok((pages.max(0) * pragma("page_size")?) as u64)
+    }
+
+    /// projects that still hold embeddings, least recently queried first.
+    /// never queried projects go first, oldest index first.
+    pub fn eviction_candidates(&self) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT p.id, p.name FROM projects p
+                 WHERE EXISTS (
+                     SELECT 1 FROM embeddings e
+                     JOIN chunks c ON e.chunk_id = c.id
+                     JOIN files f ON c.file_id = f.id
+                     WHERE f.project_id = p.id
+                 )
+                 ORDER BY p.last_queried_at IS NOT NULL, p.last_queried_at,
+                          p.last_indexed_at, p.id",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| Error::Sqlite(e.to_string()))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(rows)
+    }
+
+    /// drop every embedding in a project and flag it for 'srag reembed'.
+    /// chunks, fts rows and the call graph stay, so text search still works.
+    pub fn evict_project_embeddings(&self, project_id: i64) -> Result<u64> {
+        self.begin_transaction()?;
+        let result = (|| {
+            let removed = self
+                .conn
+                .execute(
+                    "DELETE FROM embeddings WHERE chunk_id IN (
+                         SELECT c.id FROM chunks c JOIN files f ON c.file_id = f.id
+                         WHERE f.project_id = ?1
+                     )",
+                    params![project_id],
+                )
+                .map_err(|e| Error::Sqlite(e.to_string()))?;
+            self.conn
+                .execute(
+                    "UPDATE chunks SET embedding_id = NULL
+                     WHERE file_id IN (SELECT id FROM files WHERE project_id = ?1)",
+                    params![project_id],
+                )
+                .map_err(|e| Error::Sqlite(e.to_string()))?;
+            self.conn
+                .execute(
+                    "UPDATE projects SET needs_reembed = 1, generation = generation + 1
+                     WHERE id = ?1",
+                    params![project_id],
+                )
+                .map_err(|e| Error::Sqlite(e.to_string()))?;
+            Ok(removed as u64)
+        })();
+
+        match result {
+            Ok(removed) => {
+                self.commit()?;
+                Ok(removed)
+            }
+            Err(e) => {
+                let _ = self.rollback();
+                Err(e)
+            }
+        }
+    }
+
+    /// evict embeddings project by project, least recently queried first,
+    /// until the live database size fits in `max_bytes`. `keep` is never
+    /// evicted. returns each evicted project's name and vector count.
+    pub fn evict_to_budget(&self, max_bytes: u64, keep: Option<i64>) -> Result<Vec<(String, u64)>> {
+        let mut evicted = Vec::new();
+        for (project_id, name) in self.eviction_candidates()? {
+            if self.live_database_bytes()? <= max_bytes {
+                break;
+            }
+            if Some(project_id) == keep {
+                continue;
+            }
+            let removed = self.evict_project_embeddings(project_id)?;
+            evicted.push((name, removed));
+        }
+        Ok(evicted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::store::tests::test_store;
+    use crate::index::store::Store;
+    use srag_common::types::{Chunk, FileRecord, Language};
+
+    /// one file with `chunks` embedded chunks
+    fn seed_project(store: &Store, name: &str, chunks: usize) -> i64 {
+        let project_id = store
+            .upsert_project(name, &format!("/tmp/{}", name))
+            .unwrap();
+        let file_id = store
+            .upsert_file(&FileRecord {
+                id: None,
+                project_id,
+                path: format!("/tmp/{}/lib.rs", name),
+                blake3_hash: "h".into(),
+                language: Language::Rust,
+                size_bytes: 1,
+                chunk_count: chunks as u32,
+                indexed_at: String::new(),
+            })
+            .unwrap();
+        for i in 0..chunks {
+            let chunk = Chunk {
+                id: None,
+                file_id,
+                content: format!("fn f{}() {{}}", i),
+                symbol: None,
+                symbol_kind: None,
+                start_line: i as u32 + 1,
+                end_line: i as u32 + 1,
+                language: Language::Rust,
+                suspicious: false,
+            };
+            let chunk_id = store.insert_chunk(&chunk, None).unwrap();
+            let embedding_id = store.insert_embedding(chunk_id, &vec![0.25; 384]).unwrap();
+            store
+                .update_chunk_embedding_id(chunk_id, embedding_id)
+                .unwrap();
+        }
+        project_id
+    }
+
+    #[test]
+    fn test_evict_least_recently_queried_first() {
+        let (store, _dir) = test_store();
+        let stale = seed_project(&store, "stale", 40);
+        let active = seed_project(&store, "active", 40);
+        store.touch_project_queried(active).unwrap();
+
+        let names: Vec<_> = store
+            .eviction_candidates()
+            .unwrap()
+            .into_iter()
+            .map(|(_, n)| n)
+            .collect();
+        assert_eq!(names, ["stale", "active"]);
+
+        // a tiny budget evicts everything except the project being kept
+        let evicted = store.evict_to_budget(1, Some(active)).unwrap();
+        assert_eq!(evicted, [("stale".to_string(), 40)]);
+        assert_eq!(store.embedded_chunk_count(Some(stale)).unwrap(), 0);
+        assert_eq!(store.embedded_chunk_count(Some(active)).unwrap(), 40);
+        assert!(store.project_needs_reembed(stale).unwrap());
+        assert!(!store.project_needs_reembed(active).unwrap());
+        // chunks survive for text search and a later re-embed
+        assert_eq!(store.chunk_count(Some(stale)).unwrap(), 40);
+    }
+
+    #[test]
+    fn test_evict_within_budget_is_noop() {
+        let (store, _dir) = test_store();
+        let project = seed_project(&store, "p", 5);
+        let budget = store.live_database_bytes().unwrap();
+        assert!(budget > 0);
+
+        assert!(store.evict_to_budget(budget, None).unwrap().is_empty());
+        assert_eq!(store.embedded_chunk_count(Some(project)).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_eviction_frees_space() {
+        let (store, _dir) = test_store();
+        let project = seed_project(&store, "big", 200);
+        let before = store.live_database_bytes().unwrap();
+
+        store.evict_project_embeddings(project).unwrap();
+        assert!(store.live_database_bytes().unwrap() < before);
+        assert!(store.eviction_candidates().unwrap().is_empty());
+    }
+}
@@ -5,7 +5,7 @@ use rusqlite::params;
 use srag_common::types::FileRecord;
 use srag_common::{Error, Result};
 
-use super::Store;
+use super::{escape_like_pattern, Store};
 
 impl Store {
     pub fn get_file_hash(&self, project_id: i64, path: &str) -> Result<Option<String>> {
@@ -20,6 +20,18 @@ impl Store {
             .map_err(|e| Error::Sqlite(e.to_string()))
     }
 
+    pub fn get_file_project_id(&self, file_id: i64) -> Result<Option<i64>> {
+        use rusqlite::OptionalExtension;
+        self.conn
+            .query_row(
+                "SELECT project_id FROM files WHERE id = ?1",
+                params![file_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
     pub fn upsert_file(&self, record: &FileRecord) -> Result<i64> {
         let lang = serde_json::to_value(record.language)
             .map_err(|e| Error::Sqlite(e.to_string()))?
@@ -55,6 +67,49 @@ impl Store {
         Ok(file_id)
     }
 
+    pub fn set_file_hash(&self, file_id: i64, hash: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE files SET blake3_hash = ?2 WHERE id = ?1",
+                params![file_id, hash],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(())
+    }
+
+    /// store the original bytes of a file, or drop them when `content` is
+    /// `None` so a stale copy can't outlive a re-index
+    pub fn set_file_content(&self, file_id: i64, content: Option<&[u8]>) -> Result<()> {
+        match content {
+            Some(bytes) => self.conn.execute(
+                "INSERT INTO file_contents (file_id, content) VALUES (?1, ?2)
+                 ON CONFLICT(file_id) DO UPDATE SET content = ?2",
+                params![file_id, bytes],
+            ),
+            None => self.conn.execute(
+                "DELETE FROM file_contents WHERE file_id = ?1",
+                params![file_id],
+            ),
+        }
+        .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(())
+    }
+
+    /// the stored original bytes of a file, if it was indexed with
+    /// `indexing.store_raw_content`
+    pub fn get_file_content(&self, project_id: i64, path: &str) -> Result<Option<Vec<u8>>> {
+        use rusqlite::OptionalExtension;
+        self.conn
+            .query_row(
+                "SELECT fc.content FROM file_contents fc JOIN files f ON fc.file_id = f.id
+                 WHERE f.project_id = ?1 AND f.path = ?2",
+                params![project_id, path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
     pub fn delete_project_files(&self, project_id: i64) -> Result<()> {
         self.conn
             .execute(
@@ -65,6 +120,50 @@ impl Store {
         Ok(())
     }
 
+    /// remove a single file and everything derived from it. returns the freed
+    /// embedding ids; an unknown path is a no-op.
+    pub fn delete_file(&self, project_id: i64, path: &str) -> Result<Vec<i64>> {
+        use rusqlite::OptionalExtension;
+        let file_id: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM files WHERE project_id = ?1 AND path = ?2",
+                params![project_id, path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let Some(file_id) = file_id else {
+            return Ok(Vec::new());
+        };
+
+        self.begin_transaction()?;
+        let result = self.delete_file_rows(file_id);
+
+        match result {
+            Ok(ids) => {
+                self.commit()?;
+                Ok(ids)
+            }
+            Err(e) => {
+                let _ = self.rollback();
+                Err(e)
+            }
+        }
+    }
+
+    /// `delete_file` without its own transaction
+    pub(super) fn delete_file_rows(&self, file_id: i64) -> Result<Vec<i64>> {
+        self.delete_file_chunks_fts(file_id)?;
+        self.delete_file_embeddings(file_id)?;
+        self.delete_file_call_graph(file_id)?;
+        let embedding_ids = self.delete_file_chunks(file_id)?;
+        self.conn
+            .execute("DELETE FROM files WHERE id = ?1", params![file_id])
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(embedding_ids)
+    }
+
     pub fn list_project_files(&self, project_id: i64) -> Result<Vec<FileRecord>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, project_id, path, blake3_hash, language, size_bytes, chunk_count, indexed_at
@@ -96,12 +195,31 @@ impl Store {
         }
         Ok(files)
     }
+
+    /// indexed paths equal to `path` or inside it when it is a directory.
+    /// used to drop a deleted or renamed directory's files.
+    pub fn indexed_paths_under(&self, project_id: i64, path: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/%", escape_like_pattern(path.trim_end_matches('/')));
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT path FROM files
+                 WHERE project_id = ?1 AND (path = ?2 OR path LIKE ?3 ESCAPE '\\')
+                 ORDER BY path",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![project_id, path, prefix], |row| row.get(0))
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::index::store::tests::test_store;
+    use crate::index::store::tests::{seed_file, test_store};
     use srag_common::types::Language;
 
     #[test]
@@ -130,6 +248,90 @@ mod tests {
         assert_eq!(files[0].language, Language::Rust);
     }
 
+    #[test]
+    fn test_delete_file() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp").unwrap();
+        let path = "/tmp/src/gone.rs";
+        let fid = store
+            .upsert_file(&FileRecord {
+                id: None,
+                project_id: pid,
+                path: path.to_string(),
+                blake3_hash: "hash".to_string(),
+                language: Language::Rust,
+                size_bytes: 32,
+                chunk_count: 1,
+                indexed_at: String::new(),
+            })
+            .unwrap();
+        let chunk = srag_common::types::Chunk {
+            id: None,
+            file_id: fid,
+            content: "fn vanishing_function() {}".to_string(),
+            symbol: Some("vanishing_function".to_string()),
+            symbol_kind: Some("function".to_string()),
+            start_line: 1,
+            end_line: 1,
+            language: Language::Rust,
+            suspicious: false,
+        };
+        let chunk_id = store.insert_chunk(&chunk, None).unwrap();
+        store
+            .insert_chunk_fts(chunk_id, &chunk.content, path, chunk.symbol.as_deref())
+            .unwrap();
+        let embedding_id = store.insert_embedding(chunk_id, &[0.5; 4]).unwrap();
+        store
+            .update_chunk_embedding_id(chunk_id, embedding_id)
+            .unwrap();
+
+        assert_eq!(store.get_file_chunks(pid, path).unwrap().len(), 1);
+        assert_eq!(store.search_fts("vanishing_function", 10).unwrap().len(), 1);
+
+        let freed = store.delete_file(pid, path).unwrap();
+        assert_eq!(freed, vec![embedding_id]);
+
+        assert!(store.get_file_chunks(pid, path).unwrap().is_empty());
+        assert!(store
+            .search_fts("vanishing_function", 10)
+            .unwrap()
+            .is_empty());
+        assert!(store.get_file_hash(pid, path).unwrap().is_none());
+        assert_eq!(store.embedding_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_delete_file_unknown_path() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp").unwrap();
+        assert!(store
+            .delete_file(pid, "/tmp/missing.rs")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_file_content_roundtrip() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp").unwrap();
+        let path = "/tmp/src/raw.rs";
+        let fid = seed_file(&store, pid, path, Language::Rust);
+        assert!(store.get_file_content(pid, path).unwrap().is_none());
+
+        store.set_file_content(fid, Some(b"a\r\n\n\tb\n")).unwrap();
+        assert_eq!(
+            store.get_file_content(pid, path).unwrap().as_deref(),
+            Some(&b"a\r\n\n\tb\n"[..])
+        );
+
+        store.set_file_content(fid, None).unwrap();
+        assert!(store.get_file_content(pid, path).unwrap().is_none());
+
+        store.set_file_content(fid, Some(b"x")).unwrap();
+        store.delete_file(pid, path).unwrap();
+        assert!(store.get_file_content(pid, path).unwrap().is_none());
+    }
+
     #[test]
     fn test_file_hash_not_found() {
         let (store, _dir) = test_store();
@@ -137,4 +339,32 @@ mod tests {
         let hash = store.get_file_hash(pid, "nonexistent.rs").unwrap();
         assert!(hash.is_none());
     }
+
+    #[test]
+    fn test_indexed_paths_under() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/p").unwrap();
+        for path in [
+            "/p/src/a.rs",
+            "/p/src/sub/b.rs",
+            "/p/src_old/c.rs",
+            "/p/x_y/d.rs",
+        ] {
+            seed_file(&store, pid, path, Language::Rust);
+        }
+        assert_eq!(
+            store.indexed_paths_under(pid, "/p/src").unwrap(),
+            vec!["/p/src/a.rs", "/p/src/sub/b.rs"]
+        );
+        assert_eq!(
+            store.indexed_paths_under(pid, "/p/src/a.rs").unwrap(),
+            vec!["/p/src/a.rs"]
+        );
+        // underscores are literal, not wildcards
+        assert!(store.indexed_paths_under(pid, "/p/x%").unwrap().is_empty());
+        assert_eq!(
+            store.indexed_paths_under(pid, "/p/x_y").unwrap(),
+            vec!["/p/x_y/d.rs"]
+        );
+    }
 }
@@ -0,0 +1,521 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::types::Value;
+use rusqlite::{params, OptionalExtension};
+use srag_common::types::Project;
+use srag_common::{Error, Result};
+
+use super::{ImportReport, Store};
+
+/// a chunk read from the other database
+struct OtherChunk {
+    id: i64,
+    content: String,
+    symbol: Option<String>,
+    symbol_kind: Option<String>,
+    start_line: u32,
+    end_line: u32,
+    language: String,
+    suspicious: bool,
+    content_hash: Option<String>,
+    dup_of: Option<i64>,
+    vector: Option<Vec<u8>>,
+}
+
+impl Store {
+    /// copy one project of `other` into this store as `name`. a project of
+    /// that name here is merged into, with the other root's paths moved under
+    /// this one. files are matched by path: the same hash is skipped, and a
+    /// different one replaces the local copy only when it was indexed later.
+    /// ids are remapped on the way in; the vector index needs rebuilding after.
+    pub fn import_project(
+        &self,
+        other: &Store,
+        from: &Project,
+        name: &str,
+    ) -> Result<ImportReport> {
+        let from_id = from
+            .id
+            .ok_or_else(|| Error::Index(format!("project '{}' has no id", from.name)))?;
+
+        self.begin_transaction()?;
+        let result = (|| {
+            let local = self.list_projects()?.into_iter().find(|p| p.name == name);
+            let (project_id, root) = match local {
+                Some(project) => (project.id.unwrap_or_default(), project.path),
+                None => (self.upsert_project(name, &from.path)?, from.path.clone()),
+            };
+            if self.project_embedding_model(project_id)?.is_none() {
+                if let Some(model) = other.project_embedding_model(from_id)? {
+                    self.set_project_embedding_model(project_id, &model)?;
+                }
+            }
+
+            let mut report = ImportReport::default();
+            // other chunk id -> chunk id here
+            let mut chunk_ids: HashMap<i64, i64> = HashMap::new();
+            // (chunk here, the other id of the original it duplicates)
+            let mut duplicates: Vec<(i64, i64)> = Vec::new();
+
+            for file in other.list_project_files(from_id)? {
+                let path = rebase_path(&file.path, &from.path, &root);
+                let existing: Option<(i64, String, String)> = self
+                    .conn
+                    .query_row(
+                        "SELECT id, blake3_hash, indexed_at FROM files
+                         WHERE project_id = ?1 AND path = ?2",
+                        params![project_id, path],
+                        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                    )
+                    .optional()
+                    .map_err(|e| Error::Sqlite(e.to_string()))?;
+                if let Some((file_id, hash, indexed_at)) = existing {
+                    if hash == file.blake3_hash || indexed_at >= file.indexed_at {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    self.delete_file_rows(file_id)?;
+                }
+
+                let other_file_id = file.id.unwrap_or_default();
+                let lang: String = other
+                    .conn
+                    .query_row(
+                        "SELECT language FROM files WHERE id = ?1",
+                        params![other_file_id],
+                        |row| row.get(0),
+                    )
+                    .map_err(|e| Error::Sqlite(e.to_string()))?;
+                self.conn
+                    .execute(
+                        "INSERT INTO files (project_id, path, blake3_hash, language, size_bytes, chunk_count, indexed_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            project_id,
+                            path,
+                            file.blake3_hash,
+                            lang,
+                            file.size_bytes,
+                            file.chunk_count,
+                            file.indexed_at
+                        ],
+                    )
+                    .map_err(|e| Error::Sqlite(e.to_string()))?;
+                let file_id = self.conn.last_insert_rowid();
+                if let Some(content) = other.get_file_content(from_id, &file.path)? {
+                    self.set_file_content(file_id, Some(&content))?;
+                }
+
+                for chunk in other.file_chunks_for_import(other_file_id)? {
+                    let chunk_id = self.insert_imported_chunk(file_id, &chunk)?;
+                    // the same fts row the indexer writes
+                    self.insert_chunk_fts(
+                        chunk_id,
+                        &chunk.content,
+                        &path,
+                        chunk.symbol.as_deref(),
+                    )?;
+                    if let Some(vector) = &chunk.vector {
+                        self.insert_imported_embedding(chunk_id, vector)?;
+                        report.embeddings += 1;
+                    }
+                    if let Some(original) = chunk.dup_of {
+                        duplicates.push((chunk_id, original));
+                    }
+                    chunk_ids.insert(chunk.id, chunk_id);
+                    report.chunks += 1;
+                }
+                self.copy_file_call_graph(other, other_file_id, file_id, &chunk_ids)?;
+                self.copy_file_commits(other, other_file_id, project_id, file_id)?;
+                report.files += 1;
+            }
+
+            for (chunk_id, original) in duplicates {
+                self.link_imported_duplicate(other, chunk_id, original, &mut chunk_ids)?;
+            }
+            if report.files > 0 {
+                self.resolve_calls_for_project(project_id)?;
+                self.bump_project_generation(project_id)?;
+            }
+            Ok(report)
+        })();
+
+        match result {
+            Ok(report) => {
+                self.commit()?;
+                Ok(report)
+            }
+            Err(e) => {
+                let _ = self.rollback();
+                Err(e)
+            }
+        }
+    }
+
+    /// a file's chunks with the vectors of those that own one
+    fn file_chunks_for_import(&self, file_id: i64) -> Result<Vec<OtherChunk>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT c.id, c.content, c.symbol, c.symbol_kind, c.start_line, c.end_line,
+                        c.language, c.suspicious, c.content_hash, c.dup_of, e.vector
+                 FROM chunks c LEFT JOIN embeddings e ON e.chunk_id = c.id
+                 WHERE c.file_id = ?1 ORDER BY c.id",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![file_id], |row| {
+                Ok(OtherChunk {
+                    id: row.get(0)?,
+                    content: row.get(1)?,
+                    symbol: row.get(2)?,
+                    symbol_kind: row.get(3)?,
+                    start_line: row.get(4)?,
+                    end_line: row.get(5)?,
+                    language: row.get(6)?,
+                    suspicious: row.get::<_, i32>(7)? != 0,
+                    content_hash: row.get(8)?,
+                    dup_of: row.get(9)?,
+                    vector: row.get(10)?,
+                })
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    fn insert_imported_chunk(&self, file_id: i64, chunk: &OtherChunk) -> Result<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO chunks (file_id, content, symbol, symbol_kind, start_line, end_line,
+                                     language, suspicious, content_hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    file_id,
+                    chunk.content,
+                    chunk.symbol,
+                    chunk.symbol_kind,
+                    chunk.start_line,
+                    chunk.end_line,
+                    chunk.language,
+                    chunk.suspicious as i32,
+                    chunk.content_hash,
+                ],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// vectors are copied as stored, without decoding
+    fn insert_imported_embedding(&self, chunk_id: i64, vector: &[u8]) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO embeddings (chunk_id, vector) VALUES (?1, ?2)",
+                params![chunk_id, vector],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let embedding_id = self.conn.last_insert_rowid();
+        self.update_chunk_embedding_id(chunk_id, embedding_id)
+    }
+
+    /// point an imported duplicate at its imported original. when the
+    /// original wasn't copied (its file was skipped) the duplicate takes the
+    /// original's vector and stands in for it for the rest of the import.
+    fn link_imported_duplicate(
+        &self,
+        other: &Store,
+        chunk_id: i64,
+        original: i64,
+        chunk_ids: &mut HashMap<i64, i64>,
+    ) -> Result<()> {
+        if let Some(&local) = chunk_ids.get(&original).filter(|&&id| id != chunk_id) {
+            return self
+                .conn
+                .execute(
+                    "UPDATE chunks SET dup_of = ?1,
+                        embedding_id = (SELECT embedding_id FROM chunks WHERE id = ?1)
+                     WHERE id = ?2",
+                    params![local, chunk_id],
+                )
+                .map(|_| ())
+                .map_err(|e| Error::Sqlite(e.to_string()));
+        }
+        let vector: Option<Vec<u8>> = other
+            .conn
+            .query_row(
+                "SELECT vector FROM embeddings WHERE chunk_id = ?1",
+                params![original],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        if let Some(vector) = vector {
+            self.insert_imported_embedding(chunk_id, &vector)?;
+        }
+        chunk_ids.insert(original, chunk_id);
+        Ok(())
+    }
+
+    /// definitions and calls, left unresolved for `resolve_calls_for_project`
+    fn copy_file_call_graph(
+        &self,
+        other: &Store,
+        other_file_id: i64,
+        file_id: i64,
+        chunk_ids: &HashMap<i64, i64>,
+    ) -> Result<()> {
+        let copies = [
+            (
+                "SELECT chunk_id, name, kind, scope, language, start_line, end_line, signature
+                 FROM definitions WHERE file_id = ?1",
+                "INSERT OR IGNORE INTO definitions
+                 (chunk_id, file_id, name, kind, scope, language, start_line, end_line, signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            ),
+            (
+                "SELECT chunk_id, caller_name, caller_scope, callee_name, line_number, language
+                 FROM function_calls WHERE file_id = ?1",
+                "INSERT INTO function_calls
+                 (chunk_id, file_id, caller_name, caller_scope, callee_name, line_number, language)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            ),
+        ];
+        for (select, insert) in copies {
+            for mut row in other.rows_for_import(select, other_file_id)? {
+                let Value::Integer(old_chunk) = row[0] else {
+                    continue;
+                };
+                let Some(&chunk_id) = chunk_ids.get(&old_chunk) else {
+                    continue;
+                };
+                row[0] = Value::Integer(chunk_id);
+                row.insert(1, Value::Integer(file_id));
+                self.conn
+                    .execute(insert, rusqlite::params_from_iter(row))
+                    .map_err(|e| Error::Sqlite(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// commits whose hunks live in the file; ones already here are kept
+    fn copy_file_commits(
+        &self,
+        other: &Store,
+        other_file_id: i64,
+        project_id: i64,
+        file_id: i64,
+    ) -> Result<()> {
+        let rows = other.rows_for_import(
+            "SELECT hash, author, committed_at, summary FROM commits WHERE file_id = ?1",
+            other_file_id,
+        )?;
+        for row in rows {
+            let values = [Value::Integer(project_id), Value::Integer(file_id)]
+                .into_iter()
+                .chain(row);
+            self.conn
+                .execute(
+                    "INSERT OR IGNORE INTO commits (project_id, file_id, hash, author, committed_at, summary)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params_from_iter(values),
+                )
+                .map_err(|e| Error::Sqlite(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn rows_for_import(&self, sql: &str, file_id: i64) -> Result<Vec<Vec<Value>>> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let columns = stmt.column_count();
+        let rows = stmt
+            .query_map(params![file_id], |row| {
+                (0..columns).map(|i| row.get::<_, Value>(i)).collect()
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+}
+
+/// `path` moved from under `from_root` to under `to_root`. paths outside
+/// the old root, like the `git:` files of commits, are kept.
+fn rebase_path(path: &str, from_root: &str, to_root: &str) -> String {
+    if from_root == to_root {
+        return path.to_string();
+    }
+    match Path::new(path).strip_prefix(from_root) {
+        Ok(rest) => Path::new(to_root).join(rest).to_string_lossy().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use srag_common::types::Language;
+
+    /// a file of `contents` chunks; repeated content becomes a duplicate
+    /// sharing the first copy's embedding, as with indexing.dedupe
+    fn add_file(store: &Store, project_id: i64, path: &str, hash: &str, contents: &[&str]) -> i64 {
+        let file_id = seed_file(store, project_id, path, Language::Rust);
+        store.set_file_hash(file_id, hash).unwrap();
+        for content in contents {
+            let chunk_id = seed_chunk(store, &test_chunk(file_id, content, 1), path);
+            if !store
+                .link_duplicate_chunk(project_id, chunk_id, content)
+                .unwrap()
+            {
+                let embedding_id = store.insert_embedding(chunk_id, &[0.5; 4]).unwrap();
+                store
+                    .update_chunk_embedding_id(chunk_id, embedding_id)
+                    .unwrap();
+            }
+        }
+        file_id
+    }
+
+    fn set_indexed_at(store: &Store, file_id: i64, at: &str) {
+        store
+            .conn
+            .execute(
+                "UPDATE files SET indexed_at = ?1 WHERE id = ?2",
+                params![at, file_id],
+            )
+            .unwrap();
+    }
+
+    fn project(store: &Store, id: i64) -> Project {
+        store
+            .list_projects()
+            .unwrap()
+            .into_iter()
+            .find(|p| p.id == Some(id))
+            .unwrap()
+    }
+
+    /// (chunk id, dup_of, embedding_id) of every chunk in a file
+    fn chunk_links(store: &Store, path: &str) -> Vec<(i64, Option<i64>, Option<i64>)> {
+        let mut stmt = store
+            .conn
+            .prepare(
+                "SELECT c.id, c.dup_of, c.embedding_id FROM chunks c JOIN files f ON c.file_id = f.id
+                 WHERE f.path = ?1 ORDER BY c.id",
+            )
+            .unwrap();
+        let rows = stmt
+            .query_map(params![path], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_import_relinks_duplicates() {
+        let (other, _other_dir) = test_store();
+        let pid = other.upsert_project("proj", "/p").unwrap();
+        add_file(&other, pid, "/p/a.rs", "ha", &["fn shared() {}"]);
+        add_file(&other, pid, "/p/b.rs", "hb", &["fn shared() {}"]);
+
+        // both copied: the duplicate points at the new original
+        let (store, _dir) = test_store();
+        let report = store
+            .import_project(&other, &project(&other, pid), "proj")
+            .unwrap();
+        assert_eq!((report.chunks, report.embeddings), (2, 1));
+        let [(original, None, Some(embedding))] = chunk_links(&store, "/p/a.rs")[..] else {
+            panic!("a.rs should hold the original");
+        };
+        assert_eq!(
+            chunk_links(&store, "/p/b.rs"),
+            vec![(original + 1, Some(original), Some(embedding))]
+        );
+        assert!(store.integrity_check().unwrap().is_empty());
+
+        // the original's file is already here, so the duplicate takes its vector
+        let (store, _dir) = test_store();
+        let here = store.upsert_project("proj", "/p").unwrap();
+        add_file(&store, here, "/p/a.rs", "ha", &["fn local() {}"]);
+        let report = store
+            .import_project(&other, &project(&other, pid), "proj")
+            .unwrap();
+        assert_eq!((report.files, report.skipped), (1, 1));
+        let [(_, None, Some(_))] = chunk_links(&store, "/p/b.rs")[..] else {
+            panic!("b.rs should own its embedding");
+        };
+        assert_eq!(store.embedding_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_import_keeps_newer_file() {
+        let (other, _other_dir) = test_store();
+        let there = other.upsert_project("proj", "/p").unwrap();
+        let newer = add_file(
+            &other,
+            there,
+            "/p/new.rs",
+            "new-there",
+            &["fn newer_there() {}"],
+        );
+        set_indexed_at(&other, newer, "2026-02-01 00:00:00");
+        let older = add_file(
+            &other,
+            there,
+            "/p/old.rs",
+            "old-there",
+            &["fn older_there() {}"],
+        );
+        set_indexed_at(&other, older, "2026-01-01 00:00:00");
+
+        let (store, _dir) = test_store();
+        let here = store.upsert_project("proj", "/p").unwrap();
+        let file = add_file(
+            &store,
+            here,
+            "/p/new.rs",
+            "new-here",
+            &["fn newer_here() {}"],
+        );
+        set_indexed_at(&store, file, "2026-01-15 00:00:00");
+        let file = add_file(
+            &store,
+            here,
+            "/p/old.rs",
+            "old-here",
+            &["fn older_here() {}"],
+        );
+        set_indexed_at(&store, file, "2026-01-15 00:00:00");
+
+        let report = store
+            .import_project(&other, &project(&other, there), "proj")
+            .unwrap();
+        assert_eq!((report.files, report.skipped), (1, 1));
+        assert_eq!(
+            store.get_file_hash(here, "/p/new.rs").unwrap().as_deref(),
+            Some("new-there")
+        );
+        assert_eq!(
+            store.get_file_hash(here, "/p/old.rs").unwrap().as_deref(),
+            Some("old-here")
+        );
+        assert!(store.search_fts("newer_here", 10).unwrap().is_empty());
+        assert_eq!(store.search_fts("newer_there", 10).unwrap().len(), 1);
+        assert_eq!(store.embedding_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_rebase_path() {
+        assert_eq!(rebase_path("/a/src/x.rs", "/a", "/b"), "/b/src/x.rs");
+        assert_eq!(rebase_path("/ab/x.rs", "/a", "/b"), "/ab/x.rs");
+        assert_eq!(rebase_path("git:abc123", "/a", "/b"), "git:abc123");
+    }
+}
@@ -1,6 +1,8 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use rusqlite::params;
 use srag_common::types::Project;
 use srag_common::{Error, Result};
@@ -41,10 +43,118 @@ impl Store {
         Ok(())
     }
 
-    pub fn update_project_indexed_at(&self, project_id: i64) -> Result<()> {
+    /// `started` is when the run began, so files edited while it was going
+    /// still count as modified for the next sync
+    pub fn update_project_indexed_at(&self, project_id: i64, started: SystemTime) -> Result<()> {
+        let secs = started
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        self.conn
+            .execute(
+                "UPDATE projects SET last_indexed_at = datetime(?2, 'unixepoch'),
+                 generation = generation + 1 WHERE id = ?1",
+                params![project_id, secs],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(())
+    }
+
+    /// mark a project's content as changed without touching last_indexed_at.
+    pub fn bump_project_generation(&self, project_id: i64) -> Result<()> {
         self.conn
             .execute(
-                "UPDATE projects SET last_indexed_at = datetime('now') WHERE id = ?1",
+                "UPDATE projects SET generation = generation + 1 WHERE id = ?1",
+                params![project_id],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn project_generation(&self, project_id: i64) -> Result<i64> {
+        self.conn
+            .query_row(
+                "SELECT generation FROM projects WHERE id = ?1",
+                params![project_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// moves whenever any project's content or the set of stored vectors
+    /// changes, in this process or another. keys caches built over every
+    /// project, like the mcp server's vector index.
+    pub fn vectors_generation(&self) -> Result<(i64, i64)> {
+        self.conn
+            .query_row(
+                "SELECT (SELECT COALESCE(SUM(generation), 0) FROM projects),
+                        (SELECT COUNT(*) FROM embeddings)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// last index time as unix seconds, or None if never indexed.
+    pub fn project_last_indexed_unix(&self, project_id: i64) -> Result<Option<i64>> {
+        self.conn
+            .query_row(
+                "SELECT CAST(strftime('%s', last_indexed_at) AS INTEGER) FROM projects WHERE id = ?1",
+                params![project_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// signature of the embedding model the project's vectors came from, if recorded.
+    pub fn project_embedding_model(&self, project_id: i64) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT embedding_model FROM projects WHERE id = ?1",
+                params![project_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    pub fn set_project_embedding_model(&self, project_id: i64, signature: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE projects SET embedding_model = ?1 WHERE id = ?2",
+                params![signature, project_id],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(())
+    }
+
+    /// record that a project was searched, for least-recently-queried eviction.
+    pub fn touch_project_queried(&self, project_id: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE projects SET last_queried_at = datetime('now') WHERE id = ?1",
+                params![project_id],
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(())
+    }
+
+    /// true once a project's embeddings were evicted to stay under the size budget.
+    pub fn project_needs_reembed(&self, project_id: i64) -> Result<bool> {
+        let flag: i64 = self
+            .conn
+            .query_row(
+                "SELECT needs_reembed FROM projects WHERE id = ?1",
+                params![project_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(flag != 0)
+    }
+
+    pub fn clear_project_needs_reembed(&self, project_id: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE projects SET needs_reembed = 0 WHERE id = ?1",
                 params![project_id],
             )
             .map_err(|e| Error::Sqlite(e.to_string()))?;
@@ -76,6 +186,20 @@ impl Store {
         Ok(projects)
     }
 
+    /// the project containing `cwd`, else the only project if there is
+    /// just one
+    pub fn detect_project(&self, cwd: &str) -> Result<Option<Project>> {
+        if let Some(project) = self.find_project_by_path(cwd)? {
+            return Ok(Some(project));
+        }
+        let mut projects = self.list_projects()?;
+        Ok(if projects.len() == 1 {
+            projects.pop()
+        } else {
+            None
+        })
+    }
+
     pub fn find_project_by_path(&self, dir_path: &str) -> Result<Option<Project>> {
         use rusqlite::OptionalExtension;
         let escaped = escape_like_pattern(dir_path);
@@ -104,6 +228,55 @@ impl Store {
 mod tests {
     use crate::index::store::tests::test_store;
     use srag_common::types::Language;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_project_generation_bumps() {
+        let (store, _dir) = test_store();
+        let id = store.upsert_project("gen-proj", "/tmp/gen").unwrap();
+        assert_eq!(store.project_generation(id).unwrap(), 0);
+
+        store
+            .update_project_indexed_at(id, SystemTime::now())
+            .unwrap();
+        assert_eq!(store.project_generation(id).unwrap(), 1);
+
+        store.bump_project_generation(id).unwrap();
+        assert_eq!(store.project_generation(id).unwrap(), 2);
+
+        // a change to any project moves the store-wide generation
+        let before = store.vectors_generation().unwrap();
+        let other = store.upsert_project("other", "/tmp/other").unwrap();
+        store.bump_project_generation(other).unwrap();
+        assert_ne!(store.vectors_generation().unwrap(), before);
+    }
+
+    #[test]
+    fn test_project_last_indexed_unix() {
+        let (store, _dir) = test_store();
+        let id = store.upsert_project("proj", "/tmp").unwrap();
+        assert!(store.project_last_indexed_unix(id).unwrap().is_none());
+
+        let started = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        store.update_project_indexed_at(id, started).unwrap();
+        let indexed = store.project_last_indexed_unix(id).unwrap().unwrap();
+        assert_eq!(indexed, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_project_embedding_model() {
+        let (store, _dir) = test_store();
+        let id = store.upsert_project("proj", "/tmp").unwrap();
+        assert!(store.project_embedding_model(id).unwrap().is_none());
+
+        store
+            .set_project_embedding_model(id, "model-a/384")
+            .unwrap();
+        assert_eq!(
+            store.project_embedding_model(id).unwrap().as_deref(),
+            Some("model-a/384")
+        );
+    }
 
     #[test]
     fn test_project_crud() {
@@ -191,7 +364,9 @@ mod tests {
     fn test_update_project_indexed_at() {
         let (store, _dir) = test_store();
         let pid = store.upsert_project("proj", "/tmp").unwrap();
-        store.update_project_indexed_at(pid).unwrap();
+        store
+            .update_project_indexed_at(pid, SystemTime::now())
+            .unwrap();
 
         let projects = store.list_projects().unwrap();
         assert!(projects[0].last_indexed_at.is_some());
@@ -4,7 +4,7 @@
 use rusqlite::params;
 use srag_common::{Error, Result};
 
-use super::{escape_like_pattern, LanguageStats, ProjectPatterns, Store};
+use super::{escape_like_pattern, LanguageStats, ProjectPatterns, Store, MAX_LITERAL_RESULTS};
 
 impl Store {
     pub fn search_symbols(
@@ -25,18 +25,33 @@ impl Store {
     ) -> Result<Vec<(srag_common::types::Chunk, String)>> {
         let escaped = escape_like_pattern(pattern);
         let like_pattern = format!("%{}%", escaped);
+        let prefix_pattern = format!("{}%", escaped);
+        // exact matches first, then prefixes, then any other substring;
+        // chunk id breaks ties so pages never overlap
         let query = if project_id.is_some() {
             "SELECT c.id, c.file_id, c.content, c.symbol, c.symbol_kind,
                     c.start_line, c.end_line, c.language, f.path, c.suspicious
              FROM chunks c JOIN files f ON c.file_id = f.id
-             WHERE c.symbol LIKE ?1 ESCAPE '\\' AND f.project_id = ?2
-             ORDER BY c.symbol LIMIT ?3 OFFSET ?4"
+             WHERE c.symbol LIKE ?1 ESCAPE '\\' AND f.project_id = ?4
+             ORDER BY CASE
+                        WHEN c.symbol = ?3 COLLATE NOCASE THEN 0
+                        WHEN c.symbol LIKE ?2 ESCAPE '\\' THEN 1
+                        ELSE 2
+                      END,
+                      c.symbol, c.id
+             LIMIT ?5 OFFSET ?6"
         } else {
             "SELECT c.id, c.file_id, c.content, c.symbol, c.symbol_kind,
                     c.start_line, c.end_line, c.language, f.path, c.suspicious
              FROM chunks c JOIN files f ON c.file_id = f.id
              WHERE c.symbol LIKE ?1 ESCAPE '\\'
-             ORDER BY c.symbol LIMIT ?2 OFFSET ?3"
+             ORDER BY CASE
+                        WHEN c.symbol = ?3 COLLATE NOCASE THEN 0
+                        WHEN c.symbol LIKE ?2 ESCAPE '\\' THEN 1
+                        ELSE 2
+                      END,
+                      c.symbol, c.id
+             LIMIT ?4 OFFSET ?5"
         };
 
         let mut stmt = self
@@ -46,12 +61,25 @@ impl Store {
 
         let rows = if let Some(pid) = project_id {
             stmt.query_map(
-                params![like_pattern, pid, limit as i64, offset as i64],
+                params![
+                    like_pattern,
+                    prefix_pattern,
+                    pattern,
+                    pid,
+                    limit as i64,
+                    offset as i64
+                ],
                 Self::map_chunk_row,
             )
         } else {
             stmt.query_map(
-                params![like_pattern, limit as i64, offset as i64],
+                params![
+                    like_pattern,
+                    prefix_pattern,
+                    pattern,
+                    limit as i64,
+                    offset as i64
+                ],
                 Self::map_chunk_row,
             )
         }
@@ -64,6 +92,48 @@ impl Store {
         Ok(results)
     }
 
+    /// chunks containing `substring` exactly, case and punctuation
+    /// included, for text the fts tokenizer splits up such as `foo_bar` or
+    /// `::new`. a full scan of chunk content, so slower than fts; at most
+    /// `MAX_LITERAL_RESULTS` come back, in path and line order.
+    pub fn search_literal(
+        &self,
+        substring: &str,
+        project_id: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<(srag_common::types::Chunk, String)>> {
+        if substring.is_empty() {
+            return Ok(Vec::new());
+        }
+        let like_pattern = format!("%{}%", escape_like_pattern(substring));
+        // LIKE ignores ascii case, so instr() keeps the match exact
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT c.id, c.file_id, c.content, c.symbol, c.symbol_kind,
+                        c.start_line, c.end_line, c.language, f.path, c.suspicious
+                 FROM chunks c JOIN files f ON c.file_id = f.id
+                 WHERE c.content LIKE ?1 ESCAPE '\\' AND instr(c.content, ?2) > 0
+                   AND (?3 IS NULL OR f.project_id = ?3)
+                 ORDER BY f.path, c.start_line, c.id
+                 LIMIT ?4",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let limit = limit.min(MAX_LITERAL_RESULTS);
+        let rows = stmt
+            .query_map(
+                params![like_pattern, substring, project_id, limit as i64],
+                Self::map_chunk_row,
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row.map_err(|e| Error::Sqlite(e.to_string()))?);
+        }
+        Ok(results)
+    }
+
     pub fn get_file_chunks(
         &self,
         project_id: i64,
@@ -209,6 +279,47 @@ impl Store {
         Ok(languages)
     }
 
+    /// a deleted file pieced back together from its chunks, for when neither
+    /// disk nor stored content has it
+    pub fn reconstruct_file(&self, project_id: i64, file_path: &str) -> Result<String> {
+        let chunks = self.get_file_chunks(project_id, file_path)?;
+        Ok(Self::reconstruct_from_chunks(&chunks, 1))
+    }
+
+    /// join chunks in line order from `first_line` on. overlapping lines are
+    /// written once, and lines no chunk covers are replaced by a
+    /// `... (lines N-M omitted) ...` marker so the holes stay visible.
+    pub fn reconstruct_from_chunks(
+        chunks: &[srag_common::types::Chunk],
+        first_line: u32,
+    ) -> String {
+        let mut ordered: Vec<&srag_common::types::Chunk> = chunks.iter().collect();
+        ordered.sort_by_key(|c| (c.start_line, c.end_line));
+
+        let mut content = String::new();
+        let mut next_line = first_line.max(1);
+        for chunk in ordered {
+            if chunk.end_line < next_line {
+                continue;
+            }
+            if chunk.start_line > next_line {
+                let last = chunk.start_line - 1;
+                if last == next_line {
+                    content.push_str(&format!("... (line {} omitted) ...\n", next_line));
+                } else {
+                    content.push_str(&format!("... (lines {}-{} omitted) ...\n", next_line, last));
+                }
+            }
+            let skip = next_line.saturating_sub(chunk.start_line) as usize;
+            for line in chunk.content.lines().skip(skip) {
+                content.push_str(line);
+                content.push('\n');
+            }
+            next_line = chunk.end_line + 1;
+        }
+        content
+    }
+
     pub(crate) fn map_chunk_row(
         row: &rusqlite::Row<'_>,
     ) -> rusqlite::Result<(srag_common::types::Chunk, String)> {
@@ -236,7 +347,132 @@ impl Store {
 
 #[cfg(test)]
 mod tests {
-    use crate::index::store::tests::test_store;
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use crate::index::store::Store;
+    use srag_common::types::{Chunk, Language};
+
+    fn add_symbols(store: &Store, pid: i64, symbols: &[&str]) {
+        let path = "/tmp/proj/lib.rs";
+        let file_id = seed_file(store, pid, path, Language::Rust);
+        for symbol in symbols {
+            let chunk = Chunk {
+                symbol: Some(symbol.to_string()),
+                symbol_kind: Some("function".to_string()),
+                ..test_chunk(file_id, &format!("fn {}() {{}}", symbol), 1)
+            };
+            seed_chunk(store, &chunk, path);
+        }
+    }
+
+    fn add_contents(store: &Store, pid: i64, contents: &[&str]) {
+        let path = "/tmp/proj/main.rs";
+        let file_id = seed_file(store, pid, path, Language::Rust);
+        for (i, content) in contents.iter().enumerate() {
+            seed_chunk(store, &test_chunk(file_id, content, i as u32 + 1), path);
+        }
+    }
+
+    fn symbols(results: &[(Chunk, String)]) -> Vec<&str> {
+        results
+            .iter()
+            .map(|(chunk, _)| chunk.symbol.as_deref().unwrap())
+            .collect()
+    }
+
+    fn lines_chunk(start: u32, end: u32) -> Chunk {
+        let content = (start..=end)
+            .map(|i| format!("line {}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        test_chunk(1, &content, start)
+    }
+
+    fn numbered(lines: std::ops::RangeInclusive<u32>) -> String {
+        lines.map(|i| format!("line {}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_reconstruct_abutting_chunks_have_no_marker() {
+        let chunks = vec![lines_chunk(4, 6), lines_chunk(1, 3), lines_chunk(7, 7)];
+        let text = Store::reconstruct_from_chunks(&chunks, 1);
+        assert_eq!(text, numbered(1..=7));
+        assert!(!text.contains("omitted"));
+    }
+
+    #[test]
+    fn test_reconstruct_marks_gaps_and_drops_overlap() {
+        let chunks = vec![
+            lines_chunk(3, 5),
+            lines_chunk(4, 8), // overlaps the first
+            lines_chunk(5, 6), // inside both
+            lines_chunk(10, 11),
+            lines_chunk(15, 15),
+        ];
+        let text = Store::reconstruct_from_chunks(&chunks, 1);
+        assert_eq!(
+            text,
+            format!(
+                "... (lines 1-2 omitted) ...\n{}... (line 9 omitted) ...\n{}... (lines 12-14 omitted) ...\n{}",
+                numbered(3..=8),
+                numbered(10..=11),
+                numbered(15..=15)
+            )
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_file_from_store() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        add_symbols(&store, pid, &["alpha"]);
+        assert_eq!(
+            store.reconstruct_file(pid, "/tmp/proj/lib.rs").unwrap(),
+            "fn alpha() {}\n"
+        );
+        assert_eq!(
+            store.reconstruct_file(pid, "/tmp/proj/none.rs").unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_search_symbols_ranks_exact_then_prefix_then_substring() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        add_symbols(&store, pid, &["myfoo", "afoo", "foobar", "foo", "fooa"]);
+
+        let results = store.search_symbols("foo", Some(pid), 10).unwrap();
+        assert_eq!(
+            symbols(&results),
+            vec!["foo", "fooa", "foobar", "afoo", "myfoo"]
+        );
+
+        let results = store.search_symbols("foo", None, 10).unwrap();
+        assert_eq!(symbols(&results)[0], "foo");
+    }
+
+    #[test]
+    fn test_search_symbols_pages_follow_ranking() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        add_symbols(&store, pid, &["myfoo", "foobar", "foo", "foo", "xfoo"]);
+
+        let mut paged = Vec::new();
+        for offset in (0..6).step_by(2) {
+            let page = store
+                .search_symbols_paginated("foo", Some(pid), 2, offset)
+                .unwrap();
+            paged.extend(page.into_iter().map(|(chunk, _)| chunk.id.unwrap()));
+        }
+        let all: Vec<i64> = store
+            .search_symbols("foo", Some(pid), 10)
+            .unwrap()
+            .into_iter()
+            .map(|(chunk, _)| chunk.id.unwrap())
+            .collect();
+        assert_eq!(paged, all);
+        assert_eq!(all.len(), 5);
+    }
 
     #[test]
     fn test_sql_injection_in_search_pattern() {
@@ -252,4 +488,58 @@ mod tests {
         let results = store.search_symbols("_", Some(pid), 10);
         assert!(results.is_ok());
     }
+
+    fn contents(results: &[(Chunk, String)]) -> Vec<&str> {
+        results.iter().map(|(c, _)| c.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_search_literal_matches_what_fts_splits() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        add_contents(
+            &store,
+            pid,
+            &[
+                "let foo_bar = 1;",
+                "foo bar, no underscore",
+                "let v = Vec::new();",
+                "fn new() -> Self",
+            ],
+        );
+
+        // the tokenizer drops `_` and `::`, so fts also finds the lookalikes
+        assert_eq!(store.search_fts("foo_bar", 10).unwrap().len(), 2);
+        assert_eq!(store.search_fts("::new", 10).unwrap().len(), 2);
+
+        let found = store.search_literal("foo_bar", Some(pid), 10).unwrap();
+        assert_eq!(contents(&found), vec!["let foo_bar = 1;"]);
+        let found = store.search_literal("::new", Some(pid), 10).unwrap();
+        assert_eq!(contents(&found), vec!["let v = Vec::new();"]);
+    }
+
+    #[test]
+    fn test_search_literal_is_exact() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("proj", "/tmp/proj").unwrap();
+        add_contents(
+            &store,
+            pid,
+            &["let Total = 1;", "let pct = 50%;", "let total = 2;"],
+        );
+
+        let found = store.search_literal("total", None, 10).unwrap();
+        assert_eq!(contents(&found), vec!["let total = 2;"]);
+        // like wildcards are matched literally
+        let found = store.search_literal("50%", None, 10).unwrap();
+        assert_eq!(contents(&found), vec!["let pct = 50%;"]);
+        assert!(store.search_literal("_", None, 10).unwrap().is_empty());
+        assert!(store.search_literal("", None, 10).unwrap().is_empty());
+
+        let other = store.upsert_project("other", "/tmp/other").unwrap();
+        assert!(store
+            .search_literal("total", Some(other), 10)
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -5,7 +5,7 @@ use rusqlite::{params, OptionalExtension};
 use srag_common::types::ConversationTurn;
 use srag_common::{Error, Result};
 
-use super::Store;
+use super::{SessionSummary, Store};
 
 impl Store {
     pub fn create_session(&self, session_id: &str, project_name: Option<&str>) -> Result<()> {
@@ -29,6 +29,107 @@ impl Store {
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// all sessions, most recent first
+    pub fn list_sessions(&self) -> Result<Vec<SessionSummary>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT s.id, s.project_name, s.created_at,
+                        (SELECT COUNT(*) FROM turns t WHERE t.session_id = s.id)
+                 FROM sessions s
+                 ORDER BY s.created_at DESC, s.rowid DESC",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(SessionSummary {
+                    id: row.get(0)?,
+                    project_name: row.get(1)?,
+                    created_at: row.get(2)?,
+                    turn_count: row.get::<_, i64>(3)? as u64,
+                })
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    /// session ids starting with `prefix`, so users can type the short id
+    /// shown by `srag sessions list`
+    pub fn find_sessions_by_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id FROM sessions WHERE substr(id, 1, length(?1)) = ?1
+                 ORDER BY created_at DESC",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![prefix], |row| row.get(0))
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    pub fn get_session(&self, session_id: &str) -> Result<Option<SessionSummary>> {
+        self.conn
+            .query_row(
+                "SELECT id, project_name, created_at,
+                        (SELECT COUNT(*) FROM turns WHERE session_id = ?1)
+                 FROM sessions WHERE id = ?1",
+                params![session_id],
+                |row| {
+                    Ok(SessionSummary {
+                        id: row.get(0)?,
+                        project_name: row.get(1)?,
+                        created_at: row.get(2)?,
+                        turn_count: row.get::<_, i64>(3)? as u64,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
+    pub fn count_turns(&self, session_id: &str) -> Result<u64> {
+        let count: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM turns WHERE session_id = ?1",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(count as u64)
+    }
+
+    /// delete a session and, via the foreign key cascade, its turns.
+    /// returns false if no such session existed.
+    pub fn delete_session(&self, session_id: &str) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM sessions WHERE id = ?1", params![session_id])
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        Ok(deleted > 0)
+    }
+
+    /// the full transcript of a session, oldest turn first
+    pub fn get_all_turns(&self, session_id: &str) -> Result<Vec<ConversationTurn>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, session_id, role, content, sources, created_at
+                 FROM turns WHERE session_id = ?1
+                 ORDER BY id ASC",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![session_id], Self::map_turn)
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
     pub fn get_recent_turns(
         &self,
         session_id: &str,
@@ -43,16 +144,7 @@ impl Store {
             )
             .map_err(|e| Error::Sqlite(e.to_string()))?;
         let rows = stmt
-            .query_map(params![session_id, limit], |row| {
-                Ok(ConversationTurn {
-                    id: Some(row.get(0)?),
-                    session_id: row.get(1)?,
-                    role: row.get(2)?,
-                    content: row.get(3)?,
-                    sources: row.get(4)?,
-                    created_at: row.get(5)?,
-                })
-            })
+            .query_map(params![session_id, limit], Self::map_turn)
             .map_err(|e| Error::Sqlite(e.to_string()))?;
         let mut turns = Vec::new();
         for row in rows {
@@ -62,6 +154,17 @@ impl Store {
         Ok(turns)
     }
 
+    fn map_turn(row: &rusqlite::Row) -> rusqlite::Result<ConversationTurn> {
+        Ok(ConversationTurn {
+            id: Some(row.get(0)?),
+            session_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            sources: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+
     pub fn enqueue_reindex(
         &self,
         project_id: i64,
@@ -102,6 +205,35 @@ impl Store {
         }
     }
 
+    /// take every queued event for a project, oldest first
+    pub fn drain_reindex_queue(&self, project_id: i64) -> Result<Vec<(i64, String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, file_path, event_type FROM reindex_queue
+                 WHERE project_id = ?1 ORDER BY queued_at ASC, id ASC",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let entries: Vec<(i64, String, String)> = stmt
+            .query_map(params![project_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+
+        let mut delete = self
+            .conn
+            .prepare("DELETE FROM reindex_queue WHERE id = ?1")
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        for (id, _, _) in &entries {
+            delete
+                .execute(params![id])
+                .map_err(|e| Error::Sqlite(e.to_string()))?;
+        }
+        Ok(entries)
+    }
+
     pub fn reindex_queue_len(&self, project_id: i64) -> Result<u64> {
         let count: i64 = self
             .conn
@@ -114,3 +246,102 @@ impl Store {
         Ok(count as u64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::index::store::tests::test_store;
+    use crate::index::store::Store;
+    use srag_common::types::ConversationTurn;
+
+    fn add_turn(store: &Store, session_id: &str, role: &str, content: &str) {
+        store
+            .add_turn(&ConversationTurn {
+                id: None,
+                session_id: session_id.to_string(),
+                role: role.to_string(),
+                content: content.to_string(),
+                sources: None,
+                created_at: String::new(),
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_list_sessions_with_turn_counts() {
+        let (store, _dir) = test_store();
+        store.create_session("aaaa-1", Some("proj")).unwrap();
+        store.create_session("bbbb-2", None).unwrap();
+        add_turn(&store, "aaaa-1", "user", "hi");
+        add_turn(&store, "aaaa-1", "assistant", "hello");
+
+        let sessions = store.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 2);
+        // same created_at second, so newest insert comes first
+        assert_eq!(sessions[0].id, "bbbb-2");
+        assert_eq!(sessions[0].turn_count, 0);
+        assert_eq!(sessions[1].project_name.as_deref(), Some("proj"));
+        assert_eq!(sessions[1].turn_count, 2);
+    }
+
+    #[test]
+    fn test_count_turns_and_get_all_turns() {
+        let (store, _dir) = test_store();
+        store.create_session("s1", None).unwrap();
+        for i in 0..5 {
+            add_turn(&store, "s1", "user", &format!("turn {}", i));
+        }
+
+        assert_eq!(store.count_turns("s1").unwrap(), 5);
+        assert_eq!(store.count_turns("missing").unwrap(), 0);
+        let turns = store.get_all_turns("s1").unwrap();
+        assert_eq!(turns.len(), 5);
+        assert_eq!(turns[0].content, "turn 0");
+        assert_eq!(turns[4].content, "turn 4");
+    }
+
+    #[test]
+    fn test_delete_session_cascades_turns() {
+        let (store, _dir) = test_store();
+        store.create_session("s1", None).unwrap();
+        store.create_session("s2", None).unwrap();
+        add_turn(&store, "s1", "user", "q");
+        add_turn(&store, "s2", "user", "q");
+
+        assert!(store.delete_session("s1").unwrap());
+        assert!(!store.delete_session("s1").unwrap());
+        assert_eq!(store.count_turns("s1").unwrap(), 0);
+        assert_eq!(store.count_turns("s2").unwrap(), 1);
+        assert!(store.get_session("s1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_sessions_by_prefix() {
+        let (store, _dir) = test_store();
+        store.create_session("abc123", None).unwrap();
+        store.create_session("abd456", None).unwrap();
+
+        assert_eq!(store.find_sessions_by_prefix("abc").unwrap(), ["abc123"]);
+        assert_eq!(store.find_sessions_by_prefix("ab").unwrap().len(), 2);
+        assert!(store.find_sessions_by_prefix("a%").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_drain_reindex_queue() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("p", "/tmp/p").unwrap();
+        let other = store.upsert_project("q", "/tmp/q").unwrap();
+        store.enqueue_reindex(pid, "a.rs", "modify").unwrap();
+        store.enqueue_reindex(pid, "b.rs", "delete").unwrap();
+        store.enqueue_reindex(other, "c.rs", "modify").unwrap();
+
+        let drained = store.drain_reindex_queue(pid).unwrap();
+        let paths: Vec<_> = drained
+            .iter()
+            .map(|(_, p, e)| (p.as_str(), e.as_str()))
+            .collect();
+        assert_eq!(paths, [("a.rs", "modify"), ("b.rs", "delete")]);
+        assert_eq!(store.reindex_queue_len(pid).unwrap(), 0);
+        assert_eq!(store.reindex_queue_len(other).unwrap(), 1);
+        assert!(store.drain_reindex_queue(pid).unwrap().is_empty());
+    }
+}
@@ -43,6 +43,56 @@ impl Store {
         Ok(count as u64)
     }
 
+    /// chunks the injection scanner flagged
+    pub fn suspicious_chunk_count(&self, project_id: Option<i64>) -> Result<u64> {
+        let count: i64 = if let Some(pid) = project_id {
+            self.conn
+                .query_row(
+                    "SELECT COUNT(*) FROM chunks c
+                     JOIN files f ON c.file_id = f.id
+                     WHERE f.project_id = ?1 AND c.suspicious = 1",
+                    params![pid],
+                    |row| row.get(0),
+                )
+                .map_err(|e| Error::Sqlite(e.to_string()))?
+        } else {
+            self.conn
+                .query_row(
+                    "SELECT COUNT(*) FROM chunks WHERE suspicious = 1",
+                    [],
+                    |row| row.get(0),
+                )
+                .map_err(|e| Error::Sqlite(e.to_string()))?
+        };
+        Ok(count as u64)
+    }
+
+    /// files of a project with the most flagged chunks, most first
+    pub fn top_suspicious_files(
+        &self,
+        project_id: i64,
+        limit: usize,
+    ) -> Result<Vec<(String, u64)>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT f.path, COUNT(*) AS flagged FROM chunks c
+                 JOIN files f ON c.file_id = f.id
+                 WHERE f.project_id = ?1 AND c.suspicious = 1
+                 GROUP BY f.id
+                 ORDER BY flagged DESC, f.path
+                 LIMIT ?2",
+            )
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![project_id, limit as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })
+            .map_err(|e| Error::Sqlite(e.to_string()))?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::Sqlite(e.to_string()))
+    }
+
     pub fn total_size_bytes(&self, project_id: Option<i64>) -> Result<u64> {
         let size: i64 = if let Some(pid) = project_id {
             self.conn
@@ -67,8 +117,8 @@ impl Store {
 
 #[cfg(test)]
 mod tests {
-    use crate::index::store::tests::test_store;
-    use srag_common::types::{FileRecord, Language};
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use srag_common::types::{Chunk, FileRecord, Language};
 
     #[test]
     fn test_statistics() {
@@ -94,4 +144,43 @@ mod tests {
         assert_eq!(store.file_count(Some(pid)).unwrap(), 1);
         assert_eq!(store.total_size_bytes(Some(pid)).unwrap(), 500);
     }
+
+    #[test]
+    fn test_suspicious_counts_and_top_files() {
+        let (store, _dir) = test_store();
+        let pid = store.upsert_project("vendor", "/tmp/vendor").unwrap();
+        let other = store.upsert_project("clean", "/tmp/clean").unwrap();
+        let file =
+            |project_id: i64, path: &str| seed_file(&store, project_id, path, Language::Rust);
+        let chunk = |file_id: i64, suspicious: bool| {
+            let chunk = Chunk {
+                suspicious,
+                ..test_chunk(file_id, "fn x() {}", 1)
+            };
+            seed_chunk(&store, &chunk, "x.rs");
+        };
+
+        let a = file(pid, "/tmp/vendor/a.rs");
+        let b = file(pid, "/tmp/vendor/b.rs");
+        let c = file(other, "/tmp/clean/c.rs");
+        chunk(a, true);
+        chunk(a, false);
+        chunk(b, true);
+        chunk(b, true);
+        chunk(c, false);
+
+        assert_eq!(store.suspicious_chunk_count(Some(pid)).unwrap(), 3);
+        assert_eq!(store.suspicious_chunk_count(Some(other)).unwrap(), 0);
+        assert_eq!(store.suspicious_chunk_count(None).unwrap(), 3);
+
+        assert_eq!(
+            store.top_suspicious_files(pid, 10).unwrap(),
+            vec![
+                ("/tmp/vendor/b.rs".to_string(), 2),
+                ("/tmp/vendor/a.rs".to_string(), 1),
+            ]
+        );
+        assert_eq!(store.top_suspicious_files(pid, 1).unwrap().len(), 1);
+        assert!(store.top_suspicious_files(other, 10).unwrap().is_empty());
+    }
 }
@@ -1,31 +1,64 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+use std::fmt;
+use std::future::Future;
 use std::net::SocketAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 
 use srag_common::types::{JsonRpcRequest, JsonRpcResponse};
 use srag_common::{Error, Result};
 
+use crate::config::{Config, ResourceConfig};
+
 const DEFAULT_MAX_POOL_SIZE: usize = 4;
 const CONNECTION_TIMEOUT_SECS: u64 = 10;
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// where the ML service listens, as recorded in its port file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceAddr {
+    Tcp(SocketAddr),
+    /// unix domain socket, written to the port file as `unix:<path>`
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ServiceAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceAddr::Tcp(addr) => write!(f, "{}", addr),
+            ServiceAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+enum IpcStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
 
 /// ML service client with connection pooling for better concurrency.
 /// Connections are reused when available, new ones created when needed.
 pub struct MlClient {
-    addr: SocketAddr,
-    pool: tokio::sync::Mutex<Vec<TcpStream>>,
+    addr: std::sync::Mutex<ServiceAddr>,
+    /// re-read on reconnect in case the service came back on a new port
+    port_file: Option<PathBuf>,
+    retry: RetryPolicy,
+    pool: tokio::sync::Mutex<Vec<IpcStream>>,
     max_pool_size: usize,
     next_id: AtomicU64,
-    auth_token: Option<String>,
+    auth_token: std::sync::Mutex<Option<String>>,
 }
 
 /// read the port file written by the python ML service and return the address.
-pub fn read_service_addr(port_file: &Path) -> Result<SocketAddr> {
+/// holds either a tcp port on localhost or `unix:<socket path>`.
+pub fn read_service_addr(port_file: &Path) -> Result<ServiceAddr> {
     let content = std::fs::read_to_string(port_file).map_err(|e| {
         Error::Ipc(format!(
             "failed to read port file {}: {}",
@@ -33,43 +66,184 @@ pub fn read_service_addr(port_file: &Path) -> Result<SocketAddr> {
             e
         ))
     })?;
+    let content = content.trim();
+    if let Some(path) = content.strip_prefix("unix:") {
+        if path.is_empty() {
+            return Err(Error::Ipc(format!(
+                "Empty socket path in {}",
+                port_file.display()
+            )));
+        }
+        return Ok(ServiceAddr::Unix(PathBuf::from(path)));
+    }
     let port: u16 = content
-        .trim()
         .parse()
         .map_err(|e| Error::Ipc(format!("Invalid port in {}: {}", port_file.display(), e)))?;
-    Ok(SocketAddr::from(([127, 0, 0, 1], port)))
+    Ok(ServiceAddr::Tcp(SocketAddr::from(([127, 0, 0, 1], port))))
+}
+
+/// bounded exponential backoff for connecting to the ML service
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub initial_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            initial_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config(resource: &ResourceConfig) -> Self {
+        Self {
+            attempts: resource.ml_connect_attempts.max(1),
+            initial_delay: Duration::from_millis(resource.ml_connect_backoff_ms),
+        }
+    }
+
+    /// delay before retry `n` (0-based): initial, 2x, 4x, ... capped
+    fn delay(&self, n: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(1 << n.min(16))
+            .min(MAX_RETRY_DELAY)
+    }
+
+    /// run `op` until it succeeds or the attempts run out, sleeping with
+    /// backoff in between. returns the last error.
+    pub(crate) async fn run<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut n = 0;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) if n + 1 >= self.attempts.max(1) => return Err(e),
+                Err(e) => {
+                    let delay = self.delay(n);
+                    tracing::debug!("ML service connect failed ({}), retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    n += 1;
+                }
+            }
+        }
+    }
+}
+
+/// a failed exchange. a dropped connection is worth one reconnect.
+enum SendError {
+    Disconnected(std::io::Error),
+    Failed(Error),
+}
+
+impl From<std::io::Error> for SendError {
+    fn from(e: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match e.kind() {
+            ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::UnexpectedEof => SendError::Disconnected(e),
+            _ => SendError::Failed(Error::Ipc(e.to_string())),
+        }
+    }
+}
+
+impl From<SendError> for Error {
+    fn from(e: SendError) -> Self {
+        match e {
+            SendError::Disconnected(e) => Error::Ipc(e.to_string()),
+            SendError::Failed(e) => e,
+        }
+    }
 }
 
 impl MlClient {
-    pub async fn connect(addr: SocketAddr) -> Result<Self> {
-        Self::connect_with_pool_size(addr, DEFAULT_MAX_POOL_SIZE).await
+    pub async fn connect(addr: ServiceAddr) -> Result<Self> {
+        Self::connect_with_retry(addr, RetryPolicy::default()).await
+    }
+
+    pub async fn connect_unix(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::connect(ServiceAddr::Unix(path.into())).await
+    }
+
+    pub async fn connect_with_pool_size(addr: ServiceAddr, max_pool_size: usize) -> Result<Self> {
+        Self::open(addr, None, max_pool_size, RetryPolicy::default()).await
+    }
+
+    pub async fn connect_with_retry(addr: ServiceAddr, retry: RetryPolicy) -> Result<Self> {
+        Self::open(addr, None, DEFAULT_MAX_POOL_SIZE, retry).await
     }
 
-    pub async fn connect_with_pool_size(addr: SocketAddr, max_pool_size: usize) -> Result<Self> {
+    /// connect to the running ML service using its port file and the retry
+    /// settings from config. reconnects re-read the port file, so a restarted
+    /// service on a new port is picked up.
+    pub async fn connect_service(config: &Config) -> Result<Self> {
+        let port_file = Config::port_file_path();
+        let addr = read_service_addr(&port_file)?;
+        Self::open(
+            addr,
+            Some(port_file),
+            DEFAULT_MAX_POOL_SIZE,
+            RetryPolicy::from_config(&config.resource),
+        )
+        .await
+    }
+
+    async fn open(
+        addr: ServiceAddr,
+        port_file: Option<PathBuf>,
+        max_pool_size: usize,
+        retry: RetryPolicy,
+    ) -> Result<Self> {
         // verify connection works by creating one
-        let stream = Self::create_connection(addr).await?;
+        let stream = retry.run(|| Self::create_connection(&addr)).await?;
         let auth_token = crate::ipc::lifecycle::read_auth_token().ok();
 
         Ok(Self {
-            addr,
+            addr: std::sync::Mutex::new(addr),
+            port_file,
+            retry,
             pool: tokio::sync::Mutex::new(vec![stream]),
             max_pool_size,
             next_id: AtomicU64::new(1),
-            auth_token,
+            auth_token: std::sync::Mutex::new(auth_token),
         })
     }
 
-    async fn create_connection(addr: SocketAddr) -> Result<TcpStream> {
-        tokio::time::timeout(
-            std::time::Duration::from_secs(CONNECTION_TIMEOUT_SECS),
-            TcpStream::connect(addr),
-        )
-        .await
-        .map_err(|_| Error::Ipc(format!("Connection to {} timed out", addr)))?
-        .map_err(|e| Error::Ipc(format!("Failed to connect to {}: {}", addr, e)))
+    async fn create_connection(addr: &ServiceAddr) -> Result<IpcStream> {
+        let connect = async {
+            match addr {
+                ServiceAddr::Tcp(tcp) => TcpStream::connect(tcp).await.map(IpcStream::Tcp),
+                #[cfg(unix)]
+                ServiceAddr::Unix(path) => tokio::net::UnixStream::connect(path)
+                    .await
+                    .map(IpcStream::Unix),
+                #[cfg(not(unix))]
+                ServiceAddr::Unix(_) => Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "unix sockets are not supported on this platform",
+                )),
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(CONNECTION_TIMEOUT_SECS), connect)
+            .await
+            .map_err(|_| Error::Ipc(format!("Connection to {} timed out", addr)))?
+            .map_err(|e| Error::Ipc(format!("Failed to connect to {}: {}", addr, e)))
     }
 
-    async fn acquire_connection(&self) -> Result<TcpStream> {
+    fn addr(&self) -> ServiceAddr {
+        self.addr.lock().unwrap().clone()
+    }
+
+    async fn acquire_connection(&self) -> Result<IpcStream> {
         // try to get from pool first
         {
             let mut pool = self.pool.lock().await;
@@ -78,10 +252,10 @@ impl MlClient {
             }
         }
         // pool empty, create new connection
-        Self::create_connection(self.addr).await
+        Self::create_connection(&self.addr()).await
     }
 
-    async fn release_connection(&self, stream: TcpStream) {
+    async fn release_connection(&self, stream: IpcStream) {
         let mut pool = self.pool.lock().await;
         if pool.len() < self.max_pool_size {
             pool.push(stream);
@@ -89,71 +263,113 @@ impl MlClient {
         // else drop stream (pool full)
     }
 
+    /// after a dropped connection: discard pooled streams, which likely
+    /// point at the same dead service, pick up a new address and token if
+    /// the service restarted, and connect again with backoff.
+    async fn reconnect(&self) -> Result<IpcStream> {
+        self.pool.lock().await.clear();
+        if let Some(addr) = self
+            .port_file
+            .as_deref()
+            .and_then(|f| read_service_addr(f).ok())
+        {
+            *self.addr.lock().unwrap() = addr;
+        }
+        if let Ok(token) = crate::ipc::lifecycle::read_auth_token() {
+            *self.auth_token.lock().unwrap() = Some(token);
+        }
+        let addr = self.addr();
+        self.retry.run(|| Self::create_connection(&addr)).await
+    }
+
     fn next_id(&self) -> u64 {
         self.next_id.fetch_add(1, Ordering::Relaxed)
     }
 
-    pub async fn send(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
-        let json = if let Some(ref token) = self.auth_token {
+    fn encode(&self, request: &JsonRpcRequest) -> Result<Vec<u8>> {
+        let token = self.auth_token.lock().unwrap().clone();
+        Ok(if let Some(token) = token {
             let mut val = serde_json::to_value(request)?;
             if let Some(obj) = val.as_object_mut() {
-                obj.insert("_auth".into(), serde_json::Value::String(token.clone()));
+                obj.insert("_auth".into(), serde_json::Value::String(token));
             }
             serde_json::to_vec(&val)?
         } else {
             serde_json::to_vec(request)?
-        };
+        })
+    }
+
+    async fn exchange(
+        stream: &mut IpcStream,
+        json: &[u8],
+    ) -> std::result::Result<JsonRpcResponse, SendError> {
+        match stream {
+            IpcStream::Tcp(s) => Self::send_on_stream(s, json).await,
+            #[cfg(unix)]
+            IpcStream::Unix(s) => Self::send_on_stream(s, json).await,
+        }
+    }
 
+    pub async fn send(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse> {
+        crate::ipc::lifecycle::record_activity();
+        let json = self.encode(request)?;
         let mut stream = self.acquire_connection().await?;
 
-        match self.send_on_stream(&mut stream, &json).await {
+        let (stream, result) = match Self::exchange(&mut stream, &json).await {
+            Err(SendError::Disconnected(e)) => {
+                // the service went away under us; retry once on a fresh connection
+                drop(stream);
+                tracing::warn!("ML service connection lost ({}), reconnecting", e);
+                let mut stream = self.reconnect().await?;
+                let json = self.encode(request)?;
+                let result = Self::exchange(&mut stream, &json).await;
+                (stream, result)
+            }
+            result => (stream, result),
+        };
+
+        match result {
             Ok(response) => {
+                // a long request counts as activity until it finishes
+                crate::ipc::lifecycle::record_activity();
                 // success - return connection to pool
                 self.release_connection(stream).await;
                 Ok(response)
             }
-            Err(e) => {
-                // error - drop connection, don't return to pool
-                drop(stream);
-                Err(e)
-            }
+            // error - drop connection, don't return to pool
+            Err(e) => Err(e.into()),
         }
     }
 
-    async fn send_on_stream(&self, stream: &mut TcpStream, json: &[u8]) -> Result<JsonRpcResponse> {
+    async fn send_on_stream<S>(
+        stream: &mut S,
+        json: &[u8],
+    ) -> std::result::Result<JsonRpcResponse, SendError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
         let len = json.len() as u32;
 
-        stream
-            .write_all(&len.to_be_bytes())
-            .await
-            .map_err(|e| Error::Ipc(e.to_string()))?;
-        stream
-            .write_all(json)
-            .await
-            .map_err(|e| Error::Ipc(e.to_string()))?;
-        stream
-            .flush()
-            .await
-            .map_err(|e| Error::Ipc(e.to_string()))?;
+        stream.write_all(&len.to_be_bytes()).await?;
+        stream.write_all(json).await?;
+        stream.flush().await?;
 
         let mut len_buf = [0u8; 4];
-        stream
-            .read_exact(&mut len_buf)
-            .await
-            .map_err(|e| Error::Ipc(e.to_string()))?;
+        stream.read_exact(&mut len_buf).await?;
         let resp_len = u32::from_be_bytes(len_buf) as usize;
 
         if resp_len > 10 * 1024 * 1024 {
-            return Err(Error::Ipc(format!("Response too large: {}", resp_len)));
+            return Err(SendError::Failed(Error::Ipc(format!(
+                "Response too large: {}",
+                resp_len
+            ))));
         }
 
         let mut resp_buf = vec![0u8; resp_len];
-        stream
-            .read_exact(&mut resp_buf)
-            .await
-            .map_err(|e| Error::Ipc(e.to_string()))?;
+        stream.read_exact(&mut resp_buf).await?;
 
-        let response: JsonRpcResponse = serde_json::from_slice(&resp_buf)?;
+        let response: JsonRpcResponse =
+            serde_json::from_slice(&resp_buf).map_err(|e| SendError::Failed(e.into()))?;
         Ok(response)
     }
 
@@ -271,8 +487,7 @@ mod tests {
         std::fs::write(&port_file, "12345").unwrap();
 
         let addr = read_service_addr(&port_file).unwrap();
-        assert_eq!(addr.port(), 12345);
-        assert_eq!(addr.ip().to_string(), "127.0.0.1");
+        assert_eq!(addr, ServiceAddr::Tcp("127.0.0.1:12345".parse().unwrap()));
     }
 
     #[test]
@@ -282,7 +497,7 @@ mod tests {
         std::fs::write(&port_file, "  8080\n").unwrap();
 
         let addr = read_service_addr(&port_file).unwrap();
-        assert_eq!(addr.port(), 8080);
+        assert_eq!(addr, ServiceAddr::Tcp("127.0.0.1:8080".parse().unwrap()));
     }
 
     #[test]
@@ -323,4 +538,149 @@ mod tests {
         let result = read_service_addr(&port_file);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_read_service_addr_unix_socket() {
+        let dir = tempdir().unwrap();
+        let port_file = dir.path().join("port");
+        std::fs::write(&port_file, "unix:/run/user/1000/srag/ml.sock\n").unwrap();
+
+        let addr = read_service_addr(&port_file).unwrap();
+        assert_eq!(
+            addr,
+            ServiceAddr::Unix(PathBuf::from("/run/user/1000/srag/ml.sock"))
+        );
+        assert_eq!(addr.to_string(), "unix:/run/user/1000/srag/ml.sock");
+
+        std::fs::write(&port_file, "unix:").unwrap();
+        assert!(read_service_addr(&port_file).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_unix_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ml.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        // minimal length-prefixed json-rpc echo of a ping result
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            loop {
+                let mut len_buf = [0u8; 4];
+                if stream.read_exact(&mut len_buf).await.is_err() {
+                    break;
+                }
+                let mut body = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+                stream.read_exact(&mut body).await.unwrap();
+                let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let response = serde_json::to_vec(&serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "result": {"status": "ok"},
+                    "id": request["id"],
+                }))
+                .unwrap();
+                stream
+                    .write_all(&(response.len() as u32).to_be_bytes())
+                    .await
+                    .unwrap();
+                stream.write_all(&response).await.unwrap();
+            }
+        });
+
+        let client = MlClient::connect_unix(&path).await.unwrap();
+        assert!(client.ping().await.unwrap());
+        assert!(client.ping().await.unwrap());
+    }
+
+    fn fast_retry(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            initial_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_and_caps() {
+        let policy = RetryPolicy {
+            attempts: 10,
+            initial_delay: Duration::from_millis(100),
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(30), MAX_RETRY_DELAY);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_retries_until_listener_accepts() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("ml.sock");
+        let addr = ServiceAddr::Unix(path.clone());
+
+        // the listener only comes up on the third attempt
+        let mut attempts = 0;
+        let mut listener = None;
+        let stream = fast_retry(5)
+            .run(|| {
+                attempts += 1;
+                if attempts == 3 {
+                    listener = Some(tokio::net::UnixListener::bind(&path).unwrap());
+                }
+                MlClient::create_connection(&addr)
+            })
+            .await;
+        assert!(stream.is_ok());
+        assert!(listener.is_some());
+        assert_eq!(attempts, 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_connect_gives_up_after_max_attempts() {
+        let dir = tempdir().unwrap();
+        let addr = ServiceAddr::Unix(dir.path().join("missing.sock"));
+
+        let result = MlClient::connect_with_retry(addr, fast_retry(3)).await;
+        assert!(matches!(result, Err(Error::Ipc(_))));
+    }
+
+    #[tokio::test]
+    async fn test_send_reconnects_after_dropped_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = ServiceAddr::Tcp(listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            // first connection: read the request, then hang up as if restarting
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut len_buf = [0u8; 4];
+            stream.read_exact(&mut len_buf).await.unwrap();
+            let mut body = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+            stream.read_exact(&mut body).await.unwrap();
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.read_exact(&mut len_buf).await.unwrap();
+            let mut body = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+            stream.read_exact(&mut body).await.unwrap();
+            let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let response = serde_json::to_vec(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "result": {"status": "ok"},
+                "id": request["id"],
+            }))
+            .unwrap();
+            stream
+                .write_all(&(response.len() as u32).to_be_bytes())
+                .await
+                .unwrap();
+            stream.write_all(&response).await.unwrap();
+        });
+
+        let client = MlClient::connect_with_retry(addr, fast_retry(3))
+            .await
+            .unwrap();
+        assert!(client.ping().await.unwrap());
+    }
 }
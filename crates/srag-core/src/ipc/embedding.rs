@@ -0,0 +1,329 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use srag_common::{Error, Result};
+
+use crate::config::{Config, EmbeddingConfig, EmbeddingProvider, EMBEDDING_DIMENSION};
+use crate::ipc::client::MlClient;
+use crate::ipc::lifecycle;
+
+const REQUEST_TIMEOUT_SECS: u64 = 60;
+/// most texts the ml service embeds in one request; larger batches are
+/// split into windows of this size
+pub const ML_EMBED_LIMIT: usize = 64;
+
+/// turns text into vectors for the index
+pub trait EmbeddingBackend {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+impl EmbeddingBackend for MlClient {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        MlClient::embed(self, texts).await
+    }
+}
+
+/// client for a server speaking the openai `/embeddings` api
+pub struct OpenAiEmbeddings {
+    http: reqwest::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingItem>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingItem {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    index: usize,
+}
+
+impl OpenAiEmbeddings {
+    pub fn new(base_url: &str, model: &str, api_key: Option<String>) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| Error::Ipc(e.to_string()))?;
+        Ok(Self {
+            http,
+            url: format!("{}/embeddings", base_url.trim_end_matches('/')),
+            model: model.to_string(),
+            api_key,
+        })
+    }
+
+    pub fn from_config(config: &EmbeddingConfig) -> Result<Self> {
+        let api_key = match &config.api_key_file {
+            Some(path) => Some(
+                std::fs::read_to_string(path)
+                    .map_err(|e| {
+                        Error::Config(format!(
+                            "failed to read embedding.api_key_file {}: {}",
+                            path.display(),
+                            e
+                        ))
+                    })?
+                    .trim()
+                    .to_string(),
+            ),
+            None => None,
+        };
+        Self::new(&config.base_url, &config.model, api_key)
+    }
+}
+
+impl EmbeddingBackend for OpenAiEmbeddings {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut request = self.http.post(&self.url).json(&serde_json::json!({
+            "model": self.model,
+            "input": texts,
+        }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Error::Ipc(format!("embedding request to {} failed: {}", self.url, e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::Ipc(format!(
+                "embedding request to {} failed with {}: {}",
+                self.url,
+                status,
+                body.trim()
+            )));
+        }
+        let mut parsed: EmbeddingsResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::Ipc(format!("invalid embedding response: {}", e)))?;
+
+        if parsed.data.len() != texts.len() {
+            return Err(Error::Ipc(format!(
+                "embedding server returned {} vectors for {} inputs",
+                parsed.data.len(),
+                texts.len()
+            )));
+        }
+        parsed.data.sort_by_key(|item| item.index);
+        let vectors: Vec<Vec<f32>> = parsed.data.into_iter().map(|i| i.embedding).collect();
+        check_dimension(&vectors)?;
+        Ok(vectors)
+    }
+}
+
+/// the index is built for one dimension; vectors of any other size can't be
+/// stored or compared against it.
+fn check_dimension(vectors: &[Vec<f32>]) -> Result<()> {
+    match vectors.iter().find(|v| v.len() != EMBEDDING_DIMENSION) {
+        Some(v) => Err(Error::Index(format!(
+            "embedding model returned {} dimensions but the index uses {}",
+            v.len(),
+            EMBEDDING_DIMENSION
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// the configured embedding backend
+pub enum Embedder<'a> {
+    Local(&'a MlClient),
+    OpenAi(OpenAiEmbeddings),
+}
+
+impl<'a> Embedder<'a> {
+    /// `client` is only used, and only required, for the local provider
+    pub fn new(config: &EmbeddingConfig, client: Option<&'a MlClient>) -> Result<Self> {
+        match config.provider {
+            EmbeddingProvider::Local => client
+                .map(Embedder::Local)
+                .ok_or_else(|| Error::Ipc("local embeddings need the ML service".into())),
+            EmbeddingProvider::OpenaiCompatible => {
+                Ok(Embedder::OpenAi(OpenAiEmbeddings::from_config(config)?))
+            }
+        }
+    }
+}
+
+impl EmbeddingBackend for Embedder<'_> {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        match self {
+            Embedder::Local(client) => client.embed(texts).await,
+            Embedder::OpenAi(remote) => remote.embed(texts).await,
+        }
+    }
+}
+
+/// start and connect to the ML service when embeddings come from it. with a
+/// remote provider, indexing never needs the python stack.
+pub async fn connect_for_embedding(config: &Config) -> Result<Option<MlClient>> {
+    if config.embedding.provider != EmbeddingProvider::Local {
+        return Ok(None);
+    }
+    lifecycle::ensure_ml_service_running(config)?;
+    Ok(Some(MlClient::connect_service(config).await?))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// fake backend that counts embed calls. each text gets a vector of
+    /// `dim` copies of its length, so equal texts embed equally.
+    pub struct CountingBackend {
+        dim: usize,
+        calls: AtomicUsize,
+    }
+
+    impl CountingBackend {
+        pub fn new(dim: usize) -> Self {
+            Self {
+                dim,
+                calls: AtomicUsize::new(0),
+            }
+        }
+
+        pub fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl EmbeddingBackend for CountingBackend {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(texts
+                .iter()
+                .map(|t| vec![t.len() as f32; self.dim])
+                .collect())
+        }
+    }
+
+    /// embed callback for functions that take one rather than a backend:
+    /// the same full-size vector for every text
+    pub async fn fake_embed(texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        Ok(texts
+            .iter()
+            .map(|_| vec![0.5; EMBEDDING_DIMENSION])
+            .collect())
+    }
+
+    /// answer one http request with `body`, returning the request text
+    async fn serve_once(status: &str, body: String) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let status = status.to_string();
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(head_end) = text.find("\r\n\r\n") {
+                    let length = text[..head_end]
+                        .lines()
+                        .find_map(|l| {
+                            l.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= head_end + 4 + length {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (base_url, handle)
+    }
+
+    fn vectors_body(dims: &[usize]) -> String {
+        // reversed so the client has to put them back in input order
+        let data: Vec<_> = dims
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, &d)| serde_json::json!({"index": i, "embedding": vec![i as f32; d]}))
+            .collect();
+        serde_json::json!({"object": "list", "data": data}).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_openai_embeddings_request_and_order() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            vectors_body(&[EMBEDDING_DIMENSION, EMBEDDING_DIMENSION]),
+        )
+        .await;
+        let client = OpenAiEmbeddings::new(&base_url, "test-embed", Some("secret".into())).unwrap();
+
+        let vectors = client
+            .embed(&["first".to_string(), "second".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(vectors.len(), 2);
+        assert_eq!(vectors[0][0], 0.0);
+        assert_eq!(vectors[1][0], 1.0);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/embeddings "));
+        assert!(request
+            .to_ascii_lowercase()
+            .contains("authorization: bearer secret"));
+        assert!(request.contains(r#""model":"test-embed""#));
+        assert!(request.contains(r#""input":["first","second"]"#));
+    }
+
+    #[tokio::test]
+    async fn test_openai_embeddings_dimension_mismatch() {
+        let (base_url, _server) = serve_once("200 OK", vectors_body(&[768])).await;
+        let client = OpenAiEmbeddings::new(&base_url, "big-model", None).unwrap();
+
+        let err = client.embed(&["text".to_string()]).await.unwrap_err();
+        assert!(matches!(err, Error::Index(_)));
+        assert!(err.to_string().contains("768"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_embeddings_http_error() {
+        let (base_url, _server) = serve_once(
+            "401 Unauthorized",
+            r#"{"error":{"message":"bad key"}}"#.to_string(),
+        )
+        .await;
+        let client = OpenAiEmbeddings::new(&base_url, "m", None).unwrap();
+
+        let err = client.embed(&["text".to_string()]).await.unwrap_err();
+        assert!(err.to_string().contains("401"));
+        assert!(err.to_string().contains("bad key"));
+    }
+
+    #[test]
+    fn test_embedder_local_requires_client() {
+        let config = EmbeddingConfig::default();
+        assert!(Embedder::new(&config, None).is_err());
+    }
+}
@@ -1,14 +1,18 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
-use std::net::{SocketAddr, TcpStream};
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use srag_common::{Error, Result};
 
-use crate::config::Config;
-use crate::ipc::client::read_service_addr;
+use crate::config::{Config, IpcTransport};
+use crate::ipc::client::{read_service_addr, ServiceAddr};
 
 pub fn python_executable(config: &Config) -> PathBuf {
     let venv_dir = config.data_dir.join("venv");
@@ -31,15 +35,53 @@ pub fn python_executable(config: &Config) -> PathBuf {
     }
 }
 
-fn probe_service(addr: SocketAddr) -> bool {
-    TcpStream::connect_timeout(&addr, std::time::Duration::from_secs(2)).is_ok()
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn connect_blocking(addr: &ServiceAddr) -> std::io::Result<Box<dyn Write>> {
+    match addr {
+        ServiceAddr::Tcp(tcp) => Ok(Box::new(TcpStream::connect_timeout(tcp, PROBE_TIMEOUT)?)),
+        #[cfg(unix)]
+        ServiceAddr::Unix(path) => Ok(Box::new(std::os::unix::net::UnixStream::connect(path)?)),
+        #[cfg(not(unix))]
+        ServiceAddr::Unix(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "unix sockets are not supported on this platform",
+        )),
+    }
+}
+
+pub(crate) fn probe_service(addr: &ServiceAddr) -> bool {
+    connect_blocking(addr).is_ok()
+}
+
+/// the transport to start the ml service with. unix sockets fall back to
+/// tcp on platforms without them.
+pub(crate) fn effective_transport(config: &Config) -> IpcTransport {
+    if cfg!(unix) {
+        config.resource.ipc_transport
+    } else {
+        IpcTransport::Tcp
+    }
+}
+
+/// arguments telling srag_ml where to listen
+pub(crate) fn listen_args(transport: IpcTransport) -> Vec<std::ffi::OsString> {
+    match transport {
+        IpcTransport::Tcp => vec![
+            "--host".into(),
+            "127.0.0.1".into(),
+            "--port".into(),
+            "0".into(),
+        ],
+        IpcTransport::Unix => vec!["--socket".into(), Config::socket_path().into()],
+    }
 }
 
 pub fn ensure_ml_service_running(config: &Config) -> Result<()> {
     let port_file = Config::port_file_path();
     if port_file.exists() {
         if let Ok(addr) = read_service_addr(&port_file) {
-            if probe_service(addr) {
+            if probe_service(&addr) {
                 return Ok(());
             }
         }
@@ -47,6 +89,8 @@ pub fn ensure_ml_service_running(config: &Config) -> Result<()> {
         let _ = std::fs::remove_file(&port_file);
     }
 
+    check_model_checksum(config)?;
+
     let runtime_dir = Config::runtime_dir();
     std::fs::create_dir_all(&runtime_dir)?;
 
@@ -60,13 +104,10 @@ pub fn ensure_ml_service_running(config: &Config) -> Result<()> {
     let python = python_executable(config);
     let python_pkg = find_python_package()?;
 
-    let child = Command::new(&python)
+    let mut child = Command::new(&python)
         .arg("-m")
         .arg("srag_ml")
-        .arg("--host")
-        .arg("127.0.0.1")
-        .arg("--port")
-        .arg("0")
+        .args(listen_args(effective_transport(config)))
         .arg("--port-file")
         .arg(&port_file)
         .arg("--models-dir")
@@ -77,6 +118,13 @@ pub fn ensure_ml_service_running(config: &Config) -> Result<()> {
         .arg(&config.llm.model_filename)
         .arg("--model-url")
         .arg(&config.llm.model_url)
+        .args(
+            config
+                .llm
+                .model_sha256
+                .iter()
+                .flat_map(|sha| ["--model-sha256", sha.as_str()]),
+        )
         .arg("--llm-threads")
         .arg(config.llm.threads.to_string())
         .arg("--llm-context-size")
@@ -104,36 +152,301 @@ pub fn ensure_ml_service_running(config: &Config) -> Result<()> {
 
     tracing::info!("started ML service (pid {})", child.id());
 
+    let monitor = StartupMonitor::default();
+    if let Some(stdout) = child.stdout.take() {
+        monitor.watch(stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        monitor.watch(stderr);
+    }
+
     // wait for the port file to appear and service to respond
-    let start = std::time::Instant::now();
-    let timeout = std::time::Duration::from_secs(30);
+    wait_for_startup(
+        &monitor,
+        Duration::from_secs(config.resource.ml_startup_timeout_secs),
+        || {
+            port_file.exists()
+                && read_service_addr(&port_file).is_ok_and(|addr| probe_service(&addr))
+        },
+        || match child.try_wait() {
+            Ok(Some(status)) => Some(status.to_string()),
+            _ => None,
+        },
+    )
+}
+
+/// with `llm.model_sha256` set, a local model already on disk has to match
+/// it before the service may load it. one that isn't there yet is checked
+/// by the service after it downloads it.
+fn check_model_checksum(config: &Config) -> Result<()> {
+    let Some(expected) = &config.llm.model_sha256 else {
+        return Ok(());
+    };
+    if config.is_external_api() {
+        return Ok(());
+    }
+    let path = config.models_dir().join(&config.llm.model_filename);
+    if !path.is_file() {
+        return Ok(());
+    }
+    verify_model_file(&path, expected)
+}
+
+/// sha256 of a file as lowercase hex, read in blocks so a multi-gigabyte
+/// model never sits in memory
+pub fn sha256_file(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
     loop {
-        if port_file.exists() {
-            if let Ok(addr) = read_service_addr(&port_file) {
-                if probe_service(addr) {
-                    return Ok(());
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// where a model's last good hash is remembered
+fn verified_stamp_path(path: &Path) -> PathBuf {
+    let mut stamp = path.as_os_str().to_owned();
+    stamp.push(".verified");
+    PathBuf::from(stamp)
+}
+
+/// the hash, size and mtime a stamp records for `path` as it is now
+fn stamp_line(path: &Path, sha: &str) -> Result<String> {
+    let meta = std::fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    Ok(format!("{} {} {}", sha.to_lowercase(), meta.len(), mtime))
+}
+
+/// whether `path` was verified against `expected` and hasn't changed since
+pub fn model_verified(path: &Path, expected: &str) -> bool {
+    let Ok(stamp) = std::fs::read_to_string(verified_stamp_path(path)) else {
+        return false;
+    };
+    stamp_line(path, expected).is_ok_and(|line| stamp.trim() == line)
+}
+
+/// refuse a model file whose sha256 isn't `expected`. a match is stamped
+/// with the file's size and mtime so the service doesn't rehash an
+/// unchanged model every time it starts.
+pub fn verify_model_file(path: &Path, expected: &str) -> Result<()> {
+    if model_verified(path, expected) {
+        return Ok(());
+    }
+    check_model_hash(path, expected, &sha256_file(path)?)
+}
+
+/// compare a model's `actual` hash with `expected`, stamping it on a match
+/// and dropping any stale stamp otherwise
+pub fn check_model_hash(path: &Path, expected: &str, actual: &str) -> Result<()> {
+    if !actual.eq_ignore_ascii_case(expected) {
+        let _ = std::fs::remove_file(verified_stamp_path(path));
+        return Err(Error::Ipc(format!(
+            "model checksum mismatch for {}: expected {}, got {}. delete it and run 'srag model download'",
+            path.display(),
+            expected.to_lowercase(),
+            actual
+        )));
+    }
+    std::fs::write(verified_stamp_path(path), stamp_line(path, expected)?)?;
+    Ok(())
+}
+
+const STARTUP_POLL: Duration = Duration::from_millis(100);
+/// lines of child output kept to explain a failed start
+const STARTUP_TAIL_LINES: usize = 20;
+
+/// drains the ml child's output while it starts, remembering when it last
+/// reported download progress and the most recent lines it printed.
+#[derive(Clone, Default)]
+struct StartupMonitor {
+    last_progress: Arc<Mutex<Option<Instant>>>,
+    tail: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl StartupMonitor {
+    /// read `stream` on a background thread until it closes. progress bars
+    /// redraw with `\r`, so both `\r` and `\n` end a line.
+    fn watch(&self, mut stream: impl Read + Send + 'static) {
+        let monitor = self.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            let mut line = Vec::new();
+            while let Ok(n) = stream.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                for &b in &buf[..n] {
+                    if b == b'\r' || b == b'\n' {
+                        monitor.record(&String::from_utf8_lossy(&line));
+                        line.clear();
+                    } else {
+                        line.push(b);
+                    }
                 }
             }
+            monitor.record(&String::from_utf8_lossy(&line));
+        });
+    }
+
+    fn record(&self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if is_progress_line(line) {
+            *self.last_progress.lock().unwrap() = Some(Instant::now());
+        }
+        let mut tail = self.tail.lock().unwrap();
+        if tail.len() == STARTUP_TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line.to_string());
+    }
+
+    fn last_progress(&self) -> Option<Instant> {
+        *self.last_progress.lock().unwrap()
+    }
+
+    fn tail(&self) -> String {
+        let tail = self.tail.lock().unwrap();
+        tail.iter().cloned().collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// download banners and `12/4096 MB (0%)` style progress from srag_ml
+fn is_progress_line(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    lower.ends_with("%)") || lower.starts_with("downloading") || lower.contains("checksum")
+}
+
+/// poll until `ready` succeeds. fails straight away if the child exits, or
+/// once `timeout` passes without either readiness or fresh download progress.
+fn wait_for_startup(
+    monitor: &StartupMonitor,
+    timeout: Duration,
+    mut ready: impl FnMut() -> bool,
+    mut exited: impl FnMut() -> Option<String>,
+) -> Result<()> {
+    let start = Instant::now();
+    loop {
+        if ready() {
+            return Ok(());
+        }
+
+        if let Some(status) = exited() {
+            let tail = monitor.tail();
+            return Err(Error::Ipc(if tail.is_empty() {
+                format!("ML service exited during startup ({})", status)
+            } else {
+                format!("ML service exited during startup ({}):\n{}", status, tail)
+            }));
         }
 
-        if start.elapsed() > timeout {
-            return Err(Error::Ipc(
-                "timed out waiting for ML service to start".into(),
-            ));
+        let since = monitor.last_progress().map_or(start, |p| p.max(start));
+        if since.elapsed() > timeout {
+            let reason = if monitor.last_progress().is_some() {
+                "model download stalled"
+            } else {
+                "timed out"
+            };
+            return Err(Error::Ipc(format!(
+                "{} waiting for ML service to start after {}s (raise resource.ml_startup_timeout_secs)",
+                reason,
+                timeout.as_secs()
+            )));
         }
 
-        std::thread::sleep(std::time::Duration::from_millis(100));
+        std::thread::sleep(STARTUP_POLL);
+    }
+}
+
+/// when this process last wrote the shared activity stamp
+static LAST_ACTIVITY_WRITE: Mutex<Option<Instant>> = Mutex::new(None);
+/// a busy process rewrites the stamp at most this often
+const ACTIVITY_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+/// longest gap between idle checks
+const IDLE_CHECK_MAX: Duration = Duration::from_secs(30);
+
+/// note that the ml service was just used. the stamp is shared through a
+/// file, since every srag process talks to the same service.
+pub fn record_activity() {
+    let mut last = LAST_ACTIVITY_WRITE.lock().unwrap();
+    if last.is_some_and(|t| t.elapsed() < ACTIVITY_WRITE_INTERVAL) {
+        return;
+    }
+    *last = Some(Instant::now());
+    let path = Config::activity_file_path();
+    if let Err(e) = write_activity(&path, SystemTime::now()) {
+        tracing::debug!("failed to record ML service activity: {}", e);
     }
 }
 
+fn write_activity(path: &Path, at: SystemTime) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    std::fs::write(path, secs.to_string())
+}
+
+/// the last use recorded by any process, none once the service was stopped
+/// or while the stamp is being rewritten
+fn read_activity(path: &Path) -> Option<SystemTime> {
+    let secs: u64 = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// whether a service last used at `last_activity` has sat idle for
+/// `timeout`. never true if it hasn't been used since it was last stopped,
+/// or when the timeout is zero.
+fn idle_expired(last_activity: Option<SystemTime>, now: SystemTime, timeout: Duration) -> bool {
+    !timeout.is_zero()
+        && last_activity.is_some_and(|t| now.duration_since(t).unwrap_or_default() >= timeout)
+}
+
+/// stop the ml service once no srag process has used it for
+/// `resource.llm_idle_timeout_secs`, freeing the model memory. the next
+/// request starts it again. for long-running processes like the mcp server
+/// and the watcher.
+pub fn spawn_idle_shutdown(config: &Config) {
+    let timeout = Duration::from_secs(config.resource.llm_idle_timeout_secs);
+    if timeout.is_zero() {
+        return;
+    }
+    let interval = (timeout / 4).clamp(Duration::from_secs(1), IDLE_CHECK_MAX);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let last = read_activity(&Config::activity_file_path());
+            if !idle_expired(last, SystemTime::now(), timeout) {
+                continue;
+            }
+            tracing::info!("ML service idle for {}s, stopping it", timeout.as_secs());
+            match tokio::task::spawn_blocking(stop_ml_service).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("failed to stop idle ML service: {}", e),
+                Err(e) => tracing::warn!("failed to stop idle ML service: {}", e),
+            }
+        }
+    });
+}
+
 pub fn stop_ml_service() -> Result<()> {
     let port_file = Config::port_file_path();
     if port_file.exists() {
         if let Ok(addr) = read_service_addr(&port_file) {
-            if let Ok(mut stream) =
-                TcpStream::connect_timeout(&addr, std::time::Duration::from_secs(2))
-            {
-                use std::io::Write;
+            if let Ok(mut stream) = connect_blocking(&addr) {
                 let mut req = serde_json::json!({
                     "jsonrpc": "2.0",
                     "method": "shutdown",
@@ -156,6 +469,7 @@ pub fn stop_ml_service() -> Result<()> {
         let _ = std::fs::remove_file(&port_file);
     }
     let _ = std::fs::remove_file(Config::token_file_path());
+    let _ = std::fs::remove_file(Config::activity_file_path());
     Ok(())
 }
 
@@ -167,7 +481,6 @@ fn generate_auth_token() -> String {
 }
 
 fn write_token_with_timestamp(token_path: &std::path::Path, token: &str) -> Result<()> {
-    use std::io::Write;
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -230,7 +543,7 @@ pub fn read_auth_token() -> Result<String> {
     Ok(content.to_string())
 }
 
-fn find_python_package() -> Result<PathBuf> {
+pub(crate) fn find_python_package() -> Result<PathBuf> {
     let exe = std::env::current_exe().map_err(|e| Error::Ipc(e.to_string()))?;
     let exe_dir = exe.parent().unwrap_or_else(|| std::path::Path::new("."));
 
@@ -255,3 +568,253 @@ fn find_python_package() -> Result<PathBuf> {
 
     Err(Error::Ipc("Could not find Python srag_ml package".into()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// hands out one chunk per read, sleeping before each, then sets `done`
+    struct SlowReader {
+        chunks: VecDeque<&'static str>,
+        delay: Duration,
+        done: Arc<AtomicBool>,
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            std::thread::sleep(self.delay);
+            match self.chunks.pop_front() {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(chunk.as_bytes());
+                    Ok(chunk.len())
+                }
+                None => {
+                    self.done.store(true, Ordering::SeqCst);
+                    Ok(0)
+                }
+            }
+        }
+    }
+
+    fn slow_child(chunks: &[&'static str], delay: Duration) -> (StartupMonitor, Arc<AtomicBool>) {
+        let done = Arc::new(AtomicBool::new(false));
+        let monitor = StartupMonitor::default();
+        monitor.watch(SlowReader {
+            chunks: chunks.iter().copied().collect(),
+            delay,
+            done: done.clone(),
+        });
+        (monitor, done)
+    }
+
+    /// sha256 of "abc"
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_verify_model_file_stamps_match_and_rejects_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = dir.path().join("model.gguf");
+        std::fs::write(&model, b"abc").unwrap();
+
+        assert_eq!(sha256_file(&model).unwrap(), ABC_SHA256);
+        verify_model_file(&model, &ABC_SHA256.to_uppercase()).unwrap();
+        assert!(model_verified(&model, ABC_SHA256));
+
+        // a swapped file fails and loses its stamp
+        std::fs::write(&model, b"abd").unwrap();
+        let err = verify_model_file(&model, ABC_SHA256)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("checksum mismatch"), "{}", err);
+        assert!(!verified_stamp_path(&model).exists());
+    }
+
+    #[test]
+    fn test_mismatched_model_blocks_service_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            data_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        config.ensure_dirs().unwrap();
+        std::fs::write(
+            config.models_dir().join(&config.llm.model_filename),
+            b"tampered",
+        )
+        .unwrap();
+
+        // nothing pinned, nothing checked
+        check_model_checksum(&config).unwrap();
+
+        config.llm.model_sha256 = Some(ABC_SHA256.to_string());
+        assert!(check_model_checksum(&config).is_err());
+    }
+
+    #[test]
+    fn test_idle_expired_decision() {
+        let start = SystemTime::now();
+        let timeout = Duration::from_secs(300);
+
+        assert!(!idle_expired(
+            Some(start),
+            start + Duration::from_secs(299),
+            timeout
+        ));
+        assert!(idle_expired(
+            Some(start),
+            start + Duration::from_secs(300),
+            timeout
+        ));
+        assert!(idle_expired(
+            Some(start),
+            start + Duration::from_secs(900),
+            timeout
+        ));
+        // unused since the last stop, or the check disabled
+        assert!(!idle_expired(
+            None,
+            start + Duration::from_secs(900),
+            timeout
+        ));
+        assert!(!idle_expired(
+            Some(start),
+            start + Duration::from_secs(900),
+            Duration::ZERO
+        ));
+        // activity recorded after the check started
+        assert!(!idle_expired(
+            Some(start + Duration::from_secs(1)),
+            start,
+            timeout
+        ));
+    }
+
+    #[test]
+    fn test_activity_shared_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ml.activity");
+        assert_eq!(read_activity(&path), None);
+
+        // a stamp left by another process
+        let used = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        write_activity(&path, used).unwrap();
+        assert_eq!(read_activity(&path), Some(used));
+        assert!(idle_expired(
+            read_activity(&path),
+            used + Duration::from_secs(600),
+            Duration::from_secs(300)
+        ));
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(read_activity(&path), None);
+    }
+
+    #[test]
+    fn test_progress_extends_startup_timeout() {
+        let mut chunks = vec!["\ndownloading LLM model: model.gguf\n"];
+        chunks.extend(std::iter::repeat_n("\r  [===---] 10/100 MB (10%)", 12));
+        chunks.push("\nmodel downloaded\n");
+        // ~650ms of output against a 250ms timeout
+        let (monitor, done) = slow_child(&chunks, Duration::from_millis(50));
+
+        let result = wait_for_startup(
+            &monitor,
+            Duration::from_millis(250),
+            || done.load(Ordering::SeqCst),
+            || None,
+        );
+        assert!(result.is_ok(), "{:?}", result);
+        assert!(monitor.tail().contains("model downloaded"));
+    }
+
+    #[test]
+    fn test_startup_times_out_without_progress() {
+        let (monitor, _done) = slow_child(&["loading\n"], Duration::from_millis(10));
+
+        let start = Instant::now();
+        let err = wait_for_startup(&monitor, Duration::from_millis(200), || false, || None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("timed out"), "{}", err);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_startup_stalled_download() {
+        let (monitor, _done) = slow_child(&["\r  1/100 MB (1%)"], Duration::from_millis(10));
+
+        let err = wait_for_startup(&monitor, Duration::from_millis(200), || false, || None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("stalled"), "{}", err);
+    }
+
+    #[test]
+    fn test_startup_reports_child_exit() {
+        let (monitor, done) = slow_child(
+            &[
+                "Traceback (most recent call last):\n",
+                "ImportError: no module\n",
+            ],
+            Duration::from_millis(10),
+        );
+        while !done.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let err = wait_for_startup(
+            &monitor,
+            Duration::from_secs(30),
+            || false,
+            || Some("exit status: 1".to_string()),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("exit status: 1"), "{}", err);
+        assert!(err.contains("ImportError"), "{}", err);
+    }
+
+    #[test]
+    fn test_is_progress_line() {
+        assert!(is_progress_line("[=====-----] 120/4096 MB (2%)"));
+        assert!(is_progress_line("downloading LLM model: x.gguf"));
+        assert!(is_progress_line("verifying model checksum..."));
+        assert!(!is_progress_line("INFO [srag_ml.server] listening"));
+    }
+
+    #[test]
+    fn test_listen_args_follow_transport() {
+        let mut config = Config::default();
+        assert_eq!(effective_transport(&config), IpcTransport::Tcp);
+        assert_eq!(
+            listen_args(IpcTransport::Tcp),
+            ["--host", "127.0.0.1", "--port", "0"]
+        );
+
+        config.resource.ipc_transport = IpcTransport::Unix;
+        let expected = if cfg!(unix) {
+            IpcTransport::Unix
+        } else {
+            IpcTransport::Tcp
+        };
+        assert_eq!(effective_transport(&config), expected);
+
+        let args = listen_args(IpcTransport::Unix);
+        assert_eq!(args[0], "--socket");
+        assert_eq!(PathBuf::from(&args[1]), Config::socket_path());
+        assert!(Config::socket_path().starts_with(Config::runtime_dir()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_service_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ml.sock");
+        let addr = ServiceAddr::Unix(path.clone());
+        assert!(!probe_service(&addr));
+
+        let _listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(probe_service(&addr));
+    }
+}
@@ -2,5 +2,6 @@
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
 pub mod client;
+pub mod embedding;
 pub mod lifecycle;
 pub mod protocol;
@@ -9,6 +9,7 @@ mod config;
 mod discovery;
 mod index;
 mod ipc;
+mod profile;
 mod query;
 mod resource;
 mod watcher;
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// time spent in each phase of a command, printed by `--profile`. phases
+/// keep the order they were first timed in.
+#[derive(Debug)]
+pub struct Profiler {
+    started: Instant,
+    phases: Vec<Phase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phase {
+    pub name: &'static str,
+    pub total: Duration,
+    /// how many times the phase ran, e.g. one per embedding batch
+    pub count: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &'static str, elapsed: Duration) {
+        match self.phases.iter_mut().find(|p| p.name == name) {
+            Some(phase) => {
+                phase.total += elapsed;
+                phase.count += 1;
+            }
+            None => self.phases.push(Phase {
+                name,
+                total: elapsed,
+                count: 1,
+            }),
+        }
+    }
+
+    /// run `f`, counting its time towards `name`
+    pub fn time<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.add(name, start.elapsed());
+        out
+    }
+
+    pub fn phases(&self) -> &[Phase] {
+        &self.phases
+    }
+
+    /// the table for everything timed since the profiler was created
+    pub fn report(&self) -> String {
+        self.table(self.started.elapsed())
+    }
+
+    /// one row per phase, then whatever `total` has left over as `other`
+    fn table(&self, total: Duration) -> String {
+        let timed: Duration = self.phases.iter().map(|p| p.total).sum();
+        let other = total.saturating_sub(timed);
+        let share = |d: Duration| {
+            if total.is_zero() {
+                0.0
+            } else {
+                d.as_secs_f64() / total.as_secs_f64() * 100.0
+            }
+        };
+
+        let mut out = format!(
+            "{:<12} {:>10} {:>7} {:>6}\n",
+            "phase", "time", "share", "runs"
+        );
+        for phase in &self.phases {
+            let _ = writeln!(
+                out,
+                "{:<12} {:>8.1}ms {:>6.1}% {:>6}",
+                phase.name,
+                phase.total.as_secs_f64() * 1000.0,
+                share(phase.total),
+                phase.count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{:<12} {:>8.1}ms {:>6.1}%",
+            "other",
+            other.as_secs_f64() * 1000.0,
+            share(other)
+        );
+        let _ = writeln!(
+            out,
+            "{:<12} {:>8.1}ms",
+            "total",
+            total.as_secs_f64() * 1000.0
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_accumulate_in_first_seen_order() {
+        let mut profiler = Profiler::new();
+        profiler.add("discovery", Duration::from_millis(100));
+        profiler.add("embedding", Duration::from_millis(300));
+        profiler.add("discovery", Duration::from_millis(50));
+
+        assert_eq!(
+            profiler.phases(),
+            &[
+                Phase {
+                    name: "discovery",
+                    total: Duration::from_millis(150),
+                    count: 2,
+                },
+                Phase {
+                    name: "embedding",
+                    total: Duration::from_millis(300),
+                    count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table_reports_each_phase_and_the_rest() {
+        let mut profiler = Profiler::new();
+        profiler.add("chunking", Duration::from_millis(250));
+        profiler.add("db writes", Duration::from_millis(500));
+
+        let table = profiler.table(Duration::from_secs(1));
+        let rows: Vec<Vec<&str>> = table
+            .lines()
+            .map(|line| line.split("  ").filter(|s| !s.is_empty()).collect())
+            .collect();
+        let row = |name: &str| {
+            rows.iter()
+                .find(|r| r[0].trim() == name)
+                .unwrap_or_else(|| panic!("no {} row in\n{}", name, table))
+                .iter()
+                .map(|s| s.trim())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(row("chunking"), vec!["chunking", "250.0ms", "25.0%", "1"]);
+        assert_eq!(row("db writes"), vec!["db writes", "500.0ms", "50.0%", "1"]);
+        assert_eq!(row("other"), vec!["other", "250.0ms", "25.0%"]);
+        assert_eq!(row("total"), vec!["total", "1000.0ms"]);
+    }
+
+    #[test]
+    fn test_time_counts_the_closure() {
+        let mut profiler = Profiler::new();
+        let out = profiler.time("search", || {
+            std::thread::sleep(Duration::from_millis(5));
+            42
+        });
+        assert_eq!(out, 42);
+        assert!(profiler.phases()[0].total >= Duration::from_millis(5));
+    }
+}
@@ -3,61 +3,261 @@
 
 use std::collections::HashMap;
 
-use srag_common::types::Chunk;
+use srag_common::types::{Chunk, Language};
 use srag_common::Result;
 
+use crate::config::QueryConfig;
 use crate::index::store::Store;
 
+/// a resolved chunk, its file path and a relevance score in 0..=1
+pub type ScoredChunk = (Chunk, String, f32);
+
 pub fn resolve_results(store: &Store, results: &[(usize, f32)]) -> Result<Vec<(Chunk, String)>> {
+    Ok(resolve_scored(store, results)?
+        .into_iter()
+        .map(|(chunk, path, _)| (chunk, path))
+        .collect())
+}
+
+/// like `resolve_results`, keeping each hit's cosine similarity
+pub fn resolve_scored(store: &Store, results: &[(usize, f32)]) -> Result<Vec<ScoredChunk>> {
     let mut chunks = Vec::new();
-    for &(embedding_id, _distance) in results {
-        if let Some(pair) = store.get_chunk_by_embedding_id(embedding_id as i64)? {
-            chunks.push(pair);
+    for &(embedding_id, distance) in results {
+        if let Some((chunk, path)) = store.get_chunk_by_embedding_id(embedding_id as i64)? {
+            chunks.push((chunk, path, similarity(distance)));
         }
     }
     Ok(chunks)
 }
 
+/// cosine distance from the hnsw index as a similarity, clamped to 0..=1
+pub fn similarity(distance: f32) -> f32 {
+    (1.0 - distance).clamp(0.0, 1.0)
+}
+
+/// parameters for reciprocal rank fusion: each list contributes
+/// `weight / (k + rank + 1)` per result.
+#[derive(Debug, Clone, Copy)]
+pub struct RrfWeights {
+    pub k: f64,
+    pub vector: f64,
+    pub text: f64,
+}
+
+impl Default for RrfWeights {
+    fn default() -> Self {
+        Self {
+            k: 60.0,
+            vector: 1.0,
+            text: 1.0,
+        }
+    }
+}
+
+impl RrfWeights {
+    pub fn from_config(config: &QueryConfig) -> Self {
+        Self {
+            k: config.rrf_k as f64,
+            vector: config.rrf_vector_weight as f64,
+            text: config.rrf_text_weight as f64,
+        }
+    }
+
+    /// the score of a chunk ranked first in both lists
+    pub fn max_score(&self) -> f64 {
+        (self.vector + self.text) / (self.k + 1.0)
+    }
+}
+
+/// fuse two ranked chunk id lists, best first. ties keep no particular order.
+pub fn fuse_ranks(vector_ids: &[i64], text_ids: &[i64], weights: RrfWeights) -> Vec<(i64, f64)> {
+    let mut scores: HashMap<i64, f64> = HashMap::new();
+
+    for (rank, chunk_id) in vector_ids.iter().enumerate() {
+        *scores.entry(*chunk_id).or_default() += weights.vector / (weights.k + rank as f64 + 1.0);
+    }
+    for (rank, chunk_id) in text_ids.iter().enumerate() {
+        *scores.entry(*chunk_id).or_default() += weights.text / (weights.k + rank as f64 + 1.0);
+    }
+
+    let mut ranked: Vec<(i64, f64)> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked
+}
+
+/// fuse vector and fts rankings and resolve the top chunks. a language filter
+/// is applied while resolving, so the top_k slots go to matching chunks only;
+/// empty `languages` keeps every language.
 pub fn reciprocal_rank_fusion(
     vector_results: &[(usize, f32)],
     fts_results: &[(i64, f64)],
     store: &Store,
     top_k: usize,
+    weights: RrfWeights,
+    languages: &[Language],
 ) -> Result<Vec<(Chunk, String)>> {
-    const K: f64 = 60.0;
-    let mut scores: HashMap<i64, f64> = HashMap::new();
+    Ok(reciprocal_rank_fusion_scored(
+        vector_results,
+        fts_results,
+        store,
+        top_k,
+        weights,
+        languages,
+    )?
+    .into_iter()
+    .map(|(chunk, path, _)| (chunk, path))
+    .collect())
+}
 
+/// like `reciprocal_rank_fusion`, keeping each chunk's fused score as a
+/// fraction of the best possible one
+pub fn reciprocal_rank_fusion_scored(
+    vector_results: &[(usize, f32)],
+    fts_results: &[(i64, f64)],
+    store: &Store,
+    top_k: usize,
+    weights: RrfWeights,
+    languages: &[Language],
+) -> Result<Vec<ScoredChunk>> {
     // vector results: embedding_id -> chunk_id
-    for (rank, &(embedding_id, _distance)) in vector_results.iter().enumerate() {
-        if let Ok(Some(chunk_id)) = store.get_chunk_id_by_embedding_id(embedding_id as i64) {
-            let rrf_score = 1.0 / (K + rank as f64 + 1.0);
-            *scores.entry(chunk_id).or_default() += rrf_score;
-        }
-    }
+    let vector_ids: Vec<i64> = vector_results
+        .iter()
+        .filter_map(|&(embedding_id, _distance)| {
+            store
+                .get_chunk_id_by_embedding_id(embedding_id as i64)
+                .ok()
+                .flatten()
+        })
+        .collect();
 
     // FTS results: already chunk_id
-    for (rank, &(chunk_id, _bm25)) in fts_results.iter().enumerate() {
-        let rrf_score = 1.0 / (K + rank as f64 + 1.0);
-        *scores.entry(chunk_id).or_default() += rrf_score;
+    let text_ids: Vec<i64> = fts_results
+        .iter()
+        .map(|&(chunk_id, _bm25)| chunk_id)
+        .collect();
+
+    let mut ranked = fuse_ranks(&vector_ids, &text_ids, weights);
+    if languages.is_empty() {
+        ranked.truncate(top_k);
     }
 
-    let mut ranked: Vec<(i64, f64)> = scores.into_iter().collect();
-    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-    ranked.truncate(top_k);
+    let ids: Vec<i64> = ranked.iter().map(|&(chunk_id, _)| chunk_id).collect();
+    let scores: HashMap<i64, f64> = ranked.into_iter().collect();
+    let max_score = weights.max_score();
+    let mut chunks: Vec<ScoredChunk> = store
+        .get_chunks_by_ids(&ids, languages)?
+        .into_iter()
+        .map(|(chunk, path)| {
+            let fused = chunk.id.and_then(|id| scores.get(&id)).copied();
+            let score = match fused {
+                Some(fused) if max_score > 0.0 => (fused / max_score).clamp(0.0, 1.0) as f32,
+                _ => 0.0,
+            };
+            (chunk, path, score)
+        })
+        .collect();
+    chunks.truncate(top_k);
+    Ok(chunks)
+}
 
-    let mut chunks = Vec::new();
-    for (chunk_id, _score) in ranked {
-        if let Ok(Some(pair)) = store.get_chunk_by_id(chunk_id) {
-            chunks.push(pair);
+/// reorder candidates by maximal marginal relevance. each pick maximises
+/// `lambda * sim(query, c) - (1 - lambda) * max sim(c, picked)`, so a low
+/// lambda favours chunks unlike those already chosen. candidates without a
+/// vector keep their relative order after the rest.
+pub fn mmr_diversify(
+    candidates: Vec<(Chunk, String)>,
+    vectors: &[Option<Vec<f32>>],
+    query_vec: &[f32],
+    lambda: f32,
+) -> Vec<(Chunk, String)> {
+    let (mut remaining, unscored): (Vec<usize>, Vec<usize>) =
+        (0..candidates.len()).partition(|&i| vectors.get(i).is_some_and(|v| v.is_some()));
+    let relevance: Vec<f32> = (0..candidates.len())
+        .map(|i| match vectors.get(i) {
+            Some(Some(v)) => cosine_similarity(query_vec, v),
+            _ => 0.0,
+        })
+        .collect();
+
+    let mut order: Vec<usize> = Vec::with_capacity(candidates.len());
+    while !remaining.is_empty() {
+        let mut best = 0;
+        let mut best_score = f32::NEG_INFINITY;
+        for (pos, &i) in remaining.iter().enumerate() {
+            let Some(Some(v)) = vectors.get(i) else {
+                continue;
+            };
+            let redundancy = order
+                .iter()
+                .filter_map(|&j| vectors[j].as_ref())
+                .map(|picked| cosine_similarity(v, picked))
+                .fold(0.0f32, f32::max);
+            let score = lambda * relevance[i] - (1.0 - lambda) * redundancy;
+            if score > best_score {
+                best_score = score;
+                best = pos;
+            }
         }
+        order.push(remaining.remove(best));
+    }
+    order.extend(unscored);
+
+    let mut slots: Vec<Option<(Chunk, String)>> = candidates.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| slots[i].take()).collect()
+}
+
+/// order candidates by cosine similarity of their stored vectors to the
+/// query, a cheap stand-in for the cross-encoder when it is unavailable.
+/// candidates without a vector keep their relative order after the rest.
+pub fn cosine_rescore(
+    candidates: Vec<(Chunk, String)>,
+    vectors: &[Option<Vec<f32>>],
+    query_vec: &[f32],
+) -> Vec<(Chunk, String)> {
+    let mut scored: Vec<(Option<f32>, (Chunk, String))> = candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, candidate)| {
+            let score = vectors
+                .get(i)
+                .and_then(|v| v.as_deref())
+                .map(|v| cosine_similarity(query_vec, v));
+            (score, candidate)
+        })
+        .collect();
+    // stable, so ties and unscored candidates keep their fused order
+    scored.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) => b.total_cmp(a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
     }
-    Ok(chunks)
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
+    use super::{
+        cosine_rescore, mmr_diversify, reciprocal_rank_fusion, reciprocal_rank_fusion_scored,
+        similarity, RrfWeights,
+    };
+    use crate::index::store::tests::{seed_chunk, seed_file, test_chunk, test_store};
+    use crate::index::store::Store;
+    use srag_common::types::{Chunk, Language};
+
     const K: f64 = 60.0;
 
     fn compute_rrf_scores(
@@ -125,6 +325,49 @@ mod tests {
         assert!((scores.get(&1).unwrap() - expected).abs() < 0.0001);
     }
 
+    fn order(ranked: &[(i64, f64)]) -> Vec<i64> {
+        ranked.iter().map(|(id, _)| *id).collect()
+    }
+
+    #[test]
+    fn test_fuse_ranks_default_weights_match_rrf() {
+        let ranked = super::fuse_ranks(&[1], &[], super::RrfWeights::default());
+        assert!((ranked[0].1 - 1.0 / (K + 1.0)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_fuse_ranks_weights_change_order() {
+        // 1 and 2 appear in both lists at mirrored ranks, so the weights decide
+        let vector_ids = [1, 3, 2];
+        let text_ids = [2, 4, 1];
+
+        let semantic = super::RrfWeights {
+            vector: 2.0,
+            text: 0.5,
+            ..Default::default()
+        };
+        let ranked = super::fuse_ranks(&vector_ids, &text_ids, semantic);
+        assert_eq!(order(&ranked)[0], 1);
+
+        let lexical = super::RrfWeights {
+            vector: 0.5,
+            text: 2.0,
+            ..Default::default()
+        };
+        let ranked = super::fuse_ranks(&vector_ids, &text_ids, lexical);
+        assert_eq!(order(&ranked)[0], 2);
+    }
+
+    #[test]
+    fn test_fuse_ranks_zero_text_weight_uses_vector_order() {
+        let weights = super::RrfWeights {
+            text: 0.0,
+            ..Default::default()
+        };
+        let ranked = super::fuse_ranks(&[5, 6, 7], &[7, 6, 5], weights);
+        assert_eq!(order(&ranked), vec![5, 6, 7]);
+    }
+
     #[test]
     fn test_rrf_deduplication() {
         let vector_ranks = vec![(1, 0), (1, 1)];
@@ -133,4 +376,193 @@ mod tests {
         let expected = 1.0 / (K + 1.0) + 1.0 / (K + 2.0);
         assert!((scores.get(&1).unwrap() - expected).abs() < 0.0001);
     }
+
+    fn candidate(name: &str) -> (Chunk, String) {
+        (
+            Chunk {
+                id: None,
+                file_id: 1,
+                content: name.to_string(),
+                symbol: Some(name.to_string()),
+                symbol_kind: None,
+                start_line: 1,
+                end_line: 1,
+                language: Language::Rust,
+                suspicious: false,
+            },
+            format!("/tmp/{}.rs", name),
+        )
+    }
+
+    fn names(ranked: &[(Chunk, String)]) -> Vec<&str> {
+        ranked.iter().map(|(c, _)| c.content.as_str()).collect()
+    }
+
+    type Candidates = (Vec<(Chunk, String)>, Vec<Option<Vec<f32>>>);
+
+    fn duplicates_and_distinct() -> Candidates {
+        let candidates = vec![
+            candidate("dup1"),
+            candidate("dup2"),
+            candidate("dup3"),
+            candidate("distinct"),
+        ];
+        let vectors = vec![
+            Some(vec![1.0, 0.0, 0.0]),
+            Some(vec![0.99, 0.01, 0.0]),
+            Some(vec![0.98, 0.02, 0.0]),
+            Some(vec![0.5, 0.0, 0.8]),
+        ];
+        (candidates, vectors)
+    }
+
+    #[test]
+    fn test_mmr_promotes_distinct_chunk() {
+        let (candidates, vectors) = duplicates_and_distinct();
+        let query = [1.0, 0.0, 0.3];
+
+        let ranked = mmr_diversify(candidates, &vectors, &query, 0.3);
+        assert_eq!(names(&ranked)[..2], ["dup1", "distinct"]);
+        assert_eq!(ranked.len(), 4);
+    }
+
+    #[test]
+    fn test_mmr_pure_relevance_keeps_duplicates_first() {
+        let (candidates, vectors) = duplicates_and_distinct();
+        let query = [1.0, 0.0, 0.3];
+
+        let ranked = mmr_diversify(candidates, &vectors, &query, 1.0);
+        assert_eq!(names(&ranked), ["dup1", "dup2", "dup3", "distinct"]);
+    }
+
+    #[test]
+    fn test_mmr_missing_vectors_go_last() {
+        let candidates = vec![candidate("fts_only"), candidate("a"), candidate("b")];
+        let vectors = vec![None, Some(vec![0.0, 1.0]), Some(vec![1.0, 0.0])];
+
+        let ranked = mmr_diversify(candidates, &vectors, &[1.0, 0.0], 0.5);
+        assert_eq!(names(&ranked), ["b", "a", "fts_only"]);
+    }
+
+    #[test]
+    fn test_cosine_rescore_orders_by_similarity() {
+        // fused order puts the weakest match first
+        let candidates = vec![
+            candidate("far"),
+            candidate("fts_only"),
+            candidate("close"),
+            candidate("middle"),
+        ];
+        let vectors = vec![
+            Some(vec![0.0, 1.0]),
+            None,
+            Some(vec![1.0, 0.1]),
+            Some(vec![0.7, 0.7]),
+        ];
+
+        let ranked = cosine_rescore(candidates, &vectors, &[1.0, 0.0]);
+        assert_eq!(names(&ranked), ["close", "middle", "far", "fts_only"]);
+    }
+
+    /// insert one chunk per (path, language), returning the chunk ids in order
+    fn seed_mixed(store: &Store, files: &[(&str, Language)]) -> Vec<i64> {
+        let project_id = store.upsert_project("mixed", "/tmp/mixed").unwrap();
+        files
+            .iter()
+            .map(|&(path, language)| {
+                let file_id = seed_file(store, project_id, path, language);
+                let chunk = Chunk {
+                    language,
+                    ..test_chunk(file_id, path, 1)
+                };
+                seed_chunk(store, &chunk, path)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rrf_language_filter_applied_during_resolution() {
+        let (store, _dir) = test_store();
+        let ids = seed_mixed(
+            &store,
+            &[
+                ("/tmp/mixed/a.rs", Language::Rust),
+                ("/tmp/mixed/b.py", Language::Python),
+                ("/tmp/mixed/c.rs", Language::Rust),
+                ("/tmp/mixed/d.py", Language::Python),
+            ],
+        );
+        let fts: Vec<(i64, f64)> = ids.iter().map(|&id| (id, 0.0)).collect();
+
+        let unfiltered =
+            reciprocal_rank_fusion(&[], &fts, &store, 2, RrfWeights::default(), &[]).unwrap();
+        assert_eq!(names(&unfiltered), ["/tmp/mixed/a.rs", "/tmp/mixed/b.py"]);
+
+        // both top_k slots go to python even though rust chunks rank first
+        let python = reciprocal_rank_fusion(
+            &[],
+            &fts,
+            &store,
+            2,
+            RrfWeights::default(),
+            &[Language::Python],
+        )
+        .unwrap();
+        assert_eq!(names(&python), ["/tmp/mixed/b.py", "/tmp/mixed/d.py"]);
+        assert!(python.iter().all(|(c, _)| c.language == Language::Python));
+
+        // the filter is applied in sql, so off-language rows are never fetched
+        assert_eq!(store.get_chunks_by_ids(&ids, &[]).unwrap().len(), 4);
+        assert_eq!(
+            store
+                .get_chunks_by_ids(&ids, &[Language::Python])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            store
+                .get_chunks_by_ids(&ids, &[Language::Python, Language::Rust])
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_fused_scores_fall_with_rank() {
+        let (store, _dir) = test_store();
+        let ids = seed_mixed(
+            &store,
+            &[
+                ("/tmp/mixed/a.rs", Language::Rust),
+                ("/tmp/mixed/b.rs", Language::Rust),
+                ("/tmp/mixed/c.rs", Language::Rust),
+            ],
+        );
+        let fts: Vec<(i64, f64)> = ids.iter().map(|&id| (id, 0.0)).collect();
+        let text_only = RrfWeights {
+            vector: 0.0,
+            ..RrfWeights::default()
+        };
+
+        let scored = reciprocal_rank_fusion_scored(&[], &fts, &store, 3, text_only, &[]).unwrap();
+        let scores: Vec<f32> = scored.iter().map(|(_, _, s)| *s).collect();
+        assert_eq!(scores.len(), 3);
+        assert!((scores[0] - 1.0).abs() < 1e-6);
+        assert!(scores.windows(2).all(|w| w[0] > w[1]));
+
+        // with both lists weighted, a chunk found by only one of them scores half
+        let scored =
+            reciprocal_rank_fusion_scored(&[], &fts, &store, 1, RrfWeights::default(), &[])
+                .unwrap();
+        assert!((scored[0].2 - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_similarity_from_cosine_distance() {
+        assert_eq!(similarity(0.0), 1.0);
+        assert!((similarity(0.25) - 0.75).abs() < 1e-6);
+        assert_eq!(similarity(1.4), 0.0);
+    }
 }
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: GPL-3.0
+// Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
+
+use tiktoken_rs::CoreBPE;
+
+use crate::config::{ApiProvider, Config};
+
+/// how many tokens a piece of text costs the model
+pub trait TokenCounter {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// flat characters-per-token estimate, for models without a known tokenizer
+pub struct CharsPerToken(pub f32);
+
+impl TokenCounter for CharsPerToken {
+    fn count(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.0).ceil() as usize
+    }
+}
+
+/// an openai bpe vocabulary
+pub struct Bpe(&'static CoreBPE);
+
+impl Bpe {
+    /// the vocabulary `model` uses, cl100k_base for unknown models
+    pub fn for_model(model: &str) -> Self {
+        Self(
+            tiktoken_rs::bpe_for_model(model)
+                .unwrap_or_else(|_| tiktoken_rs::cl100k_base_singleton()),
+        )
+    }
+
+    pub fn cl100k() -> Self {
+        Self(tiktoken_rs::cl100k_base_singleton())
+    }
+}
+
+impl TokenCounter for Bpe {
+    fn count(&self, text: &str) -> usize {
+        self.0.encode_ordinary(text).len()
+    }
+}
+
+/// the counter for the configured model. openai models get their own
+/// vocabulary; claude and local models have no public rust tokenizer, and
+/// cl100k tracks them far closer on code than a flat ratio does.
+/// `query.chars_per_token` overrides both.
+pub fn counter_for(config: &Config) -> Box<dyn TokenCounter> {
+    if config.query.chars_per_token > 0.0 {
+        return Box::new(CharsPerToken(config.query.chars_per_token));
+    }
+    match config.api.provider {
+        ApiProvider::OpenAI => Box::new(Bpe::for_model(&config.api.model)),
+        ApiProvider::Anthropic | ApiProvider::Local => Box::new(Bpe::cl100k()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: &[&str] = &[
+        "fn main() {\n    println!(\"hello, world\");\n}\n",
+        "def fetch_user(user_id: int) -> Optional[User]:\n    return session.query(User).filter_by(id=user_id).first()\n",
+        "const handleSubmit = async (event) => {\n  event.preventDefault();\n  await api.post('/items', { name, quantity });\n};\n",
+        "impl<'a> Iterator for Tokens<'a> {\n    type Item = &'a str;\n    fn next(&mut self) -> Option<Self::Item> { self.inner.next() }\n}\n",
+    ];
+
+    /// cl100k_base token counts of `SAMPLES`
+    const CL100K_COUNTS: &[usize] = &[12, 27, 26, 39];
+
+    #[test]
+    fn test_bpe_matches_known_counts() {
+        let bpe = Bpe::cl100k();
+        for (sample, known) in SAMPLES.iter().zip(CL100K_COUNTS) {
+            assert_eq!(bpe.count(sample), *known, "{:?}", sample);
+        }
+        // gpt-4o uses o200k_base, which splits some code differently
+        let o200k = Bpe::for_model("gpt-4o");
+        assert_eq!(o200k.count(SAMPLES[2]), 28);
+        assert_eq!(Bpe::for_model("not-a-model").count(SAMPLES[0]), 12);
+    }
+
+    #[test]
+    fn test_chars_per_token_within_tolerance() {
+        let heuristic = CharsPerToken(4.0);
+        for (sample, known) in SAMPLES.iter().zip(CL100K_COUNTS) {
+            let estimate = heuristic.count(sample) as f64;
+            let error = (estimate - *known as f64).abs() / *known as f64;
+            assert!(error <= 0.35, "{} vs {} for {:?}", estimate, known, sample);
+        }
+        assert_eq!(heuristic.count(""), 0);
+    }
+
+    #[test]
+    fn test_counter_for_config() {
+        let mut config = Config::default();
+        assert_eq!(counter_for(&config).count(SAMPLES[0]), 12);
+        config.query.chars_per_token = 2.0;
+        assert_eq!(counter_for(&config).count("abcd"), 2);
+    }
+}
@@ -1,6 +1,8 @@
 // SPDX-License-Identifier: GPL-3.0
 // Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
+use std::time::Duration;
+
 use srag_common::Result;
 
 pub fn apply_nice_level(level: i32) -> Result<()> {
@@ -16,6 +18,55 @@ pub fn apply_nice_level(level: i32) -> Result<()> {
             }
         }
     }
+
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Threading::{
+            GetCurrentProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, IDLE_PRIORITY_CLASS,
+        };
+
+        // windows has no nice value, so map onto the two priority classes
+        // below normal
+        let class = match level {
+            l if l >= IDLE_NICE_LEVEL => IDLE_PRIORITY_CLASS,
+            l if l > 0 => BELOW_NORMAL_PRIORITY_CLASS,
+            _ => return Ok(()),
+        };
+        if unsafe { SetPriorityClass(GetCurrentProcess(), class) } == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        apply_thread_priority(level)?;
+    }
+    Ok(())
+}
+
+/// nice levels from here up run at idle priority on windows
+#[cfg(windows)]
+const IDLE_NICE_LEVEL: i32 = 15;
+
+/// lower the calling thread to match `level`. worker pools call this from
+/// each thread they start: on windows a thread's priority is set per thread
+/// rather than inherited, while on unix the process nice value already
+/// covers every thread, so this is a no-op there.
+pub fn apply_thread_priority(level: i32) -> Result<()> {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Threading::{
+            GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_BELOW_NORMAL, THREAD_PRIORITY_IDLE,
+        };
+
+        let priority = match level {
+            l if l >= IDLE_NICE_LEVEL => THREAD_PRIORITY_IDLE,
+            l if l > 0 => THREAD_PRIORITY_BELOW_NORMAL,
+            _ => return Ok(()),
+        };
+        if unsafe { SetThreadPriority(GetCurrentThread(), priority) } == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+
+    #[cfg(not(windows))]
+    let _ = level;
     Ok(())
 }
 
@@ -46,3 +97,127 @@ pub fn get_system_load() -> Result<f64> {
         Ok(0.0)
     }
 }
+
+/// files between system load samples
+const LOAD_SAMPLE_INTERVAL: u64 = 20;
+
+/// the pause between indexed files: `indexing.throttle_ms`, stretched while
+/// the machine is busy so indexing backs off from whatever else is running
+pub struct LoadThrottle {
+    base: Duration,
+    load: f64,
+    files: u64,
+}
+
+impl LoadThrottle {
+    pub fn new(base: Duration) -> Self {
+        Self {
+            base,
+            load: 0.0,
+            files: 0,
+        }
+    }
+
+    /// how long to wait after another file. load is sampled on the first
+    /// file and every `LOAD_SAMPLE_INTERVAL` after, not on each one.
+    pub fn next_pause(&mut self) -> Duration {
+        if self.base.is_zero() {
+            return Duration::ZERO;
+        }
+        if self.files % LOAD_SAMPLE_INTERVAL == 0 {
+            self.load = get_system_load().unwrap_or(0.0);
+        }
+        self.files += 1;
+        self.base * load_multiplier(self.load)
+    }
+}
+
+fn load_multiplier(load: f64) -> u32 {
+    if load > 4.0 {
+        3
+    } else if load > 2.0 {
+        2
+    } else {
+        1
+    }
+}
+
+/// resident memory of this process in MiB, `None` where it can't be read
+pub fn current_rss_mb() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        // second field is resident pages
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page_size <= 0 {
+            return None;
+        }
+        Some(pages * page_size as u64 / (1024 * 1024))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let mut info: libc::mach_task_basic_info = unsafe { std::mem::zeroed() };
+        let mut count = libc::MACH_TASK_BASIC_INFO_COUNT;
+        #[allow(deprecated)]
+        let ret = unsafe {
+            libc::task_info(
+                libc::mach_task_self(),
+                libc::MACH_TASK_BASIC_INFO,
+                &mut info as *mut libc::mach_task_basic_info as libc::task_info_t,
+                &mut count,
+            )
+        };
+        if ret != libc::KERN_SUCCESS {
+            return None;
+        }
+        return Some(info.resident_size / (1024 * 1024));
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn test_current_rss_mb_is_plausible() {
+        let before = current_rss_mb().expect("rss should be readable");
+        // a test binary is at least a few MiB and far below a terabyte
+        assert!(before > 0 && before < 1024 * 1024);
+
+        let ballast = vec![1u8; 64 * 1024 * 1024];
+        let after = current_rss_mb().unwrap();
+        assert!(after >= before + 32, "{} -> {}", before, after);
+        drop(ballast);
+    }
+
+    #[test]
+    fn test_load_multiplier() {
+        assert_eq!(load_multiplier(0.5), 1);
+        assert_eq!(load_multiplier(2.5), 2);
+        assert_eq!(load_multiplier(8.0), 3);
+    }
+
+    #[test]
+    fn test_zero_throttle_never_pauses() {
+        let mut throttle = LoadThrottle::new(Duration::ZERO);
+        assert!(throttle.next_pause().is_zero());
+        assert!(throttle.next_pause().is_zero());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_apply_nice_level_windows() {
+        apply_nice_level(10).unwrap();
+        apply_thread_priority(10).unwrap();
+        // zero and below leave priority untouched
+        apply_nice_level(0).unwrap();
+    }
+}
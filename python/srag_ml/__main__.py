@@ -20,6 +20,11 @@ def main():
         default=0,
         help="Port to bind to (default: 0 for OS-assigned)",
     )
+    parser.add_argument(
+        "--socket",
+        default=None,
+        help="Unix socket path to listen on instead of a TCP port",
+    )
     parser.add_argument(
         "--port-file",
         default=None,
@@ -45,6 +50,11 @@ def main():
         default=None,
         help="URL to download model from if not present",
     )
+    parser.add_argument(
+        "--model-sha256",
+        default=None,
+        help="Expected sha256 of the LLM model, checked after download",
+    )
     parser.add_argument(
         "--llm-threads",
         type=int,
@@ -90,10 +100,12 @@ def main():
         host=args.host,
         port=args.port,
         port_file=args.port_file,
+        socket_path=args.socket,
         models_dir=args.models_dir,
         auth_token=args.auth_token,
         model_filename=args.model_filename,
         model_url=args.model_url,
+        model_sha256=args.model_sha256,
         llm_threads=args.llm_threads,
         llm_context_size=args.llm_context_size,
         api_provider=args.api_provider,
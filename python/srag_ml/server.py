@@ -6,6 +6,7 @@ import json
 import logging
 import os
 import socket
+import stat
 import struct
 import threading
 import time
@@ -31,17 +32,19 @@ logging.basicConfig(level=logging.INFO, format="%(asctime)s [%(name)s] %(message
 
 
 class MlServer:
-    """JSON-RPC server over TCP for ML operations."""
+    """JSON-RPC server over TCP or a Unix socket for ML operations."""
 
     def __init__(
         self,
         host: str = "127.0.0.1",
         port: int = 0,
         port_file: Optional[str] = None,
+        socket_path: Optional[str] = None,
         models_dir: Optional[str] = None,
         auth_token: Optional[str] = None,
         model_filename: Optional[str] = None,
         model_url: Optional[str] = None,
+        model_sha256: Optional[str] = None,
         llm_threads: int = 0,
         llm_context_size: int = 4096,
         api_provider: str = "local",
@@ -53,6 +56,7 @@ class MlServer:
         self._host = host
         self._port = port
         self._port_file = port_file
+        self._socket_path = socket_path
         self._auth_token = auth_token
         self._models_dir = get_models_dir(models_dir)
         self._model_filename = model_filename or DEFAULT_MODEL_FILENAME
@@ -70,6 +74,7 @@ class MlServer:
                 model_url=self._model_url,
                 n_threads=llm_threads,
                 n_ctx=llm_context_size,
+                model_sha256=model_sha256,
             )
             self._api_client = None
             logger.info("using local LLM: %s", self._model_filename)
@@ -91,22 +96,46 @@ class MlServer:
         self._lock = threading.Lock()
         self._request_id = 0
 
+    def _bind(self) -> str:
+        """Create the listening socket and return the address for the port file."""
+        if self._socket_path:
+            path = Path(self._socket_path)
+            path.parent.mkdir(parents=True, exist_ok=True)
+            # a stale socket from a previous run blocks bind
+            if path.exists() and stat.S_ISSOCK(path.stat().st_mode):
+                path.unlink()
+            sock = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+            # owner-only from the moment the socket file appears
+            old_umask = os.umask(0o177)
+            try:
+                sock.bind(str(path))
+            finally:
+                os.umask(old_umask)
+            os.chmod(path, 0o600)
+            self._server_socket = sock
+            return f"unix:{path}"
+
+        sock = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+        sock.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+        sock.bind((self._host, self._port))
+        self._server_socket = sock
+        return str(sock.getsockname()[1])
+
     def run(self):
-        self._server_socket = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
-        self._server_socket.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
-        self._server_socket.bind((self._host, self._port))
+        address = self._bind()
         self._server_socket.listen(5)
         self._server_socket.settimeout(1.0)
         self._running = True
 
-        assigned_port = self._server_socket.getsockname()[1]
-
         if self._port_file:
             port_path = Path(self._port_file)
             port_path.parent.mkdir(parents=True, exist_ok=True)
-            port_path.write_text(str(assigned_port))
+            port_path.write_text(address)
 
-        logger.info("ML service listening on %s:%d", self._host, assigned_port)
+        if self._socket_path:
+            logger.info("ML service listening on %s", self._socket_path)
+        else:
+            logger.info("ML service listening on %s:%s", self._host, address)
 
         idle_thread = threading.Thread(target=self._idle_monitor, daemon=True)
         idle_thread.start()
@@ -131,6 +160,8 @@ class MlServer:
             self._server_socket.close()
         if self._port_file and os.path.exists(self._port_file):
             os.unlink(self._port_file)
+        if self._socket_path and os.path.exists(self._socket_path):
+            os.unlink(self._socket_path)
         self._embedder.unload()
         if self._llm:
             self._llm.unload()
@@ -2,6 +2,8 @@
 # Copyright (c) 2026 Matt Hesketh <matt@matthesketh.pro>
 
 import json
+import socket
+import stat
 import struct
 import pytest
 from unittest.mock import MagicMock, patch
@@ -239,6 +241,47 @@ class TestMessageFraming:
             server._recv_message(mock_conn)
 
 
+class TestBind:
+    @patch("srag_ml.server.Embedder")
+    @patch("srag_ml.server.Reranker")
+    @patch("srag_ml.server.LlmEngine")
+    def test_bind_tcp_returns_port(self, mock_llm, mock_reranker, mock_embedder):
+        from srag_ml.server import MlServer
+
+        server = MlServer()
+        try:
+            address = server._bind()
+            assert int(address) > 0
+        finally:
+            server._server_socket.close()
+
+    @pytest.mark.skipif(not hasattr(socket, "AF_UNIX"), reason="needs unix sockets")
+    @patch("srag_ml.server.Embedder")
+    @patch("srag_ml.server.Reranker")
+    @patch("srag_ml.server.LlmEngine")
+    def test_bind_unix_socket_owner_only(
+        self, mock_llm, mock_reranker, mock_embedder, tmp_path
+    ):
+        from srag_ml.server import MlServer
+
+        path = tmp_path / "ml.sock"
+        server = MlServer(socket_path=str(path))
+        try:
+            address = server._bind()
+            assert address == f"unix:{path}"
+            assert stat.S_ISSOCK(path.stat().st_mode)
+            assert stat.S_IMODE(path.stat().st_mode) == 0o600
+        finally:
+            server._server_socket.close()
+
+        # a stale socket left behind is replaced on the next start
+        server = MlServer(socket_path=str(path))
+        try:
+            assert server._bind() == f"unix:{path}"
+        finally:
+            server._server_socket.close()
+
+
 class TestAuthToken:
     @patch("srag_ml.server.Embedder")
     @patch("srag_ml.server.Reranker")